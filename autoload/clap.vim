@@ -37,6 +37,10 @@ let g:clap_disable_run_rooter = get(g:, 'clap_disable_run_rooter', v:false)
 let g:clap_disable_bottom_top = get(g:, 'clap_disable_bottom_top', 0)
 let g:clap_enable_debug = get(g:, 'clap_enable_debug', v:false)
 let g:clap_forerunner_status_sign = get(g:, 'clap_forerunner_status_sign', {'done': '•', 'running': '!', 'using_cache': '*'})
+let g:clap_forerunner_low_priority = get(g:, 'clap_forerunner_low_priority', v:true)
+let g:clap_enable_cache_watcher = get(g:, 'clap_enable_cache_watcher', v:false)
+let g:clap_shorten_path = get(g:, 'clap_shorten_path', v:false)
+let g:clap_enable_icon_git_status = get(g:, 'clap_enable_icon_git_status', v:false)
 
 " Backward compatible
 if exists('g:clap_forerunner_status_sign_done')
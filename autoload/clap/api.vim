@@ -440,10 +440,13 @@ function! s:init_provider() abort
 
   " Reading from a cached file should be faster than running the command again.
   " Currently only maple extension supports --input option, for the other
-  " external filter, use cat instead.
+  " external filter, use `maple cache cat` to decompress the cache file if
+  " maple is available, otherwise use cat instead.
   function! s:read_from_file_or_pipe(ext_filter_cmd, input_file) abort
     if clap#filter#async#external#using_maple()
       let cmd = printf('%s --input %s', a:ext_filter_cmd, a:input_file)
+    elseif clap#maple#is_available()
+      let cmd = printf('%s cache cat %s | %s', clap#maple#binary(), a:input_file, a:ext_filter_cmd)
     else
       let cmd = printf('%s %s | %s', s:cat_or_type, a:input_file, a:ext_filter_cmd)
     endif
@@ -35,6 +35,15 @@ function! clap#filter#get_bonus_type() abort
   endif
 endfunction
 
+" Returns the filetype of the start buffer for the files providers.
+function! clap#filter#get_filetype() abort
+  if index(['files', 'git_files'], g:clap.provider.id) > -1
+    return getbufvar(g:clap.start.bufnr, '&filetype')
+  else
+    return ''
+  endif
+endfunction
+
 function! clap#filter#matchfuzzy(query, candidates) abort
   " `result` could be a list of two lists, or a list of three
   " lists(newer vim).
@@ -104,6 +113,10 @@ else
       if g:clap.provider.id ==# 'blines'
         let context['language'] = expand('#'.g:clap.start.bufnr.':e')
       endif
+      let filetype = clap#filter#get_filetype()
+      if !empty(filetype)
+        let context['filetype'] = filetype
+      endif
       try
         return clap#filter#sync#python#(a:query, a:candidates, clap#util#recent_files(), context)
       catch
@@ -47,7 +47,7 @@ function! s:prepare_grep_cmd() abort
         \ '--winwidth', winwidth(g:clap.display.winid),
         \ 'grep', g:clap.input.get(),
         \ ]
-  return subcmd + opts
+  return subcmd + opts + clap#maple#command#ignore_opts() + clap#maple#command#case_opts()
 endfunction
 
 function! clap#filter#async#dyn#start_grep() abort
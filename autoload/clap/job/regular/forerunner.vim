@@ -134,6 +134,9 @@ if clap#maple#is_available()
     let g:__clap_current_forerunner_status = g:clap_forerunner_status_sign.running
     call clap#spinner#refresh()
     call s:start_maple(a:sub_cmd)
+    if g:clap_enable_cache_watcher && clap#job#daemon#is_running()
+      call clap#client#notify('watch', {'cwd': clap#rooter#working_dir()})
+    endif
   endfunction
 
   function! clap#job#regular#forerunner#start_command(cmd) abort
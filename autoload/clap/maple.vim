@@ -48,5 +48,20 @@ function! clap#maple#build_cmd_list(cmd_list) abort
   return insert(a:cmd_list, s:maple_bin)
 endfunction
 
+" Splices the fresh grep lines of the saved file into the grep caches of its project.
+function! clap#maple#refresh_file(bufnr) abort
+  let fpath = fnamemodify(bufname(a:bufnr), ':p')
+  if s:maple_bin is v:null || !filereadable(fpath)
+    return
+  endif
+  let dir = g:clap_disable_run_rooter ? getcwd() : clap#path#project_root_or_default(a:bufnr)
+  let cmd = [s:maple_bin, 'cache', 'refresh-file', '--dir', dir, '--file', fpath]
+  if has('nvim')
+    call jobstart(cmd)
+  else
+    call job_start(cmd)
+  endif
+endfunction
+
 let &cpoptions = s:save_cpo
 unlet s:save_cpo
@@ -8,6 +8,9 @@ let s:maple_bin = clap#maple#binary()
 
 let s:can_enable_icon = ['files', 'git_files']
 
+" Number of the leading lines of cache scored before the full dyn filtering.
+let s:SNAPSHOT_SIZE = 10000
+
 function! clap#maple#command#start_grep_sync(cmd, query, enable_icon, glob) abort
   let global_opts = ['--number', g:clap.display.preload_capacity, '--winwidth', winwidth(g:clap.display.winid)]
 
@@ -29,6 +32,28 @@ function! clap#maple#command#start_grep_sync(cmd, query, enable_icon, glob) abor
   call clap#job#regular#maple#start([s:maple_bin] + global_opts + subcommand)
 endfunction
 
+" Returns the options of searching the hidden and ignored files, e.g., `:Clap grep2 +hidden`.
+function! clap#maple#command#ignore_opts() abort
+  let opts = []
+  if has_key(g:clap.context, 'hidden')
+    call add(opts, '--hidden')
+  endif
+  if has_key(g:clap.context, 'no-ignore')
+    call add(opts, '--no-ignore')
+  endif
+  return opts
+endfunction
+
+" Returns the case options of grep2, e.g., `:Clap grep2 +ignore-case`.
+function! clap#maple#command#case_opts() abort
+  for opt in ['ignore-case', 'smart-case', 'case-sensitive']
+    if has_key(g:clap.context, opt)
+      return ['--'.opt]
+    endif
+  endfor
+  return []
+endfunction
+
 function! clap#maple#command#ripgrep_forerunner() abort
   " TODO: add max_output
   let global_opts = g:clap_enable_icon ? ['--icon-painter=Grep'] : []
@@ -43,7 +68,12 @@ function! clap#maple#command#ripgrep_forerunner() abort
         \ '--output-threshold', clap#filter#capacity(),
         \ ]
 
+  if g:clap_forerunner_low_priority
+    call add(subcommand, '--low-priority')
+  endif
+
   return [s:maple_bin] + global_opts + subcommand
+        \ + clap#maple#command#ignore_opts() + clap#maple#command#case_opts()
 endfunction
 
 function! s:inject_icon_painter_opt(opts) abort
@@ -51,6 +81,9 @@ function! s:inject_icon_painter_opt(opts) abort
   if g:clap_enable_icon
     if index(['files', 'git_files'], g:clap.provider.id) > -1
       call add(global_opts, '--icon-painter=File')
+      if g:clap_enable_icon_git_status
+        call add(global_opts, '--icon-git-status')
+      endif
     elseif 'proj_tags' ==# g:clap.provider.id
       call add(global_opts, '--icon-painter=ProjTags')
     endif
@@ -58,6 +91,14 @@ function! s:inject_icon_painter_opt(opts) abort
   return global_opts
 endfunction
 
+" Shortens the paths of the file like providers in the narrow window.
+function! s:inject_shorten_path_opt(opts) abort
+  if g:clap_shorten_path && index(['files', 'git_files'], g:clap.provider.id) > -1
+    call add(a:opts, '--shorten-path')
+  endif
+  return a:opts
+endfunction
+
 function! clap#maple#command#exec_forerunner(cmd) abort
   " No global --number option.
   let global_opts = s:inject_icon_painter_opt([])
@@ -72,7 +113,11 @@ function! clap#maple#command#exec_forerunner(cmd) abort
         \ '--output-threshold', clap#filter#capacity(),
         \ ]
 
-  return [s:maple_bin] + global_opts + subcommand
+  if g:clap_forerunner_low_priority
+    call add(subcommand, '--low-priority')
+  endif
+
+  return [s:maple_bin] + global_opts + subcommand + clap#maple#command#ignore_opts()
 endfunction
 
 " Returns the filtered results after the input stream is complete.
@@ -87,15 +132,27 @@ function! clap#maple#command#filter_sync(query) abort
     call add(global_opts, printf('--bonus=%s', clap#filter#get_bonus_type()))
     if g:clap_enable_icon
       call add(global_opts, '--icon-painter=File')
+      if g:clap_enable_icon_git_status
+        call add(global_opts, '--icon-git-status')
+      endif
     endif
   endif
 
-  return [s:maple_bin] + global_opts + ['filter', a:query, '--sync']
+  let global_opts = s:inject_shorten_path_opt(global_opts)
+
+  let subcommand = ['filter', a:query, '--sync']
+  let filetype = clap#filter#get_filetype()
+  if !empty(filetype)
+    call add(subcommand, printf('--filetype=%s', filetype))
+  endif
+
+  return [s:maple_bin] + global_opts + subcommand
 endfunction
 
 function! clap#maple#command#filter_dyn(dyn_size, tempfile) abort
   let global_opts = ['--number', a:dyn_size, '--winwidth', winwidth(g:clap.display.winid)]
   let global_opts = s:inject_icon_painter_opt(global_opts)
+  let global_opts = s:inject_shorten_path_opt(global_opts)
 
   let subcommand = [
         \ 'filter', g:.clap.input.get(),
@@ -103,7 +160,7 @@ function! clap#maple#command#filter_dyn(dyn_size, tempfile) abort
         \ ]
 
   if g:clap.provider.id ==# 'files'
-    if has_key(g:clap.context, 'name-only')
+    if clap#provider#files#name_only()
       call add(subcommand, '--match-type=FileName')
     endif
     if !exists('g:__clap_recent_files_dyn_tmp')
@@ -111,12 +168,21 @@ function! clap#maple#command#filter_dyn(dyn_size, tempfile) abort
       call writefile(clap#util#recent_files(), g:__clap_recent_files_dyn_tmp)
     endif
     call add(subcommand, printf('--recent-files=%s', g:__clap_recent_files_dyn_tmp))
+    let filetype = clap#filter#get_filetype()
+    if !empty(filetype)
+      call add(subcommand, printf('--filetype=%s', filetype))
+    endif
   else
     if g:clap.provider.id ==# 'proj_tags'
       call add(subcommand, '--match-type=TagName')
     endif
   endif
 
+  " Show the top results of the cache head at once when opened with an initial query.
+  if has_key(g:clap.context, 'query') && !empty(g:clap.input.get())
+    call add(subcommand, '--snapshot='.s:SNAPSHOT_SIZE)
+  endif
+
   return [s:maple_bin] + global_opts + subcommand
 endfunction
 
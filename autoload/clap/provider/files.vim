@@ -25,7 +25,7 @@ endif
 function! s:files.source() abort
   call clap#rooter#try_set_cwd()
 
-  if has_key(g:clap.context, 'name-only')
+  if clap#provider#files#name_only()
     let g:__clap_match_type_enum = 'FileName'
   endif
 
@@ -40,6 +40,14 @@ function! s:files.source() abort
   return s:default_source
 endfunction
 
+" Returns true if only the file name is matched instead of the full file path.
+function! clap#provider#files#name_only() abort
+  if has_key(g:clap.context, 'full-path')
+    return v:false
+  endif
+  return has_key(g:clap.context, 'name-only') || get(g:, 'clap_provider_files_name_only', v:false)
+endfunction
+
 function! s:into_filename(line) abort
   if g:clap_enable_icon && clap#maple#is_available()
     return a:line[4:]
@@ -0,0 +1,208 @@
+//! This crate detects the filetype of a path from the well-known file names, the extensions and
+//! the shebang line, the filetype identifiers are the same with Vim's `filetype`.
+//!
+//! It's shared by the icon painter and the previewer so that they always agree on the filetype.
+
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+/// Vim filetype identifier, e.g., `rust`, `python`.
+pub type FileType = &'static str;
+
+/// Maps the well-known file names, which are compared case-insensitively, to the filetype.
+fn from_file_name(file_name: &str) -> Option<FileType> {
+    let filetype = match file_name.to_lowercase().as_str() {
+        ".bash_profile" | ".bashprofile" | ".bashrc" | ".profile" => "sh",
+        ".zshrc" | ".zshenv" | ".zprofile" => "zsh",
+        ".vimrc" | "_vimrc" | ".gvimrc" | "_gvimrc" | ".exrc" => "vim",
+        ".gitconfig" => "gitconfig",
+        ".gitignore" => "gitignore",
+        ".editorconfig" => "dosini",
+        "cargo.lock" | "pipfile" => "toml",
+        "cmakelists.txt" => "cmake",
+        "dockerfile" | "containerfile" => "dockerfile",
+        "gemfile" | "rakefile" | "vagrantfile" => "ruby",
+        "gnumakefile" | "makefile" => "make",
+        "go.mod" => "gomod",
+        "justfile" => "just",
+        "license" | "readme" => "text",
+        _ => return None,
+    };
+    Some(filetype)
+}
+
+/// Maps the file extension, which is compared case-insensitively, to the filetype.
+fn from_extension(extension: &str) -> Option<FileType> {
+    let filetype = match extension.to_lowercase().as_str() {
+        "bash" | "sh" => "sh",
+        "c" | "h" => "c",
+        "cc" | "cpp" | "cxx" | "c++" | "hh" | "hpp" | "hxx" => "cpp",
+        "clj" | "cljc" | "cljs" | "edn" => "clojure",
+        "coffee" => "coffee",
+        "cs" => "cs",
+        "css" => "css",
+        "dart" => "dart",
+        "diff" | "patch" => "diff",
+        "erl" | "hrl" => "erlang",
+        "ex" | "exs" => "elixir",
+        "fish" => "fish",
+        "go" => "go",
+        "hs" | "lhs" => "haskell",
+        "htm" | "html" => "html",
+        "ini" => "dosini",
+        "java" => "java",
+        "cjs" | "js" | "mjs" => "javascript",
+        "json" => "json",
+        "jsx" => "javascriptreact",
+        "kt" | "kts" => "kotlin",
+        "lua" => "lua",
+        "markdown" | "md" => "markdown",
+        "ml" | "mli" => "ocaml",
+        "php" => "php",
+        "pl" | "pm" => "perl",
+        "py" | "pyi" => "python",
+        "rb" => "ruby",
+        "rs" => "rust",
+        "scala" => "scala",
+        "scss" => "scss",
+        "sql" => "sql",
+        "swift" => "swift",
+        "toml" => "toml",
+        "ts" => "typescript",
+        "tsx" => "typescriptreact",
+        "txt" => "text",
+        "vim" => "vim",
+        "vue" => "vue",
+        "xml" => "xml",
+        "yaml" | "yml" => "yaml",
+        "zsh" => "zsh",
+        _ => return None,
+    };
+    Some(filetype)
+}
+
+/// Detects the filetype from the shebang line, e.g., `#!/usr/bin/env python3`.
+pub fn from_shebang(line: &str) -> Option<FileType> {
+    let command = line.strip_prefix("#!")?;
+    let mut words = command.split_whitespace();
+    let mut interpreter = words.next()?.rsplit('/').next()?;
+    if interpreter == "env" {
+        // Skip the options of env, e.g., `#!/usr/bin/env -S deno run`.
+        interpreter = words.find(|word| !word.starts_with('-'))?;
+    }
+    // python3, python3.9, lua5.1
+    let interpreter = interpreter.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.');
+
+    let filetype = match interpreter {
+        "ash" | "bash" | "dash" | "ksh" | "sh" => "sh",
+        "deno" | "node" | "nodejs" => "javascript",
+        "fish" => "fish",
+        "lua" | "luajit" => "lua",
+        "perl" => "perl",
+        "php" => "php",
+        "python" | "pypy" => "python",
+        "ruby" => "ruby",
+        "zsh" => "zsh",
+        _ => return None,
+    };
+    Some(filetype)
+}
+
+/// Detects the filetype of `path` from its file name and extension, the file is not read.
+pub fn detect_by_name(path: &Path) -> Option<FileType> {
+    path.file_name()
+        .and_then(std::ffi::OsStr::to_str)
+        .and_then(from_file_name)
+        .or_else(|| {
+            path.extension()
+                .and_then(std::ffi::OsStr::to_str)
+                .and_then(from_extension)
+        })
+}
+
+/// Detects the filetype of `path`, the shebang line is read if the name says nothing.
+pub fn detect(path: &Path) -> Option<FileType> {
+    detect_by_name(path).or_else(|| {
+        let mut first_line = String::new();
+        BufReader::new(File::open(path).ok()?)
+            .read_line(&mut first_line)
+            .ok()?;
+        from_shebang(first_line.trim_end())
+    })
+}
+
+/// Returns the canonical extension of `filetype`, e.g., for looking up the icon.
+pub fn canonical_extension(filetype: &str) -> Option<&'static str> {
+    let extension = match filetype {
+        "c" => "c",
+        "clojure" => "clj",
+        "cpp" => "cpp",
+        "cs" => "cs",
+        "dosini" => "ini",
+        "elixir" => "ex",
+        "erlang" => "erl",
+        "fish" => "fish",
+        "go" => "go",
+        "haskell" => "hs",
+        "java" => "java",
+        "javascript" => "js",
+        "javascriptreact" => "jsx",
+        "kotlin" => "kt",
+        "lua" => "lua",
+        "markdown" => "md",
+        "ocaml" => "ml",
+        "perl" => "pl",
+        "php" => "php",
+        "python" => "py",
+        "ruby" => "rb",
+        "rust" => "rs",
+        "scala" => "scala",
+        "sh" => "sh",
+        "swift" => "swift",
+        "toml" => "toml",
+        "typescript" => "ts",
+        "typescriptreact" => "tsx",
+        "vim" => "vim",
+        "yaml" => "yml",
+        "zsh" => "zsh",
+        _ => return None,
+    };
+    Some(extension)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_by_name() {
+        assert_eq!(detect_by_name(Path::new("src/lib.rs")), Some("rust"));
+        assert_eq!(detect_by_name(Path::new("include/foo.HPP")), Some("cpp"));
+        assert_eq!(detect_by_name(Path::new("/home/clap/.bashrc")), Some("sh"));
+        assert_eq!(detect_by_name(Path::new("Makefile")), Some("make"));
+        assert_eq!(detect_by_name(Path::new("CMakeLists.txt")), Some("cmake"));
+        assert_eq!(detect_by_name(Path::new("bin/maple")), None);
+    }
+
+    #[test]
+    fn test_from_shebang() {
+        assert_eq!(from_shebang("#!/bin/bash"), Some("sh"));
+        assert_eq!(from_shebang("#!/usr/bin/env python3"), Some("python"));
+        assert_eq!(from_shebang("#!/usr/bin/python3.9 -u"), Some("python"));
+        assert_eq!(
+            from_shebang("#!/usr/bin/env -S deno run"),
+            Some("javascript")
+        );
+        assert_eq!(from_shebang("#!/usr/bin/env"), None);
+        assert_eq!(from_shebang("# not a shebang"), None);
+    }
+
+    #[test]
+    fn test_canonical_extension() {
+        for filetype in &["rust", "python", "sh", "cpp", "javascript", "yaml", "vim"] {
+            let extension = canonical_extension(filetype).unwrap();
+            assert_eq!(from_extension(extension), Some(*filetype));
+        }
+    }
+}
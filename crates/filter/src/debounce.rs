@@ -0,0 +1,37 @@
+//! Typing debounce suggested to the frontend, adapted to the cost of filtering.
+
+use std::time::Duration;
+
+/// The suggested debounce is never shorter than this, e.g., for a small list.
+pub const MIN_DEBOUNCE_MS: u64 = 20;
+
+/// The suggested debounce is never longer than this so that the typing still feels responsive.
+pub const MAX_DEBOUNCE_MS: u64 = 500;
+
+/// Number of lines per extra 10ms of the debounce.
+const LINES_PER_10MS: usize = 100_000;
+
+/// Returns the debounce in milliseconds for the next query given the last run on a source of
+/// `source_size` lines took `elapsed`.
+///
+/// The next query should not be sent before the last one is likely done, and the timing of the
+/// larger source varies more between the queries, e.g., a shorter query matches more lines.
+pub fn suggested_debounce_ms(elapsed: Duration, source_size: usize) -> u64 {
+    let elapsed_ms = elapsed.as_millis() as u64;
+    let debounce = elapsed_ms * 3 / 2 + (source_size / LINES_PER_10MS) as u64 * 10;
+    debounce.clamp(MIN_DEBOUNCE_MS, MAX_DEBOUNCE_MS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_suggested_debounce_ms() {
+        let ms = Duration::from_millis;
+        assert_eq!(suggested_debounce_ms(ms(1), 100), MIN_DEBOUNCE_MS);
+        assert_eq!(suggested_debounce_ms(ms(40), 1000), 60);
+        assert_eq!(suggested_debounce_ms(ms(40), 1_000_000), 160);
+        assert_eq!(suggested_debounce_ms(ms(2000), 100), MAX_DEBOUNCE_MS);
+    }
+}
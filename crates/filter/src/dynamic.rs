@@ -1,13 +1,17 @@
-use std::io::{self, BufRead};
+use std::cell::Cell;
+use std::io;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::time::{Duration, Instant};
 
 use rayon::slice::ParallelSliceMut;
 
 use icon::{IconPainter, ICON_LEN};
 use matcher::Bonus;
-use utility::{println_json, println_json_with_length};
+use printer::Progress;
+use utility::println_json_with_length;
 
 use super::*;
+use crate::typo_fallback::MatchPass;
 use crate::FilterResult;
 
 /// The constant to define the length of `top_` queues.
@@ -18,6 +22,42 @@ const MAX_IDX: usize = ITEMS_TO_SHOW - 1;
 /// Refresh the top filtered results per 300 ms.
 const UPDATE_INTERVAL: Duration = Duration::from_millis(300);
 
+/// Maximum number of the matches retained while filtering, unlimited if 0.
+static MAX_RETAINED: AtomicUsize = AtomicUsize::new(0);
+
+/// Sets the maximum number of the matches retained by [`dyn_run`] when all of them have to be
+/// kept, i.e., without `number` or not sorted by score, the rest are dropped and the results are
+/// tagged with `limited`.
+pub fn set_max_retained(max_retained: Option<usize>) {
+    MAX_RETAINED.store(max_retained.unwrap_or_default(), Ordering::Relaxed);
+}
+
+fn max_retained() -> Option<usize> {
+    match MAX_RETAINED.load(Ordering::Relaxed) {
+        0 => None,
+        max_retained => Some(max_retained),
+    }
+}
+
+/// Drops the matches of `iter` beyond `limit`, which are still counted in `dropped`.
+fn retain_at_most<'a>(
+    iter: impl Iterator<Item = FilterResult> + 'a,
+    limit: Option<usize>,
+    dropped: &'a Cell<usize>,
+) -> impl Iterator<Item = FilterResult> + 'a {
+    let mut retained = 0usize;
+    iter.filter(move |_| match limit {
+        Some(limit) if retained >= limit => {
+            dropped.set(dropped.get() + 1);
+            false
+        }
+        _ => {
+            retained += 1;
+            true
+        }
+    })
+}
+
 trait Insert<T> {
     fn pop_and_insert(&mut self, idx: usize, value: T);
 }
@@ -112,27 +152,35 @@ fn find_best_score_idx(top_scores: &[i64; ITEMS_TO_SHOW], score: i64) -> Option<
         .map(|(idx, _)| idx)
 }
 
+/// Returns the current `sequence` of the top results snapshot and increments it.
+fn next_sequence(sequence: &Cell<usize>) -> usize {
+    let current = sequence.get();
+    sequence.set(current + 1);
+    current
+}
+
 /// Returns the new freshed time when the new top scored items are sent to the client.
 ///
-/// Printing to stdout is to send the printed content to the client.
+/// Printing to stdout is to send the printed content to the client, each snapshot of the
+/// changed top results is tagged with the next `sequence`.
 fn try_notify_top_results(
     icon_painter: &Option<IconPainter>,
     total: usize,
     past: &Instant,
-    top_results_len: usize,
     top_results: &[usize; ITEMS_TO_SHOW],
     buffer: &[FilterResult],
     last_lines: &[String],
+    sequence: &Cell<usize>,
 ) -> std::result::Result<(Instant, Option<Vec<String>>), ()> {
     if total % 16 == 0 {
         let now = Instant::now();
         if now > *past + UPDATE_INTERVAL {
-            let mut indices = Vec::with_capacity(top_results_len);
-            let mut lines = Vec::with_capacity(top_results_len);
+            let mut indices = Vec::with_capacity(top_results.len());
+            let mut lines = Vec::with_capacity(top_results.len());
             for &idx in top_results.iter() {
                 let (item, _, idxs) = std::ops::Index::index(buffer, idx);
                 let text = if let Some(painter) = icon_painter {
-                    indices.push(idxs.iter().map(|x| x + ICON_LEN).collect::<Vec<_>>());
+                    indices.push(printer::LineOffset::prefix(ICON_LEN).apply(idxs));
                     painter.paint(item.display_text())
                 } else {
                     indices.push(idxs.clone());
@@ -142,7 +190,13 @@ fn try_notify_top_results(
             }
 
             if last_lines != lines.as_slice() {
-                println_json_with_length!(total, lines, indices);
+                let mut payload = serde_json::json!({
+                    "total": total,
+                    "lines": lines,
+                    "indices": indices,
+                });
+                Progress::partial(next_sequence(sequence)).attach(&mut payload);
+                printer::println_payload_with_length(payload);
                 return Ok((now, Some(lines)));
             } else {
                 println_json_with_length!(total);
@@ -173,6 +227,7 @@ fn try_notify_top_results(
 fn dyn_collect_all(
     mut iter: impl Iterator<Item = FilterResult>,
     icon_painter: &Option<IconPainter>,
+    sequence: &Cell<usize>,
 ) -> Vec<FilterResult> {
     let mut buffer = Vec::with_capacity({
         let (low, high) = iter.size_hint();
@@ -202,10 +257,10 @@ fn dyn_collect_all(
             &icon_painter,
             total,
             &past,
-            top_results.len(),
             &top_results,
             &buffer,
             &last_lines,
+            sequence,
         ) {
             past = now;
             if let Some(lines) = new_lines {
@@ -232,6 +287,7 @@ fn dyn_collect_number(
     mut iter: impl Iterator<Item = FilterResult>,
     number: usize,
     icon_painter: &Option<IconPainter>,
+    sequence: &Cell<usize>,
 ) -> (usize, Vec<(SourceItem, i64, Vec<usize>)>) {
     // To not have problems with queues after sorting and truncating the buffer,
     // buffer has the lowest bound of `ITEMS_TO_SHOW * 2`, not `number * 2`.
@@ -260,10 +316,10 @@ fn dyn_collect_number(
             &icon_painter,
             total,
             &past,
-            top_results.len(),
             &top_results,
             &buffer,
             &last_lines,
+            sequence,
         ) {
             past = now;
             if let Some(lines) = new_lines {
@@ -287,17 +343,34 @@ fn dyn_collect_number(
     (total, buffer)
 }
 
+/// Same with [`dyn_collect_number`] for sorting by score.
+///
+/// Otherwise all the matches are collected since they can't be ranked before the source is
+/// complete, no progressive updates are sent then. The matches beyond [`set_max_retained`] are
+/// counted in `dropped` instead.
+fn dyn_collect_sorted(
+    iter: impl Iterator<Item = FilterResult>,
+    number: usize,
+    sort_by: SortBy,
+    icon_painter: &Option<IconPainter>,
+    sequence: &Cell<usize>,
+    dropped: &Cell<usize>,
+) -> (usize, Vec<FilterResult>) {
+    if sort_by == SortBy::Score {
+        dyn_collect_number(iter, number, icon_painter, sequence)
+    } else {
+        let filtered = retain_at_most(iter, max_retained(), dropped).collect::<Vec<_>>();
+        (filtered.len() + dropped.get(), filtered)
+    }
+}
+
 // macros for `dyn_collect_number` and `dyn_collect_number`
 //
 // Generate an filtered iterator from Source::Stdin.
 macro_rules! source_iter_stdin {
     ( $scorer:ident ) => {
-        io::stdin().lock().lines().filter_map(|lines_iter| {
-            lines_iter
-                .ok()
-                .map(Into::<SourceItem>::into)
-                .and_then(|item| $scorer(&item).map(|(score, indices)| (item, score, indices)))
-        })
+        crate::source::lossy_lines(io::stdin().lock())
+            .filter_map(|item| $scorer(&item).map(|(score, indices)| (item, score, indices)))
     };
 }
 
@@ -305,29 +378,20 @@ macro_rules! source_iter_stdin {
 #[cfg(feature = "enable_dyn")]
 macro_rules! source_iter_exec {
     ( $scorer:ident, $exec:ident ) => {
-        std::io::BufReader::new($exec.stream_stdout()?)
-            .lines()
-            .filter_map(|lines_iter| {
-                lines_iter
-                    .ok()
-                    .map(Into::<SourceItem>::into)
-                    .and_then(|item| $scorer(&item).map(|(score, indices)| (item, score, indices)))
-            })
+        crate::source::lossy_lines(std::io::BufReader::new($exec.stream_stdout()?))
+            .filter_map(|item| $scorer(&item).map(|(score, indices)| (item, score, indices)))
     };
 }
 
 // Generate an filtered iterator from Source::File(fpath).
 macro_rules! source_iter_file {
     ( $scorer:ident, $fpath:ident ) => {
-        // To avoid Err(Custom { kind: InvalidData, error: "stream did not contain valid UTF-8" })
-        // The line stream can contain invalid UTF-8 data.
-        std::io::BufReader::new(std::fs::File::open($fpath)?)
-            .lines()
-            .filter_map(|x| {
-                x.ok()
-                    .map(Into::<SourceItem>::into)
-                    .and_then(|item| $scorer(&item).map(|(score, indices)| (item, score, indices)))
-            })
+        // The line stream can contain invalid UTF-8 data, which is decoded lossily.
+        crate::source::numbered_lines(
+            &$fpath,
+            std::io::BufReader::new(crate::source::open_source_file(&$fpath)?),
+        )
+        .filter_map(|item| $scorer(&item).map(|(score, indices)| (item, score, indices)))
     };
 }
 
@@ -338,6 +402,40 @@ macro_rules! source_iter_list {
     };
 }
 
+/// Sends the top results of the first `snapshot` lines of `fpath`.
+///
+/// The partial cache file is skipped as reading its head may block until the writer catches up.
+fn send_snapshot(
+    fpath: &std::path::Path,
+    snapshot: usize,
+    scorer: impl Fn(&SourceItem) -> matcher::MatchResult,
+    number: usize,
+    winwidth: usize,
+    icon_painter: Option<IconPainter>,
+    sequence: &Cell<usize>,
+) -> Result<()> {
+    if utility::is_partial_cache(fpath) {
+        return Ok(());
+    }
+    let filtered = crate::source::numbered_lines(
+        fpath,
+        io::BufReader::new(crate::source::open_source_file(fpath)?),
+    )
+    .take(snapshot)
+    .filter_map(|item| scorer(&item).map(|(score, indices)| (item, score, indices)))
+    .collect::<Vec<_>>();
+    let total = filtered.len();
+    printer::print_dyn_filter_results(
+        sort_initial_filtered(filtered),
+        total,
+        number,
+        winwidth,
+        icon_painter,
+        Progress::partial(next_sequence(sequence)),
+    );
+    Ok(())
+}
+
 /// Returns the ranked results after applying fuzzy filter given the query string and a list of candidates.
 pub fn dyn_run<I: Iterator<Item = SourceItem>>(
     query: &str,
@@ -348,64 +446,225 @@ pub fn dyn_run<I: Iterator<Item = SourceItem>>(
         winwidth,
         icon_painter,
         match_type,
+        line_cutoff,
+        group_by_file,
+        snapshot,
+        case_matching,
+        sort_by,
+        sort_dir,
+        tiebreak,
     }: FilterContext,
     bonuses: Vec<Bonus>,
 ) -> Result<()> {
-    let algo = if query.contains(' ') {
-        Algo::SubString
-    } else {
-        algo.unwrap_or(Algo::Fzy)
+    let algo = crate::select_algo(query, algo);
+    let scoring_matcher = matcher::Matcher::new_with_bonuses(algo, match_type, bonuses)
+        .line_cutoff(line_cutoff)
+        .case_matching(case_matching);
+    // The typo matches are appended to the final results only, see [`MatchPass`].
+    let pass = MatchPass::new(&scoring_matcher, query);
+    // Number of the lines read from the source for the debounce hint.
+    let scanned = Cell::new(0usize);
+    let scorer = |item: &SourceItem| {
+        scanned.set(scanned.get() + 1);
+        pass.score(item)
     };
-    let scoring_matcher = matcher::Matcher::new_with_bonuses(algo, match_type, bonuses);
-    let scorer = |item: &SourceItem| scoring_matcher.do_match(item, query);
+    // Sequence of the top results snapshots sent to the client, see [`Progress`].
+    let sequence = Cell::new(0usize);
+    // Number of the matches dropped for the memory, see [`set_max_retained`].
+    let dropped = Cell::new(0usize);
+    let started = Instant::now();
     if let Some(number) = number {
-        let (total, filtered) = match source {
-            Source::Stdin => dyn_collect_number(source_iter_stdin!(scorer), number, &icon_painter),
+        if let (Some(snapshot), Source::File(ref fpath)) = (snapshot, &source) {
+            send_snapshot(
+                fpath,
+                snapshot,
+                scorer,
+                number,
+                utility::tty::display_width(winwidth, 100),
+                icon_painter.clone(),
+                &sequence,
+            )?;
+            scanned.set(0);
+        }
+
+        let (total, mut filtered) = match source {
+            Source::Stdin => dyn_collect_sorted(
+                source_iter_stdin!(scorer),
+                number,
+                sort_by,
+                &icon_painter,
+                &sequence,
+                &dropped,
+            ),
             #[cfg(feature = "enable_dyn")]
-            Source::Exec(exec) => {
-                dyn_collect_number(source_iter_exec!(scorer, exec), number, &icon_painter)
-            }
-            Source::File(fpath) => {
-                dyn_collect_number(source_iter_file!(scorer, fpath), number, &icon_painter)
-            }
-            Source::List(list) => {
-                dyn_collect_number(source_iter_list!(scorer, list), number, &icon_painter)
-            }
+            Source::Exec(exec) => dyn_collect_sorted(
+                source_iter_exec!(scorer, exec),
+                number,
+                sort_by,
+                &icon_painter,
+                &sequence,
+                &dropped,
+            ),
+            Source::File(fpath) => dyn_collect_sorted(
+                source_iter_file!(scorer, fpath),
+                number,
+                sort_by,
+                &icon_painter,
+                &sequence,
+                &dropped,
+            ),
+            Source::List(list) => dyn_collect_sorted(
+                source_iter_list!(scorer, list),
+                number,
+                sort_by,
+                &icon_painter,
+                &sequence,
+                &dropped,
+            ),
         };
 
-        let ranked = sort_initial_filtered(filtered);
+        let typo_matches = pass.typo_matches();
+        let total = total + typo_matches.len();
+        filtered.extend(typo_matches);
 
-        printer::print_dyn_filter_results(
-            ranked,
-            total,
-            number,
-            winwidth.unwrap_or(100),
-            icon_painter,
-        );
+        let read_and_match = started.elapsed();
+        let ranked = sort_filtered(filtered, sort_by, sort_dir.as_deref());
+        let ranked = if sort_by == SortBy::Score {
+            crate::sort::tiebreak(ranked, &tiebreak)
+        } else {
+            ranked
+        };
+        let sort = started.elapsed() - read_and_match;
+        let winwidth = utility::tty::display_width(winwidth, 100);
+        let progress = Progress::finished(
+            sequence.get(),
+            suggested_debounce_ms(started.elapsed(), scanned.get()),
+        )
+        .limited(dropped.get() > 0);
+
+        // The progressive updates while filtering are not grouped, only the final results.
+        if group_by_file {
+            printer::print_grouped_dyn_filter_results(
+                ranked,
+                total,
+                number,
+                winwidth,
+                icon_painter,
+                progress,
+            );
+        } else {
+            printer::print_dyn_filter_results(
+                ranked,
+                total,
+                number,
+                winwidth,
+                icon_painter,
+                progress,
+            );
+        }
+        log_timings(query, total, started, read_and_match, sort);
     } else {
-        let filtered = match source {
-            Source::Stdin => dyn_collect_all(source_iter_stdin!(scorer), &icon_painter),
+        let limit = max_retained();
+        let mut filtered = match source {
+            Source::Stdin => dyn_collect_all(
+                retain_at_most(source_iter_stdin!(scorer), limit, &dropped),
+                &icon_painter,
+                &sequence,
+            ),
             #[cfg(feature = "enable_dyn")]
-            Source::Exec(exec) => dyn_collect_all(source_iter_exec!(scorer, exec), &icon_painter),
-            Source::File(fpath) => dyn_collect_all(source_iter_file!(scorer, fpath), &icon_painter),
-            Source::List(list) => dyn_collect_all(source_iter_list!(scorer, list), &icon_painter),
+            Source::Exec(exec) => dyn_collect_all(
+                retain_at_most(source_iter_exec!(scorer, exec), limit, &dropped),
+                &icon_painter,
+                &sequence,
+            ),
+            Source::File(fpath) => dyn_collect_all(
+                retain_at_most(source_iter_file!(scorer, fpath), limit, &dropped),
+                &icon_painter,
+                &sequence,
+            ),
+            Source::List(list) => dyn_collect_all(
+                retain_at_most(source_iter_list!(scorer, list), limit, &dropped),
+                &icon_painter,
+                &sequence,
+            ),
         };
 
-        let ranked = sort_initial_filtered(filtered);
+        filtered.extend(pass.typo_matches());
+
+        let read_and_match = started.elapsed();
+        let ranked = sort_filtered(filtered, sort_by, sort_dir.as_deref());
+        let ranked = if sort_by == SortBy::Score {
+            crate::sort::tiebreak(ranked, &tiebreak)
+        } else {
+            ranked
+        };
+        let sort = started.elapsed() - read_and_match;
+        let total = ranked.len();
 
         for (item, _, indices) in ranked.into_iter() {
+            let line_number = item.line_number;
             let text = item.display_text.unwrap_or(item.raw);
-            println_json!(text, indices);
+            let mut payload = serde_json::json!({ "text": text, "indices": indices });
+            if let Some(line_number) = line_number {
+                payload["line_number"] = serde_json::json!(line_number);
+            }
+            printer::println_payload(payload);
         }
+        // The final payload follows the items, same with the final snapshot of `--number`.
+        let mut payload = serde_json::json!({ "total": total + dropped.get() });
+        Progress::finished(
+            sequence.get(),
+            suggested_debounce_ms(started.elapsed(), scanned.get()),
+        )
+        .limited(dropped.get() > 0)
+        .attach(&mut payload);
+        printer::println_payload(payload);
+        log_timings(query, total, started, read_and_match, sort);
     }
 
     Ok(())
 }
 
+/// Logs the time spent on each stage of [`dyn_run`], the source is read lazily while matching
+/// so that the two stages are measured together.
+fn log_timings(
+    query: &str,
+    total: usize,
+    started: Instant,
+    read_and_match: Duration,
+    sort: Duration,
+) {
+    let elapsed = started.elapsed();
+    log::debug!(
+        "[dyn_run] query: {:?}, matched: {}, read and match: {:?}, sort: {:?}, print: {:?}, total: {:?}",
+        query,
+        total,
+        read_and_match,
+        sort,
+        elapsed - read_and_match - sort,
+        elapsed
+    );
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_retain_at_most() {
+        let results = (0..5)
+            .map(|i| (SourceItem::from(i.to_string()), i, vec![]))
+            .collect::<Vec<FilterResult>>();
+
+        let dropped = Cell::new(0);
+        let retained = retain_at_most(results.clone().into_iter(), Some(3), &dropped).count();
+        assert_eq!((retained, dropped.get()), (3, 2));
+
+        let dropped = Cell::new(0);
+        let retained = retain_at_most(results.into_iter(), None, &dropped).count();
+        assert_eq!((retained, dropped.get()), (5, 0));
+    }
+
     #[test]
     // This is a very time-consuming test,
     // results of which could be proved only be inspecting stdout.
@@ -0,0 +1,143 @@
+//! Drops the source items whose path matches any of the exclude globs before scoring.
+//!
+//! The glob is matched against the path components, so `node_modules` excludes every path
+//! containing a `node_modules` component and `src/*.rs` excludes the rust files directly
+//! under any `src` directory.
+//!
+//! Supported wildcards:
+//!
+//! - `?` matches any single character except `/`.
+//! - `*` matches any sequence of characters except `/`.
+//! - `**` matches any sequence of characters including `/`.
+
+/// A single exclude glob.
+#[derive(Debug, Clone)]
+struct Glob {
+    pattern: Vec<char>,
+}
+
+impl Glob {
+    fn new(pattern: &str) -> Self {
+        let pattern = pattern.replace('\\', "/");
+        let pattern = pattern.trim_end_matches('/');
+        // The glob can match from any component, a leading `**/` is redundant.
+        let pattern = pattern.trim_start_matches("**/");
+        Self {
+            pattern: pattern.chars().collect(),
+        }
+    }
+
+    /// Returns true if the glob matches a run of consecutive components in `path`.
+    fn matches(&self, path: &[char]) -> bool {
+        if self.pattern.is_empty() {
+            return false;
+        }
+
+        let starts = std::iter::once(0).chain(
+            path.iter()
+                .enumerate()
+                .filter(|(_, &c)| c == '/')
+                .map(|(idx, _)| idx + 1),
+        );
+
+        starts.any(|start| {
+            let ends = path[start..]
+                .iter()
+                .enumerate()
+                .filter(|(_, &c)| c == '/')
+                .map(|(idx, _)| start + idx)
+                .chain(std::iter::once(path.len()));
+            ends.filter(|&end| end > start)
+                .any(|end| wildcard_match(&self.pattern, &path[start..end]))
+        })
+    }
+}
+
+fn wildcard_match(pattern: &[char], text: &[char]) -> bool {
+    match pattern.split_first() {
+        None => text.is_empty(),
+        Some((&'*', rest)) => match rest.split_first() {
+            Some((&'*', rest)) => (0..=text.len()).any(|idx| wildcard_match(rest, &text[idx..])),
+            _ => (0..=text.len())
+                .take_while(|&idx| idx == 0 || text[idx - 1] != '/')
+                .any(|idx| wildcard_match(rest, &text[idx..])),
+        },
+        Some((&'?', rest)) => match text.split_first() {
+            Some((&c, text)) => c != '/' && wildcard_match(rest, text),
+            None => false,
+        },
+        Some((&p, rest)) => match text.split_first() {
+            Some((&c, text)) => c == p && wildcard_match(rest, text),
+            None => false,
+        },
+    }
+}
+
+/// A set of exclude globs, the item is dropped if its path matches any of them.
+#[derive(Debug, Clone, Default)]
+pub struct ExcludeFilter {
+    globs: Vec<Glob>,
+}
+
+impl ExcludeFilter {
+    /// Constructs a new `ExcludeFilter` from the values of `--exclude`.
+    pub fn new<T: AsRef<str>>(patterns: &[T]) -> Self {
+        Self {
+            globs: patterns
+                .iter()
+                .map(|pattern| Glob::new(pattern.as_ref()))
+                .collect(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.globs.is_empty()
+    }
+
+    /// Returns true if `path` matches any of the exclude globs.
+    pub fn is_excluded(&self, path: &str) -> bool {
+        if self.globs.is_empty() {
+            return false;
+        }
+        let path = path.replace('\\', "/").chars().collect::<Vec<_>>();
+        self.globs.iter().any(|glob| glob.matches(&path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exclude_component() {
+        let filter = ExcludeFilter::new(&["node_modules", "target/"]);
+        assert!(filter.is_excluded("node_modules/foo/index.js"));
+        assert!(filter.is_excluded("web/node_modules/foo/index.js"));
+        assert!(filter.is_excluded("/home/clap/src/vim-clap/target/debug/maple"));
+        assert!(filter.is_excluded(r"crates\target\debug\maple.exe"));
+        assert!(!filter.is_excluded("crates/maple_cli/src/lib.rs"));
+        assert!(!filter.is_excluded("my_node_modules/index.js"));
+        assert!(!filter.is_excluded("targets/foo"));
+    }
+
+    #[test]
+    fn test_exclude_wildcards() {
+        let filter = ExcludeFilter::new(&["*.min.js", "src/*.rs", "gen/**/out?.txt"]);
+        assert!(filter.is_excluded("static/app.min.js"));
+        assert!(!filter.is_excluded("static/app.js"));
+        assert!(filter.is_excluded("crates/utility/src/lib.rs"));
+        assert!(!filter.is_excluded("crates/utility/src/nested/lib.rs"));
+        assert!(filter.is_excluded("gen/a/b/out1.txt"));
+        assert!(!filter.is_excluded("gen/a/b/out12.txt"));
+        assert!(ExcludeFilter::new(&["**/*.lock"]).is_excluded("Cargo.lock"));
+        assert!(ExcludeFilter::new(&["crates/**"]).is_excluded("crates/filter/src/lib.rs"));
+    }
+
+    #[test]
+    fn test_empty_filter() {
+        let filter = ExcludeFilter::new::<String>(&[]);
+        assert!(filter.is_empty());
+        assert!(!filter.is_excluded("node_modules/foo"));
+        assert!(!ExcludeFilter::new(&["/"]).is_excluded("foo"));
+    }
+}
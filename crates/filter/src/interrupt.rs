@@ -0,0 +1,18 @@
+//! Interruption of the sync filtering, e.g., by Ctrl-C.
+//!
+//! The items are no longer matched once interrupted, so that the best results collected so far
+//! can be printed instead of nothing.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Stops the ongoing sync filtering.
+pub fn interrupt() {
+    INTERRUPTED.store(true, Ordering::SeqCst);
+}
+
+/// Returns true if the filtering has been interrupted, in which case the results are partial.
+pub fn is_interrupted() -> bool {
+    INTERRUPTED.load(Ordering::Relaxed)
+}
@@ -6,18 +6,38 @@
 //! 2. sort the all lines with a match result.
 //! 3. print the top rated filtered lines to stdout.
 
+mod debounce;
 mod dynamic;
+mod exclude;
+pub mod interrupt;
+pub mod ranking;
+mod score_cache;
+mod sort;
 mod source;
+mod tail;
+mod timings;
+mod typo_fallback;
+
+use std::path::{Path, PathBuf};
 
 use anyhow::Result;
 use rayon::prelude::*;
 
 use icon::IconPainter;
-use matcher::{Algo, Bonus, MatchType, Matcher};
+use matcher::{Algo, Bonus, CaseMatching, LineCutoff, MatchType, Matcher};
 use source_item::SourceItem;
 
-pub use self::dynamic::dyn_run;
-pub use self::source::Source;
+use self::timings::elapsed_ms;
+use self::typo_fallback::MatchPass;
+
+pub use self::debounce::suggested_debounce_ms;
+pub use self::dynamic::{dyn_run, set_max_retained};
+pub use self::exclude::ExcludeFilter;
+pub use self::score_cache::sync_run_cached;
+pub use self::sort::{sort_filtered, tiebreak, SortBy, Tiebreak};
+pub use self::source::{set_max_file_size, Source};
+pub use self::tail::Tail;
+pub use self::timings::Timings;
 pub use matcher;
 #[cfg(feature = "enable_dyn")]
 pub use subprocess;
@@ -33,6 +53,13 @@ pub struct FilterContext {
     winwidth: Option<usize>,
     icon_painter: Option<IconPainter>,
     match_type: MatchType,
+    line_cutoff: Option<LineCutoff>,
+    group_by_file: bool,
+    snapshot: Option<usize>,
+    case_matching: CaseMatching,
+    sort_by: SortBy,
+    sort_dir: Option<PathBuf>,
+    tiebreak: Vec<Tiebreak>,
 }
 
 impl Default for FilterContext {
@@ -43,6 +70,13 @@ impl Default for FilterContext {
             winwidth: None,
             icon_painter: None,
             match_type: MatchType::Full,
+            line_cutoff: None,
+            group_by_file: false,
+            snapshot: None,
+            case_matching: CaseMatching::default(),
+            sort_by: SortBy::default(),
+            sort_dir: None,
+            tiebreak: Vec::new(),
         }
     }
 }
@@ -61,6 +95,13 @@ impl FilterContext {
             winwidth,
             icon_painter,
             match_type,
+            line_cutoff: None,
+            group_by_file: false,
+            snapshot: None,
+            case_matching: CaseMatching::default(),
+            sort_by: SortBy::default(),
+            sort_dir: None,
+            tiebreak: Vec::new(),
         }
     }
 
@@ -88,17 +129,102 @@ impl FilterContext {
         self.match_type = match_type;
         self
     }
+
+    pub fn line_cutoff(mut self, line_cutoff: Option<LineCutoff>) -> Self {
+        self.line_cutoff = line_cutoff;
+        self
+    }
+
+    /// Groups the final top results by the file of grep lines.
+    pub fn group_by_file(mut self, group_by_file: bool) -> Self {
+        self.group_by_file = group_by_file;
+        self
+    }
+
+    /// Scores the first `snapshot` lines of the source file and sends the top results at once
+    /// before the full filtering, so that the window is never empty for an initial query.
+    pub fn snapshot(mut self, snapshot: Option<usize>) -> Self {
+        self.snapshot = snapshot;
+        self
+    }
+
+    pub fn case_matching(mut self, case_matching: CaseMatching) -> Self {
+        self.case_matching = case_matching;
+        self
+    }
+
+    /// Sorts the final results by `sort_by`, the relative paths are resolved against `dir`.
+    pub fn sort_by(mut self, sort_by: SortBy, dir: Option<PathBuf>) -> Self {
+        self.sort_by = sort_by;
+        self.sort_dir = dir;
+        self
+    }
+
+    /// Sorts the final results of the same score by `tiebreak` when sorting by score.
+    ///
+    /// Only the top results retained by the dyn filter are tiebroken, see [`tiebreak`].
+    pub fn tiebreak(mut self, tiebreak: Vec<Tiebreak>) -> Self {
+        self.tiebreak = tiebreak;
+        self
+    }
+}
+
+/// Returns the algo matching `query` in the dyn filtering, the query containing spaces is
+/// always matched as the substrings.
+pub fn select_algo(query: &str, algo: Option<Algo>) -> Algo {
+    if query.contains(' ') {
+        Algo::SubString
+    } else {
+        algo.unwrap_or(Algo::Fzy)
+    }
 }
 
 /// Sorts the filtered result by the filter score.
 ///
-/// The item with highest score first, the item with lowest score last.
+/// The item with highest score first, the item with lowest score last. The items with the same
+/// score are kept in the source order, so that the ranking is deterministic.
 pub(crate) fn sort_initial_filtered(filtered: Vec<FilterResult>) -> Vec<FilterResult> {
     let mut filtered = filtered;
-    filtered.par_sort_unstable_by(|(_, v1, _), (_, v2, _)| v2.partial_cmp(&v1).unwrap());
+    filtered.par_sort_by(|(_, v1, _), (_, v2, _)| v2.cmp(v1));
     filtered
 }
 
+/// Returns the results of `items` matched by `matcher`, see [`MatchPass`] for the typos.
+pub(crate) fn match_items(
+    matcher: &Matcher,
+    query: &str,
+    items: Vec<SourceItem>,
+) -> Vec<FilterResult> {
+    let pass = MatchPass::new(matcher, query);
+    let filtered = items
+        .into_par_iter()
+        .filter_map(|item| pass.do_match(item))
+        .collect();
+    pass.finish(filtered)
+}
+
+/// Returns the ranked results of `items` matched by `matcher` given the query String.
+///
+/// Same ranking with [`sync_run`] but without reading any source, see also [`ranking`].
+pub fn rank(matcher: &Matcher, query: &str, items: Vec<SourceItem>) -> Vec<FilterResult> {
+    sort_initial_filtered(match_items(matcher, query, items))
+}
+
+/// Same with [`rank`] but `items` are borrowed, only the matched ones are cloned, e.g., for
+/// ranking the same items against a batch of queries.
+pub fn rank_slice(matcher: &Matcher, query: &str, items: &[SourceItem]) -> Vec<FilterResult> {
+    let pass = MatchPass::new(matcher, query);
+    let filtered = items
+        .par_iter()
+        .filter(|_| !interrupt::is_interrupted())
+        .filter_map(|item| {
+            pass.score(item)
+                .map(|(score, indices)| (item.clone(), score, indices))
+        })
+        .collect();
+    sort_initial_filtered(pass.finish(filtered))
+}
+
 /// Returns the ranked results after applying the matcher algo
 /// given the query String and filtering source.
 pub fn sync_run<I: Iterator<Item = SourceItem>>(
@@ -107,9 +233,59 @@ pub fn sync_run<I: Iterator<Item = SourceItem>>(
     algo: Algo,
     match_type: MatchType,
     bonuses: Vec<Bonus>,
+    line_cutoff: Option<LineCutoff>,
 ) -> Result<Vec<FilterResult>> {
-    let matcher = Matcher::new_with_bonuses(algo, match_type, bonuses);
+    let matcher = Matcher::new_with_bonuses(algo, match_type, bonuses).line_cutoff(line_cutoff);
+    let started = std::time::Instant::now();
     let filtered = source.filter(matcher, query)?;
+    let read_and_match = started.elapsed();
     let ranked = sort_initial_filtered(filtered);
+    log::debug!(
+        "[sync_run] query: {:?}, matched: {}, read and match: {:?}, sort: {:?}",
+        query,
+        ranked.len(),
+        read_and_match,
+        started.elapsed() - read_and_match
+    );
+    Ok(ranked)
+}
+
+/// Returns the filtered results sorted by `sort_by` given the query String and filtering source.
+pub fn sync_run_sorted<I: Iterator<Item = SourceItem>>(
+    query: &str,
+    source: Source<I>,
+    matcher: Matcher,
+    sort_by: SortBy,
+    dir: Option<&Path>,
+) -> Result<Vec<FilterResult>> {
+    // The results are in the source order before sorting.
+    let filtered = source.filter(matcher, query)?;
+    Ok(sort_filtered(filtered, sort_by, dir))
+}
+
+/// Same with [`sync_run_sorted`], but the time spent on each stage is recorded in `timings`.
+///
+/// The source is read completely before matching so that the two stages can be timed apart.
+pub fn sync_run_profiled<I: Iterator<Item = SourceItem> + 'static>(
+    query: &str,
+    source: Source<I>,
+    matcher: Matcher,
+    sort_by: SortBy,
+    dir: Option<&Path>,
+    timings: &mut Timings,
+) -> Result<Vec<FilterResult>> {
+    let started = std::time::Instant::now();
+    let items = source.into_items()?.collect::<Vec<_>>();
+    timings.read_ms = elapsed_ms(started);
+    timings.lines_scanned = items.len();
+
+    let started = std::time::Instant::now();
+    let filtered = match_items(&matcher, query, items);
+    timings.match_ms = elapsed_ms(started);
+
+    let started = std::time::Instant::now();
+    let ranked = sort_filtered(filtered, sort_by, dir);
+    timings.sort_ms = elapsed_ms(started);
+
     Ok(ranked)
 }
@@ -0,0 +1,154 @@
+//! Golden ranking of the fixture corpora in `tests/ranking`.
+//!
+//! The top results of the queries against each corpus are rendered for every combination of
+//! the algorithms and bonuses, `tests/ranking_tests.rs` checks them against the golden files and
+//! `maple rank-check --update` regenerates the golden files after an intended ranking change.
+
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+
+use matcher::{Algo, Bonus, MatchType, Matcher};
+use source_item::SourceItem;
+
+use crate::rank;
+
+/// Number of the top results recorded per query.
+pub const TOP_N: usize = 10;
+
+/// Directory of the fixture corpora and the golden files in this crate.
+pub const FIXTURES_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/ranking");
+
+/// Fixture of the lines of a provider, stored as `<name>.txt` in the fixtures dir.
+#[derive(Debug, Clone)]
+pub struct Corpus {
+    pub name: &'static str,
+    pub match_type: MatchType,
+    pub queries: &'static [&'static str],
+}
+
+pub const CORPORA: &[Corpus] = &[
+    Corpus {
+        name: "paths",
+        match_type: MatchType::Full,
+        queries: &["lib", "srcmain", "mod.rs", "cargo", "Test", "filter/src"],
+    },
+    Corpus {
+        name: "grep",
+        match_type: MatchType::IgnoreFilePath,
+        queries: &["fn new", "Result", "impl", "match", "pub struct"],
+    },
+    Corpus {
+        name: "tags",
+        match_type: MatchType::TagName,
+        queries: &["new", "run", "Matcher", "sort"],
+    },
+];
+
+pub const ALGOS: &[Algo] = &[Algo::Fzy, Algo::Skim, Algo::SubString];
+
+/// Returns the bonuses independent of the environment along with their names, unlike the
+/// filetype of current buffer or the recent files.
+pub fn bonuses() -> Vec<(&'static str, Bonus)> {
+    vec![
+        ("none", Bonus::None),
+        ("filename", Bonus::FileName),
+        ("rust", Bonus::Language("rs".into())),
+    ]
+}
+
+/// Rendered ranking of a corpus given the algorithm and bonus.
+#[derive(Debug, Clone)]
+pub struct Golden {
+    /// Path of the golden file, `golden/<corpus>_<algo>_<bonus>.txt` in the fixtures dir.
+    pub path: PathBuf,
+    pub rendered: String,
+}
+
+impl Golden {
+    /// Returns true if the golden file is identical to the rendered ranking.
+    pub fn is_up_to_date(&self) -> bool {
+        std::fs::read_to_string(&self.path)
+            .map(|recorded| recorded == self.rendered)
+            .unwrap_or(false)
+    }
+
+    pub fn write(&self) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(&self.path, &self.rendered)
+            .with_context(|| format!("Failed to write {}", self.path.display()))
+    }
+}
+
+/// Renders the top results of each query of `corpus`, one `# <query>` section per query and one
+/// `<score>\t<indices>\t<line>` line per result.
+pub fn render(corpus: &Corpus, lines: &[&str], algo: Algo, bonus: Bonus) -> String {
+    let matcher = Matcher::new(algo, corpus.match_type.clone(), bonus);
+    let mut rendered = String::new();
+    for query in corpus.queries {
+        let _ = writeln!(rendered, "# {}", query);
+        let items = lines.iter().map(|&line| SourceItem::from(line)).collect();
+        for (item, score, indices) in rank(&matcher, query, items).into_iter().take(TOP_N) {
+            let _ = writeln!(rendered, "{}\t{:?}\t{}", score, indices, item.raw);
+        }
+    }
+    rendered
+}
+
+/// Renders the ranking of all the corpora in `dir` for every combination of [`ALGOS`] and
+/// [`bonuses`].
+pub fn render_all(dir: &Path) -> Result<Vec<Golden>> {
+    let mut goldens = Vec::new();
+    for corpus in CORPORA {
+        let corpus_path = dir.join(format!("{}.txt", corpus.name));
+        let content = std::fs::read_to_string(&corpus_path)
+            .with_context(|| format!("Failed to read {}", corpus_path.display()))?;
+        let lines = content.lines().collect::<Vec<_>>();
+        for algo in ALGOS {
+            for (bonus_name, bonus) in bonuses() {
+                let name = format!(
+                    "{}_{}_{}.txt",
+                    corpus.name,
+                    format!("{:?}", algo).to_lowercase(),
+                    bonus_name
+                );
+                goldens.push(Golden {
+                    path: dir.join("golden").join(name),
+                    rendered: render(corpus, &lines, algo.clone(), bonus),
+                });
+            }
+        }
+    }
+    Ok(goldens)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let corpus = Corpus {
+            name: "test",
+            match_type: MatchType::Full,
+            queries: &["ab", "zz"],
+        };
+        // The ties are kept in the source order.
+        let rendered = render(&corpus, &["xab", "ab", "yab"], Algo::SubString, Bonus::None);
+        let lines = rendered.lines().collect::<Vec<_>>();
+        assert_eq!(lines[0], "# ab");
+        assert_eq!(lines.last(), Some(&"# zz"));
+        let ranked = lines[1..lines.len() - 1]
+            .iter()
+            .map(|line| line.rsplit('\t').next().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(ranked.len(), 3);
+        assert_eq!(
+            rendered,
+            render(&corpus, &["xab", "ab", "yab"], Algo::SubString, Bonus::None)
+        );
+    }
+}
@@ -0,0 +1,279 @@
+//! Memoized matches of the queries on a source file, shared across the invocations.
+//!
+//! The matches of a query are stored in a sidecar file named after the hash of the query, in a
+//! directory named after the source file, its size, mtime and the matcher options, including
+//! the process-wide normalize and typo settings, so that any change of them invalidates the cache:
+//!
+//! //  vim.clap/scores/<hash of source file and matcher>/<hash of query>
+//! //  <query>
+//! //  <line number> <score> <comma-separated indices>
+//! //  ...
+//!
+//! A repeated query reuses the stored scores without matching at all, a query extending a
+//! cached one only matches the lines matched by the cached one if [`Matcher::narrows_by_prefix`].
+//! The directories not written for [`SCORES_MAX_AGE`] are removed, e.g., those of the source
+//! files changed since.
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, UNIX_EPOCH};
+
+use anyhow::Result;
+
+use matcher::Matcher;
+use source_item::SourceItem;
+use utility::{cache_writer::write_atomically, calculate_hash, clap_cache_dir};
+
+use crate::{match_items, sort_initial_filtered, FilterResult, Source};
+
+const SCORES_DIR: &str = "scores";
+
+/// The scores of a source file not queried for a week are unlikely to be reused.
+const SCORES_MAX_AGE: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// The matches more than this are not stored, e.g., those of a single char query.
+const MAX_CACHED_MATCHES: usize = 100_000;
+
+/// Tuple of (line number, score, matched indices).
+type CachedMatch = (usize, i64, Vec<usize>);
+
+#[derive(Debug, Clone)]
+struct ScoreCache {
+    dir: PathBuf,
+}
+
+impl ScoreCache {
+    fn new(source_file: &Path, matcher: &Matcher) -> Result<Self> {
+        let metadata = std::fs::metadata(source_file)?;
+        let mtime = metadata
+            .modified()?
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or_default();
+        let key = (
+            source_file,
+            metadata.len(),
+            mtime,
+            format!("{:?}", matcher),
+            matcher::normalize::is_enabled(),
+            matcher::typo::is_enabled(),
+        );
+
+        let mut dir = clap_cache_dir();
+        dir.push(SCORES_DIR);
+        remove_stale_scores(&dir, SCORES_MAX_AGE);
+        dir.push(calculate_hash(&key).to_string());
+        Ok(Self { dir })
+    }
+
+    fn entry_path(&self, query: &str) -> PathBuf {
+        self.dir.join(calculate_hash(&query).to_string())
+    }
+
+    /// Returns the stored matches of `query`.
+    fn load(&self, query: &str) -> Option<Vec<CachedMatch>> {
+        let content = std::fs::read_to_string(self.entry_path(query)).ok()?;
+        let mut lines = content.lines();
+        // Guard against the hash collision.
+        if lines.next()? != query {
+            return None;
+        }
+        lines.map(parse_match).collect()
+    }
+
+    /// Returns the line numbers matched by the longest cached prefix of `query`.
+    fn load_prefix(&self, query: &str) -> Option<HashSet<usize>> {
+        query
+            .char_indices()
+            .skip(1)
+            .rev()
+            .find_map(|(idx, _)| self.load(&query[..idx]))
+            .map(|matches| matches.into_iter().map(|(lnum, _, _)| lnum).collect())
+    }
+
+    fn store(&self, query: &str, ranked: &[FilterResult]) -> Result<()> {
+        if ranked.len() > MAX_CACHED_MATCHES || query.contains('\n') {
+            return Ok(());
+        }
+        let mut content = format!("{}\n", query);
+        for (item, score, indices) in ranked {
+            let line_number = match item.line_number {
+                Some(line_number) => line_number,
+                None => return Ok(()),
+            };
+            let indices = indices
+                .iter()
+                .map(|idx| idx.to_string())
+                .collect::<Vec<_>>()
+                .join(",");
+            content.push_str(&format!("{} {} {}\n", line_number, score, indices));
+        }
+        std::fs::create_dir_all(&self.dir)?;
+        write_atomically(&self.entry_path(query), content)
+    }
+
+    /// Returns the ranked matches of `query` on `items`, which are the lines of the source file.
+    fn rank(
+        &self,
+        matcher: &Matcher,
+        query: &str,
+        items: impl Iterator<Item = SourceItem>,
+    ) -> Vec<FilterResult> {
+        if let Some(cached) = self.load(query) {
+            let mut cached = cached
+                .into_iter()
+                .map(|(line_number, score, indices)| (line_number, (score, indices)))
+                .collect::<HashMap<_, _>>();
+            let filtered = items
+                .filter_map(|item| {
+                    let (score, indices) = cached.remove(&item.line_number?)?;
+                    Some((item, score, indices))
+                })
+                .collect();
+            return sort_initial_filtered(filtered);
+        }
+
+        let items = match self
+            .load_prefix(query)
+            .filter(|_| matcher.narrows_by_prefix(query))
+        {
+            Some(candidates) => items
+                .filter(|item| {
+                    item.line_number
+                        .map_or(false, |line_number| candidates.contains(&line_number))
+                })
+                .collect(),
+            None => items.collect(),
+        };
+        let ranked = sort_initial_filtered(match_items(matcher, query, items));
+
+        // The partial results of an interrupted filtering are not cached.
+        if !crate::interrupt::is_interrupted() {
+            if let Err(e) = self.store(query, &ranked) {
+                log::error!("Failed to write the score cache: {:?}", e);
+            }
+        }
+
+        ranked
+    }
+}
+
+/// Removes the directories of the scores in `scores_dir` modified more than `max_age` ago.
+fn remove_stale_scores(scores_dir: &Path, max_age: Duration) {
+    let entries = match std::fs::read_dir(scores_dir) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+    for path in entries.filter_map(|e| e.ok()).map(|e| e.path()) {
+        let is_stale = std::fs::metadata(&path)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|modified| modified.elapsed().ok())
+            .map_or(false, |age| age > max_age);
+        if is_stale {
+            // It may be removed by another process at the same time.
+            if let Err(e) = std::fs::remove_dir_all(&path) {
+                log::debug!(
+                    "Failed to remove the stale scores {}: {}",
+                    path.display(),
+                    e
+                );
+            }
+        }
+    }
+}
+
+fn parse_match(line: &str) -> Option<CachedMatch> {
+    let mut fields = line.splitn(3, ' ');
+    let line_number = fields.next()?.parse().ok()?;
+    let score = fields.next()?.parse().ok()?;
+    let indices = fields
+        .next()?
+        .split(',')
+        .filter(|idx| !idx.is_empty())
+        .map(|idx| idx.parse().ok())
+        .collect::<Option<_>>()?;
+    Some((line_number, score, indices))
+}
+
+/// Returns the ranked results of `query` on the lines of `source_file`, reusing the matches of
+/// the same query or its prefix computed before on the unchanged file.
+///
+/// Same results with [`crate::sync_run`], the cache is bypassed with the typo tolerance on.
+pub fn sync_run_cached(
+    query: &str,
+    source_file: PathBuf,
+    matcher: Matcher,
+) -> Result<Vec<FilterResult>> {
+    if matcher::typo::is_enabled() {
+        let filtered =
+            Source::<std::iter::Empty<SourceItem>>::File(source_file).filter(matcher, query)?;
+        return Ok(sort_initial_filtered(filtered));
+    }
+    let cache = ScoreCache::new(&source_file, &matcher)?;
+    let items = Source::<std::iter::Empty<SourceItem>>::File(source_file).into_items()?;
+    Ok(cache.rank(&matcher, query, items))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use matcher::{Algo, Bonus, MatchType};
+
+    #[test]
+    fn test_score_cache() {
+        let cache = ScoreCache {
+            dir: std::env::temp_dir().join(format!("clap_test_scores_{}", std::process::id())),
+        };
+        let matcher = Matcher::new(Algo::Fzy, MatchType::Full, Bonus::None);
+        let lines = ["src/lib.rs", "src/main.rs", "README.md", "Cargo.toml"];
+        let items = || {
+            lines.iter().enumerate().map(|(idx, line)| {
+                let mut item = SourceItem::from(*line);
+                item.line_number = Some(idx + 1);
+                item
+            })
+        };
+        let texts = |ranked: Vec<FilterResult>| {
+            ranked
+                .into_iter()
+                .map(|(item, _, _)| item.raw)
+                .collect::<Vec<_>>()
+        };
+
+        let ranked = cache.rank(&matcher, "sr", items());
+        assert_eq!(cache.load("sr").unwrap().len(), ranked.len());
+        assert_eq!(texts(cache.rank(&matcher, "sr", items())), texts(ranked));
+
+        // Only the matches of `sr` are matched against `src`.
+        assert_eq!(cache.load_prefix("src").unwrap().len(), 2);
+        assert_eq!(
+            texts(cache.rank(&matcher, "srcr", items())),
+            texts(sort_initial_filtered(match_items(
+                &matcher,
+                "srcr",
+                items().collect()
+            )))
+        );
+        assert!(!matcher.narrows_by_prefix("sr|md"));
+
+        std::fs::remove_dir_all(&cache.dir).unwrap();
+    }
+
+    #[test]
+    fn test_remove_stale_scores() {
+        let scores_dir =
+            std::env::temp_dir().join(format!("clap_test_stale_scores_{}", std::process::id()));
+        let entry_dir = scores_dir.join("1");
+        std::fs::create_dir_all(&entry_dir).unwrap();
+        std::fs::write(entry_dir.join("2"), "sr\n1 10 0,1\n").unwrap();
+
+        remove_stale_scores(&scores_dir, SCORES_MAX_AGE);
+        assert!(entry_dir.exists());
+        std::thread::sleep(Duration::from_millis(10));
+        remove_stale_scores(&scores_dir, Duration::from_millis(1));
+        assert!(!entry_dir.exists());
+
+        std::fs::remove_dir_all(&scores_dir).unwrap();
+    }
+}
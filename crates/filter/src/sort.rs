@@ -0,0 +1,221 @@
+//! Order of the filtered results.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::str::FromStr;
+use std::time::SystemTime;
+
+use anyhow::{anyhow, Error};
+use structopt::clap::arg_enum;
+
+use source_item::SourceItem;
+
+use crate::{sort_initial_filtered, FilterResult};
+
+arg_enum! {
+  /// How the filtered results are sorted.
+  ///
+  /// `Path` and `Mtime` use the path of grep line or the whole line otherwise, the newest file
+  /// first for `Mtime`. `None` keeps the order of source, e.g., the commits of git log.
+  #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+  pub enum SortBy {
+      Score,
+      Path,
+      Mtime,
+      None,
+  }
+}
+
+impl Default for SortBy {
+    fn default() -> Self {
+        Self::Score
+    }
+}
+
+/// Criterion of `--tiebreak` for the results of the same score.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tiebreak {
+    /// Prefers the shorter line.
+    Length,
+    /// Prefers the line matched closer to the beginning, e.g., the tag name or the code at the
+    /// start of the buffer line.
+    Begin,
+    /// Prefers the line matched closer to the end.
+    End,
+    /// Prefers the line first in the source, the default.
+    Index,
+}
+
+impl FromStr for Tiebreak {
+    type Err = Error;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.to_lowercase().as_str() {
+            "length" => Ok(Self::Length),
+            "begin" => Ok(Self::Begin),
+            "end" => Ok(Self::End),
+            "index" => Ok(Self::Index),
+            _ => Err(anyhow!("Unsupported tiebreak: {}", name)),
+        }
+    }
+}
+
+impl Tiebreak {
+    fn key(self, (item, _, indices): &FilterResult) -> usize {
+        match self {
+            Self::Length => item.raw.chars().count(),
+            Self::Begin => indices.first().copied().unwrap_or_default(),
+            Self::End => {
+                let last = indices.last().map_or(0, |&idx| idx + 1);
+                item.raw.chars().count().saturating_sub(last)
+            }
+            Self::Index => 0,
+        }
+    }
+}
+
+/// Sorts the results of the same score by `tiebreaks`, the source order is kept otherwise.
+///
+/// The criteria after `Index` are never reached, hence ignored.
+pub fn tiebreak(mut ranked: Vec<FilterResult>, tiebreaks: &[Tiebreak]) -> Vec<FilterResult> {
+    let tiebreaks = tiebreaks
+        .iter()
+        .take_while(|&&tiebreak| tiebreak != Tiebreak::Index)
+        .collect::<Vec<_>>();
+    if !tiebreaks.is_empty() {
+        ranked.sort_by(|a, b| {
+            b.1.cmp(&a.1).then_with(|| {
+                tiebreaks
+                    .iter()
+                    .map(|tiebreak| tiebreak.key(a).cmp(&tiebreak.key(b)))
+                    .find(|ordering| ordering.is_ne())
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            })
+        });
+    }
+    ranked
+}
+
+/// Returns the path in `item`, the file path of a grep line or the whole line otherwise.
+fn path_of(item: &SourceItem) -> &str {
+    pattern::parse_grep_line(&item.raw)
+        .map(|(path, ..)| path)
+        .unwrap_or(&item.raw)
+}
+
+/// Sorts the results in the source order by `sort_by`.
+///
+/// The relative paths are resolved against `dir` or the current directory for `Mtime`, the
+/// results sharing a path are kept in the source order.
+pub fn sort_filtered(
+    mut filtered: Vec<FilterResult>,
+    sort_by: SortBy,
+    dir: Option<&Path>,
+) -> Vec<FilterResult> {
+    match sort_by {
+        SortBy::Score => return sort_initial_filtered(filtered),
+        SortBy::Path => filtered.sort_by(|(a, _, _), (b, _, _)| path_of(a).cmp(path_of(b))),
+        SortBy::Mtime => {
+            // Stat each path only once as a file can have many grep lines.
+            let mut mtimes: HashMap<String, Option<SystemTime>> = HashMap::new();
+            for (item, _, _) in filtered.iter() {
+                let path = path_of(item);
+                if !mtimes.contains_key(path) {
+                    let full_path = match dir {
+                        Some(dir) => dir.join(path),
+                        None => path.into(),
+                    };
+                    let mtime = std::fs::metadata(full_path)
+                        .and_then(|metadata| metadata.modified())
+                        .ok();
+                    mtimes.insert(path.into(), mtime);
+                }
+            }
+            // The files failed to stat are put at the end.
+            filtered.sort_by(|(a, _, _), (b, _, _)| mtimes[path_of(b)].cmp(&mtimes[path_of(a)]));
+        }
+        SortBy::None => {}
+    }
+    filtered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sorted_lines(lines: &[&str], sort_by: SortBy, dir: Option<&Path>) -> Vec<String> {
+        let filtered = lines
+            .iter()
+            .enumerate()
+            .map(|(idx, line)| (SourceItem::from(*line), idx as i64, Vec::new()))
+            .collect();
+        sort_filtered(filtered, sort_by, dir)
+            .into_iter()
+            .map(|(item, _, _)| item.raw)
+            .collect()
+    }
+
+    #[test]
+    fn test_sort_filtered() {
+        let lines = ["src/b.rs:2:1:foo", "src/a.rs:9:1:foo", "src/b.rs:1:1:foo"];
+        assert_eq!(
+            sorted_lines(&lines, SortBy::Score, None),
+            vec!["src/b.rs:1:1:foo", "src/a.rs:9:1:foo", "src/b.rs:2:1:foo"]
+        );
+        assert_eq!(
+            sorted_lines(&lines, SortBy::Path, None),
+            vec!["src/a.rs:9:1:foo", "src/b.rs:2:1:foo", "src/b.rs:1:1:foo"]
+        );
+        assert_eq!(sorted_lines(&lines, SortBy::None, None), lines);
+
+        let dir = std::env::temp_dir().join(format!("clap_test_sort_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("old.txt"), "").unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        std::fs::write(dir.join("new.txt"), "").unwrap();
+        assert_eq!(
+            sorted_lines(
+                &["missing.txt", "old.txt", "new.txt"],
+                SortBy::Mtime,
+                Some(&dir)
+            ),
+            vec!["new.txt", "old.txt", "missing.txt"]
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_tiebreak() {
+        let ranked = || {
+            vec![
+                ("long line".into(), 1, vec![8]),
+                ("line".into(), 1, vec![1]),
+                ("best".into(), 2, vec![3]),
+            ]
+        };
+        let tiebroken = |tiebreaks: &[Tiebreak]| {
+            tiebreak(ranked(), tiebreaks)
+                .into_iter()
+                .map(|(item, _, _): FilterResult| item.raw)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            tiebroken(&[Tiebreak::Length]),
+            vec!["best", "line", "long line"]
+        );
+        assert_eq!(
+            tiebroken(&[Tiebreak::Begin]),
+            vec!["best", "line", "long line"]
+        );
+        assert_eq!(
+            tiebroken(&[Tiebreak::End, Tiebreak::Length]),
+            vec!["best", "long line", "line"]
+        );
+        assert_eq!(
+            tiebroken(&[Tiebreak::Index, Tiebreak::Length]),
+            vec!["long line", "line", "best"]
+        );
+        assert_eq!("Begin".parse::<Tiebreak>().unwrap(), Tiebreak::Begin);
+        assert!("score".parse::<Tiebreak>().is_err());
+    }
+}
@@ -1,11 +1,294 @@
-use std::io::BufRead;
-use std::path::PathBuf;
+use std::fs::File;
+use std::io::{self, BufRead, Read};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
 
 #[cfg(feature = "enable_dyn")]
 use subprocess::Exec;
 
+use printer::warning::Warning;
+
 use super::*;
 
+/// Interval of polling the cache file that is still being written.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Stop following the cache file if it has not grown for such a long time.
+const FOLLOW_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Default of the size beyond which `Source::File` is no longer read into memory at once.
+const DEFAULT_MAX_FILE_SIZE: u64 = 100 * 1024 * 1024;
+
+static MAX_FILE_SIZE: AtomicU64 = AtomicU64::new(DEFAULT_MAX_FILE_SIZE);
+
+/// Sets the size in bytes beyond which `Source::File` is streamed line by line instead of being
+/// read into memory at once.
+pub fn set_max_file_size(size: Option<u64>) {
+    MAX_FILE_SIZE.store(size.unwrap_or(DEFAULT_MAX_FILE_SIZE), Ordering::Relaxed);
+}
+
+fn exceeds_max_file_size(path: &Path) -> bool {
+    std::fs::metadata(path)
+        .map(|metadata| metadata.len() > MAX_FILE_SIZE.load(Ordering::Relaxed))
+        .unwrap_or(false)
+}
+
+/// Counter of the lines of `Source::File` skipped as binary junk.
+///
+/// The skipped lines are reported as [`Warning::BinaryLinesSkipped`] once the counter is dropped,
+/// i.e., the file has been read. The counter is shared by the ranges of a file filtered in
+/// parallel.
+struct BinaryLines {
+    path: PathBuf,
+    skipped: AtomicUsize,
+}
+
+impl BinaryLines {
+    fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            skipped: AtomicUsize::new(0),
+        }
+    }
+
+    /// Returns true if `line` contains NUL, which is never in a text file.
+    fn skip(&self, line: &[u8]) -> bool {
+        let is_binary = line.contains(&0);
+        if is_binary {
+            self.skipped.fetch_add(1, Ordering::Relaxed);
+        }
+        is_binary
+    }
+}
+
+impl Drop for BinaryLines {
+    fn drop(&mut self) {
+        let count = *self.skipped.get_mut();
+        if count > 0 {
+            printer::warning::warn(Warning::BinaryLinesSkipped {
+                path: std::mem::take(&mut self.path),
+                count,
+            });
+        }
+    }
+}
+
+/// Reader of the partial cache file that keeps reading until the writer is done, like `tail -f`.
+///
+/// The partial cache file is renamed or removed once the writer finishes, the opened
+/// file handle is still valid afterwards so that the rest content can be drained.
+struct FollowReader {
+    path: PathBuf,
+    file: File,
+    last_growth: Instant,
+}
+
+impl FollowReader {
+    fn open(path: &Path) -> io::Result<Self> {
+        Ok(Self {
+            path: path.to_path_buf(),
+            file: File::open(path)?,
+            last_growth: Instant::now(),
+        })
+    }
+}
+
+impl Read for FollowReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            let n = self.file.read(buf)?;
+            if n > 0 {
+                self.last_growth = Instant::now();
+                return Ok(n);
+            }
+            if !self.path.exists() || self.last_growth.elapsed() > FOLLOW_TIMEOUT {
+                // The content written before the writer finishes may be unread.
+                return self.file.read(buf);
+            }
+            std::thread::sleep(POLL_INTERVAL);
+        }
+    }
+}
+
+/// Opens the file of `Source::File`, follows it if it's still being written by the forerunner.
+///
+/// The partial cache file is never compressed, the complete one is decompressed transparently.
+pub(crate) fn open_source_file(path: &Path) -> io::Result<Box<dyn Read>> {
+    let path: &Path = &utility::paths::long_path(path);
+    if utility::is_partial_cache(path) {
+        Ok(Box::new(FollowReader::open(path)?))
+    } else {
+        utility::compression::open_decompressed(path)
+    }
+}
+
+/// Reads the whole content of `Source::File`.
+fn read_source_file(path: &Path) -> io::Result<Vec<u8>> {
+    let mut content = Vec::new();
+    open_source_file(path)?.read_to_end(&mut content)?;
+    Ok(content)
+}
+
+/// Maps the content of `Source::File` into memory, `None` if it can't be filtered in place.
+///
+/// Only the complete cache entries of maple are mapped, the other files may be truncated by
+/// other processes while being mapped. The compressed cache entry has to be decoded, the empty
+/// file can't be mapped on some platforms.
+#[cfg(feature = "mmap")]
+fn map_source_file(path: &Path) -> io::Result<Option<memmap2::Mmap>> {
+    if !utility::is_cache_entry(path) {
+        return Ok(None);
+    }
+    let file = File::open(utility::paths::long_path(path))?;
+    if file.metadata()?.len() == 0 {
+        return Ok(None);
+    }
+    // SAFETY: the cache entries are written to a temp file and renamed into place, never
+    // modified or truncated in place, the mapped file stays intact even if it's replaced or
+    // removed meanwhile.
+    let content = unsafe { memmap2::Mmap::map(&file)? };
+    if utility::compression::Compression::detect(&content)
+        != utility::compression::Compression::None
+    {
+        return Ok(None);
+    }
+    Ok(Some(content))
+}
+
+/// Number of the ranges per thread the mapped file is split into, more ranges balance the
+/// workload better as the matched lines are not evenly distributed.
+#[cfg(feature = "mmap")]
+const RANGES_PER_THREAD: usize = 4;
+
+/// Splits `content` into about `n` byte ranges, each range except the last one ends with `\n`.
+#[cfg(feature = "mmap")]
+fn split_ranges(content: &[u8], n: usize) -> Vec<&[u8]> {
+    let range_size = (content.len() / n.max(1)).max(1);
+    let mut ranges = Vec::with_capacity(n);
+    let mut rest = content;
+    while !rest.is_empty() {
+        let end = rest
+            .get(range_size - 1..)
+            .and_then(|tail| tail.iter().position(|&b| b == b'\n'))
+            .map(|pos| range_size + pos)
+            .unwrap_or_else(|| rest.len());
+        let (range, tail) = rest.split_at(end);
+        ranges.push(range);
+        rest = tail;
+    }
+    ranges
+}
+
+/// Returns the lines of `range` along with the line numbers starting from `first_line_number`,
+/// the binary lines are skipped as [`numbered_lines`].
+#[cfg(feature = "mmap")]
+fn mapped_lines<'a>(
+    range: &'a [u8],
+    first_line_number: usize,
+    binary_lines: &'a BinaryLines,
+) -> impl Iterator<Item = (usize, &'a [u8])> {
+    let range = range.strip_suffix(b"\n").unwrap_or(range);
+    range
+        .split(|&b| b == b'\n')
+        .enumerate()
+        .filter(move |(_, line)| !binary_lines.skip(line))
+        .map(move |(idx, line)| (first_line_number + idx, trim_line_ending(line)))
+}
+
+/// Filters the lines of `content` mapped from `path`, each range of [`split_ranges`] is scored
+/// on its own rayon task.
+///
+/// `do_match_line` is given the line borrowed from `content` and its line number.
+#[cfg(feature = "mmap")]
+fn filter_mapped(
+    path: &Path,
+    content: &[u8],
+    do_match_line: impl Fn(&[u8], usize) -> Option<FilterResult> + Sync,
+) -> Vec<FilterResult> {
+    let ranges = split_ranges(content, rayon::current_num_threads() * RANGES_PER_THREAD);
+
+    // Each range starts right after the lines of the previous ranges.
+    let line_counts = ranges
+        .par_iter()
+        .map(|range| range.iter().filter(|&&b| b == b'\n').count())
+        .collect::<Vec<_>>();
+    let first_line_numbers = line_counts
+        .iter()
+        .scan(1, |next, count| {
+            let first = *next;
+            *next += count;
+            Some(first)
+        })
+        .collect::<Vec<_>>();
+
+    let binary_lines = BinaryLines::new(path);
+    ranges
+        .into_par_iter()
+        .zip(first_line_numbers)
+        .flat_map_iter(|(range, first_line_number)| {
+            mapped_lines(range, first_line_number, &binary_lines)
+                .filter_map(|(line_number, line)| do_match_line(line, line_number))
+        })
+        .collect()
+}
+
+/// Strips the trailing `\n` or `\r\n` of a line, same with [`BufRead::lines`].
+pub(crate) fn trim_line_ending(line: &[u8]) -> &[u8] {
+    let line = line.strip_suffix(b"\n").unwrap_or(line);
+    line.strip_suffix(b"\r").unwrap_or(line)
+}
+
+/// Returns an iterator of `SourceItem` from the lines of `reader`.
+///
+/// Unlike [`BufRead::lines`], the lines containing invalid UTF-8 are not dropped but decoded
+/// lossily, see [`SourceItem::from_bytes`].
+pub(crate) fn lossy_lines<R: BufRead>(reader: R) -> impl Iterator<Item = SourceItem> {
+    reader
+        .split(b'\n')
+        .filter_map(|line| line.ok())
+        .map(|line| SourceItem::from_bytes(trim_line_ending(&line)))
+}
+
+/// Matches the lines of `reader` by `do_match_line` as the slices of a reused buffer, so that
+/// only the matched lines are allocated as the items.
+fn filter_lines<R: BufRead>(
+    mut reader: R,
+    do_match_line: impl Fn(&[u8]) -> Option<FilterResult>,
+) -> Vec<FilterResult> {
+    let mut filtered = Vec::new();
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        match reader.read_until(b'\n', &mut line) {
+            Ok(0) | Err(_) => break,
+            Ok(_) => filtered.extend(do_match_line(trim_line_ending(&line))),
+        }
+    }
+    filtered
+}
+
+/// Returns an iterator of `SourceItem` from the lines of `Source::File` at `path`, each item
+/// carries its line number.
+///
+/// The binary lines are skipped, the line numbers of the rest are still the ones in the file.
+pub(crate) fn numbered_lines<R: BufRead>(
+    path: &Path,
+    reader: R,
+) -> impl Iterator<Item = SourceItem> {
+    let binary_lines = BinaryLines::new(path);
+    reader
+        .split(b'\n')
+        .filter_map(|line| line.ok())
+        .enumerate()
+        .filter(move |(_, line)| !binary_lines.skip(line))
+        .map(|(idx, line)| {
+            let mut item = SourceItem::from_bytes(trim_line_ending(&line));
+            item.line_number = Some(idx + 1);
+            item
+        })
+}
+
 /// Source is anything that can produce an iterator of String.
 #[derive(Debug)]
 pub enum Source<I: Iterator<Item = SourceItem>> {
@@ -29,48 +312,414 @@ impl<I: Iterator<Item = SourceItem>> From<Exec> for Source<I> {
     }
 }
 
+/// Converts the line into a `SourceItem` whose display and match text is relative to `dir`.
+///
+/// The original line is kept as the raw text of `SourceItem`.
+fn relative_item(item: SourceItem, dir: &Path, match_type: &MatchType) -> SourceItem {
+    // The display text supplied by the provider is kept as is.
+    if item.display_index_map.is_some() {
+        return item;
+    }
+    match utility::strip_dir_prefix(&item.raw, dir) {
+        Some(relative) => {
+            let relative: SourceItem = relative.into();
+            let match_text = relative
+                .match_text_for(match_type)
+                .map(|(text, offset)| (text.to_string(), offset));
+            let mut relative_item = SourceItem::new(item.raw, match_text, Some(relative.raw));
+            relative_item.lossy = item.lossy;
+            relative_item.line_number = item.line_number;
+            relative_item.user_data = item.user_data;
+            relative_item
+        }
+        None => item,
+    }
+}
+
+/// Converts the line `<filter text><delimiter><display text>` into a `SourceItem` that is
+/// matched against the filter text but displayed as the display text.
+///
+/// The filter text is kept as the raw text, the line without `delimiter` is left untouched.
+fn display_item(item: SourceItem, delimiter: char) -> SourceItem {
+    let (filter_text, display_text) = match item.raw.split_once(delimiter) {
+        Some((filter_text, display_text)) => (filter_text.to_string(), display_text.to_string()),
+        None => return item,
+    };
+    let mut display_item = SourceItem::with_display(filter_text.clone(), filter_text, display_text);
+    display_item.lossy = item.lossy;
+    display_item.line_number = item.line_number;
+    display_item.user_data = item.user_data;
+    display_item
+}
+
+/// Returns the path part of `item` that is checked by the exclude globs.
+fn path_for_exclude<'a>(item: &'a SourceItem, match_type: &MatchType) -> &'a str {
+    match match_type {
+        MatchType::IgnoreFilePath => {
+            pattern::extract_fpath_from_grep_line(&item.raw).unwrap_or(&item.raw)
+        }
+        _ => &item.raw,
+    }
+}
+
+impl<I: Iterator<Item = SourceItem> + 'static> Source<I> {
+    /// Converts all kinds of Source into an iterator of `SourceItem`.
+    pub fn into_items(self) -> Result<Box<dyn Iterator<Item = SourceItem>>> {
+        let items: Box<dyn Iterator<Item = SourceItem>> = match self {
+            Self::Stdin => Box::new(lossy_lines(io::BufReader::new(io::stdin()))),
+            #[cfg(feature = "enable_dyn")]
+            Self::Exec(exec_cmd) => {
+                Box::new(lossy_lines(io::BufReader::new(exec_cmd.stream_stdout()?)))
+            }
+            Self::File(fpath) => Box::new(numbered_lines(
+                &fpath,
+                io::BufReader::new(open_source_file(&fpath)?),
+            )),
+            Self::List(list) => Box::new(list),
+        };
+        Ok(items)
+    }
+
+    /// Rewrites the items whose path is in `dir` relative to `dir` for display and matching.
+    pub fn relative_to(
+        self,
+        dir: PathBuf,
+        match_type: MatchType,
+    ) -> Result<Source<Box<dyn Iterator<Item = SourceItem>>>> {
+        Ok(Source::List(Box::new(
+            self.into_items()?
+                .map(move |item| relative_item(item, &dir, &match_type)),
+        )))
+    }
+
+    /// Splits each line into the filter text and the display text by `delimiter`.
+    pub fn split_display(
+        self,
+        delimiter: char,
+    ) -> Result<Source<Box<dyn Iterator<Item = SourceItem>>>> {
+        Ok(Source::List(Box::new(
+            self.into_items()?
+                .map(move |item| display_item(item, delimiter)),
+        )))
+    }
+
+    /// Numbers the items by their line numbers in the input, the items of `Source::File` are
+    /// numbered already.
+    pub fn numbered(self) -> Result<Source<Box<dyn Iterator<Item = SourceItem>>>> {
+        match self {
+            Self::File(fpath) => Ok(Source::File(fpath)),
+            source => Ok(Source::List(Box::new(
+                source.into_items()?.enumerate().map(|(idx, mut item)| {
+                    item.line_number.get_or_insert(idx + 1);
+                    item
+                }),
+            ))),
+        }
+    }
+
+    /// Drops the items whose path matches any glob of `exclude_filter` before scoring.
+    ///
+    /// The path of grep line is extracted when `match_type` is `IgnoreFilePath`, otherwise
+    /// the whole line is treated as the path.
+    pub fn exclude(
+        self,
+        exclude_filter: ExcludeFilter,
+        match_type: MatchType,
+    ) -> Result<Source<Box<dyn Iterator<Item = SourceItem>>>> {
+        Ok(Source::List(Box::new(self.into_items()?.filter(
+            move |item| !exclude_filter.is_excluded(path_for_exclude(item, &match_type)),
+        ))))
+    }
+}
+
 impl<I: Iterator<Item = SourceItem>> Source<I> {
     /// Returns the complete filtered results after applying the specified
     /// matcher algo on each item in the input stream.
     ///
     /// This is kind of synchronous filtering, can be used for multi-staged processing.
+    ///
+    /// With the `mmap` feature, the plain file is filtered in place so that the large cache
+    /// file is never read into memory as a whole.
+    ///
+    /// The lines of a file, stdin and the command output are matched as the borrowed slices,
+    /// only the matched lines are allocated as the items unless the typos are tolerated.
     pub fn filter(self, matcher: Matcher, query: &str) -> Result<Vec<FilterResult>> {
-        let do_match = |line: &str| matcher.do_match(&line.into(), query);
+        let pass = MatchPass::new(&matcher, query);
+        let do_match_item = |item: SourceItem| pass.do_match(item);
+        let do_match_line = |line: &str, line_number: Option<usize>| {
+            if pass.typo_tolerant() {
+                let mut item: SourceItem = line.into();
+                item.line_number = line_number;
+                return do_match_item(item);
+            }
+            if crate::interrupt::is_interrupted() {
+                return None;
+            }
+            matcher
+                .match_line(line, query)
+                .map(|(mut item, score, indices)| {
+                    item.line_number = line_number;
+                    (item, score, indices)
+                })
+        };
+        // Only the lines containing invalid UTF-8 are decoded into the items before matching.
+        let do_match_bytes =
+            |line: &[u8], line_number: Option<usize>| match std::str::from_utf8(line) {
+                Ok(line) => do_match_line(line, line_number),
+                Err(_) => {
+                    let mut item = SourceItem::from_bytes(line);
+                    item.line_number = line_number;
+                    do_match_item(item)
+                }
+            };
+
+        #[cfg(feature = "mmap")]
+        if let Self::File(ref fpath) = self {
+            if let Some(content) = map_source_file(fpath)? {
+                let filtered = filter_mapped(fpath, &content, |line, line_number| {
+                    do_match_bytes(line, Some(line_number))
+                });
+                return Ok(pass.finish(filtered));
+            }
+        }
 
         let filtered = match self {
-            Self::Stdin => std::io::stdin()
-                .lock()
-                .lines()
-                .filter_map(|lines_iter| {
-                    lines_iter.ok().and_then(|line| {
-                        do_match(&line).map(|(score, indices)| (line.into(), score, indices))
-                    })
-                })
-                .collect::<Vec<_>>(),
+            Self::Stdin => filter_lines(std::io::stdin().lock(), |line| do_match_bytes(line, None)),
             #[cfg(feature = "enable_dyn")]
-            Self::Exec(exec_cmd) => std::io::BufReader::new(exec_cmd.stream_stdout()?)
-                .lines()
-                .filter_map(|lines_iter| {
-                    lines_iter.ok().and_then(|line| {
-                        do_match(&line).map(|(score, indices)| (line.into(), score, indices))
-                    })
-                })
-                .collect::<Vec<_>>(),
-            Self::File(fpath) => std::fs::read_to_string(fpath)?
-                .par_lines()
-                .filter_map(|line| {
-                    do_match(&line)
-                        .map(|(score, indices)| (line.to_string().into(), score, indices))
-                })
-                .collect::<Vec<_>>(),
-            Self::List(list) => list
-                .filter_map(|item| {
-                    let line = item.match_text();
-                    do_match(line).map(|(score, indices)| (line.into(), score, indices))
+            Self::Exec(exec_cmd) => {
+                filter_lines(std::io::BufReader::new(exec_cmd.stream_stdout()?), |line| {
+                    do_match_bytes(line, None)
                 })
-                .collect::<Vec<_>>(),
+            }
+            // The large file is matched while being read, at the cost of parallelism.
+            Self::File(fpath) if exceeds_max_file_size(&fpath) => {
+                numbered_lines(&fpath, io::BufReader::new(open_source_file(&fpath)?))
+                    .filter_map(do_match_item)
+                    .collect::<Vec<_>>()
+            }
+            Self::File(fpath) => match String::from_utf8(read_source_file(&fpath)?) {
+                Ok(content) => {
+                    let binary_lines = BinaryLines::new(&fpath);
+                    content
+                        .lines()
+                        .enumerate()
+                        .filter(|(_, line)| !binary_lines.skip(line.as_bytes()))
+                        .collect::<Vec<_>>()
+                        .into_par_iter()
+                        .filter_map(|(idx, line)| do_match_line(line, Some(idx + 1)))
+                        .collect::<Vec<_>>()
+                }
+                // Only the files containing invalid UTF-8 take the slow path.
+                Err(e) => numbered_lines(&fpath, e.as_bytes())
+                    .collect::<Vec<_>>()
+                    .into_par_iter()
+                    .filter_map(do_match_item)
+                    .collect::<Vec<_>>(),
+            },
+            Self::List(list) => list.filter_map(do_match_item).collect::<Vec<_>>(),
         };
 
-        Ok(filtered)
+        Ok(pass.finish(filtered))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_follow_partial_cache() {
+        let mut dir = std::env::temp_dir();
+        dir.push(format!("clap_test_follow_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let partial = dir.join(utility::PARTIAL_CACHE_ENTRY);
+
+        let mut file = File::create(&partial).unwrap();
+        file.write_all(b"line1\n").unwrap();
+
+        let writer_partial = partial.clone();
+        let writer = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(200));
+            file.write_all(b"line2\n").unwrap();
+            std::fs::rename(&writer_partial, writer_partial.with_file_name("1_2")).unwrap();
+        });
+
+        let content = read_source_file(&partial).unwrap();
+        writer.join().unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(content, b"line1\nline2\n");
+    }
+
+    #[test]
+    fn test_lossy_lines() {
+        let items = lossy_lines(&b"foo\r\nb\xffr\n\ncaf\xe9"[..]).collect::<Vec<_>>();
+        let lines = items
+            .iter()
+            .map(|item| (item.raw.as_str(), item.lossy))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            lines,
+            vec![
+                ("foo", false),
+                ("b\u{FFFD}r", true),
+                ("", false),
+                ("caf\u{FFFD}", true)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_filter_lines() {
+        let filtered = filter_lines(&b"foo\r\nbar\n\nfoobar"[..], |line| {
+            if line.starts_with(b"foo") {
+                Some((SourceItem::from_bytes(line), 0, Vec::new()))
+            } else {
+                None
+            }
+        });
+        let lines = filtered
+            .iter()
+            .map(|(item, _, _)| item.raw.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(lines, vec!["foo", "foobar"]);
+    }
+
+    #[test]
+    fn test_filter_mixed_encoding_file() {
+        let path = std::env::temp_dir().join(format!("clap_test_mixed_{}", std::process::id()));
+        std::fs::write(
+            &path,
+            b"utf8 line\nlatin1 caf\xe9 line\nshift_jis \x83e\x83X\x83g\n",
+        )
+        .unwrap();
+
+        let matcher = Matcher::new(Algo::Fzy, MatchType::Full, Bonus::None);
+        let ranked = Source::<std::iter::Empty<SourceItem>>::File(path.clone())
+            .filter(matcher, "line")
+            .unwrap();
+        let mut matched = ranked
+            .iter()
+            .map(|(item, _, _)| (item.raw.as_str(), item.lossy))
+            .collect::<Vec<_>>();
+        matched.sort();
+        assert_eq!(
+            matched,
+            vec![("latin1 caf\u{FFFD} line", true), ("utf8 line", false)]
+        );
+
+        // The indices are against the decoded text.
+        let matcher = Matcher::new(Algo::Fzy, MatchType::Full, Bonus::None);
+        let ranked = Source::<std::iter::Empty<SourceItem>>::File(path.clone())
+            .filter(matcher, "jis")
+            .unwrap();
+        assert_eq!(ranked.len(), 1);
+        assert!(ranked[0].0.lossy);
+        assert_eq!(ranked[0].2, vec![6, 7, 8]);
+
+        let items = Source::<std::iter::Empty<SourceItem>>::File(path.clone())
+            .into_items()
+            .unwrap()
+            .collect::<Vec<_>>();
+        assert_eq!(items.len(), 3);
+        assert_eq!(items.iter().filter(|item| item.lossy).count(), 2);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_file_line_numbers() {
+        let path = std::env::temp_dir().join(format!("clap_test_lnum_{}", std::process::id()));
+        std::fs::write(&path, "foo\nbar\nfoobar\n").unwrap();
+
+        let matcher = Matcher::new(Algo::Fzy, MatchType::Full, Bonus::None);
+        let ranked = Source::<std::iter::Empty<SourceItem>>::File(path.clone())
+            .filter(matcher, "foo")
+            .unwrap();
+        let mut matched = ranked
+            .iter()
+            .map(|(item, _, _)| (item.raw.as_str(), item.line_number))
+            .collect::<Vec<_>>();
+        matched.sort();
+        assert_eq!(matched, vec![("foo", Some(1)), ("foobar", Some(3))]);
+
+        let line_numbers = Source::<std::iter::Empty<SourceItem>>::File(path.clone())
+            .into_items()
+            .unwrap()
+            .map(|item| item.line_number)
+            .collect::<Vec<_>>();
+        assert_eq!(line_numbers, vec![Some(1), Some(2), Some(3)]);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_numbered_items() {
+        let items = vec![
+            SourceItem::from("foo".to_string()),
+            "bar".to_string().into(),
+        ];
+        let line_numbers = Source::List(items.into_iter())
+            .numbered()
+            .unwrap()
+            .into_items()
+            .unwrap()
+            .map(|item| item.line_number)
+            .collect::<Vec<_>>();
+        assert_eq!(line_numbers, vec![Some(1), Some(2)]);
+    }
+
+    #[test]
+    fn test_skip_binary_lines() {
+        let path = Path::new("a.bin");
+        let items = numbered_lines(path, &b"foo\n\x7fELF\x02\x00\x00\nbar\n"[..])
+            .map(|item| (item.raw, item.line_number))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            items,
+            vec![("foo".to_string(), Some(1)), ("bar".to_string(), Some(3))]
+        );
+
+        let binary_lines = BinaryLines::new(path);
+        assert!(binary_lines.skip(b"\x00"));
+        assert!(!binary_lines.skip(b"foo"));
+        assert_eq!(binary_lines.skipped.load(Ordering::Relaxed), 1);
+    }
+
+    #[cfg(feature = "mmap")]
+    #[test]
+    fn test_mapped_lines() {
+        let binary_lines = BinaryLines::new(Path::new("a.txt"));
+        let lines = mapped_lines(b"foo\r\n\nb\x00r\nbaz\n", 1, &binary_lines).collect::<Vec<_>>();
+        assert_eq!(
+            lines,
+            vec![(1, &b"foo"[..]), (2, &b""[..]), (4, &b"baz"[..])]
+        );
+        assert_eq!(
+            mapped_lines(b"foo", 5, &binary_lines).collect::<Vec<_>>(),
+            vec![(5, &b"foo"[..])]
+        );
+
+        let content = b"a\nbb\n\nccc\nd";
+        let ranges = split_ranges(content, 3);
+        assert_eq!(ranges.concat(), content);
+        assert!(ranges[..ranges.len() - 1]
+            .iter()
+            .all(|range| range.ends_with(b"\n")));
+        assert_eq!(split_ranges(b"abc", 8), vec![&b"abc"[..]]);
+
+        let matcher = Matcher::new(Algo::Fzy, MatchType::Full, Bonus::None);
+        let mut filtered = filter_mapped(Path::new("a.txt"), content, |line, line_number| {
+            let (mut item, score, indices) =
+                matcher.match_line(std::str::from_utf8(line).unwrap(), "c")?;
+            item.line_number = Some(line_number);
+            Some((item, score, indices))
+        })
+        .into_iter()
+        .map(|(item, _, _)| (item.raw, item.line_number))
+        .collect::<Vec<_>>();
+        filtered.sort();
+        assert_eq!(filtered, vec![("ccc".to_string(), Some(4))]);
     }
 }
@@ -0,0 +1,109 @@
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use source_item::SourceItem;
+
+use crate::source::{numbered_lines, trim_line_ending};
+
+/// Reader of the lines appended to a growing file, e.g., a log file, like `tail -f`.
+///
+/// The file is read up to the offset recorded when the tail starts, the lines appended after
+/// that are returned by [`Tail::read_appended`].
+#[derive(Debug)]
+pub struct Tail {
+    path: PathBuf,
+    /// Length of the file when the tail starts.
+    start: u64,
+    offset: u64,
+    /// The last line appended without the line ending yet.
+    pending: Vec<u8>,
+}
+
+impl Tail {
+    /// Starts following `path` from its current end.
+    pub fn from_end(path: &Path) -> io::Result<Self> {
+        let start = std::fs::metadata(path)?.len();
+        Ok(Self {
+            path: path.to_path_buf(),
+            start,
+            offset: start,
+            pending: Vec::new(),
+        })
+    }
+
+    /// Returns the lines of the file before the tail starts.
+    pub fn initial_lines(&self) -> io::Result<Box<dyn Iterator<Item = SourceItem>>> {
+        let file = File::open(&self.path)?.take(self.start);
+        Ok(Box::new(numbered_lines(
+            &self.path,
+            io::BufReader::new(file),
+        )))
+    }
+
+    /// Returns the complete lines appended since the last read.
+    ///
+    /// The file is read from the start again if it has been truncated, e.g., the log rotation.
+    pub fn read_appended(&mut self) -> io::Result<Vec<SourceItem>> {
+        let len = std::fs::metadata(&self.path)?.len();
+        if len < self.offset {
+            self.offset = 0;
+            self.pending.clear();
+        }
+        if len == self.offset {
+            return Ok(Vec::new());
+        }
+
+        let mut file = File::open(&self.path)?;
+        file.seek(SeekFrom::Start(self.offset))?;
+        let read = file
+            .take(len - self.offset)
+            .read_to_end(&mut self.pending)?;
+        self.offset += read as u64;
+
+        let complete = match self.pending.iter().rposition(|&b| b == b'\n') {
+            Some(idx) => idx + 1,
+            None => return Ok(Vec::new()),
+        };
+        let rest = self.pending.split_off(complete);
+        let lines = std::mem::replace(&mut self.pending, rest);
+
+        Ok(lines[..lines.len() - 1]
+            .split(|&b| b == b'\n')
+            .map(|line| SourceItem::from_bytes(trim_line_ending(line)))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_read_appended() {
+        let path = std::env::temp_dir().join(format!("clap_test_tail_{}", std::process::id()));
+        std::fs::write(&path, "old\n").unwrap();
+
+        let mut tail = Tail::from_end(&path).unwrap();
+        let raw = |items: Vec<SourceItem>| items.into_iter().map(|i| i.raw).collect::<Vec<_>>();
+
+        let mut file = std::fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap();
+        file.write_all(b"new1\r\nnew2\nparti").unwrap();
+        assert_eq!(raw(tail.read_appended().unwrap()), vec!["new1", "new2"]);
+        assert!(tail.read_appended().unwrap().is_empty());
+
+        file.write_all(b"al\n").unwrap();
+        assert_eq!(raw(tail.read_appended().unwrap()), vec!["partial"]);
+
+        assert_eq!(raw(tail.initial_lines().unwrap().collect()), vec!["old"]);
+
+        std::fs::write(&path, "rotated\n").unwrap();
+        assert_eq!(raw(tail.read_appended().unwrap()), vec!["rotated"]);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
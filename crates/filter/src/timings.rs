@@ -0,0 +1,58 @@
+use std::time::Instant;
+
+use serde::Serialize;
+
+/// Time spent on each stage of filtering in milliseconds.
+#[derive(Serialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct Timings {
+    pub read_ms: u64,
+    pub match_ms: u64,
+    pub sort_ms: u64,
+    pub print_ms: u64,
+    pub total_ms: u64,
+    /// Number of the items read from the source.
+    pub lines_scanned: usize,
+}
+
+/// Returns the milliseconds elapsed since `started`.
+pub fn elapsed_ms(started: Instant) -> u64 {
+    started.elapsed().as_millis() as u64
+}
+
+impl Timings {
+    /// Records the time of printing which started at `print_started` and the total time of the
+    /// whole run which started at `started`.
+    pub fn finish(&mut self, started: Instant, print_started: Instant) {
+        self.print_ms = elapsed_ms(print_started);
+        self.total_ms = elapsed_ms(started);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{sync_run_profiled, SortBy, Source};
+    use matcher::{Algo, Bonus, MatchType, Matcher};
+    use source_item::SourceItem;
+
+    #[test]
+    fn test_sync_run_profiled() {
+        let items: Vec<SourceItem> = vec!["src/lib.rs".into(), "src/main.rs".into()];
+        let mut timings = Timings::default();
+        let ranked = sync_run_profiled(
+            "lib",
+            Source::List(items.into_iter()),
+            Matcher::new(Algo::Fzy, MatchType::Full, Bonus::None),
+            SortBy::Score,
+            None,
+            &mut timings,
+        )
+        .unwrap();
+        assert_eq!(ranked.len(), 1);
+        assert_eq!(timings.lines_scanned, 2);
+
+        let started = Instant::now();
+        timings.finish(started, started);
+        assert!(timings.total_ms >= timings.print_ms);
+    }
+}
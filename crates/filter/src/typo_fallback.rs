@@ -0,0 +1,168 @@
+//! Fallback pass of the typo tolerant matching, see [`matcher::typo`].
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use matcher::typo::MIN_STRICT_MATCHES;
+use matcher::{MatchResult, Matcher};
+use source_item::SourceItem;
+
+use crate::FilterResult;
+
+/// Matches the items strictly, along with the fallback pass tolerating a typo of the query if
+/// the typo tolerance is enabled.
+///
+/// The matches of the fallback pass are only kept if the strict pass yields fewer than
+/// [`MIN_STRICT_MATCHES`] results, in which case they are appended to the strict matches in
+/// the order of score and then line number, regardless of the order they are matched.
+pub(crate) struct MatchPass<'a> {
+    matcher: &'a Matcher,
+    query: &'a str,
+    typo_tolerant: bool,
+    strict_matches: AtomicUsize,
+    typo_matches: Mutex<Vec<FilterResult>>,
+}
+
+impl<'a> MatchPass<'a> {
+    pub(crate) fn new(matcher: &'a Matcher, query: &'a str) -> Self {
+        Self::with_typo_tolerance(matcher, query, matcher::typo::is_enabled())
+    }
+
+    fn with_typo_tolerance(matcher: &'a Matcher, query: &'a str, typo_tolerant: bool) -> Self {
+        Self {
+            matcher,
+            query,
+            typo_tolerant,
+            strict_matches: AtomicUsize::new(0),
+            typo_matches: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub(crate) fn typo_tolerant(&self) -> bool {
+        self.typo_tolerant
+    }
+
+    /// Returns the strict score of `item`.
+    ///
+    /// The item failed to match strictly is matched with a typo tolerated and kept for
+    /// [`Self::typo_matches`], unless there are enough strict matches already.
+    pub(crate) fn score(&self, item: &SourceItem) -> MatchResult {
+        match self.matcher.do_match(item, self.query) {
+            Some(matched) => {
+                if self.typo_tolerant {
+                    self.strict_matches.fetch_add(1, Ordering::Relaxed);
+                }
+                Some(matched)
+            }
+            None => {
+                if self.typo_tolerant
+                    && self.strict_matches.load(Ordering::Relaxed) < MIN_STRICT_MATCHES
+                {
+                    if let Some((score, indices)) = self.matcher.typo_match(item, self.query) {
+                        self.typo_matches
+                            .lock()
+                            .unwrap()
+                            .push((item.clone(), score, indices));
+                    }
+                }
+                None
+            }
+        }
+    }
+
+    /// Returns the strict match of `item`, see [`Self::score`].
+    ///
+    /// Nothing is matched once the filtering is interrupted.
+    pub(crate) fn do_match(&self, item: SourceItem) -> Option<FilterResult> {
+        if crate::interrupt::is_interrupted() {
+            return None;
+        }
+        self.score(&item)
+            .map(|(score, indices)| (item, score, indices))
+    }
+
+    /// Returns the matches of the fallback pass if the strict matches are too few.
+    pub(crate) fn typo_matches(self) -> Vec<FilterResult> {
+        if !self.typo_tolerant || self.strict_matches.load(Ordering::Relaxed) >= MIN_STRICT_MATCHES
+        {
+            return Vec::new();
+        }
+        let mut typo_matches = self.typo_matches.into_inner().unwrap();
+        // The fallback pass may run in parallel.
+        typo_matches.sort_by(|(a, a_score, _), (b, b_score, _)| {
+            b_score
+                .cmp(a_score)
+                .then_with(|| a.line_number.cmp(&b.line_number))
+                .then_with(|| a.raw.cmp(&b.raw))
+        });
+        typo_matches
+    }
+
+    /// Appends the matches of the fallback pass to the strict matches if they are too few.
+    pub(crate) fn finish(self, mut filtered: Vec<FilterResult>) -> Vec<FilterResult> {
+        filtered.extend(self.typo_matches());
+        filtered
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use matcher::{Algo, Bonus, MatchType};
+
+    fn filter(lines: &[&str], query: &str, typo_tolerant: bool) -> Vec<String> {
+        let matcher = Matcher::new(Algo::Fzy, MatchType::Full, Bonus::None);
+        let pass = MatchPass::with_typo_tolerance(&matcher, query, typo_tolerant);
+        let filtered = lines
+            .iter()
+            .filter_map(|line| pass.do_match(SourceItem::from(*line)))
+            .collect();
+        pass.finish(filtered)
+            .into_iter()
+            .map(|(item, _, _)| item.raw)
+            .collect()
+    }
+
+    #[test]
+    fn test_match_pass() {
+        let lines = ["src/matcher.rs", "src/mtacher.rs"];
+        assert_eq!(filter(&lines, "matcher", false), vec!["src/matcher.rs"]);
+        assert_eq!(
+            filter(&lines, "matcher", true),
+            vec!["src/matcher.rs", "src/mtacher.rs"]
+        );
+        // The fallback pass is dropped given enough strict matches.
+        let lines = [
+            "a/matcher",
+            "b/matcher",
+            "c/matcher",
+            "d/matcher",
+            "e/matcher",
+            "mtacher",
+        ];
+        assert_eq!(filter(&lines, "matcher", true).len(), MIN_STRICT_MATCHES);
+    }
+
+    #[test]
+    fn test_typo_matches_order() {
+        let matcher = Matcher::new(Algo::Fzy, MatchType::Full, Bonus::None);
+        let pass = MatchPass::with_typo_tolerance(&matcher, "matcher", true);
+        for line_number in [3, 1, 2].iter() {
+            let mut item = SourceItem::from("src/mtacher.rs");
+            item.line_number = Some(*line_number);
+            assert!(pass.score(&item).is_none());
+        }
+        assert!(pass.score(&SourceItem::from("mtacher")).is_none());
+
+        let typo_matches = pass.typo_matches();
+        assert_eq!(typo_matches.len(), 4);
+        // The tied matches are ordered by line number.
+        let line_numbers = typo_matches
+            .iter()
+            .filter(|(item, _, _)| item.raw == "src/mtacher.rs")
+            .map(|(item, _, _)| item.line_number)
+            .collect::<Vec<_>>();
+        assert_eq!(line_numbers, vec![Some(1), Some(2), Some(3)]);
+        assert!(typo_matches.windows(2).all(|pair| pair[0].1 >= pair[1].1));
+    }
+}
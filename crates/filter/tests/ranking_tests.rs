@@ -0,0 +1,25 @@
+//! Golden tests of the ranking, see [`filter::ranking`].
+
+use std::path::Path;
+
+use filter::ranking::{render_all, FIXTURES_DIR};
+
+#[test]
+fn test_golden_ranking() {
+    let goldens = render_all(Path::new(FIXTURES_DIR)).unwrap();
+
+    // The missing golden files are failures as well, they are only recorded by
+    // `maple rank-check --update` and committed along with the change.
+    let outdated = goldens
+        .into_iter()
+        .filter(|golden| !golden.is_up_to_date())
+        .map(|golden| golden.path.display().to_string())
+        .collect::<Vec<_>>();
+
+    assert!(
+        outdated.is_empty(),
+        "The ranking differs from the golden files or they are missing, run \
+         `maple rank-check --update` and review the diff if the change is intended:\n{}",
+        outdated.join("\n")
+    );
+}
@@ -0,0 +1,121 @@
+//! Git status markers of the files, a modified or untracked file is displayed with the marker
+//! instead of its file icon.
+//!
+//! The status is fetched by a single `git status --porcelain` of the current directory on the
+//! first lookup, then reused for all the lines of this process.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use once_cell::sync::OnceCell;
+
+use crate::Icon;
+
+/// nf-oct-diff_modified, of the same width with the file icons.
+pub const MODIFIED_ICON: Icon = '\u{f459}';
+/// nf-oct-diff_added.
+pub const UNTRACKED_ICON: Icon = '\u{f457}';
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+static STATUS_MAP: OnceCell<HashMap<PathBuf, GitStatus>> = OnceCell::new();
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GitStatus {
+    /// Modified in the worktree or the index, including the added and renamed ones.
+    Modified,
+    Untracked,
+}
+
+impl GitStatus {
+    pub fn icon(self) -> Icon {
+        match self {
+            Self::Modified => MODIFIED_ICON,
+            Self::Untracked => UNTRACKED_ICON,
+        }
+    }
+}
+
+/// Enables the git status markers of the files painted by `IconPainter::File`.
+pub fn enable() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+/// Parses the output of `git status --porcelain -z`, the paths are joined with `root`.
+fn parse_porcelain(output: &str, root: &Path) -> HashMap<PathBuf, GitStatus> {
+    let mut map = HashMap::new();
+    let mut entries = output.split('\0');
+    while let Some(entry) = entries.next() {
+        if entry.len() < 4 {
+            continue;
+        }
+        let (xy, path) = entry.split_at(3);
+        let status = match xy.trim_end() {
+            "??" => GitStatus::Untracked,
+            "!!" => continue,
+            _ => GitStatus::Modified,
+        };
+        // The original path of a renamed or copied entry follows it.
+        if xy.starts_with('R') || xy.starts_with('C') {
+            entries.next();
+        }
+        map.insert(root.join(path), status);
+    }
+    map
+}
+
+fn load_status_map() -> HashMap<PathBuf, GitStatus> {
+    let git_output = |args: &[&str]| {
+        Command::new("git")
+            .args(args)
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .map(|output| String::from_utf8_lossy(&output.stdout).into_owned())
+    };
+    let root = match git_output(&["rev-parse", "--show-toplevel"]) {
+        Some(root) => PathBuf::from(root.trim_end()),
+        None => return HashMap::new(),
+    };
+    git_output(&["status", "--porcelain", "-z", "--untracked-files=all"])
+        .map(|output| parse_porcelain(&output, &root))
+        .unwrap_or_default()
+}
+
+/// Returns the git status of the file `line` relative to the current directory, `None` if it's
+/// clean or the git status markers are not enabled.
+pub fn status_of(line: &str) -> Option<GitStatus> {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return None;
+    }
+    let map = STATUS_MAP.get_or_init(load_status_map);
+    if map.is_empty() {
+        return None;
+    }
+    let path = Path::new(line);
+    if path.is_absolute() {
+        map.get(path).copied()
+    } else {
+        let cwd = std::env::current_dir().ok()?;
+        map.get(&cwd.join(path)).copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_porcelain() {
+        let output = " M src/lib.rs\0?? new.rs\0R  renamed.rs\0old.rs\0A  added.rs\0";
+        let map = parse_porcelain(output, Path::new("/repo"));
+        assert_eq!(map.len(), 4);
+        assert_eq!(map[Path::new("/repo/src/lib.rs")], GitStatus::Modified);
+        assert_eq!(map[Path::new("/repo/new.rs")], GitStatus::Untracked);
+        assert_eq!(map[Path::new("/repo/renamed.rs")], GitStatus::Modified);
+        assert!(!map.contains_key(Path::new("/repo/old.rs")));
+        assert_eq!(format!("{} ", GitStatus::Modified.icon()).len(), 4);
+    }
+}
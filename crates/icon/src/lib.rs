@@ -3,6 +3,9 @@ include!(concat!(env!("OUT_DIR"), "/constants.rs"));
 // mod constants;
 // pub use constants::*;
 
+pub mod git_status;
+pub mod width;
+
 use std::path::Path;
 
 use structopt::clap::arg_enum;
@@ -22,9 +25,13 @@ type Icon = char;
 
 /// Return appropriate icon for the path. If no icon matched, return the specified default one.
 ///
-/// Try matching the exactmatch map against the file name, and then the extension map.
+/// Try matching the exactmatch map against the file name, and then the extension map, finally
+/// the extension map against the canonical extension of the detected filetype.
 #[inline]
 pub fn get_icon_or(path: &Path, default: Icon) -> Icon {
+    let extension_icon = |ext: &str| {
+        bsearch_icon_table(ext, EXTENSION_ICON_TABLE).map(|idx| EXTENSION_ICON_TABLE[idx].1)
+    };
     path.file_name()
         .and_then(std::ffi::OsStr::to_str)
         .and_then(|filename| {
@@ -34,9 +41,12 @@ pub fn get_icon_or(path: &Path, default: Icon) -> Icon {
         .unwrap_or_else(|| {
             path.extension()
                 .and_then(std::ffi::OsStr::to_str)
-                .and_then(|ext| {
-                    bsearch_icon_table(ext, EXTENSION_ICON_TABLE)
-                        .map(|idx| EXTENSION_ICON_TABLE[idx].1)
+                .and_then(extension_icon)
+                .or_else(|| {
+                    // The well-known files without an extension in the map, e.g., `Gemfile`.
+                    filetype::detect_by_name(path)
+                        .and_then(filetype::canonical_extension)
+                        .and_then(extension_icon)
                 })
                 .unwrap_or(default)
         })
@@ -51,6 +61,13 @@ pub fn prepend_icon(line: &str) -> String {
     format!("{} {}", icon_for(line), line)
 }
 
+/// Returns the git status marker of the file `line` if it's dirty, otherwise its icon.
+fn file_icon_for(line: &str) -> Icon {
+    git_status::status_of(line)
+        .map(git_status::GitStatus::icon)
+        .unwrap_or_else(|| icon_for(line))
+}
+
 #[inline]
 pub fn icon_for_filer(path: &Path) -> Icon {
     if path.is_dir() {
@@ -98,7 +115,7 @@ impl IconPainter {
     /// Returns a `String` of raw str with icon added.
     pub fn paint(&self, raw_str: &str) -> String {
         match *self {
-            Self::File => prepend_icon(raw_str),
+            Self::File => format!("{} {}", file_icon_for(raw_str), raw_str),
             Self::Grep => prepend_grep_icon(raw_str),
             Self::ProjTags => format!("{} {}", get_tagkind_icon(raw_str), raw_str),
         }
@@ -107,7 +124,7 @@ impl IconPainter {
     /// Returns appropriate icon for the given text.
     pub fn get_icon(&self, text: &str) -> Icon {
         match *self {
-            Self::File => icon_for(text),
+            Self::File => file_icon_for(text),
             Self::Grep => grep_icon_for(text),
             Self::ProjTags => get_tagkind_icon(text),
         }
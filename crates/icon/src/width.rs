@@ -0,0 +1,120 @@
+//! Display width of the icon prefix and whether the icons can be rendered at all.
+//!
+//! The nerd-font glyphs are the ambiguous-width chars, which take two cells instead of one if
+//! `ambiwidth` of Vim is `double`, the truncation of the iconized lines has to know it to keep
+//! the matched chars visible.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use structopt::clap::arg_enum;
+
+/// Width of the icon prefix, i.e., a single-width glyph followed by a space.
+pub const DEFAULT_ICON_WIDTH: usize = 2;
+
+static ICON_WIDTH: AtomicUsize = AtomicUsize::new(DEFAULT_ICON_WIDTH);
+
+arg_enum! {
+  /// Value of the `ambiwidth` option of Vim.
+  #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+  pub enum AmbiWidth {
+      Single,
+      Double,
+  }
+}
+
+impl Default for AmbiWidth {
+    fn default() -> Self {
+        Self::Single
+    }
+}
+
+impl AmbiWidth {
+    /// Returns the width of the icon prefix in cells.
+    pub fn icon_width(self) -> usize {
+        match self {
+            Self::Single => DEFAULT_ICON_WIDTH,
+            Self::Double => DEFAULT_ICON_WIDTH + 1,
+        }
+    }
+}
+
+/// Sets the width of the icon prefix in cells, see [`icon_width_with`].
+pub fn set_icon_width(width: usize) {
+    ICON_WIDTH.store(width, Ordering::SeqCst);
+}
+
+pub fn icon_width() -> usize {
+    ICON_WIDTH.load(Ordering::Relaxed)
+}
+
+/// Returns the width of the icon prefix given the `ambiwidth`, `CLAP_ICON_WIDTH` of the
+/// environment variable `lookup` takes precedence.
+pub fn icon_width_with<F>(ambiwidth: AmbiWidth, lookup: F) -> usize
+where
+    F: Fn(&str) -> Option<String>,
+{
+    lookup("CLAP_ICON_WIDTH")
+        .and_then(|width| width.trim().parse().ok())
+        .unwrap_or_else(|| ambiwidth.icon_width())
+}
+
+/// Returns false if the icons are unlikely to be rendered given the environment variable
+/// `lookup`, i.e., in the Linux console, which has no nerd-font glyphs.
+///
+/// `TERM=dumb` is not a hint since it's usually the TERM of the jobs of Vim, whose output is
+/// rendered by Vim itself. `CLAP_ICONS=0` or `CLAP_ICONS=1` overrides the detection.
+pub fn icons_enabled_with<F>(lookup: F) -> bool
+where
+    F: Fn(&str) -> Option<String>,
+{
+    if let Some(icons) = lookup("CLAP_ICONS") {
+        return !matches!(
+            icons.trim().to_lowercase().as_str(),
+            "0" | "off" | "no" | "false"
+        );
+    }
+    lookup("TERM").as_deref() != Some("linux")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_icon_width() {
+        assert_eq!(icon_width_with(AmbiWidth::Single, |_| None), 2);
+        assert_eq!(icon_width_with(AmbiWidth::Double, |_| None), 3);
+        let lookup = |var: &str| match var {
+            "CLAP_ICON_WIDTH" => Some("4".to_string()),
+            _ => None,
+        };
+        assert_eq!(icon_width_with(AmbiWidth::Double, lookup), 4);
+        assert_eq!("double".parse::<AmbiWidth>(), Ok(AmbiWidth::Double));
+    }
+
+    #[test]
+    fn test_icons_enabled() {
+        assert!(icons_enabled_with(|_| None));
+        let linux_console = |var: &str| match var {
+            "TERM" => Some("linux".to_string()),
+            _ => None,
+        };
+        assert!(!icons_enabled_with(linux_console));
+        let vim_job = |var: &str| match var {
+            "TERM" => Some("dumb".to_string()),
+            _ => None,
+        };
+        assert!(icons_enabled_with(vim_job));
+        let forced = |var: &str| match var {
+            "TERM" => Some("linux".to_string()),
+            "CLAP_ICONS" => Some("1".to_string()),
+            _ => None,
+        };
+        assert!(icons_enabled_with(forced));
+        let disabled = |var: &str| match var {
+            "CLAP_ICONS" => Some("off".to_string()),
+            _ => None,
+        };
+        assert!(!icons_enabled_with(disabled));
+    }
+}
@@ -0,0 +1,83 @@
+//! Library API of `maple exec`.
+
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::process::light::set_current_dir;
+use crate::tools::ignore::IgnoreOptions;
+
+/// Options of [`run`].
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct ExecOptions {
+    /// The shell command to run, e.g., `fd --type f`.
+    pub cmd: String,
+    /// Working directory of the command, the current directory is used if not given.
+    pub cmd_dir: Option<PathBuf>,
+    /// Add --hidden to the command if it's rg or fd.
+    pub hidden: bool,
+    /// Add --no-ignore to the command if it's rg or fd.
+    pub no_ignore: bool,
+    /// Only return the first N lines.
+    pub number: Option<usize>,
+}
+
+/// Result of [`run`].
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExecOutput {
+    /// Number of all the output lines, which may be larger than the length of `lines`.
+    pub total: usize,
+    pub lines: Vec<String>,
+}
+
+/// Runs the shell command of `options` and returns after it's finished.
+pub fn run(options: &ExecOptions) -> Result<ExecOutput> {
+    let ignore = IgnoreOptions {
+        hidden: options.hidden,
+        no_ignore: options.no_ignore,
+    };
+    let mut cmd = crate::process::std::build_command(&ignore.apply_to_cmd(&options.cmd));
+    set_current_dir(&mut cmd, options.cmd_dir.clone());
+
+    let output = cmd.output()?;
+    if !output.status.success() && output.stdout.is_empty() {
+        return Err(anyhow!(
+            "{} failed: {}",
+            options.cmd,
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let lines = stdout.lines().collect::<Vec<_>>();
+    Ok(ExecOutput {
+        total: lines.len(),
+        lines: lines
+            .into_iter()
+            .take(options.number.unwrap_or(usize::MAX))
+            .map(Into::into)
+            .collect(),
+    })
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exec() {
+        let options: ExecOptions =
+            serde_json::from_str(r#"{"cmd": "printf 'foo\\nbar\\nbaz\\n'", "number": 2}"#).unwrap();
+        let output = run(&options).unwrap();
+        assert_eq!(output.total, 3);
+        assert_eq!(output.lines, vec!["foo", "bar"]);
+
+        let options = ExecOptions {
+            cmd: "exit 2".into(),
+            ..Default::default()
+        };
+        assert!(run(&options).is_err());
+    }
+}
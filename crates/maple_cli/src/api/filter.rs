@@ -0,0 +1,140 @@
+//! Library API of `maple filter --sync`.
+
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+use filter::{
+    matcher::{Algo, Bonus, MatchType},
+    ExcludeFilter, FilterResult, Source,
+};
+use source_item::SourceItem;
+
+/// Options of [`run`].
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct FilterOptions {
+    pub query: String,
+    /// Filter the lines of this file, `lines` is used if not given.
+    pub input: Option<PathBuf>,
+    /// Filter these lines when there is no `input`.
+    pub lines: Vec<String>,
+    /// Name of the filter algorithm, case insensitive, fzy is used if not given.
+    pub algo: Option<String>,
+    /// Name of the match type, case insensitive, full is used if not given.
+    pub match_type: Option<String>,
+    /// Add a bonus to the files of related extensions.
+    pub filetype: Option<String>,
+    /// Exclude the items matching these glob patterns.
+    pub exclude: Vec<String>,
+    /// Make the items relative to this directory.
+    pub relative_to: Option<PathBuf>,
+    /// Only return the top N items.
+    pub number: Option<usize>,
+}
+
+/// A matched item of [`FilterOutput`].
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct FilteredItem {
+    pub text: String,
+    pub score: i64,
+    /// Char indices of the matches in `text`.
+    pub indices: Vec<usize>,
+    /// Line number of the item in the input file.
+    #[serde(default)]
+    pub line_number: Option<usize>,
+}
+
+/// Result of [`run`].
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct FilterOutput {
+    /// Number of all the matched items, which may be larger than the length of `items`.
+    pub total: usize,
+    /// Matched items sorted by the score.
+    pub items: Vec<FilteredItem>,
+}
+
+impl From<FilterResult> for FilteredItem {
+    fn from((item, score, indices): FilterResult) -> Self {
+        Self {
+            line_number: item.line_number,
+            text: item.display_text.unwrap_or(item.raw),
+            score,
+            indices,
+        }
+    }
+}
+
+pub(crate) fn parse_arg_enum<T: std::str::FromStr>(
+    value: &Option<String>,
+    kind: &str,
+) -> Result<Option<T>> {
+    value
+        .as_ref()
+        .map(|v| v.parse().map_err(|_| anyhow!("Unknown {}: {}", kind, v)))
+        .transpose()
+}
+
+/// Filters the items of `options` and returns after all of them are processed.
+pub fn run(options: &FilterOptions) -> Result<FilterOutput> {
+    let algo = parse_arg_enum::<Algo>(&options.algo, "algo")?.unwrap_or(Algo::Fzy);
+    let match_type =
+        parse_arg_enum::<MatchType>(&options.match_type, "match type")?.unwrap_or(MatchType::Full);
+
+    let mut source: Source<Box<dyn Iterator<Item = SourceItem>>> = match options.input {
+        Some(ref input) => input.clone().into(),
+        None => Source::List(Box::new(
+            options
+                .lines
+                .clone()
+                .into_iter()
+                .map(Into::<SourceItem>::into),
+        )),
+    };
+    if !options.exclude.is_empty() {
+        source = source.exclude(ExcludeFilter::new(&options.exclude), match_type.clone())?;
+    }
+    if let Some(ref dir) = options.relative_to {
+        source = source.relative_to(dir.clone(), match_type.clone())?;
+    }
+
+    let mut bonuses = vec![Bonus::None];
+    if let Some(ref filetype) = options.filetype {
+        bonuses.push(Bonus::FileType(filetype.into()));
+    }
+
+    let ranked = filter::sync_run(&options.query, source, algo, match_type, bonuses, None)?;
+
+    Ok(FilterOutput {
+        total: ranked.len(),
+        items: ranked
+            .into_iter()
+            .take(options.number.unwrap_or(usize::MAX))
+            .map(Into::into)
+            .collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filter_lines() {
+        let options: FilterOptions = serde_json::from_str(
+            r#"{"query": "lib", "lines": ["src/lib.rs", "src/main.rs", "crates/lib/mod.rs"], "number": 1}"#,
+        )
+        .unwrap();
+        let output = run(&options).unwrap();
+        assert_eq!(output.total, 2);
+        assert_eq!(output.items.len(), 1);
+        assert!(output.items[0].text.contains("lib"));
+
+        let options = FilterOptions {
+            algo: Some("unknown".into()),
+            ..Default::default()
+        };
+        assert!(run(&options).is_err());
+    }
+}
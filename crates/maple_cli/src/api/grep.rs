@@ -0,0 +1,266 @@
+//! Library API of `maple grep --sync`.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+use filter::ExcludeFilter;
+
+use crate::process::light::set_current_dir;
+use crate::tools::rg::{attach_contexts, SearchResult};
+
+pub use crate::tools::rg::{ContextLine, Elapsed, FileMatches, MatchContext, MatchPosition, Stats};
+
+/// Options of [`run`].
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct GrepOptions {
+    /// Pattern of rg.
+    pub query: String,
+    /// Working directory of rg, the current directory is used if not given.
+    pub cmd_dir: Option<PathBuf>,
+    /// Delegate to -g option of rg.
+    pub glob: Option<String>,
+    /// Delegate to -C option of rg.
+    pub context: Option<usize>,
+    /// Delegate to -A option of rg, overrides `context` for the trailing context.
+    pub after_context: Option<usize>,
+    /// Delegate to -B option of rg, overrides `context` for the leading context.
+    pub before_context: Option<usize>,
+    /// Delegate to -w option of rg.
+    pub word: bool,
+    /// Delegate to -F option of rg.
+    pub literal: bool,
+    /// Delegate to --hidden option of rg.
+    pub hidden: bool,
+    /// Delegate to --no-ignore option of rg.
+    pub no_ignore: bool,
+    /// Exclude the files matching these glob patterns.
+    pub exclude: Vec<String>,
+    /// Make the paths relative to this directory.
+    pub relative_to: Option<PathBuf>,
+    /// Leave the room for the icon in front of each line.
+    pub enable_icon: bool,
+}
+
+/// Result of [`run`].
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct GrepResult {
+    /// Number of the matched lines, the context lines are not counted.
+    pub total: usize,
+    /// Grep lines in the format of `path:line:column:text`.
+    pub lines: Vec<String>,
+    /// Byte indices of the matches in each line.
+    pub indices: Vec<Vec<usize>>,
+    /// Position of the first match in the file of each line.
+    pub positions: Vec<Option<MatchPosition>>,
+    /// Context lines of each line, enabled by the context options.
+    #[serde(default)]
+    pub contexts: Vec<MatchContext>,
+    pub file_matches: Vec<FileMatches>,
+    /// `None` if the output of rg is incomplete.
+    pub stats: Option<Stats>,
+}
+
+impl GrepOptions {
+    /// Returns the args of rg, excluding the program itself.
+    fn rg_args(&self) -> Vec<String> {
+        let mut args = vec!["--json".to_string(), "--smart-case".to_string()];
+        let flags = [
+            (self.word, "-w"),
+            (self.literal, "-F"),
+            (self.hidden, "--hidden"),
+            (self.no_ignore, "--no-ignore"),
+        ];
+        args.extend(
+            flags
+                .iter()
+                .filter(|(enabled, _)| *enabled)
+                .map(|(_, flag)| flag.to_string()),
+        );
+        if let Some(ref glob) = self.glob {
+            args.push("-g".into());
+            args.push(glob.clone());
+        }
+        let contexts = [
+            ("-C", self.context),
+            ("-A", self.after_context),
+            ("-B", self.before_context),
+        ];
+        for (flag, context) in contexts.iter() {
+            if let Some(context) = context {
+                args.push(flag.to_string());
+                args.push(context.to_string());
+            }
+        }
+        // Use -e in case of the query starting with `-`.
+        args.push("-e".into());
+        args.push(self.query.clone());
+        // Ref https://github.com/liuchengxu/vim-clap/issues/533
+        if cfg!(windows) {
+            args.push(".".into());
+        }
+        args
+    }
+}
+
+impl GrepResult {
+    /// Builds the result from the output lines of rg --json, the context lines are attached to
+    /// the matched lines given the number of the trailing context lines `after_context`.
+    pub(crate) fn from_grep_lines<T: AsRef<str>>(
+        grep_lines: &[T],
+        exclude: &[String],
+        relative_to: Option<&Path>,
+        enable_icon: bool,
+        after_context: usize,
+    ) -> Self {
+        let exclude_filter = ExcludeFilter::new(exclude);
+
+        let SearchResult {
+            lines,
+            file_matches,
+            stats,
+        } = SearchResult::from_lines(grep_lines);
+
+        let mut grep_lines = Vec::new();
+        let mut indices = Vec::new();
+        let mut positions = Vec::new();
+        let mut contexts = Vec::new();
+        for (mut line, context) in attach_contexts(lines, after_context)
+            .into_iter()
+            .filter(|(line, _)| !exclude_filter.is_excluded(&line.data.path.text))
+        {
+            if let Some(dir) = relative_to {
+                line.relative_to(dir);
+            }
+            let (grep_line, line_indices) = line.build_grep_line(enable_icon);
+            grep_lines.push(grep_line);
+            indices.push(line_indices);
+            positions.push(line.data.match_position());
+            contexts.push(context);
+        }
+
+        let file_matches = file_matches
+            .into_iter()
+            .filter(|file| !exclude_filter.is_excluded(&file.path))
+            .map(|mut file| {
+                if let Some(relative) =
+                    relative_to.and_then(|dir| utility::strip_dir_prefix(&file.path, dir))
+                {
+                    file.path = relative.to_string();
+                }
+                file
+            })
+            .collect();
+
+        Self {
+            total: grep_lines.len(),
+            lines: grep_lines,
+            indices,
+            positions,
+            contexts,
+            file_matches,
+            stats,
+        }
+    }
+
+    /// Keeps the first `number` matched lines along with their contexts, `total` is unchanged.
+    pub(crate) fn truncate(&mut self, number: usize) {
+        self.lines.truncate(number);
+        self.indices.truncate(number);
+        self.positions.truncate(number);
+        self.contexts.truncate(number);
+    }
+}
+
+/// Runs rg with `options` and returns after it's finished.
+pub fn run(options: &GrepOptions) -> Result<GrepResult> {
+    let mut cmd = Command::new("rg");
+    cmd.args(options.rg_args());
+    set_current_dir(&mut cmd, options.cmd_dir.clone());
+
+    let output = cmd.output()?;
+    // Exit code 1 means no match, 2 means an error occurred, the matches found are still valid.
+    if output.status.code() == Some(2) && output.stdout.is_empty() {
+        return Err(anyhow!(
+            "rg failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let grep_lines = stdout.lines().collect::<Vec<_>>();
+
+    Ok(GrepResult::from_grep_lines(
+        &grep_lines,
+        &options.exclude,
+        options.relative_to.as_deref(),
+        options.enable_icon,
+        options
+            .after_context
+            .or(options.context)
+            .unwrap_or_default(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_grep_result_from_grep_lines() {
+        let grep_lines = [
+            r#"{"type":"match","data":{"path":{"text":"/root/src/lib.rs"},"lines":{"text":"fn main() {}\n"},"line_number":1,"absolute_offset":0,"submatches":[{"match":{"text":"main"},"start":3,"end":7}]}}"#,
+            r#"{"type":"match","data":{"path":{"text":"/root/target/gen.rs"},"lines":{"text":"fn main() {}\n"},"line_number":1,"absolute_offset":0,"submatches":[{"match":{"text":"main"},"start":3,"end":7}]}}"#,
+        ];
+        let result = GrepResult::from_grep_lines(
+            &grep_lines,
+            &["target/".to_string()],
+            Some(Path::new("/root")),
+            false,
+            0,
+        );
+        assert_eq!(result.total, 1);
+        assert_eq!(result.lines, vec!["src/lib.rs:1:3:fn main() {}"]);
+        assert_eq!(
+            result.positions,
+            vec![Some(MatchPosition {
+                column: 4,
+                length: 4
+            })]
+        );
+
+        let json = serde_json::to_string(&result).unwrap();
+        assert_eq!(serde_json::from_str::<GrepResult>(&json).unwrap(), result);
+    }
+
+    #[test]
+    fn test_context_lines_not_counted() {
+        let grep_lines = [
+            r#"{"type":"begin","data":{"path":{"text":"src/lib.rs"}}}"#,
+            r#"{"type":"context","data":{"path":{"text":"src/lib.rs"},"lines":{"text":"// main\n"},"line_number":1,"absolute_offset":0,"submatches":[]}}"#,
+            r#"{"type":"match","data":{"path":{"text":"src/lib.rs"},"lines":{"text":"fn main() {}\n"},"line_number":2,"absolute_offset":8,"submatches":[{"match":{"text":"main"},"start":3,"end":7}]}}"#,
+            r#"{"type":"context","data":{"path":{"text":"src/lib.rs"},"lines":{"text":"\n"},"line_number":3,"absolute_offset":21,"submatches":[]}}"#,
+            r#"{"type":"match","data":{"path":{"text":"src/lib.rs"},"lines":{"text":"fn main2() {}\n"},"line_number":4,"absolute_offset":22,"submatches":[{"match":{"text":"main"},"start":3,"end":7}]}}"#,
+            r#"{"type":"end","data":{"path":{"text":"src/lib.rs"},"binary_offset":null,"stats":{"elapsed":{"secs":0,"nanos":1000,"human":"0.000001s"},"searches":1,"searches_with_match":1,"bytes_searched":36,"bytes_printed":512,"matched_lines":2,"matches":2}}}"#,
+        ];
+        let mut result = GrepResult::from_grep_lines(&grep_lines, &[], None, false, 1);
+        assert_eq!(result.total, 2);
+        assert_eq!(
+            result.lines,
+            vec![
+                "src/lib.rs:2:3:fn main() {}",
+                "src/lib.rs:4:3:fn main2() {}"
+            ]
+        );
+        assert_eq!(result.contexts[0].before[0].line_number, 1);
+        assert_eq!(result.contexts[0].after[0].line_number, 3);
+
+        result.truncate(1);
+        assert_eq!(result.total, 2);
+        assert_eq!(result.lines.len(), 1);
+        assert_eq!(result.contexts.len(), 1);
+    }
+}
@@ -0,0 +1,12 @@
+//! Library API of the subcommands, for embedding maple in the other Rust tools.
+//!
+//! Each module has a serde-serializable options struct and a `run` function returning a
+//! serde-serializable result, which is the same data printed by the subcommand in the JSON
+//! format, without the display related processing such as truncating the long lines.
+//!
+//! Only exec, filter and grep are exposed so far. The subcommands serving the Vim state, e.g.,
+//! lines, marks and registers, and the interactive ones are not part of the library API.
+
+pub mod exec;
+pub mod filter;
+pub mod grep;
@@ -1,8 +1,19 @@
+use std::path::Path;
+
 use anyhow::Result;
 use structopt::{clap::AppSettings, StructOpt};
 
-use filter::FilterContext;
-use icon::IconPainter;
+use filter::{
+    matcher::{LineCutoff, LongLineStrategy},
+    FilterContext, Tiebreak,
+};
+use icon::{width::AmbiWidth, IconPainter};
+use printer::index_format::IndexFormat;
+use printer::output_format::OutputFormat;
+use utility::compression::Compression;
+
+use crate::config::Config;
+use crate::params::{ParamsBuilder, ParamsError};
 
 #[derive(StructOpt, Debug)]
 pub enum Cmd {
@@ -33,15 +44,69 @@ pub enum Cmd {
     /// Filter against current Vim buffer.
     #[structopt(name = "blines")]
     Blines(crate::cmd::blines::Blines),
+    /// Filter against the symbols of a file, tagged by ctags or the built-in tagger.
+    #[structopt(name = "buffer-tags")]
+    BufferTags(crate::cmd::buffer_tags::BufferTags),
+    /// Filter against the lines of all the open Vim buffers.
+    #[structopt(name = "lines")]
+    Lines(crate::cmd::lines::Lines),
+    /// Filter against the marks or jumplist, with the invalid entries dropped.
+    #[structopt(name = "marks")]
+    Marks(crate::cmd::marks::Marks),
+    /// Run a pipeline of the source, transforms and filter described by a JSON spec.
+    #[structopt(name = "pipe")]
+    Pipe(crate::cmd::pipe::Pipe),
+    /// Search the workspace symbols provided by a language server.
+    #[structopt(name = "lsp-symbols")]
+    LspSymbols(crate::cmd::lsp_symbols::LspSymbols),
+    /// Run the providers implemented by the external executables.
+    #[structopt(name = "plugin")]
+    Plugin(crate::cmd::plugin::Plugin),
+    /// Run the providers declared in the config file.
+    #[structopt(name = "provider")]
+    Provider(crate::cmd::provider::Provider),
+    /// Filter against the contents of registers, e.g., the yank history.
+    #[structopt(name = "registers")]
+    Registers(crate::cmd::registers::Registers),
+    /// Filter against the commits of git log.
+    #[structopt(name = "commits")]
+    Commits(crate::cmd::commits::Commits),
+    /// Collect the comments of TODO, FIXME, etc. in the project.
+    #[structopt(name = "todos")]
+    Todos(crate::cmd::todos::Todos),
+    /// Start the interactive fuzzy finder in the terminal.
+    #[structopt(name = "tui")]
+    Tui(crate::cmd::tui::Tui),
     /// Generate vim help tags.
     #[structopt(name = "helptags")]
     Helptags(crate::cmd::helptags::Helptags),
+    /// Record and suggest the previous queries of the providers.
+    #[structopt(name = "history")]
+    History(crate::cmd::history::History),
+    /// Detect the filetype of a file.
+    #[structopt(name = "detect-filetype")]
+    DetectFiletype(crate::cmd::detect_filetype::DetectFiletype),
+    /// Query the status of the forerunner jobs.
+    #[structopt(name = "jobs")]
+    Jobs(crate::cmd::jobs::Jobs),
     /// Start the forerunner job of grep.
     #[structopt(name = "ripgrep-forerunner")]
     RipGrepForerunner(crate::cmd::grep::RipGrepForerunner),
+    /// Keep the files and grep caches up to date with the file changes.
+    #[structopt(name = "watch")]
+    Watch(crate::cmd::watch::Watch),
+    /// Generate the shell completion script, e.g., `maple completions bash > maple.bash`.
+    #[structopt(name = "completions")]
+    Completions(crate::cmd::completions::Completions),
     /// Retrive the latest remote release info.
     #[structopt(name = "upgrade")]
     Upgrade(upgrade::Upgrade),
+    /// Replay the invocations recorded by `--record`, e.g., attached to a bug report.
+    #[structopt(name = "replay")]
+    Replay(crate::cmd::replay::Replay),
+    /// Check or regenerate the golden ranking of the fixture corpora.
+    #[structopt(name = "rank-check", setting = AppSettings::Hidden)]
+    RankCheck(crate::cmd::rank_check::RankCheck),
 }
 
 #[derive(StructOpt, Debug)]
@@ -54,15 +119,108 @@ pub struct Maple {
     #[structopt(flatten)]
     pub params: Params,
 
-    /// Enable the logging system.
-    #[structopt(long = "log", parse(from_os_str))]
+    /// Enable the logging system, the log level can be specified by RUST_LOG, debug by default.
+    #[structopt(long = "log", parse(from_os_str = utility::paths::expand_os))]
     pub log: Option<std::path::PathBuf>,
 
+    /// Path of the config file, `~/.config/vim-clap/config.toml` is used if not specified.
+    #[structopt(long = "config-file", parse(from_os_str = utility::paths::expand_os))]
+    pub config_file: Option<std::path::PathBuf>,
+
+    /// Append the args, query, timing and result count of this invocation to the file, which
+    /// can be replayed by `maple replay`.
+    #[structopt(long = "record", parse(from_os_str = utility::paths::expand_os))]
+    pub record: Option<std::path::PathBuf>,
+
+    /// Number of threads used for filtering, defaults to the number of logical CPUs.
+    ///
+    /// Overrides the `threads` key of the config file.
+    #[structopt(long = "threads")]
+    pub threads: Option<usize>,
+
+    /// Compression of the cache files: none, gzip or zstd, defaults to none.
+    ///
+    /// Overrides the `cache_compression` key of the config file.
+    #[structopt(long = "cache-compression")]
+    pub cache_compression: Option<Compression>,
+
+    /// Format of the matched indices in the results: indices or ranges, defaults to indices.
+    ///
+    /// `ranges` replaces `indices` with `indices_ranges`, the `[start, end]` pairs of the
+    /// adjacent indices with `end` exclusive.
+    #[structopt(long = "index-format", default_value = "indices")]
+    pub index_format: IndexFormat,
+
+    /// Format of the printed results: json or human, defaults to json.
+    ///
+    /// `human` prints the results as the lines with the matched chars highlighted and the paths
+    /// dimmed, only if stdout is a terminal.
+    #[structopt(long = "output-format", default_value = "json")]
+    pub output_format: OutputFormat,
+
+    /// Abbreviate the intermediate directories of the paths in the results to one char.
+    ///
+    /// The full paths of the shortened lines are returned in `truncated_map`.
+    #[structopt(long = "shorten-path")]
+    pub shorten_path: bool,
+
+    /// Display the git status marker instead of the icon for the modified and untracked files,
+    /// valid only when --icon-painter=File is used.
+    ///
+    /// The status is fetched once by `git status --porcelain` of the current directory.
+    #[structopt(long = "icon-git-status")]
+    pub icon_git_status: bool,
+
+    /// Value of `ambiwidth` of Vim, the icons take one more cell if it's double.
+    #[structopt(
+        long = "ambiwidth",
+        default_value = "Single",
+        possible_values = &AmbiWidth::variants(),
+        case_insensitive = true
+    )]
+    pub ambiwidth: AmbiWidth,
+
+    /// Width of the icon prefix in cells, i.e., the icon and the following space.
+    ///
+    /// Overrides the width inferred from --ambiwidth, so does `CLAP_ICON_WIDTH`. The icons are
+    /// disabled in the Linux console unless `CLAP_ICONS=1`, `CLAP_ICONS=0` disables them.
+    #[structopt(long = "icon-width")]
+    pub icon_width: Option<usize>,
+
+    /// Match the diacritics insensitively, e.g., `e` in the query matches `é`, `è` and `ê`.
+    #[structopt(long = "normalize")]
+    pub normalize: bool,
+
+    /// Tolerate one transposed, wrong or extra char in the query if it has too few matches.
+    ///
+    /// The matches with the typo fixed are penalized and follow the strict matches, only the
+    /// synchronous filtering is supported.
+    #[structopt(long = "typo-tolerant")]
+    pub typo_tolerant: bool,
+
+    /// Spill the results printed line by line, i.e., without --number, to a tempfile once they
+    /// exceed this size in bytes, only the path of the tempfile is printed as `tempfile`.
+    #[structopt(long = "spill-threshold")]
+    pub spill_threshold: Option<usize>,
+
+    /// Stream the source file line by line instead of reading it into memory at once if it's
+    /// larger than this size in bytes, 100MiB by default.
+    #[structopt(long = "max-file-size")]
+    pub max_file_size: Option<u64>,
+
+    /// Cap the number of the matches retained by the dyn filtering given the available memory,
+    /// the results are tagged with `limited: true` if some matches are dropped.
+    ///
+    /// The matches have to be retained when the results are printed without --number or not
+    /// sorted by score.
+    #[structopt(long = "auto-limit")]
+    pub auto_limit: bool,
+
     #[structopt(subcommand)]
     pub command: Cmd,
 }
 
-#[derive(StructOpt, Debug)]
+#[derive(StructOpt, Debug, Default)]
 pub struct Params {
     /// Print the top NUM of filtered items.
     ///
@@ -70,10 +228,14 @@ pub struct Params {
     ///   - total: total number of initial filtered result set.
     ///   - lines: text lines used for displaying directly.
     ///   - indices: the indices of matched elements per line, used for the highlight purpose.
+    ///
+    /// `lossy_lines` is added if some lines are decoded lossily from invalid UTF-8.
     #[structopt(long = "number", name = "NUM")]
     pub number: Option<usize>,
 
     /// Width of clap window.
+    ///
+    /// Defaults to the terminal width or `COLUMNS` when maple is run in a terminal.
     #[structopt(long = "winwidth")]
     pub winwidth: Option<usize>,
 
@@ -84,37 +246,220 @@ pub struct Params {
     /// Do not use the cached file for exec subcommand.
     #[structopt(long = "no-cache")]
     pub no_cache: bool,
+
+    /// Rewrite the file path of grep and files results relative to this directory for display.
+    ///
+    /// The original line is still used as the raw text of the item.
+    #[structopt(long = "relative-to", parse(from_os_str = utility::paths::expand_os))]
+    pub relative_to: Option<std::path::PathBuf>,
+
+    /// Exclude the items whose path components match this glob, can be specified multiple times.
+    #[structopt(long = "exclude", number_of_values = 1)]
+    pub exclude: Vec<String>,
+
+    /// Score only a part of the line longer than this many chars, e.g., minified files.
+    #[structopt(long = "max-line-len")]
+    pub max_line_len: Option<usize>,
+
+    /// Which part of the line longer than --max-line-len to score.
+    #[structopt(
+        long = "long-line-strategy",
+        default_value = "Prefix",
+        possible_values = &LongLineStrategy::variants(),
+        case_insensitive = true
+    )]
+    pub long_line_strategy: LongLineStrategy,
+
+    /// Sort the results of the same score by these criteria in order, e.g., `begin,length`.
+    ///
+    /// `length` prefers the shorter line, `begin` and `end` prefer the line matched closer to the
+    /// beginning and the end, `index` keeps the source order, the default. Only applied when
+    /// sorting by score.
+    #[structopt(long = "tiebreak", use_delimiter = true)]
+    pub tiebreak: Vec<Tiebreak>,
 }
 
 impl Params {
+    /// Returns the cutoff for the long line if --max-line-len is specified.
+    pub fn line_cutoff(&self) -> Option<LineCutoff> {
+        self.max_line_len
+            .map(|max_len| LineCutoff::new(max_len, self.long_line_strategy.clone()))
+    }
+
     pub fn into_filter_context(self) -> FilterContext {
         FilterContext::default()
+            .line_cutoff(self.line_cutoff())
             .number(self.number)
             .winwidth(self.winwidth)
             .icon_painter(self.icon_painter)
+            .tiebreak(self.tiebreak)
+    }
+}
+
+impl Cmd {
+    /// Returns the working directory of the command, where the project config is looked up.
+    fn cmd_dir(&self) -> Option<&Path> {
+        match self {
+            Self::Grep(grep) => grep.cmd_dir(),
+            Self::RipGrepForerunner(rip_grep_forerunner) => rip_grep_forerunner.cmd_dir(),
+            Self::Filter(filter) => filter.cmd_dir(),
+            _ => None,
+        }
+    }
+
+    /// Checks the combinations of the options of the command, see [`ParamsBuilder`].
+    ///
+    /// Only exec, grep, forerunner and filter have the options depending on each other, the
+    /// options of the other commands are valid in any combination or checked by structopt.
+    pub fn validate(&self, params: &Params) -> Result<(), ParamsError> {
+        match self {
+            Self::Exec(exec) => exec.validate(params),
+            Self::Grep(grep) => grep.validate(),
+            Self::RipGrepForerunner(rip_grep_forerunner) => rip_grep_forerunner.validate(),
+            Self::Filter(filter) => filter.validate(),
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns the query of the command, recorded by `--record`.
+    pub fn query(&self) -> Option<&str> {
+        match self {
+            Self::Grep(grep) => Some(grep.query()),
+            Self::Filter(filter) => Some(filter.query()),
+            _ => None,
+        }
     }
 }
 
+/// Initializes the global rayon thread pool used by the filtering pipeline.
+fn init_thread_pool(threads: usize) -> Result<()> {
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .build_global()?;
+    Ok(())
+}
+
 impl Maple {
-    pub async fn run(self) -> Result<()> {
+    pub async fn run(mut self) -> Result<()> {
+        match self.record.take() {
+            Some(record_file) => {
+                let recording = crate::cmd::replay::Recording::start(&self.command);
+                let result = self.execute().await;
+                if let Err(e) = recording.finish(&record_file, result.is_ok()) {
+                    log::error!("Failed to record the invocation: {:?}", e);
+                }
+                result
+            }
+            None => self.execute().await,
+        }
+    }
+
+    async fn execute(mut self) -> Result<()> {
+        // The completion script depends on nothing else, even an invalid config file.
+        if let Cmd::Completions(ref completions) = self.command {
+            return completions.run();
+        }
+
+        let params = std::mem::take(&mut self.params);
+        self.params = ParamsBuilder::from(params)
+            .build(&self.command)
+            .map_err(|e| {
+                e.print();
+                e
+            })?;
+
+        // The logger is ready before loading the config, whose invalid parts are logged.
+        if let Some(ref log_path) = self.log {
+            crate::logger::init(log_path)?;
+        } else if let (Cmd::Rpc, Ok(log_path)) = (&self.command, std::env::var("VIM_CLAP_LOG_PATH"))
+        {
+            crate::logger::init(log_path)?;
+        }
+
+        let project_dir = match self.command.cmd_dir() {
+            Some(dir) => dir.to_path_buf(),
+            None => std::env::current_dir()?,
+        };
+        let config = Config::load_for_dir(self.config_file.as_deref(), &project_dir)?;
+        self.params.exclude.extend(config.exclude.iter().cloned());
+        if let Some(threads) = self.threads.or(config.threads) {
+            init_thread_pool(threads)?;
+        }
+        let cache_compression = match self.cache_compression {
+            Some(compression) => Some(compression),
+            None => config
+                .cache_compression
+                .as_deref()
+                .map(str::parse)
+                .transpose()?,
+        };
+        if let Some(compression) = cache_compression {
+            crate::cmd::cache::set_cache_compression(compression);
+        }
+        printer::index_format::set_index_format(self.index_format);
+        if atty::is(atty::Stream::Stdout) {
+            printer::output_format::set_output_format(self.output_format);
+        }
+        printer::shorten_path::set_shorten_path(self.shorten_path);
+        filter::matcher::normalize::set_normalize(self.normalize);
+        filter::matcher::typo::set_typo_tolerant(self.typo_tolerant);
+        printer::writer::set_spill_threshold(self.spill_threshold);
+        filter::set_max_file_size(self.max_file_size);
+        if self.auto_limit {
+            filter::set_max_retained(crate::tools::memory::auto_limit());
+        }
+        crate::dumb_analyzer::set_user_patterns(&config.dumb_analyzer);
+        if self.icon_git_status {
+            icon::git_status::enable();
+        }
+        let env_var = |var: &str| std::env::var(var).ok();
+        if self.params.icon_painter.is_some() && !icon::width::icons_enabled_with(env_var) {
+            self.params.icon_painter = None;
+        }
+        // The lines without icons are not narrowed by the wider icon prefix.
+        if self.params.icon_painter.is_some() {
+            icon::width::set_icon_width(
+                self.icon_width
+                    .unwrap_or_else(|| icon::width::icon_width_with(self.ambiwidth, env_var)),
+            );
+        }
+
         match self.command {
             Cmd::Version | Cmd::Upgrade(_) => unreachable!("Version and Upgrade are unusable"),
             Cmd::Exec(exec) => exec.run(self.params)?,
-            Cmd::Grep(grep) => grep.run(self.params)?,
+            Cmd::Grep(grep) => grep
+                .config_flags(config.grep_flags.clone())
+                .run(self.params)?,
             Cmd::Tags(tags) => tags.run(self.params)?,
             Cmd::Cache(cache) => cache.run()?,
+            Cmd::Jobs(jobs) => jobs.run()?,
+            Cmd::Watch(watch) => watch.run()?,
             Cmd::Blines(blines) => blines.run(self.params)?,
-            Cmd::Filter(filter) => filter.run(self.params)?,
+            Cmd::BufferTags(buffer_tags) => buffer_tags.run(self.params)?,
+            Cmd::Lines(lines) => lines.run(self.params)?,
+            Cmd::Marks(marks) => marks.run(self.params)?,
+            Cmd::Registers(registers) => registers.run(self.params)?,
+            Cmd::Commits(commits) => commits.run(self.params)?,
+            Cmd::Todos(todos) => todos.run(self.params)?,
+            Cmd::Provider(provider) => provider.run(self.params, &config)?,
+            Cmd::Pipe(pipe) => pipe.run(self.params)?,
+            Cmd::LspSymbols(lsp_symbols) => lsp_symbols.run(self.params)?,
+            Cmd::Plugin(plugin) => plugin.run(self.params)?,
+            Cmd::Filter(filter) => filter
+                .default_bonus(config.bonus.as_deref())
+                .run(self.params)?,
+            Cmd::Tui(tui) => tui.run(self.params)?,
             Cmd::Helptags(helptags) => helptags.run()?,
+            Cmd::History(history) => history.run(self.params)?,
+            Cmd::RankCheck(rank_check) => rank_check.run()?,
+            Cmd::Replay(replay) => replay.run()?,
+            Cmd::Completions(_) => unreachable!("Completions is handled before loading the config"),
+            Cmd::DetectFiletype(detect_filetype) => detect_filetype.run()?,
             Cmd::DumbJump(dumb_jump) => dumb_jump.run().await?,
-            Cmd::RipGrepForerunner(rip_grep_forerunner) => rip_grep_forerunner.run(self.params)?,
+            Cmd::RipGrepForerunner(rip_grep_forerunner) => rip_grep_forerunner
+                .config_flags(config.grep_flags.clone())
+                .run(self.params)?,
             Cmd::Rpc => {
-                if let Some(ref log_path) = self.log {
-                    crate::logger::init(log_path)?;
-                } else if let Ok(log_path) = std::env::var("VIM_CLAP_LOG_PATH") {
-                    crate::logger::init(log_path)?;
-                }
-
                 crate::stdio_server::run_forever(std::io::BufReader::new(std::io::stdin()));
             }
         };
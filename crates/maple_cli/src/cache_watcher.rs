@@ -0,0 +1,483 @@
+//! Keeps the files and grep caches of a project up to date by watching the file changes.
+//!
+//! The cache entries are updated incrementally instead of being rebuilt from scratch:
+//!
+//! - files cache: the created files are appended and the removed ones are dropped.
+//! - grep cache: the lines of the changed files are dropped and the files are searched again.
+//!
+//! The hidden files and the files ignored by the ignore files of the project, e.g., `target/`,
+//! are never added, run the provider with `+no-cache` for an exact rebuild.
+
+use std::collections::HashSet;
+use std::path::{Component, Path, PathBuf};
+use std::process::Command;
+use std::sync::mpsc::RecvTimeoutError;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use notify::{DebouncedEvent, RecursiveMode, Watcher};
+use once_cell::sync::Lazy;
+
+use utility::{compression::open_decompressed, dirs, is_cache_meta};
+
+use crate::cmd::cache::{CacheEntry, CacheMeta};
+
+/// Delay of the file events to coalesce the bursts of writes to the same file.
+const DEBOUNCE_DELAY: Duration = Duration::from_millis(500);
+
+/// The changes arriving within this interval are applied together.
+const BATCH_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Directories watched by the watchers spawned in this process.
+static WATCHED_DIRS: Lazy<Mutex<HashSet<PathBuf>>> = Lazy::new(Default::default);
+
+/// Change of a file in the project, the path is relative to the project root.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change {
+    Created(PathBuf),
+    Modified(PathBuf),
+    Removed(PathBuf),
+    /// The events may be lost, all the caches have to be rebuilt.
+    Rescan,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CacheKind {
+    Files,
+    Grep,
+}
+
+/// Returns the kind of cache produced by `args`, the caches of other commands are left untouched.
+fn cache_kind(args: &[String]) -> Option<CacheKind> {
+    let has_arg = |arg: &str| args.iter().any(|a| a == arg);
+    match args.first().map(String::as_str) {
+        Some("fd") | Some("fdfind") | Some("find") => Some(CacheKind::Files),
+        Some("rg") if has_arg("--files") => Some(CacheKind::Files),
+        // The grep forerunner always ends with the empty pattern.
+        Some("rg") if args.last().map(String::as_str) == Some("") => Some(CacheKind::Grep),
+        _ => None,
+    }
+}
+
+fn is_hidden(path: &Path) -> bool {
+    path.components().any(|c| match c {
+        Component::Normal(name) => name.to_string_lossy().starts_with('.'),
+        _ => false,
+    })
+}
+
+/// Builds the matcher of the ignore files of project `root`, i.e., the ones in `root` and its
+/// ancestors, the ignore files in the subdirectories are not respected.
+fn build_ignore(root: &Path) -> Gitignore {
+    let mut builder = GitignoreBuilder::new(root);
+    for ignore_file in crate::tools::ignore::ignore_files(root) {
+        if let Some(e) = builder.add(&ignore_file.path) {
+            log::debug!("Invalid ignore file {}: {}", ignore_file.path.display(), e);
+        }
+    }
+    builder.build().unwrap_or_else(|e| {
+        log::error!(
+            "Failed to build the ignore matcher of {}: {}",
+            root.display(),
+            e
+        );
+        Gitignore::empty()
+    })
+}
+
+/// Returns `path` relative to `root`, the changes of the hidden and ignored files are ignored.
+fn relative_change_path(path: &Path, root: &Path, ignore: &Gitignore) -> Option<PathBuf> {
+    let relative = path.strip_prefix(root).ok()?;
+    if relative.as_os_str().is_empty()
+        || is_hidden(relative)
+        || ignore
+            .matched_path_or_any_parents(relative, path.is_dir())
+            .is_ignore()
+    {
+        None
+    } else {
+        Some(relative.to_path_buf())
+    }
+}
+
+/// Converts the file event into the changes of project `root`.
+fn changes_of(event: &DebouncedEvent, root: &Path, ignore: &Gitignore) -> Vec<Change> {
+    let relative = |path: &Path| relative_change_path(path, root, ignore);
+    match event {
+        DebouncedEvent::Create(path) => relative(path).map(Change::Created).into_iter().collect(),
+        DebouncedEvent::Write(path) => relative(path).map(Change::Modified).into_iter().collect(),
+        DebouncedEvent::Remove(path) => relative(path).map(Change::Removed).into_iter().collect(),
+        DebouncedEvent::Rename(from, to) => relative(from)
+            .map(Change::Removed)
+            .into_iter()
+            .chain(relative(to).map(Change::Created))
+            .collect(),
+        DebouncedEvent::Rescan => vec![Change::Rescan],
+        _ => Vec::new(),
+    }
+}
+
+/// Returns the line of cache in the form of the relative path, e.g., `./src/lib.rs` of `find`.
+fn normalize_line(line: &str) -> &str {
+    line.strip_prefix("./").unwrap_or(line)
+}
+
+/// Returns true if `line_path` is `path` or a file under the directory `path`.
+fn is_affected(line_path: &str, path: &str) -> bool {
+    let line_path = normalize_line(line_path);
+    line_path == path
+        || (line_path.starts_with(path)
+            && line_path[path.len()..].starts_with(|c| c == '/' || c == std::path::MAIN_SEPARATOR))
+}
+
+fn path_str(path: &Path) -> String {
+    path.to_string_lossy().into_owned()
+}
+
+/// Applies the changes to the lines of files cache.
+///
+/// `is_file` tells whether the created path is a file, the directories are not listed.
+fn update_files_lines(
+    lines: &mut Vec<String>,
+    changes: &[Change],
+    is_file: impl Fn(&Path) -> bool,
+) -> bool {
+    let mut updated = false;
+    for change in changes {
+        match change {
+            Change::Created(path) => {
+                let path_str = path_str(path);
+                if is_file(path) && !lines.iter().any(|line| normalize_line(line) == path_str) {
+                    lines.push(path_str);
+                    updated = true;
+                }
+            }
+            Change::Removed(path) => {
+                let path_str = path_str(path);
+                let old_len = lines.len();
+                lines.retain(|line| !is_affected(line, &path_str));
+                updated |= lines.len() != old_len;
+            }
+            Change::Modified(_) | Change::Rescan => {}
+        }
+    }
+    updated
+}
+
+/// Applies the changes to the lines of grep cache.
+///
+/// `search` returns the grep lines of a changed file, `None` if it's gone.
+fn update_grep_lines(
+    lines: &mut Vec<String>,
+    changes: &[Change],
+    search: impl Fn(&Path) -> Option<Vec<String>>,
+) -> bool {
+    let mut updated = false;
+    for change in changes {
+        let path = match change {
+            Change::Created(path) | Change::Modified(path) | Change::Removed(path) => path,
+            Change::Rescan => continue,
+        };
+        let path_str = path_str(path);
+        let is_affected_line = |line: &String| {
+            pattern::extract_fpath_from_grep_line(line)
+                .map(|line_path| is_affected(line_path, &path_str))
+                .unwrap_or(false)
+        };
+        // The new lines are spliced where the old lines were to keep the order of files.
+        let splice_at = lines.iter().position(is_affected_line);
+        let old_len = lines.len();
+        lines.retain(|line| !is_affected_line(line));
+        updated |= lines.len() != old_len;
+        if let Some(new_lines) = search(path) {
+            updated |= !new_lines.is_empty();
+            let splice_at = splice_at.unwrap_or_else(|| lines.len());
+            lines.splice(splice_at..splice_at, new_lines);
+        }
+    }
+    updated
+}
+
+/// Searches the file `path` again using the args of the grep cache.
+fn grep_file(args: &[String], root: &Path, path: &Path) -> Option<Vec<String>> {
+    if !root.join(path).is_file() {
+        return None;
+    }
+    let output = Command::new(&args[0])
+        .args(&args[1..])
+        .arg("--with-filename")
+        .arg(path)
+        .current_dir(root)
+        .output()
+        .ok()?;
+    Some(
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(Into::into)
+            .collect(),
+    )
+}
+
+/// Applies the changes to the cache `entry`, returns true if it's rewritten.
+fn update_cache_entry(
+    entry: &Path,
+    meta: &CacheMeta,
+    root: &Path,
+    changes: &[Change],
+) -> Result<bool> {
+    let kind = match cache_kind(&meta.args) {
+        Some(kind) => kind,
+        None => return Ok(false),
+    };
+    let args = meta.args.iter().map(String::as_str).collect::<Vec<_>>();
+
+    // The forerunner is writing a new cache, which is as fresh as the changes.
+    let _lock = match CacheEntry::try_lock(&args, root)? {
+        Some(lock) => lock,
+        None => return Ok(false),
+    };
+
+    if changes.contains(&Change::Rescan) {
+        std::fs::remove_file(entry)?;
+        return Ok(true);
+    }
+
+    let mut contents = Vec::new();
+    std::io::Read::read_to_end(&mut open_decompressed(entry)?, &mut contents)?;
+    let mut lines = match String::from_utf8(contents) {
+        Ok(contents) => contents.lines().map(Into::into).collect::<Vec<String>>(),
+        // The lines can't be matched reliably, drop the cache instead.
+        Err(_) => {
+            std::fs::remove_file(entry)?;
+            return Ok(true);
+        }
+    };
+
+    let updated = match kind {
+        CacheKind::Files => {
+            update_files_lines(&mut lines, changes, |path| root.join(path).is_file())
+        }
+        CacheKind::Grep => update_grep_lines(&mut lines, changes, |path| {
+            grep_file(&meta.args, root, path)
+        }),
+    };
+
+    if updated {
+        let mut contents = lines.join("\n");
+        contents.push('\n');
+        CacheEntry::create(&args, Some(root), lines.len(), contents)?;
+    }
+
+    Ok(updated)
+}
+
+/// Applies the changes to all the files and grep caches of project `root`.
+///
+/// Returns the number of updated cache entries.
+pub fn apply_changes(root: &Path, changes: &[Change]) -> Result<usize> {
+    let project_dir = dirs::project_cache_dir(root);
+    let cache_dirs = match std::fs::read_dir(&project_dir) {
+        Ok(entries) => entries,
+        Err(_) => return Ok(0),
+    };
+
+    let mut updated = 0;
+    for cache_dir in cache_dirs.filter_map(|e| e.ok()).map(|e| e.path()) {
+        let metas = match std::fs::read_dir(&cache_dir) {
+            Ok(entries) => entries,
+            Err(_) => continue,
+        };
+        for meta_path in metas
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|p| is_cache_meta(p))
+        {
+            let entry = meta_path.with_extension("");
+            let meta = match CacheMeta::read_for(&entry) {
+                Ok(meta) if entry.exists() && meta.cwd.as_deref() == Some(root) => meta,
+                _ => continue,
+            };
+            match update_cache_entry(&entry, &meta, root, changes) {
+                Ok(true) => updated += 1,
+                Ok(false) => {}
+                Err(e) => log::error!("Failed to update the cache {}: {:?}", entry.display(), e),
+            }
+        }
+    }
+
+    Ok(updated)
+}
+
+/// Searches the file `file` of project `root` again and splices the new lines into the grep
+/// caches, returns the number of updated cache entries.
+pub fn refresh_file(root: &Path, file: &Path) -> Result<usize> {
+    let relative = if file.is_absolute() {
+        file.strip_prefix(root)
+            .map_err(|_| anyhow!("{} is not in {}", file.display(), root.display()))?
+    } else {
+        file
+    };
+    apply_changes(root, &[Change::Modified(relative.to_path_buf())])
+}
+
+/// Watches the project `root` and keeps its caches up to date, `on_update` is called with the
+/// number of updated caches after each batch of changes.
+pub fn watch(root: &Path, on_update: impl Fn(usize)) -> Result<()> {
+    let (tx, rx) = std::sync::mpsc::channel();
+    let mut watcher = notify::watcher(tx, DEBOUNCE_DELAY)?;
+    watcher.watch(root, RecursiveMode::Recursive)?;
+
+    // The events may be reported for the canonical path.
+    let canonical_root = root.canonicalize()?;
+    let to_changes = |event: DebouncedEvent, ignore: &Gitignore| {
+        let changes = changes_of(&event, root, ignore);
+        if changes.is_empty() && canonical_root != root {
+            changes_of(&event, &canonical_root, ignore)
+        } else {
+            changes
+        }
+    };
+
+    // The watcher stops once all the senders are dropped.
+    while let Ok(event) = rx.recv() {
+        // Built for each batch in case the ignore files are changed.
+        let ignore = build_ignore(root);
+        let mut changes = to_changes(event, &ignore);
+        loop {
+            match rx.recv_timeout(BATCH_INTERVAL) {
+                Ok(event) => changes.extend(to_changes(event, &ignore)),
+                Err(RecvTimeoutError::Timeout) => break,
+                Err(RecvTimeoutError::Disconnected) => return Ok(()),
+            }
+        }
+        if changes.is_empty() {
+            continue;
+        }
+        log::debug!("Applying the changes of {}: {:?}", root.display(), changes);
+        on_update(apply_changes(root, &changes)?);
+    }
+
+    Ok(())
+}
+
+/// Starts watching the project `root` in a background thread unless it's watched already.
+pub fn spawn_watcher(root: PathBuf) {
+    if !WATCHED_DIRS.lock().unwrap().insert(root.clone()) {
+        return;
+    }
+    std::thread::spawn(move || {
+        if let Err(e) = watch(&root, |_| {}) {
+            log::error!("Failed to watch {}: {:?}", root.display(), e);
+        }
+        WATCHED_DIRS.lock().unwrap().remove(&root);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(lines: &[&str]) -> Vec<String> {
+        lines.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_update_files_lines() {
+        let mut files = lines(&["src/lib.rs", "./src/main.rs", "src/cmd/mod.rs", "README.md"]);
+        let changes = vec![
+            Change::Created("src/new.rs".into()),
+            Change::Created("src/lib.rs".into()),
+            Change::Created("src/new_dir".into()),
+            Change::Removed("src/main.rs".into()),
+            Change::Removed("src/cmd".into()),
+        ];
+        assert!(update_files_lines(&mut files, &changes, |p| p != Path::new("src/new_dir")));
+        assert_eq!(files, lines(&["src/lib.rs", "README.md", "src/new.rs"]));
+        assert!(!update_files_lines(
+            &mut files,
+            &[Change::Modified("README.md".into())],
+            |_| true
+        ));
+    }
+
+    #[test]
+    fn test_update_grep_lines() {
+        let mut grep_lines = lines(&[
+            "README.md:1:1:readme",
+            "src/lib.rs:1:1:old",
+            "src/lib.rs:9:1:old",
+            "src/main.rs:2:3:main",
+            "tests/lib.rs:1:1:test",
+        ]);
+        let changes = vec![
+            Change::Modified("src/lib.rs".into()),
+            Change::Removed("src/main.rs".into()),
+        ];
+        let search = |path: &Path| {
+            if path == Path::new("src/lib.rs") {
+                Some(lines(&["src/lib.rs:3:1:new"]))
+            } else {
+                None
+            }
+        };
+        assert!(update_grep_lines(&mut grep_lines, &changes, search));
+        assert_eq!(
+            grep_lines,
+            lines(&[
+                "README.md:1:1:readme",
+                "src/lib.rs:3:1:new",
+                "tests/lib.rs:1:1:test"
+            ])
+        );
+    }
+
+    #[test]
+    fn test_changes_of() {
+        let root = Path::new("/project");
+        let ignore = Gitignore::empty();
+        assert_eq!(
+            changes_of(
+                &DebouncedEvent::Rename("/project/a.rs".into(), "/project/b.rs".into()),
+                root,
+                &ignore
+            ),
+            vec![
+                Change::Removed("a.rs".into()),
+                Change::Created("b.rs".into())
+            ]
+        );
+        assert!(changes_of(
+            &DebouncedEvent::Write("/project/.git/index".into()),
+            root,
+            &ignore
+        )
+        .is_empty());
+        assert!(changes_of(
+            &DebouncedEvent::Write("/elsewhere/a.rs".into()),
+            root,
+            &ignore
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn test_ignored_changes() {
+        let root =
+            std::env::temp_dir().join(format!("clap_test_watcher_ignore_{}", std::process::id()));
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::write(root.join(".ignore"), "target/\nnode_modules/\n").unwrap();
+
+        let ignore = build_ignore(&root);
+        let write = |path: &str| DebouncedEvent::Write(root.join(path));
+        assert!(changes_of(&write("target/debug/build.log"), &root, &ignore).is_empty());
+        assert!(changes_of(&write("node_modules/foo/index.js"), &root, &ignore).is_empty());
+        assert_eq!(
+            changes_of(&write("src/lib.rs"), &root, &ignore),
+            vec![Change::Modified("src/lib.rs".into())]
+        );
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
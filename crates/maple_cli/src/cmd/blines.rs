@@ -15,7 +15,7 @@ pub struct Blines {
     query: String,
 
     /// File path of current vim buffer.
-    #[structopt(index = 2, short, long, parse(from_os_str))]
+    #[structopt(index = 2, short, long, parse(from_os_str = utility::paths::expand_os))]
     input: PathBuf,
 }
 
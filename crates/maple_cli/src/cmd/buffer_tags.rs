@@ -0,0 +1,224 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use structopt::StructOpt;
+
+use filter::{
+    matcher::{Algo, Bonus, MatchType},
+    Source,
+};
+use source_item::SourceItem;
+
+use crate::app::Params;
+use crate::dumb_analyzer::{Analyzer, Symbol};
+use crate::tools::ctags::ensure_has_json_support;
+
+/// Returns the icon of the tag `kind`, the kinds of ctags and the built-in symbol extraction.
+fn kind_icon(kind: &str) -> char {
+    match kind {
+        "function" | "method" | "func" => '\u{f794}',
+        "struct" | "class" => '\u{f0e8}',
+        "enum" => '\u{f435}',
+        "interface" | "trait" => '\u{f417}',
+        "module" | "namespace" | "package" => '\u{f487}',
+        "macro" => '\u{f0ad}',
+        "constant" => '\u{f8fe}',
+        "variable" | "field" | "member" => '\u{f71b}',
+        "type" | "typedef" => '\u{f7fd}',
+        _ => '\u{f02b}',
+    }
+}
+
+/// A symbol in the buffer.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+struct BufferTag {
+    name: String,
+    kind: String,
+    line: usize,
+}
+
+impl From<Symbol> for BufferTag {
+    fn from(symbol: Symbol) -> Self {
+        let Symbol { name, kind, line } = symbol;
+        Self { name, kind, line }
+    }
+}
+
+impl BufferTag {
+    /// Builds the item `[icon ]name:line [kind]`, only the name is used for matching.
+    fn build_item(&self, enable_icon: bool) -> SourceItem {
+        let prefix = if enable_icon {
+            format!("{} ", kind_icon(&self.kind))
+        } else {
+            String::new()
+        };
+        let offset = prefix.chars().count();
+        SourceItem::new(
+            format!(
+                "{}{:<30} [{}]",
+                prefix,
+                format!("{}:{}", self.name, self.line),
+                self.kind
+            ),
+            Some((self.name.clone(), offset)),
+            None,
+        )
+    }
+}
+
+/// Runs ctags on the single `file`.
+fn ctags_tags(file: &Path) -> Result<Vec<BufferTag>> {
+    ensure_has_json_support()?;
+    let output = Command::new("ctags")
+        .args(&["--output-format=json", "--fields=+n", "-f", "-"])
+        .arg(file)
+        .output()?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "ctags failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| serde_json::from_str::<BufferTag>(line).ok())
+        .collect())
+}
+
+/// Fuzzy filter the symbols of a file, e.g., for the document outline.
+///
+/// The file is tagged by ctags, or by the built-in symbol extraction for the common languages if
+/// ctags is unavailable.
+#[derive(StructOpt, Debug, Clone)]
+pub struct BufferTags {
+    /// Initial query string
+    #[structopt(index = 1, short, long, default_value = "")]
+    query: String,
+
+    /// File to list the tags of.
+    #[structopt(long, parse(from_os_str = utility::paths::expand_os))]
+    file: PathBuf,
+}
+
+impl BufferTags {
+    fn collect_tags(&self) -> Result<Vec<BufferTag>> {
+        match ctags_tags(&self.file) {
+            Ok(tags) => Ok(tags),
+            Err(e) => {
+                log::debug!("Fallback to the built-in symbol extraction: {}", e);
+                let filetype = filetype::detect(&self.file)
+                    .ok_or_else(|| anyhow!("Unknown filetype of {}", self.file.display()))?;
+                let content = std::fs::read_to_string(&self.file)?;
+                Ok(Analyzer::new(filetype)?
+                    .analyze(&content)
+                    .into_iter()
+                    .map(Into::into)
+                    .collect())
+            }
+        }
+    }
+
+    pub fn run(&self, params: Params) -> Result<()> {
+        let line_cutoff = params.line_cutoff();
+        let Params {
+            number,
+            winwidth,
+            icon_painter,
+            ..
+        } = params;
+
+        let mut tags = self.collect_tags()?;
+        tags.sort_by_key(|tag| tag.line);
+
+        let mut tags_by_line = HashMap::new();
+        let items = tags
+            .into_iter()
+            .map(|tag| {
+                let item = tag.build_item(icon_painter.is_some());
+                tags_by_line.insert(item.raw.clone(), tag);
+                item
+            })
+            .collect::<Vec<_>>();
+
+        // The tags are in the order of line without a query, like an outline.
+        let ranked = if self.query.is_empty() {
+            items
+                .into_iter()
+                .map(|item| (item, 0, Vec::new()))
+                .collect()
+        } else {
+            filter::sync_run(
+                &self.query,
+                Source::List(items.into_iter()),
+                Algo::Fzy,
+                MatchType::Full,
+                vec![Bonus::Acronym],
+                line_cutoff,
+            )?
+        };
+
+        if let Some(number) = number {
+            let total = ranked.len();
+            let tags = ranked
+                .iter()
+                .take(number)
+                .map(|(item, _, _)| &tags_by_line[&item.raw])
+                .collect::<Vec<_>>();
+            let (lines, indices, truncated_map) = printer::process_top_items(
+                ranked.into_iter().take(number),
+                utility::tty::display_width(winwidth, 100),
+                None,
+            );
+            let mut payload = serde_json::json!({
+                "total": total,
+                "lines": lines,
+                "indices": indices,
+                "tags": tags,
+            });
+            if !truncated_map.is_empty() {
+                payload["truncated_map"] = serde_json::json!(truncated_map);
+            }
+            printer::println_payload(payload);
+        } else {
+            for (item, _, indices) in ranked.into_iter() {
+                let tag = &tags_by_line[&item.raw];
+                printer::println_payload(serde_json::json!({
+                    "text": item.raw,
+                    "indices": indices,
+                    "tag": tag,
+                }));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_item() {
+        let tag = BufferTag {
+            name: "new".into(),
+            kind: "function".into(),
+            line: 7,
+        };
+        let item = tag.build_item(false);
+        assert!(item.raw.starts_with("new:7 "));
+        assert!(item.raw.ends_with(" [function]"));
+
+        // The kind is not matched.
+        assert!(Algo::Fzy
+            .apply_match("func", &item, &MatchType::Full)
+            .is_none());
+
+        let ctags_line = r#"{"_type": "tag", "name": "Foo", "path": "a.rs", "pattern": "/^struct Foo {$/", "line": 2, "kind": "struct"}"#;
+        let tag = serde_json::from_str::<BufferTag>(ctags_line).unwrap();
+        assert_eq!(tag.line, 2);
+    }
+}
@@ -1,18 +1,25 @@
-use std::fs::{read_dir, DirEntry, File};
+use std::fs::read_dir;
 use std::hash::Hash;
-use std::io::Write;
 use std::path::{self, Path, PathBuf};
 use std::time::SystemTime;
 
 use anyhow::{anyhow, Result};
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
 use structopt::StructOpt;
 
 use icon::IconPainter;
 use utility::{
-    calculate_hash, clap_cache_dir, get_cached_entry, println_json, println_json_with_length,
-    read_first_lines, remove_dir_contents,
+    cache_meta_path,
+    cache_writer::{self, temp_file_writer, write_atomically, CacheLock},
+    clap_cache_dir,
+    compression::Compression,
+    dirs, get_cache_dir, get_cached_entry, is_git_repo, is_partial_cache, println_json,
+    read_first_lines, remove_dir_contents, PARTIAL_CACHE_ENTRY,
 };
 
+use crate::tools::ignore::{ignore_files, IgnoreFile};
+
 /// List and remove all the cached contents.
 #[derive(StructOpt, Debug, Clone)]
 pub struct Cache {
@@ -23,13 +30,76 @@ pub struct Cache {
     /// Purge all the cached contents.
     #[structopt(short, long)]
     purge: bool,
+
+    /// Only list or purge the cached contents of the project in this directory.
+    #[structopt(long, parse(from_os_str = utility::paths::expand_os))]
+    project: Option<PathBuf>,
+
+    #[structopt(subcommand)]
+    cmd: Option<CacheCommand>,
+}
+
+#[derive(StructOpt, Debug, Clone)]
+enum CacheCommand {
+    /// Search a file again and splice the new lines into the grep caches of the project.
+    #[structopt(name = "refresh-file")]
+    RefreshFile(RefreshFile),
+    /// Print the contents of a cache file, which is decompressed if necessary.
+    #[structopt(name = "cat")]
+    Cat(Cat),
+}
+
+#[derive(StructOpt, Debug, Clone)]
+struct Cat {
+    /// The cache file to print.
+    #[structopt(index = 1, parse(from_os_str = utility::paths::expand_os))]
+    file: PathBuf,
+}
+
+impl Cat {
+    fn run(&self) -> Result<()> {
+        let mut reader = utility::compression::open_decompressed(&self.file)?;
+        let stdout = std::io::stdout();
+        let mut lock = stdout.lock();
+        std::io::copy(&mut reader, &mut lock)?;
+        Ok(())
+    }
+}
+
+#[derive(StructOpt, Debug, Clone)]
+struct RefreshFile {
+    /// Root directory of the project.
+    #[structopt(long, parse(from_os_str = utility::paths::expand_os))]
+    dir: PathBuf,
+
+    /// The file changed, either absolute or relative to --dir.
+    #[structopt(long, parse(from_os_str = utility::paths::expand_os))]
+    file: PathBuf,
+}
+
+impl RefreshFile {
+    fn run(&self) -> Result<()> {
+        let updated = crate::cache_watcher::refresh_file(&self.dir, &self.file)?;
+        println_json!(updated);
+        Ok(())
+    }
 }
 
 impl Cache {
     pub fn run(&self) -> Result<()> {
-        let cache_dir = clap_cache_dir();
+        match self.cmd {
+            Some(CacheCommand::RefreshFile(ref refresh_file)) => return refresh_file.run(),
+            Some(CacheCommand::Cat(ref cat)) => return cat.run(),
+            None => {}
+        }
+        let cache_dir = match self.project {
+            Some(ref project) => dirs::project_cache_dir(project),
+            None => clap_cache_dir(),
+        };
         if self.purge {
-            remove_dir_contents(&cache_dir)?;
+            if cache_dir.exists() {
+                remove_dir_contents(&cache_dir)?;
+            }
             println!(
                 "Current cache directory {} has been purged",
                 cache_dir.display()
@@ -48,31 +118,72 @@ impl Cache {
         println!("\t{}\n", cache_dir_str);
         if self.list {
             println!("Cached entries:");
-            let mut entries = read_dir(cache_dir)?
-                .map(|res| {
-                    res.map(|e| {
-                        e.path()
-                            .file_name()
-                            .and_then(std::ffi::OsStr::to_str)
-                            .map(Into::into)
-                            .unwrap_or_else(|| panic!("Couldn't get file name from {:?}", e.path()))
+            let mut entries = if cache_dir.exists() {
+                read_dir(cache_dir)?
+                    .map(|res| {
+                        res.map(|e| {
+                            e.path()
+                                .file_name()
+                                .and_then(std::ffi::OsStr::to_str)
+                                .map(Into::into)
+                                .unwrap_or_else(|| {
+                                    panic!("Couldn't get file name from {:?}", e.path())
+                                })
+                        })
                     })
-                })
-                .collect::<Result<Vec<String>, std::io::Error>>()?;
+                    .collect::<Result<Vec<String>, std::io::Error>>()?
+            } else {
+                Vec::new()
+            };
 
             entries.sort();
 
             for fname in entries {
                 println!("\t{}{}{}", cache_dir_str, path::MAIN_SEPARATOR, fname);
             }
+
+            if self.project.is_none() {
+                println!("\nCached projects:");
+                for (root, dir) in dirs::list_projects() {
+                    println!("\t{}: {}", root.display(), dir.display());
+                }
+            }
         }
         Ok(())
     }
 }
 
+static CACHE_COMPRESSION: OnceCell<Compression> = OnceCell::new();
+
+/// Sets the compression of the cache entries written by this process, should be called once.
+pub fn set_cache_compression(compression: Compression) {
+    if CACHE_COMPRESSION.set(compression).is_err() {
+        log::debug!("Cache compression has been set");
+    }
+}
+
+fn cache_compression() -> Compression {
+    CACHE_COMPRESSION.get().copied().unwrap_or_default()
+}
+
 pub struct CacheEntry;
 
 impl CacheEntry {
+    /// Returns the directory of cache entries given command arguments and its working directory.
+    ///
+    /// The directory will be created if it does not exist yet.
+    fn cache_dir<T: AsRef<Path> + Hash>(cmd_args: &[&str], cmd_dir: Option<T>) -> Result<PathBuf> {
+        let mut dir = match cmd_dir {
+            Some(cmd_dir) => dirs::create_project_cache_dir(cmd_dir.as_ref())?,
+            None => dirs::no_project_cache_dir(),
+        };
+        dir.push(cmd_args.join("_"));
+        if !dir.exists() {
+            std::fs::create_dir_all(&dir)?;
+        }
+        Ok(dir)
+    }
+
     /// Construct the cache entry given command arguments and its working directory, the `total`
     /// info is cached in the file name.
     pub fn try_new<T: AsRef<Path> + Hash>(
@@ -80,16 +191,7 @@ impl CacheEntry {
         cmd_dir: Option<T>,
         total: usize,
     ) -> Result<PathBuf> {
-        let mut dir = clap_cache_dir();
-        dir.push(cmd_args.join("_"));
-        if let Some(cmd_dir) = cmd_dir {
-            dir.push(format!("{}", calculate_hash(&cmd_dir.as_ref())));
-        } else {
-            dir.push("no_cmd_dir");
-        }
-        if !dir.exists() {
-            std::fs::create_dir_all(&dir)?;
-        }
+        let mut dir = Self::cache_dir(cmd_args, cmd_dir)?;
         dir.push(format!(
             "{}_{}",
             SystemTime::now()
@@ -100,20 +202,96 @@ impl CacheEntry {
         Ok(dir)
     }
 
-    /// Write the `contents` to given cache entry.
+    /// Construct the cache entry that is written while the command is still running.
+    ///
+    /// It will be renamed to a complete cache entry once the command finishes.
+    pub fn partial<T: AsRef<Path> + Hash>(
+        cmd_args: &[&str],
+        cmd_dir: Option<T>,
+    ) -> Result<PathBuf> {
+        Ok(Self::cache_dir(cmd_args, cmd_dir)?.join(PARTIAL_CACHE_ENTRY))
+    }
+
+    /// Removes all the cache entries in the same directory except `entry` and its metadata.
     ///
-    /// Remove all the existing old entries if there are any.
-    pub fn write<T: AsRef<[u8]>>(entry: &Path, contents: T) -> Result<()> {
-        // Remove the other outdated cache file if there are any.
-        //
-        // There should be only one cache file in parent_dir at this moment.
+    /// The files still being produced by the other writers are kept, i.e., the partial entry of
+    /// the forerunner job holding the lock and the temp files of the live processes. There
+    /// should be only one complete cache file in the directory after the cleanup.
+    fn remove_outdated(entry: &Path) -> Result<()> {
+        let meta_path = cache_meta_path(entry);
         if let Some(parent_dir) = entry.parent() {
-            remove_dir_contents(&parent_dir.to_path_buf())?;
+            let locked_by_other = CacheLock::is_held_by_other(parent_dir);
+            for old_entry in read_dir(parent_dir)?.filter_map(|e| e.ok()) {
+                let path = old_entry.path();
+                if path == entry || path == meta_path {
+                    continue;
+                }
+                if is_partial_cache(&path) && locked_by_other {
+                    continue;
+                }
+                if temp_file_writer(&path).map_or(false, |pid| {
+                    pid != std::process::id() && cache_writer::is_alive(pid)
+                }) {
+                    continue;
+                }
+                std::fs::remove_file(path)?;
+            }
         }
+        Ok(())
+    }
 
-        File::create(entry)?.write_all(contents.as_ref())?;
+    /// Turns the partial cache entry into a complete one, the outdated entries are removed.
+    ///
+    /// The partial cache entry is written uncompressed so that it can be followed by the
+    /// readers, it's compressed here if the cache compression is enabled.
+    pub fn finish_partial(partial: &Path, entry: &Path, meta: &CacheMeta) -> Result<()> {
+        meta.write_for(entry)?;
+        match cache_compression() {
+            Compression::None => std::fs::rename(partial, entry)?,
+            compression => {
+                let contents = std::fs::read(partial)?;
+                write_atomically(entry, compression.compress(&contents)?)?;
+                std::fs::remove_file(partial)?;
+            }
+        }
+        Self::remove_outdated(entry)
+    }
 
-        Ok(())
+    /// Write the `contents` to given cache entry as well as its metadata.
+    ///
+    /// The contents are compressed if the cache compression is enabled.
+    /// The cache entry is written atomically, then all the existing old entries
+    /// will be removed if there are any.
+    pub fn write<T: AsRef<[u8]>>(entry: &Path, contents: T, meta: &CacheMeta) -> Result<()> {
+        // The metadata is written first so that the cache entry is always valid once it appears.
+        let started = std::time::Instant::now();
+        meta.write_for(entry)?;
+        match cache_compression() {
+            Compression::None => write_atomically(entry, contents)?,
+            compression => write_atomically(entry, compression.compress(contents.as_ref())?)?,
+        }
+        log::debug!(
+            "Wrote the cache entry {} of {} items in {:?}",
+            entry.display(),
+            meta.total,
+            started.elapsed()
+        );
+        Self::remove_outdated(entry)
+    }
+
+    /// Returns the lock of cache directory given command arguments and its working directory.
+    ///
+    /// Returns `None` if another forerunner job is writing the cache.
+    pub fn try_lock(cmd_args: &[&str], cmd_dir: &Path) -> Result<Option<CacheLock>> {
+        CacheLock::try_acquire(&get_cache_dir(cmd_args, cmd_dir))
+    }
+
+    /// Acquires the lock of cache directory, waiting for the other forerunner job if needed.
+    pub fn lock(cmd_args: &[&str], cmd_dir: &Path) -> Result<CacheLock> {
+        CacheLock::acquire(
+            &get_cache_dir(cmd_args, cmd_dir),
+            std::time::Duration::from_secs(60),
+        )
     }
 
     /// Creates a new cache entry.
@@ -123,24 +301,86 @@ impl CacheEntry {
         total: usize,
         contents: T,
     ) -> Result<PathBuf> {
+        let meta = CacheMeta::new(cmd_args, cmd_dir.as_ref().map(|d| d.as_ref()), total);
         let entry = Self::try_new(cmd_args, cmd_dir, total)?;
-        Self::write(&entry, contents)?;
+        Self::write(&entry, contents, &meta)?;
         Ok(entry)
     }
+}
 
-    /// Get the total number of this cache entry from its file name.
-    pub fn get_total(cached_entry: &DirEntry) -> Result<usize> {
-        if let Some(path_str) = cached_entry.file_name().to_str() {
-            let info = path_str.split('_').collect::<Vec<_>>();
-            if info.len() == 2 {
-                info[1].parse().map_err(Into::into)
-            } else {
-                Err(anyhow!("Invalid cache entry name: {:?}", info))
-            }
-        } else {
-            Err(anyhow!("Couldn't get total from cached entry"))
+/// Returns the HEAD commit if `dir` is a git repo.
+fn git_head_commit(dir: &Path) -> Option<String> {
+    if !is_git_repo(dir) {
+        return None;
+    }
+    let output = utility::execute_at("git rev-parse HEAD", Some(dir)).ok()?;
+    if output.status.success() {
+        Some(String::from_utf8_lossy(&output.stdout).trim().into())
+    } else {
+        None
+    }
+}
+
+/// Metadata of a cache entry, stored in a sidecar file next to the cache file.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct CacheMeta {
+    /// Command line producing the cache.
+    pub command: String,
+    /// Arguments of the command, used for locating the cache.
+    pub args: Vec<String>,
+    /// Working directory of the command.
+    pub cwd: Option<PathBuf>,
+    /// Unix timestamp in seconds when the cache was created.
+    pub timestamp: u64,
+    /// Number of lines of the cache file.
+    pub total: usize,
+    /// HEAD commit of the working directory if it's a git repo.
+    pub git_commit: Option<String>,
+    /// Ignore files influencing the command, the cache is stale once any of them is changed.
+    #[serde(default)]
+    pub ignore_files: Vec<IgnoreFile>,
+}
+
+impl CacheMeta {
+    pub fn new(cmd_args: &[&str], cmd_dir: Option<&Path>, total: usize) -> Self {
+        Self {
+            command: cmd_args.join(" "),
+            args: cmd_args.iter().map(|s| s.to_string()).collect(),
+            cwd: cmd_dir.map(|d| d.to_path_buf()),
+            timestamp: SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
+            total,
+            git_commit: cmd_dir.and_then(git_head_commit),
+            ignore_files: cmd_dir.map(ignore_files).unwrap_or_default(),
         }
     }
+
+    /// Reads the metadata of cache entry `entry`.
+    pub fn read_for(entry: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(cache_meta_path(entry))?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    /// Writes the metadata of cache entry `entry`.
+    pub fn write_for(&self, entry: &Path) -> Result<()> {
+        write_atomically(&cache_meta_path(entry), serde_json::to_string(self)?)
+    }
+
+    /// Returns true if the cache is produced by `cmd_args` in `cmd_dir`, none of the ignore
+    /// files has been changed and the HEAD commit is still the same since then.
+    ///
+    /// The HEAD is checked last as it takes running git.
+    pub fn is_valid_for(&self, cmd_args: &[&str], cmd_dir: &Path) -> bool {
+        self.args
+            .iter()
+            .map(|s| s.as_str())
+            .eq(cmd_args.iter().copied())
+            && self.cwd.as_deref() == Some(cmd_dir)
+            && self.ignore_files == ignore_files(cmd_dir)
+            && self.git_commit == git_head_commit(cmd_dir)
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -163,28 +403,42 @@ pub fn send_response_from_cache(
         } else {
             lines_iter.collect()
         };
-        match response_ty {
-            SendResponse::Json => println_json!(total, tempfile, using_cache, lines),
-            SendResponse::JsonWithContentLength => {
-                println_json_with_length!(total, tempfile, using_cache, lines)
-            }
-        }
+        send_payload(
+            serde_json::json!({
+                "total": total,
+                "tempfile": tempfile,
+                "using_cache": using_cache,
+                "lines": lines,
+            }),
+            response_ty,
+        );
     } else {
-        match response_ty {
-            SendResponse::Json => println_json!(total, tempfile, using_cache),
-            SendResponse::JsonWithContentLength => {
-                println_json_with_length!(total, tempfile, using_cache)
-            }
-        }
+        send_payload(
+            serde_json::json!({
+                "total": total,
+                "tempfile": tempfile,
+                "using_cache": using_cache,
+            }),
+            response_ty,
+        );
+    }
+}
+
+fn send_payload(payload: serde_json::Value, response_ty: SendResponse) {
+    match response_ty {
+        SendResponse::Json => printer::println_payload(payload),
+        SendResponse::JsonWithContentLength => printer::println_payload_with_length(payload),
     }
 }
 
 /// Returns the cache file path and number of total cached items.
 pub fn cache_exists(args: &[&str], cmd_dir: &Path) -> Result<(PathBuf, usize)> {
     if let Ok(cached_entry) = get_cached_entry(args, cmd_dir) {
-        if let Ok(total) = CacheEntry::get_total(&cached_entry) {
-            let tempfile = cached_entry.path();
-            return Ok((tempfile, total));
+        let tempfile = cached_entry.path();
+        if let Ok(meta) = CacheMeta::read_for(&tempfile) {
+            if meta.is_valid_for(args, cmd_dir) {
+                return Ok((tempfile, meta.total));
+            }
         }
     }
     Err(anyhow!(
@@ -193,3 +447,20 @@ pub fn cache_exists(args: &[&str], cmd_dir: &Path) -> Result<(PathBuf, usize)> {
         cmd_dir
     ))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cache_meta_git_commit() {
+        let dir = std::env::current_dir().unwrap();
+        let args = ["rg", "--files"];
+        let mut meta = CacheMeta::new(&args, Some(&dir), 1);
+        assert!(meta.is_valid_for(&args, &dir));
+
+        // The cache made at another HEAD is stale.
+        meta.git_commit = Some("0000000000000000000000000000000000000000".into());
+        assert!(!meta.is_valid_for(&args, &dir));
+    }
+}
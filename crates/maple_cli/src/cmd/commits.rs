@@ -0,0 +1,122 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use anyhow::Result;
+use structopt::StructOpt;
+
+use filter::{
+    matcher::{Algo, Bonus, MatchType},
+    Source,
+};
+use source_item::SourceItem;
+
+use crate::app::Params;
+use crate::tools::git::{self, Commit};
+
+/// Fuzzy filter the commits of git log, the full hash of each commit is returned as `hashes`.
+#[derive(StructOpt, Debug, Clone)]
+pub struct Commits {
+    /// Initial query string
+    #[structopt(index = 1, short, long)]
+    query: String,
+
+    /// Specify the working directory of git log.
+    #[structopt(long = "cmd-dir", parse(from_os_str = utility::paths::expand_os))]
+    cmd_dir: Option<PathBuf>,
+
+    /// List only the commits touching this file, following the renames.
+    #[structopt(long, parse(from_os_str = utility::paths::expand_os))]
+    buffer: Option<PathBuf>,
+}
+
+/// Returns the items of commits matched on the date, hash, subject and author, and the full
+/// hash of them.
+fn into_items(commits: Vec<Commit>) -> (Vec<SourceItem>, HashMap<String, String>) {
+    let mut hashes = HashMap::new();
+    let items = commits
+        .into_iter()
+        .map(|commit| {
+            let line = commit.display_line();
+            hashes.insert(line.clone(), commit.hash);
+            line.into()
+        })
+        .collect();
+    (items, hashes)
+}
+
+impl Commits {
+    pub fn run(&self, params: Params) -> Result<()> {
+        let line_cutoff = params.line_cutoff();
+        let Params {
+            number, winwidth, ..
+        } = params;
+
+        let commits = git::log(self.cmd_dir.clone(), self.buffer.as_deref())?;
+        let (items, hashes) = into_items(commits);
+
+        let ranked = filter::sync_run(
+            &self.query,
+            Source::List(items.into_iter()),
+            Algo::Fzy,
+            MatchType::Full,
+            vec![Bonus::None],
+            line_cutoff,
+        )?;
+
+        if let Some(number) = number {
+            let total = ranked.len();
+            let hashes = ranked
+                .iter()
+                .take(number)
+                .filter_map(|(item, _, _)| hashes.get(&item.raw))
+                .collect::<Vec<_>>();
+            let (lines, indices, truncated_map) = printer::process_top_items(
+                ranked.into_iter().take(number),
+                utility::tty::display_width(winwidth, 100),
+                None,
+            );
+            let mut payload = serde_json::json!({
+                "total": total,
+                "lines": lines,
+                "indices": indices,
+                "hashes": hashes,
+            });
+            if !truncated_map.is_empty() {
+                payload["truncated_map"] = serde_json::json!(truncated_map);
+            }
+            printer::println_payload(payload);
+        } else {
+            for (item, _, indices) in ranked.into_iter() {
+                let hash = hashes.get(&item.raw);
+                let text = item.raw;
+                printer::println_payload(serde_json::json!({
+                    "text": text,
+                    "indices": indices,
+                    "hash": hash,
+                }));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_into_items() {
+        let commits = git::parse_log(
+            "1a2b3c4d5e6f\u{1f}1a2b3c4\u{1f}Alice\u{1f}2021-03-14\u{1f}Add commits\n",
+        );
+        let (items, hashes) = into_items(commits);
+        assert_eq!(items[0].raw, "2021-03-14 1a2b3c4 Add commits (Alice)");
+        assert_eq!(hashes[&items[0].raw], "1a2b3c4d5e6f");
+
+        // The author is matched too.
+        assert!(Algo::Fzy
+            .apply_match("alice", &items[0], &MatchType::Full)
+            .is_some());
+    }
+}
@@ -0,0 +1,21 @@
+use anyhow::Result;
+use structopt::clap::Shell;
+use structopt::StructOpt;
+
+use crate::app::Maple;
+
+/// Generate the completion script of maple for a shell.
+#[derive(StructOpt, Debug, Clone)]
+pub struct Completions {
+    /// Shell to generate the completion script for.
+    #[structopt(index = 1, possible_values = &Shell::variants(), case_insensitive = true)]
+    shell: Shell,
+}
+
+impl Completions {
+    /// Prints the completion script generated from the command line definitions to stdout.
+    pub fn run(&self) -> Result<()> {
+        Maple::clap().gen_completions_to("maple", self.shell, &mut std::io::stdout());
+        Ok(())
+    }
+}
@@ -0,0 +1,21 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use structopt::StructOpt;
+
+/// Detect the filetype of a file, the same one used by the icon painter and the previewer.
+#[derive(StructOpt, Debug, Clone)]
+pub struct DetectFiletype {
+    /// Path of the file, the shebang line is read if its name says nothing.
+    #[structopt(index = 1, parse(from_os_str = utility::paths::expand_os))]
+    path: PathBuf,
+}
+
+impl DetectFiletype {
+    /// Prints `{"filetype": <filetype or null>}`.
+    pub fn run(self) -> Result<()> {
+        let filetype = filetype::detect(&self.path);
+        utility::println_json!(filetype);
+        Ok(())
+    }
+}
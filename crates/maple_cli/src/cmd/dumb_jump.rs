@@ -1,13 +1,14 @@
 //! Inspired by https://github.com/jacktasia/dumb-jump/blob/master/dumb-jump.el.
 
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use anyhow::{anyhow, Result};
 use once_cell::sync::{Lazy, OnceCell};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use structopt::StructOpt;
 
+use crate::dumb_analyzer::Analyzer;
 use crate::process::tokio::TokioCommand;
 use crate::tools::rg::{JsonLine, Word};
 
@@ -81,25 +82,95 @@ impl DefinitionRegexp {
     }
 }
 
+/// Location of the search word along with its kind, e.g., function or references.
+#[derive(Clone, Debug, Serialize, PartialEq, Eq)]
+pub struct JumpTarget {
+    pub kind: String,
+    pub path: String,
+    pub lnum: u64,
+    /// 1-based byte column.
+    pub col: usize,
+    pub text: String,
+}
+
+impl JumpTarget {
+    fn new(kind: &str, line: &JsonLine) -> Self {
+        Self {
+            kind: kind.into(),
+            path: line.data.path().into(),
+            lnum: line.data.line_number(),
+            col: line.data.column() + 1,
+            text: line.data.line().into(),
+        }
+    }
+}
+
 /// All the lines as well as their match indices that can be sent to the vim side directly.
 #[derive(Clone, Debug)]
 pub struct Lines {
     pub lines: Vec<String>,
     pub indices: Vec<Vec<usize>>,
+    /// Structured form of the lines, in the same order.
+    pub targets: Vec<JumpTarget>,
 }
 
 impl Lines {
-    pub fn new(lines: Vec<String>, indices: Vec<Vec<usize>>) -> Self {
-        Self { lines, indices }
+    /// Builds the jump lines of `word` from the pairs of (kind, rg line).
+    pub fn from_jump_lines<'a>(
+        jump_lines: impl IntoIterator<Item = (&'a str, &'a JsonLine)>,
+        word: &Word,
+    ) -> Self {
+        let mut lines = Vec::new();
+        let mut indices = Vec::new();
+        let mut targets = Vec::new();
+        for (kind, line) in jump_lines {
+            let (jump_line, line_indices) = line.build_jump_line(kind, word);
+            lines.push(jump_line);
+            indices.push(line_indices);
+            targets.push(JumpTarget::new(kind, line));
+        }
+        Self {
+            lines,
+            indices,
+            targets,
+        }
     }
 
     pub fn print(&self) {
         let total = self.lines.len();
-        let Self { lines, indices } = self;
-        utility::println_json_with_length!(total, lines, indices);
+        let Self {
+            lines,
+            indices,
+            targets,
+        } = self;
+        let mut payload = serde_json::json!({
+            "total": total,
+            "lines": lines,
+            "indices": indices,
+            "targets": targets,
+        });
+        printer::Payload::new(total, total).attach_to(&mut payload);
+        printer::println_payload_with_length(payload);
     }
 }
 
+/// Returns the kind of the definition of `word` in `line` told by the built-in symbol extraction.
+fn analyzed_kind<'a>(
+    analyzer: Option<&'a Analyzer>,
+    line: &JsonLine,
+    word: &Word,
+) -> Option<&'a str> {
+    analyzer.and_then(|analyzer| analyzer.definition_kind(line.data.line(), &word.raw))
+}
+
+/// Sorts the definitions before the references, stably.
+fn definitions_first(jump_lines: &mut [(&str, &JsonLine)]) {
+    jump_lines.sort_by_key(|(kind, _)| *kind == REFERENCES);
+}
+
+/// Kind label of the occurrences which are not definitions.
+const REFERENCES: &str = "references";
+
 /// Definition rules of a language.
 #[derive(Clone, Debug, Deserialize)]
 pub struct DefinitionRules(HashMap<DefinitionKind, DefinitionRegexp>);
@@ -139,21 +210,11 @@ impl DefinitionRules {
     }
 
     pub async fn definitions(lang: &str, word: &Word, dir: &Option<PathBuf>) -> Result<Lines> {
-        let all_def_futures = LanguageDefinition::get_rules(lang)?
-            .0
-            .keys()
-            .map(|kind| find_definitions_per_kind(lang, kind, word, dir))
-            .collect::<Vec<_>>();
-
-        let maybe_defs = futures::future::join_all(all_def_futures).await;
-
-        let (lines, indices): (Vec<String>, Vec<Vec<usize>>) = maybe_defs
-            .into_iter()
-            .filter_map(|def| def.ok())
-            .flatten()
-            .unzip();
-
-        Ok(Lines::new(lines, indices))
+        let definitions = Self::all_definitions(lang, word.clone(), dir).await?;
+        let jump_lines = definitions
+            .iter()
+            .flat_map(|(kind, lines)| lines.iter().map(move |line| (kind.as_ref(), line)));
+        Ok(Lines::from_jump_lines(jump_lines, word))
     }
 
     pub async fn definitions_and_references(
@@ -183,34 +244,31 @@ impl DefinitionRules {
             .filter(|def| occurrences.contains(def))
             .collect::<Vec<_>>();
 
-        let (lines, indices): (Vec<String>, Vec<Vec<usize>>) = definitions
-            .iter()
-            .flat_map(|(kind, lines)| {
-                lines
-                    .iter()
-                    .filter(|line| positive_defs.contains(&line))
-                    .map(|line| line.build_jump_line(kind.as_ref(), &word))
-                    .collect::<Vec<_>>()
-            })
-            .chain(
-                // references are these occurrences not in the definitions.
-                occurrences
-                    .iter()
-                    .filter(|r| !defs.contains(&r))
-                    .map(|line| line.build_jump_line("references", &word)),
-            )
-            .unzip();
+        let mut jump_lines: Vec<(&str, &JsonLine)> = Vec::new();
+        for (kind, lines) in definitions.iter() {
+            for line in lines.iter().filter(|line| positive_defs.contains(&line)) {
+                jump_lines.push((kind.as_ref(), line));
+            }
+        }
+
+        // references are these occurrences not in the definitions, unless the built-in symbol
+        // extraction tells a definition missed by the rules.
+        let analyzer = Analyzer::new(lang).ok();
+        for line in occurrences.iter().filter(|r| !defs.contains(r)) {
+            let kind = analyzed_kind(analyzer.as_ref(), line, &word).unwrap_or(REFERENCES);
+            jump_lines.push((kind, line));
+        }
+        definitions_first(&mut jump_lines);
 
-        if lines.is_empty() {
+        if jump_lines.is_empty() {
             let lines = fallback_to_grep(word.clone(), lang, dir, comments).await?;
-            let (lines, indices): (Vec<String>, Vec<Vec<usize>>) = lines
-                .into_iter()
-                .map(|line| line.build_jump_line("plain", &word))
-                .unzip();
-            return Ok(Lines::new(lines, indices));
+            return Ok(Lines::from_jump_lines(
+                lines.iter().map(|line| ("plain", line)),
+                &word,
+            ));
         }
 
-        Ok(Lines::new(lines, indices))
+        Ok(Lines::from_jump_lines(jump_lines, &word))
     }
 }
 
@@ -298,37 +356,19 @@ async fn find_occurrences_by_ext(word: &Word, ext: &str, dir: &Option<PathBuf>)
     let comments = get_comments_by_ext(ext);
     let occurrences = collect_json_lines(command, dir, Some(comments)).await?;
 
-    let (lines, indices) = occurrences
+    // Tell the definitions from the references by the built-in symbol extraction if possible.
+    let analyzer = filetype::detect_by_name(Path::new(&format!("_.{}", ext)))
+        .and_then(|filetype| Analyzer::new(filetype).ok());
+    let mut jump_lines = occurrences
         .iter()
-        .map(|line| line.build_jump_line("usages", word))
-        .unzip();
-
-    Ok(Lines::new(lines, indices))
-}
-
-async fn find_definitions_per_kind(
-    lang: &str,
-    kind: &DefinitionKind,
-    word: &Word,
-    dir: &Option<PathBuf>,
-) -> Result<Vec<(String, Vec<usize>)>> {
-    let definitions = find_definitions_in_jsonline(lang, kind, word, dir).await?;
+        .map(|line| {
+            let kind = analyzed_kind(analyzer.as_ref(), line, word).unwrap_or(REFERENCES);
+            (kind, line)
+        })
+        .collect::<Vec<_>>();
+    definitions_first(&mut jump_lines);
 
-    Ok(definitions
-        .iter()
-        .map(|line| line.build_jump_line(kind.as_ref(), word))
-        .collect())
-}
-
-async fn find_definitions_in_jsonline(
-    lang: &str,
-    kind: &DefinitionKind,
-    word: &Word,
-    dir: &Option<PathBuf>,
-) -> Result<Vec<JsonLine>> {
-    let regexp = DefinitionRules::build_full_regexp(lang, kind, word)?;
-    let command = format!("rg --trim --json --pcre2 --type {} -e '{}'", lang, regexp);
-    collect_json_lines(command, dir, None).await
+    Ok(Lines::from_jump_lines(jump_lines, word))
 }
 
 async fn find_definitions_in_jsonline_with_kind(
@@ -344,7 +384,7 @@ async fn find_definitions_in_jsonline_with_kind(
         .map(|defs| (kind.clone(), defs))
 }
 
-/// Execute the shell command
+/// Find the definitions and references of a word, the definitions go first.
 #[derive(StructOpt, Debug, Clone)]
 pub struct DumbJump {
     /// Search term.
@@ -352,42 +392,91 @@ pub struct DumbJump {
     pub word: String,
 
     /// File extension.
-    #[structopt(index = 2, short, long)]
-    pub extension: String,
+    #[structopt(index = 2, short, long, required_unless = "lang")]
+    pub extension: Option<String>,
+
+    /// Filetype of the word, e.g., rust, used instead of the file extension.
+    #[structopt(long, conflicts_with = "extension")]
+    pub lang: Option<String>,
 
     /// Definition kind.
     #[structopt(long = "kind")]
     pub kind: Option<String>,
 
     /// Specify the working directory.
-    #[structopt(long = "cmd-dir", parse(from_os_str))]
+    #[structopt(long = "cmd-dir", parse(from_os_str = utility::paths::expand_os))]
     pub cmd_dir: Option<PathBuf>,
 }
 
 impl DumbJump {
-    pub async fn run(self) -> Result<()> {
-        let lang = get_language_by_ext(&self.extension)?;
-        let comments = get_comments_by_ext(&self.extension);
-
-        let word = Word::new(self.word.to_string());
-        DefinitionRules::definitions_and_references(lang, word, &self.cmd_dir, comments)
-            .await?
-            .print();
+    /// Returns the file extension given directly or by the filetype.
+    fn extension(&self) -> Result<&str> {
+        match (&self.extension, &self.lang) {
+            (Some(extension), _) => Ok(extension),
+            (None, Some(lang)) => filetype::canonical_extension(lang)
+                .ok_or_else(|| anyhow!("Unknown extension of the filetype {}", lang)),
+            (None, None) => Err(anyhow!("Neither the extension nor the filetype is given")),
+        }
+    }
 
+    pub async fn run(self) -> Result<()> {
+        self.references_or_occurrences().await?.print();
         Ok(())
     }
 
     pub async fn references_or_occurrences(&self) -> Result<Lines> {
         let word = Word::new(self.word.to_string());
+        let extension = self.extension()?;
 
-        let lang = match get_language_by_ext(&self.extension) {
+        let lang = match get_language_by_ext(extension) {
             Ok(lang) => lang,
             Err(_) => {
-                return find_occurrences_by_ext(&word, &self.extension, &self.cmd_dir).await;
+                return find_occurrences_by_ext(&word, extension, &self.cmd_dir).await;
             }
         };
 
-        let comments = get_comments_by_ext(&self.extension);
+        let comments = get_comments_by_ext(extension);
         DefinitionRules::definitions_and_references(lang, word, &self.cmd_dir, comments).await
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_jump_lines() {
+        let json_line = |path: &str, text: &str| {
+            let start = text.find("foo").unwrap();
+            serde_json::from_value::<JsonLine>(serde_json::json!({
+                "type": "match",
+                "data": {
+                    "path": {"text": path},
+                    "lines": {"text": format!("{}\n", text)},
+                    "line_number": 3,
+                    "absolute_offset": 0,
+                    "submatches": [{"match": {"text": "foo"}, "start": start, "end": start + 3}],
+                }
+            }))
+            .unwrap()
+        };
+        let usage = json_line("src/b.rs", "    foo();");
+        let definition = json_line("src/a.rs", "fn foo() {}");
+        let mut jump_lines = vec![(REFERENCES, &usage), ("function", &definition)];
+        definitions_first(&mut jump_lines);
+
+        let lines = Lines::from_jump_lines(jump_lines, &Word::new("foo".into()));
+        assert_eq!(lines.lines[0], "[function]src/a.rs:3:3:fn foo() {}");
+        assert_eq!(lines.lines[1], "[references]src/b.rs:3:4:    foo();");
+        assert_eq!(
+            lines.targets[1],
+            JumpTarget {
+                kind: REFERENCES.into(),
+                path: "src/b.rs".into(),
+                lnum: 3,
+                col: 5,
+                text: "    foo();".into(),
+            }
+        );
+    }
+}
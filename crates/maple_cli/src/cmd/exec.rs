@@ -5,7 +5,10 @@ use anyhow::Result;
 use structopt::StructOpt;
 
 use crate::app::Params;
-use crate::process::light::{set_current_dir, LightCommand};
+use crate::cmd::jobs::JobHandle;
+use crate::params::ParamsError;
+use crate::process::light::{set_current_dir, set_low_priority, EnvOptions, LightCommand};
+use crate::tools::ignore::IgnoreOptions;
 
 /// Execute the shell command
 #[derive(StructOpt, Debug, Clone)]
@@ -19,24 +22,55 @@ pub struct Exec {
     output: Option<String>,
 
     /// Specify the working directory of CMD
-    #[structopt(long = "cmd-dir", parse(from_os_str))]
+    #[structopt(long = "cmd-dir", parse(from_os_str = utility::paths::expand_os))]
     cmd_dir: Option<PathBuf>,
 
     /// Specify the threshold for writing the output of command to a tempfile.
     #[structopt(long = "output-threshold", default_value = "100000")]
     output_threshold: usize,
+
+    /// Run CMD with a lower scheduling priority, used by the background forerunner job.
+    #[structopt(long = "low-priority")]
+    low_priority: bool,
+
+    /// Print the top N lines of `--number` as soon as they are read and write the whole output to
+    /// the cache as it streams, regardless of the output threshold.
+    #[structopt(long = "tee-output")]
+    tee_output: bool,
+
+    #[structopt(flatten)]
+    env: EnvOptions,
+
+    #[structopt(flatten)]
+    ignore: IgnoreOptions,
 }
 
 impl Exec {
     // This can work with the piped command, e.g., git ls-files | uniq.
-    fn prepare_exec_cmd(&self) -> Command {
-        let mut cmd = crate::process::std::build_command(&self.cmd);
+    fn prepare_exec_cmd(&self, shell_cmd: &str) -> Command {
+        let mut cmd = crate::process::std::build_command(shell_cmd);
 
         set_current_dir(&mut cmd, self.cmd_dir.clone());
 
+        if self.low_priority {
+            set_low_priority(&mut cmd);
+        }
+
+        self.env.apply(&mut cmd);
+
         cmd
     }
 
+    pub fn validate(&self, params: &Params) -> Result<(), ParamsError> {
+        if self.tee_output && params.number.is_none() {
+            return Err(ParamsError::new(
+                &["--tee-output", "--number"],
+                "--tee-output requires --number",
+            ));
+        }
+        Ok(())
+    }
+
     pub fn run(
         &self,
         Params {
@@ -46,7 +80,9 @@ impl Exec {
             ..
         }: Params,
     ) -> Result<()> {
-        let mut exec_cmd = self.prepare_exec_cmd();
+        // The ignore options are part of the cache key as well since they are added to the command.
+        let shell_cmd = self.ignore.apply_to_cmd(&self.cmd);
+        let mut exec_cmd = self.prepare_exec_cmd(&shell_cmd);
 
         let mut light_cmd = LightCommand::new(
             &mut exec_cmd,
@@ -54,18 +90,29 @@ impl Exec {
             self.output.clone(),
             icon_painter,
             self.output_threshold,
-        );
+        )
+        .tee_output(self.tee_output);
 
-        let args = self
-            .cmd
+        // The output may vary with the environment, which is hence part of the cache key.
+        let env_args = self.env.cache_args();
+        let args = shell_cmd
             .split_whitespace()
-            .map(Into::into)
+            .chain(env_args.iter().map(String::as_str))
             .collect::<Vec<_>>();
 
-        if !no_cache && self.cmd_dir.is_some() {
-            light_cmd
-                .try_cache_or_execute(&args, self.cmd_dir.clone().unwrap())?
-                .print();
+        if let Some(ref cmd_dir) = self.cmd_dir {
+            let job = JobHandle::register("exec", &shell_cmd, cmd_dir)
+                .map_err(|e| log::error!("Failed to register the exec job: {:?}", e))
+                .ok();
+            let executed = if no_cache {
+                light_cmd.execute(&args)
+            } else {
+                light_cmd.try_cache_or_execute(&args, cmd_dir.clone())
+            };
+            if let Some(job) = job {
+                job.finish(&executed);
+            }
+            executed?.print();
         } else {
             light_cmd.execute(&args)?.print();
         }
@@ -1,21 +1,28 @@
-use std::path::PathBuf;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 
-use anyhow::Result;
+use anyhow::{anyhow, Context, Result};
 use structopt::StructOpt;
 
 use filter::{
-    matcher::{Algo, Bonus, MatchType},
-    subprocess, FilterContext, Source,
+    matcher::{Algo, Bonus, CaseMatching, LineCutoff, MatchType, Matcher},
+    subprocess, ExcludeFilter, FilterContext, FilterResult, SortBy, Source, Tail, Tiebreak,
+    Timings,
 };
+use icon::IconPainter;
 use source_item::SourceItem;
 
 use crate::app::Params;
+use crate::params::ParamsError;
+use crate::session_cache::SessionCache;
+use crate::tools::fzf::{Fields, FzfArgs};
 
 fn parse_bonus(s: &str) -> Bonus {
-    if s.to_lowercase().as_str() == "filename" {
-        Bonus::FileName
-    } else {
-        Bonus::None
+    match s.to_lowercase().as_str() {
+        "filename" => Bonus::FileName,
+        "acronym" => Bonus::Acronym,
+        _ => Bonus::None,
     }
 }
 
@@ -39,29 +46,170 @@ pub struct Filter {
     cmd_dir: Option<String>,
 
     /// Recently opened file list for adding a bonus to the initial score.
-    #[structopt(long, parse(from_os_str))]
+    #[structopt(long, parse(from_os_str = utility::paths::expand_os))]
     recent_files: Option<PathBuf>,
 
     /// Read input from a file instead of stdin, only absolute file path is supported.
-    #[structopt(long, parse(from_os_str))]
+    #[structopt(long, parse(from_os_str = utility::paths::expand_os))]
     input: Option<PathBuf>,
 
     /// Apply the filter on the full line content or parial of it.
     #[structopt(short, long, possible_values = &MatchType::variants(), case_insensitive = true)]
     match_type: Option<MatchType>,
 
-    /// Add a bonus to the score of base matching algorithm.
+    /// Add a bonus to the score of base matching algorithm: filename or acronym.
     #[structopt(short, long, parse(from_str = parse_bonus))]
     bonus: Option<Bonus>,
 
+    /// Filetype of current buffer, add a bonus to the files of related extensions.
+    #[structopt(long)]
+    filetype: Option<String>,
+
     /// Synchronous filtering, returns after the input stream is complete.
     #[structopt(short, long)]
     sync: bool,
+
+    /// Reuse the results of the same query computed before in this session.
+    ///
+    /// Only valid when --sync is on.
+    #[structopt(long)]
+    session_id: Option<String>,
+
+    /// Keep the relative order of the results of the same score as in the previous results of
+    /// this session, instead of reshuffling them while typing.
+    ///
+    /// Only valid when --session-id is given and the results are sorted by score.
+    #[structopt(long)]
+    stable_order: bool,
+
+    /// Write the raw input read from stdin to a cache file, whose path is returned as `tee`.
+    ///
+    /// The subsequent queries on the same source can use `--input <tee>` instead of rerunning
+    /// the producer command. Only valid when --sync is on.
+    #[structopt(long)]
+    stdin_tee: bool,
+
+    /// Split each input line `<filter text><delimiter><display text>` by this delimiter.
+    ///
+    /// The items are matched against the filter text but displayed as the display text, the
+    /// matched indices are remapped onto the display text.
+    #[structopt(long)]
+    display_delimiter: Option<char>,
+
+    /// Send the top results of the first N lines of the input file at once before the full
+    /// filtering, so that the window is never empty when opened with an initial query.
+    ///
+    /// Only valid when --sync is off.
+    #[structopt(long)]
+    snapshot: Option<usize>,
+
+    /// Keep watching the input file and stream the matches of the appended lines, like `tail -f`.
+    ///
+    /// The matches of each batch of the appended lines are ranked and capped by --number like
+    /// the initial results. Only valid when --input is given and --sync is off.
+    #[structopt(long)]
+    follow: bool,
+
+    /// Add the time spent on each stage of filtering to the results as `timings`.
+    ///
+    /// The timings are built right before being serialized along with the results, so that
+    /// `print_ms` covers the processing of the printed lines, and the lines themselves if
+    /// --number is not given. Only valid when --sync is on.
+    #[structopt(long)]
+    profile: bool,
+
+    /// Sort the results by score, path, mtime or keep the source order with none.
+    ///
+    /// The relative paths are resolved against --cmd-dir for mtime.
+    #[structopt(long, possible_values = &SortBy::variants(), case_insensitive = true)]
+    sort_by: Option<SortBy>,
+
+    /// Score the source against each query of this file too, one query per line, the results
+    /// of every query are returned together as `batch`.
+    ///
+    /// The source is read only once. Only valid when --sync is on.
+    #[structopt(long, parse(from_os_str = utility::paths::expand_os))]
+    queries_file: Option<PathBuf>,
+
+    /// Memoize the matches of each query on the input file across the invocations, so that the
+    /// same query or one extending it is faster on the same big file.
+    ///
+    /// Only valid when --sync is on, the input is a file, i.e., --input or --stdin-tee, and the
+    /// lines are not transformed, e.g., by --display-delimiter.
+    #[structopt(long)]
+    score_cache: bool,
+
+    /// Common fzf flags mapped onto the options of maple, e.g., `--fzf-args "-e +i --nth 2.."`.
+    ///
+    /// `-e`, `-i`, `+i`, `--nth`, `--delimiter` and `--tiebreak` are supported, `--tiebreak`
+    /// overrides the global one.
+    #[structopt(long)]
+    fzf_args: Option<String>,
+
+    /// Return the line numbers of the results in the input as `line_numbers` along with their
+    /// `indices` instead of the lines, for the frontend holding the input lines already.
+    ///
+    /// The line numbers are 1-based. Only valid when --sync is on.
+    #[structopt(long)]
+    indices_only: bool,
+
+    #[structopt(skip)]
+    case_matching: CaseMatching,
+
+    #[structopt(skip)]
+    fields: Option<Fields>,
+
+    #[structopt(skip)]
+    tiebreak: Vec<Tiebreak>,
+}
+
+type ItemSource = Source<Box<dyn Iterator<Item = SourceItem>>>;
+
+/// Interval of checking the followed file for the appended lines.
+const FOLLOW_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Reads the whole stdin and writes it to a cache file, returns the path of cache file.
+///
+/// The cache file is named after the hash of the input, the same input shares one cache file.
+fn tee_stdin() -> Result<PathBuf> {
+    let mut input = Vec::new();
+    std::io::stdin()
+        .read_to_end(&mut input)
+        .context("Failed to read stdin")?;
+
+    let mut path = utility::clap_cache_dir();
+    path.push("stdin");
+    std::fs::create_dir_all(&path)?;
+    path.push(format!("{}.txt", utility::calculate_hash(&input)));
+
+    if !path.exists() {
+        utility::cache_writer::write_atomically(&path, &input)?;
+    }
+
+    Ok(path)
 }
 
 impl Filter {
+    pub fn query(&self) -> &str {
+        &self.query
+    }
+
+    pub fn cmd_dir(&self) -> Option<&Path> {
+        self.cmd_dir.as_deref().map(Path::new)
+    }
+
+    /// Sets the bonus of the config file used if --bonus is not specified.
+    pub fn default_bonus(mut self, bonus: Option<&str>) -> Self {
+        if self.bonus.is_none() {
+            self.bonus = bonus.map(parse_bonus);
+        }
+        self
+    }
+
     /// Firstly try building the Source from shell command, then the input file, finally reading the source from stdin.
-    fn generate_source<I: Iterator<Item = SourceItem>>(&self) -> Source<I> {
+    ///
+    /// `input` takes precedence over `--input`, e.g., the cache file of stdin.
+    fn generate_source<I: Iterator<Item = SourceItem>>(&self, input: Option<PathBuf>) -> Source<I> {
         if let Some(ref cmd_str) = self.cmd {
             if let Some(ref dir) = self.cmd_dir {
                 subprocess::Exec::shell(cmd_str).cwd(dir).into()
@@ -69,17 +217,95 @@ impl Filter {
                 subprocess::Exec::shell(cmd_str).into()
             }
         } else {
-            self.input
-                .clone()
+            input
+                .or_else(|| self.input.clone())
                 .map(Into::into)
                 .unwrap_or(Source::<I>::Stdin)
         }
     }
 
+    /// Builds the Source with the display delimiter, the exclude globs and `relative_to` applied
+    /// if specified.
+    fn prepare_source(
+        &self,
+        input: Option<PathBuf>,
+        exclude: &[String],
+        relative_to: Option<PathBuf>,
+        match_type: &MatchType,
+    ) -> Result<ItemSource> {
+        self.transform_source(
+            self.generate_source(input),
+            exclude,
+            relative_to,
+            match_type,
+        )
+    }
+
+    fn transform_source(
+        &self,
+        mut source: ItemSource,
+        exclude: &[String],
+        relative_to: Option<PathBuf>,
+        match_type: &MatchType,
+    ) -> Result<ItemSource> {
+        // Numbered before any line is dropped.
+        if self.indices_only {
+            source = source.numbered()?;
+        }
+        if let Some(delimiter) = self.display_delimiter {
+            source = source.split_display(delimiter)?;
+        }
+        if let Some(fields) = self.fields.clone() {
+            source = Source::List(Box::new(
+                source.into_items()?.map(move |item| fields.apply(item)),
+            ));
+        }
+        if !exclude.is_empty() {
+            source = source.exclude(ExcludeFilter::new(exclude), match_type.clone())?;
+        }
+        if let Some(dir) = relative_to {
+            source = source.relative_to(dir, match_type.clone())?;
+        }
+        Ok(source)
+    }
+
+    /// Returns the input file whose matches can be memoized, see `--score-cache`.
+    fn score_cache_input(
+        &self,
+        tee: &Option<PathBuf>,
+        exclude: &[String],
+        relative_to: &Option<PathBuf>,
+    ) -> Option<PathBuf> {
+        if !self.score_cache
+            || self.cmd.is_some()
+            || self.display_delimiter.is_some()
+            || self.fields.is_some()
+            || !exclude.is_empty()
+            || relative_to.is_some()
+        {
+            return None;
+        }
+        tee.clone().or_else(|| self.input.clone())
+    }
+
+    fn sort_by(&self) -> SortBy {
+        self.sort_by.unwrap_or_default()
+    }
+
+    /// Returns the directory the relative paths in the results are relative to.
+    fn sort_dir(&self, relative_to: &Option<PathBuf>) -> Option<PathBuf> {
+        relative_to
+            .clone()
+            .or_else(|| self.cmd_dir.as_ref().map(PathBuf::from))
+    }
+
     fn get_bonuses(&self) -> Vec<Bonus> {
         use std::io::BufRead;
 
         let mut bonuses = vec![self.bonus.clone().unwrap_or_default()];
+        if let Some(ref filetype) = self.filetype {
+            bonuses.push(Bonus::FileType(filetype.into()));
+        }
         if let Some(ref recent_files) = self.recent_files {
             // Ignore the error cases.
             if let Ok(file) = std::fs::File::open(recent_files) {
@@ -102,18 +328,218 @@ impl Filter {
             number,
             winwidth,
             icon_painter,
+            relative_to,
+            exclude,
+            max_line_len,
+            long_line_strategy,
+            tiebreak,
             ..
         }: Params,
     ) -> Result<()> {
-        let ranked = filter::sync_run::<std::iter::Empty<_>>(
-            &self.query,
-            self.generate_source(),
+        crate::interrupt::catch_interrupt();
+
+        let started = Instant::now();
+        let session_cache = self
+            .session_id
+            .as_ref()
+            .map(|session_id| SessionCache::new(session_id, "filter"));
+        let options = format!(
+            "{:?} {:?} {:?} {:?} {:?}",
+            self, exclude, relative_to, max_line_len, long_line_strategy
+        );
+
+        // Tee stdin before checking the session cache so that the payload always has `tee`.
+        let tee = if self.stdin_tee && self.cmd.is_none() && self.input.is_none() {
+            Some(tee_stdin()?)
+        } else {
+            None
+        };
+
+        let mut extra = serde_json::Map::new();
+        if let Some(ref tee) = tee {
+            extra.insert("tee".into(), tee.to_string_lossy().into());
+        }
+
+        let mut timings = Timings::default();
+
+        let ranked = match session_cache
+            .as_ref()
+            .and_then(|cache| cache.load_ranked(&self.query, &options))
+        {
+            Some(ranked) => ranked,
+            None => {
+                let match_type = self.match_type.clone().unwrap_or(MatchType::Full);
+                let sort_dir = self.sort_dir(&relative_to);
+                let cached_input = self.score_cache_input(&tee, &exclude, &relative_to);
+                let source = self.prepare_source(tee, &exclude, relative_to, &match_type)?;
+                let algo = self.algo.clone().unwrap_or(Algo::Fzy);
+                let bonuses = self.get_bonuses();
+                let line_cutoff =
+                    max_line_len.map(|max_len| LineCutoff::new(max_len, long_line_strategy));
+                let matcher = Matcher::new_with_bonuses(algo, match_type, bonuses)
+                    .line_cutoff(line_cutoff)
+                    .case_matching(self.case_matching);
+                let sort_by = self.sort_by();
+                let ranked = if self.profile {
+                    filter::sync_run_profiled(
+                        &self.query,
+                        source,
+                        matcher,
+                        sort_by,
+                        sort_dir.as_deref(),
+                        &mut timings,
+                    )?
+                } else if sort_by != SortBy::Score {
+                    filter::sync_run_sorted(
+                        &self.query,
+                        source,
+                        matcher,
+                        sort_by,
+                        sort_dir.as_deref(),
+                    )?
+                } else if let Some(input) = cached_input {
+                    filter::sync_run_cached(&self.query, input, matcher)?
+                } else {
+                    filter::sync_run_sorted(&self.query, source, matcher, sort_by, None)?
+                };
+
+                // The partial results of an interrupted filtering are not cached.
+                if let Some(cache) = session_cache
+                    .as_ref()
+                    .filter(|_| !filter::interrupt::is_interrupted())
+                {
+                    if let Err(e) = cache.store_ranked(&self.query, &options, &ranked) {
+                        log::error!("Failed to write the session cache: {:?}", e);
+                    }
+                }
+
+                ranked
+            }
+        };
+
+        let ranked = match session_cache {
+            Some(ref cache) if self.stable_order && self.sort_by() == SortBy::Score => {
+                cache.stabilize_order(ranked, number)
+            }
+            _ => ranked,
+        };
+
+        let ranked = if self.sort_by() == SortBy::Score {
+            filter::tiebreak(ranked, self.tiebreaks(&tiebreak))
+        } else {
+            ranked
+        };
+
+        let print_started = Instant::now();
+        let profile = self.profile;
+        let extra = move || {
+            let mut extra = extra;
+            if profile {
+                timings.finish(started, print_started);
+                extra.insert("timings".into(), serde_json::json!(timings));
+            }
+            extra
+        };
+        if self.indices_only {
+            printer::print_indices_only(ranked, number, extra());
+        } else {
+            printer::print_sync_filter_results_with(
+                ranked,
+                number,
+                utility::tty::display_width(winwidth, 100),
+                icon_painter,
+                extra,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the queries of the batch, the query of the arguments is the first one.
+    fn batch_queries(&self, queries_file: &Path) -> Result<Vec<String>> {
+        let content = std::fs::read_to_string(queries_file)
+            .with_context(|| format!("Failed to read {}", queries_file.display()))?;
+        Ok(std::iter::once(self.query.clone())
+            .chain(
+                content
+                    .lines()
+                    .filter(|line| !line.trim().is_empty())
+                    .map(Into::into),
+            )
+            .collect())
+    }
+
+    /// Returns the results of each query of the batch against the same source.
+    fn batch_run(
+        &self,
+        queries_file: &Path,
+        Params {
+            number,
+            winwidth,
+            icon_painter,
+            relative_to,
+            exclude,
+            max_line_len,
+            long_line_strategy,
+            tiebreak,
+            ..
+        }: Params,
+    ) -> Result<()> {
+        crate::interrupt::catch_interrupt();
+
+        let queries = self.batch_queries(queries_file)?;
+        let match_type = self.match_type.clone().unwrap_or(MatchType::Full);
+        let sort_dir = self.sort_dir(&relative_to);
+        let items = self
+            .prepare_source(None, &exclude, relative_to, &match_type)?
+            .into_items()?
+            .collect::<Vec<_>>();
+        let line_cutoff = max_line_len.map(|max_len| LineCutoff::new(max_len, long_line_strategy));
+        let matcher = Matcher::new_with_bonuses(
             self.algo.clone().unwrap_or(Algo::Fzy),
-            self.match_type.clone().unwrap_or(MatchType::Full),
+            match_type,
             self.get_bonuses(),
-        )?;
+        )
+        .line_cutoff(line_cutoff)
+        .case_matching(self.case_matching);
+        let winwidth = utility::tty::display_width(winwidth, 100);
+
+        let mut batch = Vec::with_capacity(queries.len());
+        for query in queries {
+            // The remaining queries are not scored once interrupted.
+            if filter::interrupt::is_interrupted() {
+                break;
+            }
+            let ranked = filter::sort_filtered(
+                filter::rank_slice(&matcher, &query, &items),
+                self.sort_by(),
+                sort_dir.as_deref(),
+            );
+            let ranked = if self.sort_by() == SortBy::Score {
+                filter::tiebreak(ranked, self.tiebreaks(&tiebreak))
+            } else {
+                ranked
+            };
+            let total = ranked.len();
+            let (lines, indices, truncated_map) = printer::process_top_items(
+                ranked.into_iter().take(number.unwrap_or(total)),
+                winwidth,
+                icon_painter.clone(),
+            );
+            let mut result = serde_json::json!({
+                "query": query,
+                "total": total,
+                "lines": lines,
+                "indices": indices,
+            });
+            if !truncated_map.is_empty() {
+                result["truncated_map"] = serde_json::json!(truncated_map);
+            }
+            printer::index_format::apply(&mut result);
+            batch.push(result);
+        }
 
-        printer::print_sync_filter_results(ranked, number, winwidth.unwrap_or(100), icon_painter);
+        printer::println_payload(serde_json::json!({ "batch": batch }));
 
         Ok(())
     }
@@ -125,25 +551,218 @@ impl Filter {
             number,
             winwidth,
             icon_painter,
+            relative_to,
+            exclude,
+            max_line_len,
+            long_line_strategy,
+            tiebreak,
             ..
         }: Params,
     ) -> Result<()> {
-        filter::dyn_run::<std::iter::Empty<_>>(
+        let match_type = self.match_type.clone().unwrap_or(MatchType::Full);
+        let line_cutoff = max_line_len.map(|max_len| LineCutoff::new(max_len, long_line_strategy));
+
+        let tail = if self.follow {
+            let input = self
+                .input
+                .as_ref()
+                .ok_or_else(|| anyhow!("--follow requires --input"))?;
+            Some(Tail::from_end(input)?)
+        } else {
+            None
+        };
+
+        // Only the lines before the tail starts are filtered initially, the rest are appended.
+        let source = match tail {
+            Some(ref tail) => Source::List(tail.initial_lines()?),
+            None => self.generate_source(None),
+        };
+
+        filter::dyn_run(
             &self.query,
-            self.generate_source(),
+            self.transform_source(source, &exclude, relative_to.clone(), &match_type)?,
             FilterContext::new(
                 self.algo.clone(),
                 number,
                 winwidth,
-                icon_painter,
-                self.match_type.clone().unwrap_or(MatchType::Full),
-            ),
+                icon_painter.clone(),
+                match_type.clone(),
+            )
+            .line_cutoff(line_cutoff.clone())
+            .snapshot(self.snapshot)
+            .case_matching(self.case_matching)
+            .sort_by(self.sort_by(), self.sort_dir(&relative_to))
+            .tiebreak(self.tiebreaks(&tiebreak).to_vec()),
             self.get_bonuses(),
-        )
+        )?;
+
+        if let Some(tail) = tail {
+            let sort_dir = self.sort_dir(&relative_to);
+            let algo = filter::select_algo(&self.query, self.algo.clone());
+            let matcher = Matcher::new_with_bonuses(algo, match_type.clone(), self.get_bonuses())
+                .line_cutoff(line_cutoff)
+                .case_matching(self.case_matching);
+            self.follow(
+                tail,
+                matcher,
+                |source| self.transform_source(source, &exclude, relative_to.clone(), &match_type),
+                |matched| {
+                    let ranked =
+                        filter::sort_filtered(matched, self.sort_by(), sort_dir.as_deref());
+                    let ranked = if self.sort_by() == SortBy::Score {
+                        filter::tiebreak(ranked, self.tiebreaks(&tiebreak))
+                    } else {
+                        ranked
+                    };
+                    ranked
+                        .into_iter()
+                        .take(number.unwrap_or(usize::MAX))
+                        .collect()
+                },
+                utility::tty::display_width(winwidth, 100),
+                icon_painter,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Streams the matches of the lines appended to the input file until it's removed.
+    ///
+    /// The matches of each batch of the appended lines are ranked by `rank` on their own.
+    fn follow(
+        &self,
+        mut tail: Tail,
+        matcher: Matcher,
+        transform: impl Fn(ItemSource) -> Result<ItemSource>,
+        rank: impl Fn(Vec<FilterResult>) -> Vec<FilterResult>,
+        winwidth: usize,
+        icon_painter: Option<IconPainter>,
+    ) -> Result<()> {
+        loop {
+            std::thread::sleep(FOLLOW_INTERVAL);
+
+            let appended = match tail.read_appended() {
+                Ok(appended) => appended,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+                Err(e) => return Err(e.into()),
+            };
+            if appended.is_empty() {
+                continue;
+            }
+
+            let matched = transform(Source::List(Box::new(appended.into_iter())))?
+                .into_items()?
+                .filter_map(|item| {
+                    matcher
+                        .do_match(&item, &self.query)
+                        .map(|(score, indices)| (item, score, indices))
+                })
+                .collect::<Vec<_>>();
+
+            if !matched.is_empty() {
+                printer::print_appended_filter_results(
+                    rank(matched),
+                    winwidth,
+                    icon_painter.clone(),
+                );
+            }
+        }
+    }
+
+    /// Returns the tiebreaks of --fzf-args if any, `tiebreak` of --tiebreak otherwise.
+    fn tiebreaks<'a>(&'a self, tiebreak: &'a [Tiebreak]) -> &'a [Tiebreak] {
+        if self.tiebreak.is_empty() {
+            tiebreak
+        } else {
+            &self.tiebreak
+        }
+    }
+
+    /// Returns a copy of the filter with the options of `fzf_args` applied.
+    fn with_fzf_args(&self, fzf_args: FzfArgs) -> Self {
+        let mut filter = self.clone();
+        filter.fzf_args = None;
+        if fzf_args.exact {
+            filter.algo = Some(Algo::SubString);
+        }
+        if let Some(case_matching) = fzf_args.case_matching {
+            filter.case_matching = case_matching;
+        }
+        filter.fields = fzf_args.fields;
+        filter.tiebreak = fzf_args.tiebreak;
+        filter
+    }
+
+    pub fn validate(&self) -> Result<(), ParamsError> {
+        let requires_sync = [
+            (self.session_id.is_some(), "--session-id"),
+            (self.stdin_tee, "--stdin-tee"),
+            (self.profile, "--profile"),
+            (self.queries_file.is_some(), "--queries-file"),
+            (self.score_cache, "--score-cache"),
+            (self.indices_only, "--indices-only"),
+        ];
+        for (enabled, flag) in requires_sync.iter() {
+            if *enabled && !self.sync {
+                return Err(ParamsError::new(
+                    &[*flag, "--sync"],
+                    format!("{} requires --sync", flag),
+                ));
+            }
+        }
+        let conflicts_with_sync = [
+            (self.snapshot.is_some(), "--snapshot"),
+            (self.follow, "--follow"),
+        ];
+        for (enabled, flag) in conflicts_with_sync.iter() {
+            if *enabled && self.sync {
+                return Err(ParamsError::new(
+                    &[*flag, "--sync"],
+                    format!("{} conflicts with --sync", flag),
+                ));
+            }
+        }
+        if self.stable_order && self.session_id.is_none() {
+            return Err(ParamsError::new(
+                &["--stable-order", "--session-id"],
+                "--stable-order requires --session-id",
+            ));
+        }
+        if self.stable_order && self.sort_by() != SortBy::Score {
+            return Err(ParamsError::new(
+                &["--stable-order", "--sort-by"],
+                "--stable-order requires the results sorted by score",
+            ));
+        }
+        if self.follow && self.input.is_none() {
+            return Err(ParamsError::new(
+                &["--follow", "--input"],
+                "--follow requires --input",
+            ));
+        }
+        if self.score_cache && self.input.is_none() && !self.stdin_tee {
+            return Err(ParamsError::new(
+                &["--score-cache", "--input"],
+                "--score-cache requires --input or --stdin-tee",
+            ));
+        }
+        if self.score_cache && self.display_delimiter.is_some() {
+            return Err(ParamsError::new(
+                &["--score-cache", "--display-delimiter"],
+                "--score-cache conflicts with --display-delimiter",
+            ));
+        }
+        Ok(())
     }
 
     pub fn run(&self, params: Params) -> Result<()> {
-        if self.sync {
+        if let Some(ref fzf_args) = self.fzf_args {
+            return self.with_fzf_args(FzfArgs::parse(fzf_args)?).run(params);
+        }
+        if let Some(ref queries_file) = self.queries_file {
+            self.batch_run(queries_file, params)?;
+        } else if self.sync {
             self.sync_run(params)?;
         } else {
             self.dyn_run(params)?;
@@ -1,21 +1,35 @@
-use std::path::PathBuf;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 use anyhow::{Context, Result};
+use ignore::{WalkBuilder, WalkState};
 use structopt::StructOpt;
 
 use filter::{
-    matcher::{Bonus, MatchType},
+    matcher::{Bonus, LineCutoff, MatchType},
     subprocess::Exec,
-    FilterContext, Source,
+    ExcludeFilter, FilterContext, SortBy, Source,
 };
 use icon::IconPainter;
-use utility::is_git_repo;
+use printer::group::{self, GroupedLines, IndexUnit};
+use source_item::SourceItem;
+use utility::{get_cache_dir, get_partial_cache, is_git_repo};
 
+use crate::api::grep::GrepResult;
 use crate::app::Params;
-use crate::cmd::cache::{cache_exists, send_response_from_cache, SendResponse};
-use crate::process::light::{set_current_dir, LightCommand};
-use crate::tools::rg::JsonLine;
+use crate::cmd::cache::{cache_exists, send_response_from_cache, CacheEntry, SendResponse};
+use crate::cmd::jobs::{self, JobHandle};
+use crate::params::ParamsError;
+use crate::process::light::{
+    set_current_dir, set_low_priority, EnvOptions, ExecutedInfo, Failure, LightCommand,
+};
+use crate::session_cache::SessionCache;
+use crate::tools::case::CaseOptions;
+use crate::tools::ignore::{is_rg_or_fd, IgnoreOptions};
+use crate::tools::path_set::PathSet;
+use crate::tools::shell_words;
 
 const RG_ARGS: [&str; 7] = [
     "rg",
@@ -27,24 +41,179 @@ const RG_ARGS: [&str; 7] = [
     "",
 ];
 
-// Ref https://github.com/liuchengxu/vim-clap/issues/533
-#[cfg(windows)]
-const RG_EXEC_CMD: &str = "rg --column --line-number --no-heading --color=never --smart-case '' .";
-#[cfg(not(windows))]
-const RG_EXEC_CMD: &str = "rg --column --line-number --no-heading --color=never --smart-case ''";
+/// Kind of the forerunner job in the job registry.
+const RIPGREP_JOB: &str = "ripgrep";
+
+/// Returns `RG_ARGS` with `flags` inserted before the empty pattern, which are also the key of
+/// the forerunner cache.
+fn rg_args_with<'a>(flags: &[&'a str]) -> Vec<&'a str> {
+    let (pattern, options): (&&'a str, &[&'a str]) =
+        RG_ARGS.split_last().expect("RG_ARGS is not empty");
+    options
+        .iter()
+        .chain(flags)
+        .chain(std::iter::once(pattern))
+        .copied()
+        .collect()
+}
+
+/// Returns the shell command of `rg_args`, whose last arg is the empty pattern.
+fn rg_exec_cmd(rg_args: &[&str]) -> String {
+    let options = rg_args[..rg_args.len() - 1].join(" ");
+    // Ref https://github.com/liuchengxu/vim-clap/issues/533
+    if cfg!(windows) {
+        format!("{} '' .", options)
+    } else {
+        format!("{} ''", options)
+    }
+}
+
+/// Returns `rg_args` with the arg identifying `path_set` appended, which are the key of the cache
+/// of the grep restricted to the path set.
+fn cache_key_with<'a>(rg_args: &[&'a str], path_set_arg: Option<&'a str>) -> Vec<&'a str> {
+    rg_args.iter().copied().chain(path_set_arg).collect()
+}
+
+/// Reads the path set of `--paths-file`, the paths are relative to `cmd_dir`.
+fn read_path_set(paths_file: Option<&Path>, cmd_dir: Option<&Path>) -> Result<Option<PathSet>> {
+    let paths_file = match paths_file {
+        Some(paths_file) => paths_file,
+        None => return Ok(None),
+    };
+    let dir = match cmd_dir {
+        Some(dir) => dir.to_path_buf(),
+        None => std::env::current_dir()?,
+    };
+    PathSet::read(paths_file, &dir).map(Some)
+}
+
+/// Flags of rg allowed in the grep query, with whether the flag takes a value.
+const QUERY_FLAGS: &[(&str, bool)] = &[
+    ("-g", true),
+    ("--glob", true),
+    ("--iglob", true),
+    ("-t", true),
+    ("--type", true),
+    ("-T", true),
+    ("--type-not", true),
+    ("-w", false),
+    ("--word-regexp", false),
+    ("-F", false),
+    ("--fixed-strings", false),
+    ("-i", false),
+    ("--ignore-case", false),
+    ("-s", false),
+    ("--case-sensitive", false),
+    ("--hidden", false),
+];
+
+/// Grep query of the provider input, the pattern optionally followed by the flags of rg, e.g.,
+/// `"fn run" --glob *.rs`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct GrepQuery {
+    pattern: String,
+    flags: Vec<String>,
+}
+
+impl GrepQuery {
+    /// Parses `query` by [`shell_words::split_spanned`] if it has any flag in `QUERY_FLAGS`, the
+    /// pattern is the text of the other words exactly as typed, the whitespaces included. The
+    /// quotes of a single quoted pattern, e.g., `"fn run" -w`, are removed unless it's a literal
+    /// search by `-F`.
+    ///
+    /// `query` is the pattern as is otherwise, including an incomplete one being typed.
+    fn parse(query: &str) -> Self {
+        let as_is = || Self {
+            pattern: query.into(),
+            flags: Vec::new(),
+        };
+        let is_flag = |word: &str| {
+            QUERY_FLAGS
+                .iter()
+                .find(|(flag, _)| word == *flag || word.starts_with(&format!("{}=", flag)))
+                .map(|(flag, takes_value)| *takes_value && word == *flag)
+        };
+        if !query.split_whitespace().any(|w| is_flag(w).is_some()) {
+            return as_is();
+        }
+        let words = match shell_words::split_spanned(query) {
+            Ok(words) => words,
+            Err(_) => return as_is(),
+        };
+
+        let mut has_flag = false;
+        // Byte ranges of the runs of the pattern words between the flags.
+        let mut runs: Vec<Range<usize>> = Vec::new();
+        let mut last_is_pattern = false;
+        let mut unquoted = Vec::new();
+        let mut flags = Vec::new();
+        let mut words = words.into_iter();
+        while let Some((word, span)) = words.next() {
+            // The quoted flags are part of the pattern.
+            match is_flag(&query[span.clone()]) {
+                Some(takes_value) => {
+                    has_flag = true;
+                    last_is_pattern = false;
+                    if !takes_value {
+                        flags.push(word);
+                    } else if let Some((value, _)) = words.next() {
+                        flags.push(word);
+                        flags.push(value);
+                    }
+                    // The value is not typed yet otherwise.
+                }
+                None => {
+                    match runs.last_mut() {
+                        Some(run) if last_is_pattern => run.end = span.end,
+                        _ => runs.push(span),
+                    }
+                    last_is_pattern = true;
+                    unquoted.push(word);
+                }
+            }
+        }
+        if !has_flag {
+            return as_is();
+        }
+
+        let is_literal = flags
+            .iter()
+            .any(|flag| flag == "-F" || flag == "--fixed-strings");
+        let is_quoted = |raw: &str| {
+            raw.len() >= 2
+                && ((raw.starts_with('"') && raw.ends_with('"'))
+                    || (raw.starts_with('\'') && raw.ends_with('\'')))
+        };
+        let pattern = match (unquoted.as_slice(), runs.as_slice()) {
+            // The quotes only group the words of the pattern.
+            ([word], [run]) if !is_literal && is_quoted(&query[run.clone()]) => word.clone(),
+            _ => runs
+                .iter()
+                .map(|run| &query[run.clone()])
+                .collect::<Vec<_>>()
+                .join(" "),
+        };
+        Self { pattern, flags }
+    }
+}
 
 #[derive(StructOpt, Debug, Clone)]
 pub struct Grep {
     /// Specify the query string for GREP_CMD.
+    ///
+    /// The query can be followed by the glob, type, word, literal and case flags of rg when --sync
+    /// is on, and the pattern with spaces can be quoted, e.g., `"fn run" --glob *.rs`.
     #[structopt(index = 1, short, long)]
     grep_query: String,
 
     /// Specify the grep command to run, normally rg will be used.
     ///
+    /// The options delegated to rg, e.g., --word and --hidden, are only added to it if it's rg.
+    ///
     /// Incase of clap can not reconginize such option: --cmd "rg --vimgrep ... "fn ul"".
     ///                                                       |-----------------|
     ///                                                   this can be seen as an option by mistake.
-    #[structopt(short, long, required_if("sync", "true"))]
+    #[structopt(short, long)]
     grep_cmd: Option<String>,
 
     /// Delegate to -g option of rg
@@ -52,16 +221,88 @@ pub struct Grep {
     glob: Option<String>,
 
     /// Specify the working directory of CMD
-    #[structopt(long = "cmd-dir", parse(from_os_str))]
+    #[structopt(long = "cmd-dir", parse(from_os_str = utility::paths::expand_os))]
     cmd_dir: Option<PathBuf>,
 
+    /// Search only the paths listed in this file, one per line relative to --cmd-dir.
+    ///
+    /// E.g., the output of `git diff --name-only` for grepping the changed files only, the paths
+    /// not existing are skipped.
+    #[structopt(long = "paths-file", parse(from_os_str = utility::paths::expand_os))]
+    paths_file: Option<PathBuf>,
+
     /// Read input from a cached grep tempfile, only absolute file path is supported.
-    #[structopt(long = "input", parse(from_os_str))]
+    #[structopt(long = "input", parse(from_os_str = utility::paths::expand_os))]
     input: Option<PathBuf>,
 
     /// Synchronous filtering, returns after the input stream is complete.
     #[structopt(short, long)]
     sync: bool,
+
+    /// Show NUM lines before and after each match, delegate to -C option of rg.
+    ///
+    /// The context lines are attached to the matched lines as `contexts` of the returned JSON,
+    /// except when grouping by file. Only valid when --sync is on.
+    #[structopt(short = "C", long = "context", name = "NUM_CONTEXT")]
+    context: Option<usize>,
+
+    /// Show NUM lines after each match, delegate to -A option of rg.
+    ///
+    /// Overrides --context for the trailing context, only valid when --sync is on.
+    #[structopt(short = "A", long = "after-context", name = "NUM_AFTER")]
+    after_context: Option<usize>,
+
+    /// Show NUM lines before each match, delegate to -B option of rg.
+    ///
+    /// Overrides --context for the leading context, only valid when --sync is on.
+    #[structopt(short = "B", long = "before-context", name = "NUM_BEFORE")]
+    before_context: Option<usize>,
+
+    /// Group the results by file, each file header is followed by its matched lines.
+    ///
+    /// The extra `entries` field of the returned JSON tells the kind of each line.
+    #[structopt(long)]
+    group_by_file: bool,
+
+    /// Reuse the grep output of the same query computed before in this session.
+    ///
+    /// Only valid when --sync is on.
+    #[structopt(long)]
+    session_id: Option<String>,
+
+    /// Match only the whole words, delegate to -w option of rg.
+    #[structopt(long)]
+    word: bool,
+
+    /// Treat the query as a literal string instead of a regex, delegate to -F option of rg.
+    #[structopt(long)]
+    literal: bool,
+
+    /// Sort the results by score, path, mtime or keep the output order of rg with none.
+    ///
+    /// Only valid when --sync is off.
+    #[structopt(long, possible_values = &SortBy::variants(), case_insensitive = true)]
+    sort_by: Option<SortBy>,
+
+    // The cache created by the forerunner job is not used if the environment is customized.
+    #[structopt(flatten)]
+    env: EnvOptions,
+
+    #[structopt(flatten)]
+    ignore: IgnoreOptions,
+
+    #[structopt(flatten)]
+    case: CaseOptions,
+
+    /// Print the ignore files influencing the search in --cmd-dir instead of searching.
+    ///
+    /// For debugging the results unexpectedly ignored or the stale cache.
+    #[structopt(long)]
+    list_ignored: bool,
+
+    /// Extra flags of rg from the config file.
+    #[structopt(skip)]
+    config_flags: Vec<String>,
 }
 
 fn prepare_sync_grep_cmd(cmd_str: &str, cmd_dir: Option<PathBuf>) -> (Command, Vec<&str>) {
@@ -92,7 +333,85 @@ fn prepare_sync_grep_cmd(cmd_str: &str, cmd_dir: Option<PathBuf>) -> (Command, V
 }
 
 impl Grep {
+    pub fn validate(&self) -> Result<(), ParamsError> {
+        if self.sync && self.grep_cmd.is_none() {
+            return Err(ParamsError::new(
+                &["--sync", "--grep-cmd"],
+                "--grep-cmd is required when --sync is on",
+            ));
+        }
+        let requires_sync = [
+            (self.context.is_some(), "--context"),
+            (self.after_context.is_some(), "--after-context"),
+            (self.before_context.is_some(), "--before-context"),
+            (self.session_id.is_some(), "--session-id"),
+        ];
+        for (enabled, flag) in requires_sync.iter() {
+            if *enabled && !self.sync {
+                return Err(ParamsError::new(
+                    &[*flag, "--sync"],
+                    format!("{} requires --sync", flag),
+                ));
+            }
+        }
+        if self.sort_by.is_some() && self.sync {
+            return Err(ParamsError::new(
+                &["--sort-by", "--sync"],
+                "--sort-by conflicts with --sync",
+            ));
+        }
+        self.case.validate()
+    }
+
+    pub fn query(&self) -> &str {
+        &self.grep_query
+    }
+
+    pub fn cmd_dir(&self) -> Option<&Path> {
+        self.cmd_dir.as_deref()
+    }
+
+    /// Sets the extra flags of rg from the config file.
+    pub fn config_flags(mut self, flags: Vec<String>) -> Self {
+        self.config_flags = flags;
+        self
+    }
+
+    /// Returns the extra flags of rg for the ignore options, `--word`, `--literal`, the case
+    /// options and the config file.
+    fn rg_flags(&self) -> Vec<&str> {
+        let mut flags = self.ignore.flags();
+        flags.extend(self.case.flags());
+        if self.word {
+            flags.push("-w");
+        }
+        if self.literal {
+            flags.push("-F");
+        }
+        flags.extend(self.config_flags.iter().map(String::as_str));
+        flags
+    }
+
+    /// Returns [`Self::rg_flags`] if the program of `cmd` is rg, the other commands may not
+    /// accept them.
+    fn rg_flags_for(&self, cmd: &str) -> Vec<&str> {
+        if is_rg_or_fd(cmd) {
+            self.rg_flags()
+        } else {
+            Vec::new()
+        }
+    }
+
     pub fn run(&self, params: Params) -> Result<()> {
+        if self.list_ignored {
+            let dir = match self.cmd_dir {
+                Some(ref dir) => dir.clone(),
+                None => std::env::current_dir()?,
+            };
+            let ignore_files = crate::tools::ignore::ignore_files(&dir);
+            utility::println_json!(ignore_files);
+            return Ok(());
+        }
         if self.sync {
             self.sync_run(params)?;
         } else {
@@ -110,6 +429,8 @@ impl Grep {
             number,
             winwidth,
             icon_painter,
+            relative_to,
+            exclude,
             ..
         }: Params,
     ) -> Result<()> {
@@ -117,51 +438,161 @@ impl Grep {
             .grep_cmd
             .clone()
             .context("--grep-cmd is required when --sync is on")?;
+        crate::interrupt::catch_interrupt();
+
+        let path_set = read_path_set(self.paths_file.as_deref(), self.cmd_dir.as_deref())?;
+        let grep_query = GrepQuery::parse(&self.grep_query);
         let (mut cmd, mut args) = prepare_sync_grep_cmd(&grep_cmd, self.cmd_dir.clone());
 
+        // The extra flags of rg are only added to the user's grep command if it's rg.
+        args.extend(self.rg_flags_for(&grep_cmd));
+        args.extend(grep_query.flags.iter().map(String::as_str));
+
         // We split out the grep opts and query in case of the possible escape issue of clap.
-        args.push(&self.grep_query);
+        args.push(&grep_query.pattern);
 
         if let Some(ref g) = self.glob {
             args.push("-g");
             args.push(g);
         }
 
-        // currently vim-clap only supports rg.
-        // Ref https://github.com/liuchengxu/vim-clap/pull/60
-        if cfg!(windows) {
+        let context_flags = [
+            ("-C", self.context),
+            ("-A", self.after_context),
+            ("-B", self.before_context),
+        ]
+        .iter()
+        .filter_map(|(flag, n)| n.map(|n| (*flag, n.to_string())))
+        .collect::<Vec<_>>();
+        for (flag, n) in context_flags.iter() {
+            args.push(*flag);
+            args.push(n);
+        }
+
+        if let Some(ref path_set) = path_set {
+            args.extend(path_set.paths());
+        } else if cfg!(windows) {
+            // currently vim-clap only supports rg.
+            // Ref https://github.com/liuchengxu/vim-clap/pull/60
             args.push(".");
         }
 
         cmd.args(&args[1..]);
-
-        let mut light_cmd = LightCommand::new_grep(&mut cmd, None, number, None, None);
-
-        let execute_info = light_cmd.execute(&args)?;
+        self.env.apply(&mut cmd);
+
+        let session_cache = self
+            .session_id
+            .as_ref()
+            .map(|session_id| SessionCache::new(session_id, "grep"));
+        let options = format!("{:?} {:?} {:?} {:?}", args, self.cmd_dir, number, self.env);
+
+        let (grep_lines, failure) = match session_cache
+            .as_ref()
+            .and_then(|cache| cache.load::<Vec<String>>(&self.grep_query, &options))
+        {
+            Some(grep_lines) => (grep_lines, None),
+            // rg would search the working directory without any path.
+            None if path_set.as_ref().map_or(false, PathSet::is_empty) => (Vec::new(), None),
+            None => {
+                // The output is not truncated to `number` here, otherwise the context and the
+                // other messages of rg would take up the room of the matched lines.
+                let mut light_cmd = LightCommand::new_grep(&mut cmd, None, None, None, None);
+                // rg is killed on interrupt, the lines read so far are printed as the results.
+                let ExecutedInfo { lines, failure, .. } = light_cmd.execute_interruptible()?;
+                // The partial results of an interrupted or failed grep are not cached.
+                let complete = !filter::interrupt::is_interrupted()
+                    && failure.as_ref().map_or(true, Failure::is_complete_output);
+                if let Some(cache) = session_cache.as_ref().filter(|_| complete) {
+                    if let Err(e) = cache.store(&self.grep_query, &options, &lines) {
+                        log::error!("Failed to write the session cache: {:?}", e);
+                    }
+                }
+                (lines, failure)
+            }
+        };
 
         let enable_icon = icon_painter.is_some();
 
-        let (lines, indices): (Vec<String>, Vec<Vec<usize>>) = execute_info
-            .lines
-            .iter()
-            .filter_map(|s| serde_json::from_str::<JsonLine>(s).ok())
-            .map(|line| line.build_grep_line(enable_icon))
-            .unzip();
+        let mut grep_result = GrepResult::from_grep_lines(
+            &grep_lines,
+            &exclude,
+            relative_to.as_deref(),
+            // The icon is added to the file headers instead when grouping by file.
+            enable_icon && !self.group_by_file,
+            self.after_context.or(self.context).unwrap_or_default(),
+        );
+        if let Some(number) = number {
+            grep_result.truncate(number);
+        }
+        let GrepResult {
+            total,
+            lines,
+            indices,
+            positions,
+            contexts: match_contexts,
+            file_matches,
+            stats,
+        } = grep_result;
+
+        let winwidth = utility::tty::display_width(winwidth, 80);
+
+        if self.group_by_file {
+            let shown = lines.len();
+            let GroupedLines {
+                lines,
+                indices,
+                entries,
+            } = group::group_by_file(lines, indices, IndexUnit::Byte, enable_icon);
+
+            let (lines, indices, truncated_map) =
+                printer::truncate_grep_lines(lines, indices, winwidth, None);
+
+            let mut payload = serde_json::json!({
+                "total": total,
+                "lines": lines,
+                "indices": indices,
+                "entries": entries,
+                "file_matches": file_matches,
+                "stats": stats,
+            });
+            if !truncated_map.is_empty() {
+                payload["truncated_map"] = serde_json::json!(truncated_map);
+            }
+            if let Some(ref failure) = failure {
+                failure.attach_to(&mut payload);
+            }
+            // The file headers are not counted in `shown`.
+            printer::Payload::new(total, shown).attach_to(&mut payload);
+            printer::println_payload(payload);
 
-        let total = lines.len();
+            return Ok(());
+        }
 
         let (lines, indices, truncated_map) = printer::truncate_grep_lines(
             lines,
             indices,
-            winwidth.unwrap_or(80),
+            winwidth,
             if enable_icon { Some(2) } else { None },
         );
 
-        if truncated_map.is_empty() {
-            utility::println_json!(total, lines, indices);
-        } else {
-            utility::println_json!(total, lines, indices, truncated_map);
+        let mut payload = serde_json::json!({
+            "total": total,
+            "lines": lines,
+            "indices": indices,
+            "positions": positions,
+            "file_matches": file_matches,
+            "stats": stats,
+        });
+        if !context_flags.is_empty() {
+            payload["contexts"] = serde_json::json!(match_contexts);
+        }
+        if !truncated_map.is_empty() {
+            payload["truncated_map"] = serde_json::json!(truncated_map);
         }
+        if let Some(failure) = failure {
+            failure.attach_to(&mut payload);
+        }
+        printer::println_payload(payload);
 
         Ok(())
     }
@@ -176,11 +607,27 @@ impl Grep {
             winwidth,
             icon_painter,
             no_cache,
+            relative_to,
+            exclude,
+            max_line_len,
+            long_line_strategy,
+            tiebreak,
         }: Params,
     ) -> Result<()> {
-        let do_dyn_filter = |source: Source<std::iter::Empty<_>>| {
+        // The flags are only applicable to rg.
+        let grep_query = GrepQuery::parse(&self.grep_query);
+        let do_dyn_filter = |source: Source<Box<dyn Iterator<Item = SourceItem>>>| {
+            let source = if exclude.is_empty() {
+                source
+            } else {
+                source.exclude(ExcludeFilter::new(&exclude), MatchType::IgnoreFilePath)?
+            };
+            let source = match relative_to {
+                Some(ref dir) => source.relative_to(dir.clone(), MatchType::IgnoreFilePath)?,
+                None => source,
+            };
             filter::dyn_run(
-                &self.grep_query,
+                &grep_query.pattern,
                 source,
                 FilterContext::new(
                     None,
@@ -188,24 +635,58 @@ impl Grep {
                     winwidth,
                     icon_painter,
                     MatchType::IgnoreFilePath,
-                ),
+                )
+                .line_cutoff(
+                    max_line_len
+                        .map(|max_len| LineCutoff::new(max_len, long_line_strategy.clone())),
+                )
+                .group_by_file(self.group_by_file)
+                .case_matching(self.case.case_matching())
+                .sort_by(
+                    self.sort_by.unwrap_or_default(),
+                    relative_to.clone().or_else(|| self.cmd_dir.clone()),
+                )
+                .tiebreak(tiebreak.clone()),
                 vec![Bonus::None],
             )
         };
 
-        let source: Source<std::iter::Empty<_>> = if let Some(ref tempfile) = self.input {
-            Source::File(tempfile.clone())
-        } else if let Some(ref dir) = self.cmd_dir {
-            if !no_cache {
-                if let Ok((cached_file, _)) = cache_exists(&RG_ARGS, dir) {
-                    return do_dyn_filter(Source::File(cached_file));
-                }
-            }
-            Exec::shell(RG_EXEC_CMD).cwd(dir).into()
-        } else {
-            Exec::shell(RG_EXEC_CMD).into()
+        let path_set = match self.input {
+            Some(_) => None,
+            None => read_path_set(self.paths_file.as_deref(), self.cmd_dir.as_deref())?,
+        };
+        let rg_args = rg_args_with(&self.rg_flags());
+        let path_set_arg = path_set.as_ref().map(PathSet::cache_arg);
+        let cache_key = cache_key_with(&rg_args, path_set_arg.as_deref());
+        let rg_exec = || match path_set {
+            Some(ref path_set) => path_set
+                .paths()
+                .fold(Exec::cmd(rg_args[0]).args(&rg_args[1..]), |exec, path| {
+                    exec.arg(path)
+                }),
+            None => Exec::shell(rg_exec_cmd(&rg_args)),
         };
 
+        let source: Source<Box<dyn Iterator<Item = SourceItem>>> =
+            if let Some(ref tempfile) = self.input {
+                Source::File(tempfile.clone())
+            } else if path_set.as_ref().map_or(false, PathSet::is_empty) {
+                Source::List(Box::new(std::iter::empty()))
+            } else if let Some(ref dir) = self.cmd_dir {
+                if !no_cache && self.env.is_inherited() {
+                    if let Ok((cached_file, _)) = cache_exists(&cache_key, dir) {
+                        return do_dyn_filter(Source::File(cached_file));
+                    }
+                    // The forerunner job is still running, filter what has been written so far.
+                    if let Some(partial_cache) = get_partial_cache(&cache_key, dir) {
+                        return do_dyn_filter(Source::File(partial_cache));
+                    }
+                }
+                self.env.apply_to_exec(rg_exec().cwd(dir)).into()
+            } else {
+                self.env.apply_to_exec(rg_exec()).into()
+            };
+
         do_dyn_filter(source)
     }
 }
@@ -213,15 +694,88 @@ impl Grep {
 #[derive(StructOpt, Debug, Clone)]
 pub struct RipGrepForerunner {
     /// Specify the working directory of CMD
-    #[structopt(long = "cmd-dir", parse(from_os_str))]
+    #[structopt(long = "cmd-dir", parse(from_os_str = utility::paths::expand_os))]
     cmd_dir: Option<PathBuf>,
 
     /// Specify the threshold for writing the output of command to a tempfile.
     #[structopt(long = "output-threshold", default_value = "30000")]
     output_threshold: usize,
+
+    /// Decide whether to run the forerunner job by sampling the project size.
+    ///
+    /// Instead of checking if `cmd_dir` is a git repo, count the files first: the huge project
+    /// is left to the streaming dyn filter, the small one is cached by the forerunner job.
+    #[structopt(long)]
+    auto: bool,
+
+    /// Maximum number of files of a project that will be cached in the auto mode.
+    #[structopt(long = "auto-file-limit", default_value = "20000")]
+    auto_file_limit: usize,
+
+    /// Search only the paths listed in this file, one per line relative to --cmd-dir.
+    ///
+    /// E.g., the output of `git diff --name-only` for caching the grep in the changed files, the paths
+    /// not existing are skipped.
+    #[structopt(long = "paths-file", parse(from_os_str = utility::paths::expand_os))]
+    paths_file: Option<PathBuf>,
+
+    /// Run rg with a lower scheduling priority so that creating the cache of a big project
+    /// doesn't make the editor stutter.
+    #[structopt(long = "low-priority")]
+    low_priority: bool,
+
+    #[structopt(flatten)]
+    ignore: IgnoreOptions,
+
+    /// Only for the cache key to be same with the grep using the same case options.
+    #[structopt(flatten)]
+    case: CaseOptions,
+
+    /// Extra flags of rg from the config file, same with those of the grep.
+    #[structopt(skip)]
+    config_flags: Vec<String>,
+}
+
+/// Returns true if there are more than `limit` files in `dir`, stops walking once exceeded.
+///
+/// The hidden and ignored files are not counted, same with the files searched by rg, so that an
+/// ignored tree like `target/` or `node_modules/` doesn't make the project look huge.
+fn exceeds_file_limit(dir: &Path, limit: usize) -> bool {
+    let counter = AtomicUsize::new(0);
+    WalkBuilder::new(dir).build_parallel().run(|| {
+        let counter = &counter;
+        Box::new(move |entry| {
+            if counter.load(Ordering::Relaxed) > limit {
+                return WalkState::Quit;
+            }
+            let is_file = entry
+                .ok()
+                .and_then(|entry| entry.file_type())
+                .map_or(false, |file_type| file_type.is_file());
+            if is_file {
+                counter.fetch_add(1, Ordering::Relaxed);
+            }
+            WalkState::Continue
+        })
+    });
+    counter.load(Ordering::Relaxed) > limit
 }
 
 impl RipGrepForerunner {
+    pub fn validate(&self) -> Result<(), ParamsError> {
+        self.case.validate()
+    }
+
+    pub fn cmd_dir(&self) -> Option<&Path> {
+        self.cmd_dir.as_deref()
+    }
+
+    /// Sets the extra flags of rg from the config file.
+    pub fn config_flags(mut self, flags: Vec<String>) -> Self {
+        self.config_flags = flags;
+        self
+    }
+
     /// Skip the forerunner job if `cmd_dir` is not a git repo.
     ///
     /// Only spawn the forerunner job for git repo for now.
@@ -238,6 +792,20 @@ impl RipGrepForerunner {
         false
     }
 
+    /// Skip the forerunner job if the project is too large to be cached instantly.
+    ///
+    /// The huge project should use the streaming dyn filter directly.
+    fn should_skip_auto(&self) -> bool {
+        let dir = match self.cmd_dir {
+            Some(ref dir) => dir.clone(),
+            None => match std::env::current_dir() {
+                Ok(dir) => dir,
+                Err(_) => return true,
+            },
+        };
+        exceeds_file_limit(&dir, self.auto_file_limit)
+    }
+
     pub fn run(
         self,
         Params {
@@ -247,9 +815,17 @@ impl RipGrepForerunner {
             ..
         }: Params,
     ) -> Result<()> {
+        let mut flags = self.ignore.flags();
+        flags.extend(self.case.flags());
+        flags.extend(self.config_flags.iter().map(String::as_str));
+        let rg_args = rg_args_with(&flags);
+        let path_set = read_path_set(self.paths_file.as_deref(), self.cmd_dir.as_deref())?;
+        let path_set_arg = path_set.as_ref().map(PathSet::cache_arg);
+        let cache_key = cache_key_with(&rg_args, path_set_arg.as_deref());
+
         if !no_cache {
             if let Some(ref dir) = self.cmd_dir {
-                if let Ok((cache, total)) = cache_exists(&RG_ARGS, dir) {
+                if let Ok((cache, total)) = cache_exists(&cache_key, dir) {
                     send_response_from_cache(
                         &cache,
                         total,
@@ -261,16 +837,58 @@ impl RipGrepForerunner {
             }
         }
 
-        if self.should_skip() {
+        let skip = if self.auto {
+            self.should_skip_auto()
+        } else {
+            self.should_skip()
+        };
+
+        // Nothing to cache, rg would search the whole directory without any path.
+        if skip || path_set.as_ref().map_or(false, PathSet::is_empty) {
             return Ok(());
         }
 
-        let mut cmd = Command::new(RG_ARGS[0]);
+        let rg_cmd = cache_key.join(" ");
+
+        // Another forerunner job is already creating the cache for the same directory, which is
+        // superseded by this one since the provider has been reopened in the meanwhile.
+        let _cache_lock = match self.cmd_dir {
+            Some(ref dir) => {
+                let cache_lock = match CacheEntry::try_lock(&cache_key, dir)? {
+                    Some(cache_lock) => Some(cache_lock),
+                    None if jobs::supersede(
+                        RIPGREP_JOB,
+                        &rg_cmd,
+                        dir,
+                        &get_cache_dir(&cache_key, dir),
+                    )? =>
+                    {
+                        CacheEntry::try_lock(&cache_key, dir)?
+                    }
+                    None => None,
+                };
+                match cache_lock {
+                    Some(cache_lock) => Some(cache_lock),
+                    None => return Ok(()),
+                }
+            }
+            None => None,
+        };
+
+        let mut cmd = Command::new(rg_args[0]);
         // Do not use --vimgrep here.
-        cmd.args(&RG_ARGS[1..]);
+        cmd.args(&rg_args[1..]);
+        if let Some(ref path_set) = path_set {
+            cmd.args(path_set.paths());
+        }
 
         set_current_dir(&mut cmd, self.cmd_dir.clone());
 
+        if self.low_priority {
+            set_low_priority(&mut cmd);
+        }
+
+        let cmd_dir = self.cmd_dir.clone();
         let mut light_cmd = LightCommand::new_grep(
             &mut cmd,
             self.cmd_dir,
@@ -279,8 +897,91 @@ impl RipGrepForerunner {
             Some(self.output_threshold),
         );
 
-        light_cmd.execute(&RG_ARGS)?.print();
+        if let Some(ref dir) = cmd_dir {
+            jobs::lead_process_group();
+            let job = JobHandle::register(RIPGREP_JOB, &rg_cmd, dir)
+                .map_err(|e| log::error!("Failed to register the ripgrep job: {:?}", e))
+                .ok();
+            let executed = light_cmd.execute_with_partial_cache(&cache_key);
+            if let Some(job) = job {
+                job.finish(&executed);
+            }
+            executed?.print();
+        } else {
+            light_cmd.execute(&cache_key)?.print();
+        }
 
         Ok(())
     }
 }
+
+#[test]
+fn test_rg_args_with() {
+    let rg_args = rg_args_with(&["-w", "-F"]);
+    assert_eq!(rg_args[rg_args.len() - 3..], ["-w", "-F", ""]);
+    assert_eq!(rg_args_with(&[]), RG_ARGS);
+    assert!(rg_exec_cmd(&rg_args).starts_with("rg --column"));
+    assert_eq!(cache_key_with(&rg_args, None), rg_args);
+    assert_eq!(
+        cache_key_with(&rg_args, Some("--paths=1f")).last(),
+        Some(&"--paths=1f")
+    );
+}
+
+#[test]
+fn test_exceeds_file_limit() {
+    let dir = std::env::temp_dir().join(format!("clap_test_file_limit_{}", std::process::id()));
+    let ignored = dir.join("target");
+    std::fs::create_dir_all(&ignored).unwrap();
+    std::fs::write(dir.join(".ignore"), "target/\n").unwrap();
+    for i in 0..3 {
+        std::fs::write(dir.join(format!("{}.rs", i)), "").unwrap();
+    }
+    for i in 0..10 {
+        std::fs::write(ignored.join(format!("{}.o", i)), "").unwrap();
+    }
+    assert!(exceeds_file_limit(&dir, 2));
+    assert!(!exceeds_file_limit(&dir, 3));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_parse_grep_query() {
+    let query = GrepQuery::parse(r#""fn run" --glob *.rs -w"#);
+    assert_eq!(query.pattern, "fn run");
+    assert_eq!(query.flags, vec!["--glob", "*.rs", "-w"]);
+
+    let query = GrepQuery::parse(r"fn\s+run  -foo --type=rust");
+    assert_eq!(query.pattern, r"fn\s+run  -foo");
+    assert_eq!(query.flags, vec!["--type=rust"]);
+
+    // The quotes of a literal search are searched as well.
+    let query = GrepQuery::parse(r#""fn run" -F"#);
+    assert_eq!(query.pattern, r#""fn run""#);
+    assert_eq!(query.flags, vec!["-F"]);
+    let query = GrepQuery::parse(r#"say "hi"  "-w" -g *.rs"#);
+    assert_eq!(query.pattern, r#"say "hi"  "-w""#);
+    assert_eq!(query.flags, vec!["-g", "*.rs"]);
+
+    // Left as is.
+    assert_eq!(
+        GrepQuery::parse(r"fn\s+run  -foo").pattern,
+        r"fn\s+run  -foo"
+    );
+    assert_eq!(GrepQuery::parse(r#""fn run"#).pattern, r#""fn run"#);
+    assert_eq!(GrepQuery::parse(r#""fn run""#).pattern, r#""fn run""#);
+    let query = GrepQuery::parse("fn run -g");
+    assert_eq!(query.pattern, "fn run");
+    assert_eq!(query.flags, Vec::<String>::new());
+}
+
+#[test]
+fn test_rg_flags_for() {
+    let grep = Grep::from_iter(&["grep", "foo", "--word", "--hidden", "--smart-case"])
+        .config_flags(vec!["--max-columns=200".into()]);
+    let flags = grep.rg_flags_for("/usr/bin/rg --vimgrep");
+    for flag in &["-w", "--hidden", "--max-columns=200"] {
+        assert!(flags.contains(flag));
+    }
+    assert!(grep.rg_flags_for("grep -rn").is_empty());
+}
@@ -10,7 +10,7 @@ use utility::read_lines;
 #[derive(StructOpt, Debug, Clone)]
 pub struct Helptags {
     /// Tempfile containing the info of vim helptags.
-    #[structopt(index = 1, short, long, parse(from_os_str))]
+    #[structopt(index = 1, short, long, parse(from_os_str = utility::paths::expand_os))]
     meta_info: PathBuf,
 }
 
@@ -0,0 +1,172 @@
+//! Persistent query history of each provider, for suggesting the previous queries.
+//!
+//! The queries are stored in `history/<provider>` of the cache directory, one per line with the
+//! most recent last.
+
+use std::path::PathBuf;
+
+use anyhow::Result;
+use structopt::StructOpt;
+
+use filter::{
+    matcher::{Algo, Bonus, MatchType, Matcher},
+    FilterResult,
+};
+use source_item::SourceItem;
+use utility::{cache_writer::write_atomically, clap_cache_dir};
+
+use crate::app::Params;
+use crate::session_cache::sanitize;
+
+const HISTORY_DIR: &str = "history";
+
+/// Maximum number of the queries kept per provider, the oldest ones are dropped.
+const MAX_HISTORY: usize = 500;
+
+/// Query history of a provider.
+#[derive(Debug, Clone)]
+struct QueryHistory {
+    path: PathBuf,
+}
+
+impl QueryHistory {
+    fn new(provider: &str) -> Self {
+        let mut path = clap_cache_dir();
+        path.push(HISTORY_DIR);
+        path.push(sanitize(provider));
+        Self { path }
+    }
+
+    /// Returns the recorded queries, the most recent first.
+    fn load(&self) -> Vec<String> {
+        std::fs::read_to_string(&self.path)
+            .map(|content| content.lines().rev().map(Into::into).collect())
+            .unwrap_or_default()
+    }
+
+    /// Records `query` as the most recent one, the same query recorded before is moved.
+    fn add(&self, query: &str) -> Result<()> {
+        let query = query.trim().replace('\n', " ");
+        if query.is_empty() {
+            return Ok(());
+        }
+        let mut queries = self.load();
+        queries.retain(|q| *q != query);
+        queries.insert(0, query);
+        queries.truncate(MAX_HISTORY);
+
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let mut content = queries.into_iter().rev().collect::<Vec<_>>().join("\n");
+        content.push('\n');
+        write_atomically(&self.path, content)
+    }
+
+    /// Returns the recorded queries fuzzy matched against the `partial` query.
+    ///
+    /// The queries of the same score are in the order of recency.
+    fn suggest(&self, partial: &str) -> Vec<FilterResult> {
+        let items = self
+            .load()
+            .into_iter()
+            .map(SourceItem::from)
+            .collect::<Vec<_>>();
+        if partial.is_empty() {
+            return items
+                .into_iter()
+                .map(|item| (item, 0, Vec::new()))
+                .collect();
+        }
+        let matcher = Matcher::new(Algo::Fzy, MatchType::Full, Bonus::None);
+        filter::rank(&matcher, partial, items)
+    }
+}
+
+#[derive(StructOpt, Debug, Clone)]
+enum HistoryCommand {
+    /// Record a query of the provider.
+    #[structopt(name = "add")]
+    Add {
+        /// Name of the provider, e.g., `files`.
+        #[structopt(index = 1)]
+        provider: String,
+        #[structopt(index = 2)]
+        query: String,
+    },
+    /// List the previous queries of the provider, the most recent first.
+    #[structopt(name = "list")]
+    List {
+        /// Name of the provider, e.g., `files`.
+        #[structopt(index = 1)]
+        provider: String,
+        /// Only list the queries fuzzy matched against this partial query, the best first.
+        #[structopt(long)]
+        suggest: Option<String>,
+    },
+}
+
+/// Record and suggest the previous queries of the providers.
+#[derive(StructOpt, Debug, Clone)]
+pub struct History {
+    #[structopt(subcommand)]
+    cmd: HistoryCommand,
+}
+
+impl History {
+    pub fn run(&self, params: Params) -> Result<()> {
+        match self.cmd {
+            HistoryCommand::Add {
+                ref provider,
+                ref query,
+            } => QueryHistory::new(provider).add(query),
+            HistoryCommand::List {
+                ref provider,
+                ref suggest,
+            } => {
+                let suggestions =
+                    QueryHistory::new(provider).suggest(suggest.as_deref().unwrap_or_default());
+                let total = suggestions.len();
+                let (lines, indices): (Vec<_>, Vec<_>) = suggestions
+                    .into_iter()
+                    .take(params.number.unwrap_or(total))
+                    .map(|(item, _, indices)| (item.raw, indices))
+                    .unzip();
+                printer::println_payload(serde_json::json!({
+                    "total": total,
+                    "lines": lines,
+                    "indices": indices,
+                }));
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_query_history() {
+        let history = QueryHistory {
+            path: std::env::temp_dir()
+                .join(format!("clap_test_history_{}", std::process::id()))
+                .join("files"),
+        };
+        for query in &["main", "lib.rs", " ", "src/main", "main"] {
+            history.add(query).unwrap();
+        }
+        assert_eq!(history.load(), vec!["main", "src/main", "lib.rs"]);
+
+        let suggested = history
+            .suggest("mai")
+            .into_iter()
+            .map(|(item, _, _)| item.raw)
+            .collect::<Vec<_>>();
+        assert_eq!(suggested, vec!["main", "src/main"]);
+        assert_eq!(history.suggest("").len(), 3);
+
+        std::fs::remove_dir_all(history.path.parent().unwrap()).unwrap();
+    }
+}
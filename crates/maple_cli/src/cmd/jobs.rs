@@ -0,0 +1,298 @@
+//! Registry of the forerunner jobs.
+//!
+//! Each job is recorded as `jobs/<hash of job>.json` in the project cache directory of cmd_dir,
+//! so that the frontend can ask whether the forerunner job of a directory is still running,
+//! finished or failed instead of guessing from the existence of the cache.
+
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use structopt::StructOpt;
+
+use utility::{
+    cache_writer::{is_alive, write_atomically, CacheLock},
+    calculate_hash, dirs, println_json,
+};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum JobStatus {
+    Running,
+    Finished,
+    Failed,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct JobRecord {
+    /// Kind of the job, e.g., `ripgrep`, `exec`.
+    pub kind: String,
+    pub cmd: String,
+    pub pid: u32,
+    /// Process group of the job if it's the group leader, the spawned commands are in it.
+    #[serde(default)]
+    pub pgid: Option<u32>,
+    pub status: JobStatus,
+    /// Seconds since the UNIX epoch.
+    pub started_at: u64,
+    pub finished_at: Option<u64>,
+    pub error: Option<String>,
+}
+
+const JOBS_DIR: &str = "jobs";
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// Returns the directory of the job records whose working directory is `cmd_dir`.
+fn jobs_dir(cmd_dir: &Path) -> PathBuf {
+    dirs::project_cache_dir(cmd_dir).join(JOBS_DIR)
+}
+
+/// Makes this process the leader of a new process group, so that the commands spawned by it
+/// can be terminated together when the job is superseded.
+pub fn lead_process_group() {
+    // SAFETY: setpgid fails harmlessly if this process is a session leader, which is the case
+    // of the job started by Vim and it's already the group leader then.
+    #[cfg(unix)]
+    unsafe {
+        libc::setpgid(0, 0);
+    }
+}
+
+/// Returns the process group of this process if it's the group leader.
+#[cfg(unix)]
+fn own_process_group() -> Option<u32> {
+    // SAFETY: getpid and getpgid never fail for the calling process.
+    unsafe {
+        let pid = libc::getpid();
+        if libc::getpgid(0) == pid {
+            Some(pid as u32)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn own_process_group() -> Option<u32> {
+    None
+}
+
+/// Terminates the job, returns false if it's unsupported on this platform.
+#[cfg(unix)]
+fn terminate(record: &JobRecord) -> bool {
+    /// Time for the job to exit after SIGTERM before it's killed.
+    const GRACE_PERIOD: std::time::Duration = std::time::Duration::from_millis(500);
+    const POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(50);
+
+    // The negative pid targets the whole process group.
+    let target = match record.pgid {
+        Some(pgid) => -(pgid as libc::pid_t),
+        None => record.pid as libc::pid_t,
+    };
+
+    // SAFETY: kill is only sent to the job verified by the caller.
+    unsafe {
+        libc::kill(target, libc::SIGTERM);
+        let start = std::time::Instant::now();
+        while is_alive(record.pid) && start.elapsed() < GRACE_PERIOD {
+            std::thread::sleep(POLL_INTERVAL);
+        }
+        if is_alive(record.pid) {
+            libc::kill(target, libc::SIGKILL);
+        }
+    }
+
+    true
+}
+
+#[cfg(not(unix))]
+fn terminate(_record: &JobRecord) -> bool {
+    false
+}
+
+impl JobRecord {
+    /// A running job whose process is gone must have been killed or crashed.
+    fn corrected(mut self) -> Self {
+        if self.status == JobStatus::Running && !is_alive(self.pid) {
+            self.status = JobStatus::Failed;
+            self.error = Some("the job exited unexpectedly".into());
+        }
+        self
+    }
+}
+
+/// The job being run by this process, recorded as failed if dropped without [`Self::finish`].
+#[derive(Debug)]
+pub struct JobHandle {
+    path: PathBuf,
+    record: Option<JobRecord>,
+}
+
+impl JobHandle {
+    /// Records the job `cmd` running in `cmd_dir`, the previous record of it is overwritten.
+    pub fn register(kind: &str, cmd: &str, cmd_dir: &Path) -> Result<Self> {
+        let dir = dirs::create_project_cache_dir(cmd_dir)?.join(JOBS_DIR);
+        std::fs::create_dir_all(&dir)?;
+
+        let record = JobRecord {
+            kind: kind.into(),
+            cmd: cmd.into(),
+            pid: std::process::id(),
+            pgid: own_process_group(),
+            status: JobStatus::Running,
+            started_at: now_secs(),
+            finished_at: None,
+            error: None,
+        };
+        write_record(&dir, &record)?;
+
+        Ok(Self {
+            path: record_path(&dir, kind, cmd),
+            record: Some(record),
+        })
+    }
+
+    /// Records the result of the job.
+    pub fn finish<T>(mut self, result: &Result<T>) {
+        self.complete(match result {
+            Ok(_) => None,
+            Err(e) => Some(format!("{:?}", e)),
+        });
+    }
+
+    fn complete(&mut self, error: Option<String>) {
+        if let Some(mut record) = self.record.take() {
+            record.status = if error.is_some() {
+                JobStatus::Failed
+            } else {
+                JobStatus::Finished
+            };
+            record.finished_at = Some(now_secs());
+            record.error = error;
+            if let Some(dir) = self.path.parent() {
+                if let Err(e) = write_record(dir, &record) {
+                    log::error!("Failed to record the job {:?}: {:?}", record, e);
+                }
+            }
+        }
+    }
+}
+
+impl Drop for JobHandle {
+    fn drop(&mut self) {
+        self.complete(Some("the job was aborted".into()));
+    }
+}
+
+fn record_path(dir: &Path, kind: &str, cmd: &str) -> PathBuf {
+    dir.join(format!("{}.json", calculate_hash(&(kind, cmd))))
+}
+
+fn write_record(dir: &Path, record: &JobRecord) -> Result<()> {
+    write_atomically(
+        &record_path(dir, &record.kind, &record.cmd),
+        serde_json::to_vec(record)?,
+    )
+}
+
+/// Terminates the job `cmd` of `cmd_dir` still running in another process, e.g., the forerunner
+/// started before the provider was reopened, so that the same command won't run twice.
+///
+/// The job is only terminated if it's holding the lock of its cache directory `cache_dir`, in
+/// case of the pid has been reused. Returns true if the job is terminated and the lock released.
+pub fn supersede(kind: &str, cmd: &str, cmd_dir: &Path, cache_dir: &Path) -> Result<bool> {
+    let dir = jobs_dir(cmd_dir);
+    let mut record = match std::fs::read(record_path(&dir, kind, cmd))
+        .ok()
+        .and_then(|contents| serde_json::from_slice::<JobRecord>(&contents).ok())
+    {
+        Some(record) => record,
+        None => return Ok(false),
+    };
+
+    if record.status != JobStatus::Running
+        || record.pid == std::process::id()
+        || CacheLock::holder(cache_dir) != Some(record.pid)
+        || !is_alive(record.pid)
+        || !terminate(&record)
+    {
+        return Ok(false);
+    }
+
+    CacheLock::force_release(cache_dir)?;
+
+    record.status = JobStatus::Failed;
+    record.finished_at = Some(now_secs());
+    record.error = Some(format!("superseded by {}", std::process::id()));
+    write_record(&dir, &record)?;
+
+    Ok(true)
+}
+
+/// Returns the records of the jobs run in `cmd_dir`, the most recently started first.
+pub fn read_records(cmd_dir: &Path) -> Vec<JobRecord> {
+    let entries = match std::fs::read_dir(jobs_dir(cmd_dir)) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+    let mut records = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().extension().and_then(|e| e.to_str()) == Some("json"))
+        .filter_map(|entry| std::fs::read(entry.path()).ok())
+        .filter_map(|contents| serde_json::from_slice::<JobRecord>(&contents).ok())
+        .map(JobRecord::corrected)
+        .collect::<Vec<_>>();
+    records.sort_by(|a, b| b.started_at.cmp(&a.started_at));
+    records
+}
+
+/// Query the forerunner jobs.
+#[derive(StructOpt, Debug, Clone)]
+pub struct Jobs {
+    /// Print the status of the forerunner jobs run in this directory.
+    #[structopt(long, parse(from_os_str = utility::paths::expand_os))]
+    status: PathBuf,
+}
+
+impl Jobs {
+    pub fn run(&self) -> Result<()> {
+        let jobs = read_records(&self.status);
+        let running = jobs.iter().any(|job| job.status == JobStatus::Running);
+        println_json!(running, jobs);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_job_registry() {
+        let cmd_dir = std::env::temp_dir().join(format!("clap_test_jobs_{}", std::process::id()));
+
+        let job = JobHandle::register("exec", "ls", &cmd_dir).unwrap();
+        let records = read_records(&cmd_dir);
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].status, JobStatus::Running);
+
+        job.finish(&Err::<(), _>(anyhow::anyhow!("boom")));
+        let records = read_records(&cmd_dir);
+        assert_eq!(records[0].status, JobStatus::Failed);
+        assert!(records[0].finished_at.is_some());
+
+        drop(JobHandle::register("exec", "ls", &cmd_dir).unwrap());
+        let records = read_records(&cmd_dir);
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].error.as_deref(), Some("the job was aborted"));
+
+        std::fs::remove_dir_all(dirs::project_cache_dir(&cmd_dir)).unwrap();
+    }
+}
@@ -0,0 +1,195 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use structopt::StructOpt;
+
+use filter::{
+    matcher::{Algo, Bonus, MatchType},
+    Source,
+};
+use source_item::SourceItem;
+
+use crate::app::Params;
+
+/// Lines of a buffer, either read from the file or dumped by the frontend.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(untagged)]
+enum BufferSource {
+    Path(PathBuf),
+    Lines(Vec<String>),
+}
+
+impl BufferSource {
+    fn into_lines(self) -> Result<Vec<String>> {
+        match self {
+            Self::Path(path) => Ok(std::fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read {}", path.display()))?
+                .lines()
+                .map(Into::into)
+                .collect()),
+            Self::Lines(lines) => Ok(lines),
+        }
+    }
+}
+
+/// Location of a matched line.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+struct Location {
+    bufnr: usize,
+    lnum: usize,
+}
+
+/// Builds the item `bufnr:lnum line`, only `line` is used for matching.
+fn build_item(bufnr: usize, lnum: usize, line: &str) -> SourceItem {
+    let prefix = format!("{}:{} ", bufnr, lnum);
+    let offset = prefix.chars().count();
+    SourceItem::new(
+        format!("{}{}", prefix, line),
+        Some((line.into(), offset)),
+        None,
+    )
+}
+
+/// Extracts the location from the item built by [`build_item`].
+fn parse_location(raw: &str) -> Option<Location> {
+    let (location, _) = raw.split_at(raw.find(' ')?);
+    let mut iter = location.split(':');
+    let bufnr = iter.next()?.parse().ok()?;
+    let lnum = iter.next()?.parse().ok()?;
+    Some(Location { bufnr, lnum })
+}
+
+/// Fuzzy filter the lines of all the open vim buffers given the query.
+#[derive(StructOpt, Debug, Clone)]
+pub struct Lines {
+    /// Initial query string
+    #[structopt(index = 1, short, long)]
+    query: String,
+
+    /// JSON file of the map from buffer number to the file path or the dumped lines.
+    ///
+    /// {"1": "/path/to/file", "3": ["dumped line 1", "dumped line 2"]}
+    #[structopt(long, parse(from_os_str = utility::paths::expand_os))]
+    input: PathBuf,
+}
+
+impl Lines {
+    fn read_buffers(&self) -> Result<BTreeMap<usize, BufferSource>> {
+        let content = std::fs::read_to_string(&self.input)?;
+        let buffers: BTreeMap<String, BufferSource> = serde_json::from_str(&content)?;
+        buffers
+            .into_iter()
+            .map(|(bufnr, source)| {
+                let bufnr = bufnr
+                    .parse()
+                    .with_context(|| format!("Invalid buffer number: {}", bufnr))?;
+                Ok((bufnr, source))
+            })
+            .collect()
+    }
+
+    fn collect_items(&self) -> Result<Vec<SourceItem>> {
+        let mut items = Vec::new();
+        for (bufnr, source) in self.read_buffers()? {
+            for (idx, line) in source.into_lines()?.iter().enumerate() {
+                items.push(build_item(bufnr, idx + 1, line));
+            }
+        }
+        Ok(items)
+    }
+
+    /// Looks for matches of `query` in the lines of all the buffers.
+    pub fn run(&self, params: Params) -> Result<()> {
+        let line_cutoff = params.line_cutoff();
+        let Params {
+            number, winwidth, ..
+        } = params;
+
+        let ranked = filter::sync_run(
+            &self.query,
+            Source::List(self.collect_items()?.into_iter()),
+            Algo::Fzy,
+            MatchType::Full,
+            vec![Bonus::None],
+            line_cutoff,
+        )?;
+
+        if let Some(number) = number {
+            let total = ranked.len();
+            let locations = ranked
+                .iter()
+                .take(number)
+                .filter_map(|(item, _, _)| parse_location(&item.raw))
+                .collect::<Vec<_>>();
+            let shown = number.min(total);
+            let (lines, indices, truncated_map) = printer::process_top_items(
+                ranked.into_iter().take(number),
+                utility::tty::display_width(winwidth, 100),
+                None,
+            );
+            let mut payload = serde_json::json!({
+                "total": total,
+                "lines": lines,
+                "indices": indices,
+                "locations": locations,
+            });
+            if !truncated_map.is_empty() {
+                payload["truncated_map"] = serde_json::json!(truncated_map);
+            }
+            printer::Payload::new(total, shown).attach_to(&mut payload);
+            printer::println_payload(payload);
+        } else {
+            for (item, _, indices) in ranked.into_iter() {
+                let location = parse_location(&item.raw);
+                let text = item.raw;
+                printer::println_payload(serde_json::json!({
+                    "text": text,
+                    "indices": indices,
+                    "location": location,
+                }));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_buffer_source() {
+        let buffers: BTreeMap<String, BufferSource> =
+            serde_json::from_str(r#"{"1": "/tmp/foo.rs", "3": ["foo", "bar"]}"#).unwrap();
+        assert_eq!(buffers["1"], BufferSource::Path("/tmp/foo.rs".into()));
+        assert_eq!(
+            buffers["3"],
+            BufferSource::Lines(vec!["foo".into(), "bar".into()])
+        );
+    }
+
+    #[test]
+    fn test_location() {
+        let item = build_item(12, 345, "let foo = 1;");
+        assert_eq!(item.raw, "12:345 let foo = 1;");
+        assert_eq!(
+            parse_location(&item.raw),
+            Some(Location {
+                bufnr: 12,
+                lnum: 345
+            })
+        );
+
+        let (_, indices) = Algo::Fzy
+            .apply_match("345", &item, &MatchType::Full)
+            .unwrap_or_default();
+        assert!(indices.is_empty());
+        let (_, indices) = Algo::Fzy
+            .apply_match("foo", &item, &MatchType::Full)
+            .unwrap();
+        assert_eq!(indices, vec![11, 12, 13]);
+    }
+}
@@ -0,0 +1,85 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use structopt::StructOpt;
+
+use filter::matcher::{Algo, Bonus, MatchType, Matcher};
+use source_item::SourceItem;
+
+use crate::app::Params;
+use crate::tools::lsp::{LspClient, SymbolInformation};
+
+/// Search the workspace symbols provided by a language server.
+#[derive(StructOpt, Debug, Clone)]
+pub struct LspSymbols {
+    /// Command to start the language server, e.g., `rust-analyzer` or `pyls`.
+    #[structopt(long)]
+    server: String,
+
+    /// Root directory of the workspace.
+    #[structopt(long, parse(from_os_str = utility::paths::expand_os))]
+    root: PathBuf,
+
+    /// Initial query string
+    #[structopt(long, default_value = "")]
+    query: String,
+}
+
+impl LspSymbols {
+    pub fn run(
+        &self,
+        Params {
+            number,
+            winwidth,
+            icon_painter,
+            ..
+        }: Params,
+    ) -> Result<()> {
+        let mut client = LspClient::start(&self.server, &self.root)?;
+        let response = client.request(
+            "workspace/symbol",
+            serde_json::json!({ "query": self.query }),
+        )?;
+        if let Err(e) = client.shutdown() {
+            log::debug!("Failed to shutdown the language server: {:?}", e);
+        }
+
+        let symbols: Vec<SymbolInformation> = serde_json::from_value(response).unwrap_or_default();
+        let items = symbols
+            .iter()
+            .map(|symbol| SourceItem::from(symbol.display_line(&self.root)))
+            .collect::<Vec<_>>();
+
+        // The server has filtered the symbols loosely, which are ranked by the query again.
+        let ranked = if self.query.is_empty() {
+            items
+                .into_iter()
+                .map(|item| (item, 0, Vec::new()))
+                .collect()
+        } else {
+            let matcher = Matcher::new(Algo::Fzy, MatchType::TagName, Bonus::Acronym);
+            filter::rank(&matcher, &self.query, items)
+        };
+
+        // In case of passing an invalid icon-painter option.
+        let icon_painter = icon_painter.map(|_| icon::IconPainter::ProjTags);
+
+        let total = ranked.len();
+        let (lines, indices, truncated_map) = printer::process_top_items(
+            ranked.into_iter().take(number.unwrap_or(total)),
+            utility::tty::display_width(winwidth, 100),
+            icon_painter,
+        );
+        let mut payload = serde_json::json!({
+            "total": total,
+            "lines": lines,
+            "indices": indices,
+        });
+        if !truncated_map.is_empty() {
+            payload["truncated_map"] = serde_json::json!(truncated_map);
+        }
+        printer::println_payload(payload);
+
+        Ok(())
+    }
+}
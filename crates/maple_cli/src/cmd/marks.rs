@@ -0,0 +1,220 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use structopt::StructOpt;
+
+use filter::{
+    matcher::{Algo, Bonus, MatchType},
+    Source,
+};
+use source_item::SourceItem;
+
+use crate::app::Params;
+
+/// A mark or jumplist entry dumped by the frontend.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+struct Entry {
+    /// Name of the mark, `None` for the jumplist entry.
+    #[serde(default)]
+    mark: Option<String>,
+    file: String,
+    lnum: usize,
+    #[serde(default)]
+    col: usize,
+    /// Content of the marked line, read from the file if not dumped.
+    #[serde(default)]
+    text: Option<String>,
+}
+
+/// Where to jump when a mark is selected.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+struct JumpTarget {
+    mark: Option<String>,
+    file: PathBuf,
+    lnum: usize,
+    col: usize,
+}
+
+/// Returns the content of line `lnum` of `path`, `None` if the line does not exist.
+fn read_line_at(path: &Path, lnum: usize) -> Option<String> {
+    utility::read_lines_from(path, lnum.checked_sub(1)?, 1)
+        .ok()?
+        .next()
+}
+
+/// Returns the path for display, relative to `cwd` if it's under `cwd`.
+fn display_path<'a>(path: &'a Path, cwd: Option<&Path>) -> std::borrow::Cow<'a, str> {
+    cwd.and_then(|cwd| path.strip_prefix(cwd).ok())
+        .unwrap_or(path)
+        .to_string_lossy()
+}
+
+impl Entry {
+    /// Returns the jump target of this entry, `None` if it points to a nonexistent location.
+    fn into_valid(self) -> Option<(JumpTarget, String)> {
+        let file = utility::paths::expand(&self.file);
+        if !file.is_file() {
+            return None;
+        }
+        let text = match self.text {
+            Some(text) => text,
+            None => read_line_at(&file, self.lnum)?,
+        };
+        let target = JumpTarget {
+            mark: self.mark,
+            file,
+            lnum: self.lnum,
+            col: self.col,
+        };
+        Some((target, text))
+    }
+}
+
+/// Builds the item `mark lnum:col file text`, only `file text` is used for matching.
+fn build_item(target: &JumpTarget, text: &str, cwd: Option<&Path>) -> SourceItem {
+    let prefix = format!(
+        "{:<2} {:>5}:{:<3} ",
+        target.mark.as_deref().unwrap_or("-"),
+        target.lnum,
+        target.col
+    );
+    let match_text = format!("{} {}", display_path(&target.file, cwd), text.trim());
+    let offset = prefix.chars().count();
+    SourceItem::new(
+        format!("{}{}", prefix, match_text),
+        Some((match_text, offset)),
+        None,
+    )
+}
+
+/// Fuzzy filter the marks or jumplist, the entries pointing to nonexistent files are dropped.
+#[derive(StructOpt, Debug, Clone)]
+pub struct Marks {
+    /// Initial query string
+    #[structopt(index = 1, short, long)]
+    query: String,
+
+    /// JSON file of the dumped marks or jumplist.
+    ///
+    /// [{"mark": "a", "file": "/path/to/file", "lnum": 12, "col": 3, "text": "marked line"}]
+    ///
+    /// `mark` is omitted for the jumplist entries, `text` is read from the file if omitted.
+    #[structopt(long, parse(from_os_str = utility::paths::expand_os))]
+    input: PathBuf,
+}
+
+impl Marks {
+    fn read_entries(&self) -> Result<Vec<Entry>> {
+        let content = std::fs::read_to_string(&self.input)
+            .with_context(|| format!("Failed to read {}", self.input.display()))?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    /// Returns the items to filter and the jump targets of them.
+    fn collect_items(&self) -> Result<(Vec<SourceItem>, HashMap<String, JumpTarget>)> {
+        let cwd = std::env::current_dir().ok();
+        let mut items = Vec::new();
+        let mut targets = HashMap::new();
+        for (target, text) in self
+            .read_entries()?
+            .into_iter()
+            .filter_map(Entry::into_valid)
+        {
+            let item = build_item(&target, &text, cwd.as_deref());
+            targets.insert(item.raw.clone(), target);
+            items.push(item);
+        }
+        Ok((items, targets))
+    }
+
+    /// Looks for matches of `query` in the valid marks.
+    pub fn run(&self, params: Params) -> Result<()> {
+        let line_cutoff = params.line_cutoff();
+        let Params {
+            number, winwidth, ..
+        } = params;
+
+        let (items, targets) = self.collect_items()?;
+
+        let ranked = filter::sync_run(
+            &self.query,
+            Source::List(items.into_iter()),
+            Algo::Fzy,
+            MatchType::Full,
+            vec![Bonus::None],
+            line_cutoff,
+        )?;
+
+        if let Some(number) = number {
+            let total = ranked.len();
+            let targets = ranked
+                .iter()
+                .take(number)
+                .filter_map(|(item, _, _)| targets.get(&item.raw))
+                .collect::<Vec<_>>();
+            let shown = number.min(total);
+            let (lines, indices, truncated_map) = printer::process_top_items(
+                ranked.into_iter().take(number),
+                utility::tty::display_width(winwidth, 100),
+                None,
+            );
+            let mut payload = serde_json::json!({
+                "total": total,
+                "lines": lines,
+                "indices": indices,
+                "targets": targets,
+            });
+            if !truncated_map.is_empty() {
+                payload["truncated_map"] = serde_json::json!(truncated_map);
+            }
+            printer::Payload::new(total, shown).attach_to(&mut payload);
+            printer::println_payload(payload);
+        } else {
+            for (item, _, indices) in ranked.into_iter() {
+                let target = targets.get(&item.raw);
+                let text = item.raw;
+                printer::println_payload(serde_json::json!({
+                    "text": text,
+                    "indices": indices,
+                    "target": target,
+                }));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entry() {
+        let entries: Vec<Entry> = serde_json::from_str(
+            r#"[{"mark": "a", "file": "Cargo.toml", "lnum": 1, "col": 0},
+                {"file": "/nonexistent/file", "lnum": 1, "text": "foo"},
+                {"file": "Cargo.toml", "lnum": 100000}]"#,
+        )
+        .unwrap();
+        let valid = entries
+            .into_iter()
+            .filter_map(Entry::into_valid)
+            .collect::<Vec<_>>();
+        assert_eq!(valid.len(), 1);
+
+        let (target, text) = &valid[0];
+        assert_eq!(target.mark.as_deref(), Some("a"));
+        assert_eq!(text, "[package]");
+
+        let item = build_item(target, text, std::env::current_dir().ok().as_deref());
+        assert_eq!(item.raw, "a      1:0   Cargo.toml [package]");
+
+        let (_, indices) = Algo::Fzy
+            .apply_match("pack", &item, &MatchType::Full)
+            .unwrap();
+        assert_eq!(indices, vec![25, 26, 27, 28]);
+    }
+}
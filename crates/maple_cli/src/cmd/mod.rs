@@ -1,8 +1,26 @@
 pub mod blines;
+pub mod buffer_tags;
 pub mod cache;
+pub mod commits;
+pub mod completions;
+pub mod detect_filetype;
 pub mod dumb_jump;
 pub mod exec;
 pub mod filter;
 pub mod grep;
 pub mod helptags;
+pub mod history;
+pub mod jobs;
+pub mod lines;
+pub mod lsp_symbols;
+pub mod marks;
+pub mod pipe;
+pub mod plugin;
+pub mod provider;
+pub mod rank_check;
+pub mod registers;
+pub mod replay;
 pub mod tags;
+pub mod todos;
+pub mod tui;
+pub mod watch;
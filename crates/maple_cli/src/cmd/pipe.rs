@@ -0,0 +1,191 @@
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Context, Result};
+use serde::Deserialize;
+use structopt::StructOpt;
+
+use filter::{
+    matcher::{Algo, Bonus, MatchType},
+    subprocess::Exec,
+    ExcludeFilter, FilterResult, Source,
+};
+use source_item::SourceItem;
+
+use crate::api::filter::parse_arg_enum;
+use crate::app::Params;
+
+type ItemSource = Source<Box<dyn Iterator<Item = SourceItem>>>;
+
+/// Where the items of a pipeline come from.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(untagged)]
+enum SourceSpec {
+    /// Output lines of a shell command.
+    Cmd {
+        cmd: String,
+        #[serde(default)]
+        cmd_dir: Option<PathBuf>,
+    },
+    /// Lines of a file.
+    File {
+        file: PathBuf,
+    },
+    Lines {
+        lines: Vec<String>,
+    },
+}
+
+/// A transform applied to the items before filtering.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+enum Stage {
+    /// Drop the items matching any of these globs.
+    Exclude(Vec<String>),
+    /// Make the paths relative to this directory.
+    RelativeTo(PathBuf),
+    /// Split each item into the filter text and display text by this delimiter.
+    SplitDisplay(char),
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+struct FilterSpec {
+    query: String,
+    #[serde(default)]
+    algo: Option<String>,
+    #[serde(default)]
+    match_type: Option<String>,
+}
+
+/// Pipeline described by the JSON spec.
+///
+/// {"source": {"cmd": "git diff --name-only"}, "stages": [{"exclude": ["*.lock"]}],
+///  "filter": {"query": "src"}}
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+struct Pipeline {
+    source: SourceSpec,
+    #[serde(default)]
+    stages: Vec<Stage>,
+    /// All the items are kept in order if no filter.
+    #[serde(default)]
+    filter: Option<FilterSpec>,
+}
+
+impl Pipeline {
+    fn source(&self) -> ItemSource {
+        match self.source {
+            SourceSpec::Cmd {
+                ref cmd,
+                ref cmd_dir,
+            } => match cmd_dir {
+                Some(dir) => Exec::shell(cmd).cwd(dir).into(),
+                None => Exec::shell(cmd).into(),
+            },
+            SourceSpec::File { ref file } => file.clone().into(),
+            SourceSpec::Lines { ref lines } => {
+                Source::List(Box::new(lines.clone().into_iter().map(Into::into)))
+            }
+        }
+    }
+
+    /// Runs all the stages and returns the filtered results.
+    fn run(&self) -> Result<Vec<FilterResult>> {
+        let (algo, match_type, query) = match self.filter {
+            Some(ref spec) => (
+                parse_arg_enum::<Algo>(&spec.algo, "algo")?.unwrap_or(Algo::Fzy),
+                parse_arg_enum::<MatchType>(&spec.match_type, "match type")?
+                    .unwrap_or(MatchType::Full),
+                Some(spec.query.as_str()),
+            ),
+            None => (Algo::Fzy, MatchType::Full, None),
+        };
+
+        let mut source = self.source();
+        for stage in self.stages.iter() {
+            source = match stage {
+                Stage::Exclude(globs) => {
+                    source.exclude(ExcludeFilter::new(globs), match_type.clone())?
+                }
+                Stage::RelativeTo(dir) => source.relative_to(dir.clone(), match_type.clone())?,
+                Stage::SplitDisplay(delimiter) => source.split_display(*delimiter)?,
+            };
+        }
+
+        match query {
+            Some(query) => {
+                filter::sync_run(query, source, algo, match_type, vec![Bonus::None], None)
+            }
+            None => Ok(source
+                .into_items()?
+                .map(|item| (item, 0, Vec::new()))
+                .collect()),
+        }
+    }
+}
+
+/// Run a pipeline of the source, transforms and filter in one process.
+#[derive(StructOpt, Debug, Clone)]
+pub struct Pipe {
+    /// JSON spec of the pipeline.
+    #[structopt(index = 1, required_unless = "spec-file")]
+    spec: Option<String>,
+
+    /// Read the JSON spec of the pipeline from a file instead.
+    #[structopt(long = "spec-file", parse(from_os_str = utility::paths::expand_os))]
+    spec_file: Option<PathBuf>,
+}
+
+impl Pipe {
+    fn pipeline(&self) -> Result<Pipeline> {
+        let spec = match (&self.spec, &self.spec_file) {
+            (Some(spec), _) => spec.clone(),
+            (None, Some(path)) => std::fs::read_to_string(path)
+                .with_context(|| format!("Failed to read {}", path.display()))?,
+            (None, None) => return Err(anyhow!("The spec of pipeline is required")),
+        };
+        serde_json::from_str(&spec).context("Invalid pipeline spec")
+    }
+
+    pub fn run(
+        &self,
+        Params {
+            number,
+            winwidth,
+            icon_painter,
+            ..
+        }: Params,
+    ) -> Result<()> {
+        let ranked = self.pipeline()?.run()?;
+        printer::print_sync_filter_results(
+            ranked,
+            number,
+            utility::tty::display_width(winwidth, 100),
+            icon_painter,
+        )?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pipeline() {
+        let pipeline: Pipeline = serde_json::from_str(
+            r#"{"source": {"lines": ["/root/src/lib.rs", "/root/Cargo.lock", "/root/src/main.rs"]},
+                "stages": [{"exclude": ["*.lock"]}, {"relative_to": "/root"}],
+                "filter": {"query": "lib"}}"#,
+        )
+        .unwrap();
+        let ranked = pipeline.run().unwrap();
+        assert_eq!(ranked.len(), 1);
+        assert_eq!(ranked[0].0.display_text(), "src/lib.rs");
+
+        let pipeline: Pipeline =
+            serde_json::from_str(r#"{"source": {"lines": ["b", "a"]}}"#).unwrap();
+        let items = pipeline.run().unwrap();
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].0.raw, "b");
+    }
+}
@@ -0,0 +1,260 @@
+//! Providers implemented by the external executables, in any language.
+//!
+//! The plugin executable prints the items to stdout, one per line, each of which is either the
+//! plain text or a JSON object:
+//!
+//! {"display": "foo.rs:12 fn main()", "filter": "main", "jump": {"path": "foo.rs", "lnum": 12}}
+//!
+//! - `display`: text displayed in the results, required.
+//! - `filter`: text matched against the query, `display` by default.
+//! - `jump`: any JSON value sent back along with the item, e.g., the location to jump to.
+//!
+//! The output is cached, filtered and printed the same way as the native providers.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use serde_json::Value;
+use structopt::StructOpt;
+
+use filter::{
+    matcher::{Algo, Bonus, MatchType},
+    Source,
+};
+use source_item::SourceItem;
+
+use crate::app::Params;
+use crate::cmd::cache::{cache_exists, CacheEntry};
+use crate::process::light::set_current_dir;
+
+/// Run the providers implemented by the external executables.
+#[derive(StructOpt, Debug, Clone)]
+pub struct Plugin {
+    #[structopt(subcommand)]
+    cmd: PluginCommand,
+}
+
+#[derive(StructOpt, Debug, Clone)]
+enum PluginCommand {
+    /// Filter the items printed by a plugin executable.
+    #[structopt(name = "run")]
+    Run(Run),
+}
+
+/// An item printed by the plugin executable.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+struct PluginItem {
+    display: String,
+    #[serde(default)]
+    filter: Option<String>,
+    #[serde(default)]
+    jump: Option<Value>,
+}
+
+impl PluginItem {
+    /// Parses a line of the plugin output, the line that is not a JSON item is a plain item.
+    fn parse(line: &str) -> Self {
+        if line.starts_with('{') {
+            if let Ok(item) = serde_json::from_str(line) {
+                return item;
+            }
+        }
+        Self {
+            display: line.into(),
+            filter: None,
+            jump: None,
+        }
+    }
+
+    /// Converts into the item for filtering, `line_number` is the position in the output.
+    ///
+    /// `jump` is carried as the user data of the item.
+    fn into_source_item(self, line_number: usize) -> SourceItem {
+        let Self {
+            display,
+            filter,
+            jump,
+        } = self;
+        let mut item = match filter {
+            Some(filter) if filter != display => {
+                SourceItem::with_display(display.clone(), filter, display)
+            }
+            _ => display.into(),
+        };
+        item.line_number = Some(line_number);
+        item.user_data = jump;
+        item
+    }
+}
+
+#[derive(StructOpt, Debug, Clone)]
+struct Run {
+    /// Path or name of the plugin executable.
+    #[structopt(index = 1, parse(from_os_str = utility::paths::expand_os))]
+    exe: PathBuf,
+
+    /// Arguments passed to the plugin executable, e.g., `maple plugin run my-plugin -- --all`.
+    #[structopt(index = 2)]
+    args: Vec<String>,
+
+    /// Initial query string
+    #[structopt(long, default_value = "")]
+    query: String,
+
+    /// Working directory of the plugin, defaults to the current directory.
+    #[structopt(long = "cmd-dir", parse(from_os_str = utility::paths::expand_os))]
+    cmd_dir: Option<PathBuf>,
+}
+
+impl Run {
+    /// Returns the cache of the plugin output, the plugin is executed if there is no cache yet
+    /// or `no_cache` is on.
+    fn source_file(&self, no_cache: bool) -> Result<PathBuf> {
+        let cmd_dir = match self.cmd_dir {
+            Some(ref dir) => dir.clone(),
+            None => std::env::current_dir()?,
+        };
+        let exe = self.exe.to_string_lossy();
+        let args = std::iter::once("plugin")
+            .chain(std::iter::once(exe.as_ref()))
+            .chain(self.args.iter().map(String::as_str))
+            .collect::<Vec<_>>();
+
+        if !no_cache {
+            if let Ok((tempfile, _)) = cache_exists(&args, &cmd_dir) {
+                return Ok(tempfile);
+            }
+        }
+
+        let mut cmd = Command::new(&self.exe);
+        cmd.args(&self.args);
+        set_current_dir(&mut cmd, Some(cmd_dir.clone()));
+        let output = cmd
+            .output()
+            .map_err(|e| anyhow!("Failed to run the plugin {}: {}", exe, e))?;
+        if !output.status.success() && output.stdout.is_empty() {
+            return Err(anyhow!(
+                "Plugin {} failed: {}",
+                exe,
+                String::from_utf8_lossy(&output.stderr)
+            ));
+        }
+
+        let total = bytecount::count(&output.stdout, b'\n');
+        CacheEntry::create(&args, Some(cmd_dir), total, &output.stdout)
+    }
+
+    fn run(&self, params: Params) -> Result<()> {
+        let line_cutoff = params.line_cutoff();
+        let source_file = self.source_file(params.no_cache)?;
+        let Params {
+            number,
+            winwidth,
+            icon_painter,
+            ..
+        } = params;
+
+        let items = read_items(&source_file)?;
+
+        // The items are in the order of the plugin output without a query.
+        let ranked = if self.query.is_empty() {
+            items
+                .into_iter()
+                .map(|item| (item, 0, Vec::new()))
+                .collect()
+        } else {
+            filter::sync_run(
+                &self.query,
+                Source::List(items.into_iter()),
+                Algo::Fzy,
+                MatchType::Full,
+                vec![Bonus::None],
+                line_cutoff,
+            )?
+        };
+
+        if let Some(number) = number {
+            let total = ranked.len();
+            let top_jumps = ranked
+                .iter()
+                .take(number)
+                .map(|(item, _, _)| item.user_data.as_ref())
+                .collect::<Vec<_>>();
+            let (lines, indices, truncated_map) = printer::process_top_items(
+                ranked.into_iter().take(number),
+                utility::tty::display_width(winwidth, 100),
+                icon_painter,
+            );
+            let mut payload = serde_json::json!({
+                "total": total,
+                "lines": lines,
+                "indices": indices,
+            });
+            if top_jumps.iter().any(Option::is_some) {
+                payload["jumps"] = serde_json::json!(top_jumps);
+            }
+            if !truncated_map.is_empty() {
+                payload["truncated_map"] = serde_json::json!(truncated_map);
+            }
+            printer::println_payload(payload);
+        } else {
+            for (item, _, indices) in ranked.iter() {
+                let mut payload = serde_json::json!({
+                    "text": item.display_text(),
+                    "indices": indices,
+                });
+                if let Some(ref jump) = item.user_data {
+                    payload["jump"] = jump.clone();
+                }
+                printer::println_payload(payload);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Reads the items of the plugin output.
+fn read_items(source_file: &Path) -> Result<Vec<SourceItem>> {
+    Ok(
+        Source::<std::iter::Empty<SourceItem>>::File(source_file.to_path_buf())
+            .into_items()?
+            .enumerate()
+            .map(|(idx, line)| PluginItem::parse(&line.raw).into_source_item(idx + 1))
+            .collect(),
+    )
+}
+
+impl Plugin {
+    pub fn run(&self, params: Params) -> Result<()> {
+        match self.cmd {
+            PluginCommand::Run(ref run) => run.run(params),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_item() {
+        let line = r#"{"display": "foo.rs:12 fn main()", "filter": "main", "jump": {"lnum": 12}}"#;
+        let item = PluginItem::parse(line).into_source_item(3);
+        assert_eq!(item.display_text(), "foo.rs:12 fn main()");
+        assert_eq!(item.match_text(), "main");
+        assert_eq!(item.line_number, Some(3));
+        assert_eq!(item.user_data, Some(serde_json::json!({ "lnum": 12 })));
+
+        let item = PluginItem::parse("plain item").into_source_item(1);
+        assert_eq!(item.raw, "plain item");
+        assert_eq!(item.match_text(), "plain item");
+        assert_eq!(item.user_data, None);
+
+        // Not a valid item.
+        let item = PluginItem::parse(r#"{"text": "foo"}"#).into_source_item(1);
+        assert_eq!(item.raw, r#"{"text": "foo"}"#);
+    }
+}
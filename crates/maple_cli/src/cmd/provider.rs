@@ -0,0 +1,131 @@
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Result};
+use structopt::StructOpt;
+
+use filter::{matcher::MatchType, Source};
+use icon::IconPainter;
+use source_item::SourceItem;
+
+use crate::app::Params;
+use crate::cmd::cache::{cache_exists, CacheEntry};
+use crate::config::{Config, ProviderConfig};
+use crate::process::light::set_current_dir;
+
+/// Run the providers declared in the config file.
+#[derive(StructOpt, Debug, Clone)]
+pub struct Provider {
+    #[structopt(subcommand)]
+    cmd: ProviderCommand,
+}
+
+#[derive(StructOpt, Debug, Clone)]
+enum ProviderCommand {
+    /// Filter the output of the command of a provider.
+    #[structopt(name = "run")]
+    Run(Run),
+    /// List the declared providers as `{"providers": [{"name", "cmd"}]}`.
+    #[structopt(name = "list")]
+    List,
+}
+
+#[derive(StructOpt, Debug, Clone)]
+struct Run {
+    /// Name of the provider, i.e., `my_todos` of `[provider.my_todos]` in the config file.
+    #[structopt(index = 1)]
+    name: String,
+
+    /// Initial query string
+    #[structopt(long, default_value = "")]
+    query: String,
+
+    /// Working directory of the command, defaults to the current directory.
+    #[structopt(long = "cmd-dir", parse(from_os_str = utility::paths::expand_os))]
+    cmd_dir: Option<PathBuf>,
+}
+
+impl Run {
+    /// Returns the cache of the output of `provider`, the command is executed if there is no
+    /// cache yet or `no_cache` is on.
+    fn source_file(&self, provider: &ProviderConfig, no_cache: bool) -> Result<PathBuf> {
+        let cmd_dir = match self.cmd_dir {
+            Some(ref dir) => dir.clone(),
+            None => std::env::current_dir()?,
+        };
+        let args = provider.cmd.split_whitespace().collect::<Vec<_>>();
+
+        if !no_cache {
+            if let Ok((tempfile, _)) = cache_exists(&args, &cmd_dir) {
+                return Ok(tempfile);
+            }
+        }
+
+        let mut cmd = crate::process::std::build_command(&provider.cmd);
+        set_current_dir(&mut cmd, Some(cmd_dir.clone()));
+        let output = cmd.output()?;
+        if !output.status.success() && output.stdout.is_empty() {
+            return Err(anyhow!(
+                "Failed to run the command of provider {}: {}",
+                self.name,
+                String::from_utf8_lossy(&output.stderr)
+            ));
+        }
+
+        let total = bytecount::count(&output.stdout, b'\n');
+        CacheEntry::create(&args, Some(cmd_dir), total, &output.stdout)
+    }
+
+    fn run(&self, params: Params, config: &Config) -> Result<()> {
+        let provider = config
+            .providers
+            .get(&self.name)
+            .ok_or_else(|| anyhow!("Provider {} is not declared in the config file", self.name))?;
+
+        let match_type = provider
+            .match_type
+            .as_deref()
+            .map(|match_type| match_type.parse().map_err(|e: String| anyhow!(e)))
+            .transpose()?
+            .unwrap_or(MatchType::Full);
+
+        // The icon painter of the command line takes precedence over the config file.
+        let icon_painter = match params.icon_painter {
+            Some(ref painter) => Some(painter.clone()),
+            None => provider
+                .icon
+                .as_deref()
+                .map(|icon| icon.parse::<IconPainter>().map_err(|e| anyhow!(e)))
+                .transpose()?,
+        };
+
+        let source_file = self.source_file(provider, params.no_cache)?;
+
+        filter::dyn_run(
+            &self.query,
+            Source::<std::iter::Empty<SourceItem>>::File(source_file),
+            params
+                .into_filter_context()
+                .icon_painter(icon_painter)
+                .match_type(match_type),
+            vec![Default::default()],
+        )
+    }
+}
+
+impl Provider {
+    pub fn run(&self, params: Params, config: &Config) -> Result<()> {
+        match self.cmd {
+            ProviderCommand::Run(ref run) => run.run(params, config),
+            ProviderCommand::List => {
+                let mut names = config.providers.keys().collect::<Vec<_>>();
+                names.sort();
+                let providers = names
+                    .into_iter()
+                    .map(|name| serde_json::json!({ "name": name, "cmd": config.providers[name].cmd }))
+                    .collect::<Vec<_>>();
+                utility::println_json!(providers);
+                Ok(())
+            }
+        }
+    }
+}
@@ -0,0 +1,53 @@
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Result};
+use structopt::StructOpt;
+
+use filter::ranking::{render_all, FIXTURES_DIR};
+
+/// Check the golden ranking of the fixture corpora, for the development of the ranking.
+#[derive(StructOpt, Debug, Clone)]
+pub struct RankCheck {
+    /// Directory of the fixture corpora, `crates/filter/tests/ranking` of the source tree by
+    /// default.
+    #[structopt(long, parse(from_os_str = utility::paths::expand_os))]
+    dir: Option<PathBuf>,
+
+    /// Record the missing golden files and regenerate the outdated ones instead of reporting
+    /// them, which is the only way the golden files are written.
+    #[structopt(long)]
+    update: bool,
+}
+
+impl RankCheck {
+    pub fn run(self) -> Result<()> {
+        let dir = self.dir.unwrap_or_else(|| PathBuf::from(FIXTURES_DIR));
+        let outdated = render_all(&dir)?
+            .into_iter()
+            .filter(|golden| !golden.is_up_to_date())
+            .collect::<Vec<_>>();
+
+        for golden in outdated.iter() {
+            let status = match (self.update, golden.path.exists()) {
+                (true, true) => "Updated",
+                (true, false) => "Recorded",
+                (false, true) => "Outdated",
+                (false, false) => "Missing",
+            };
+            if self.update {
+                golden.write()?;
+            }
+            println!("{} {}", status, golden.path.display());
+        }
+
+        if outdated.is_empty() || self.update {
+            Ok(())
+        } else {
+            Err(anyhow!(
+                "{} golden files are outdated or missing, rerun with --update if the ranking \
+                 change is intended",
+                outdated.len()
+            ))
+        }
+    }
+}
@@ -0,0 +1,162 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use structopt::StructOpt;
+
+use filter::{
+    matcher::{Algo, Bonus, MatchType},
+    Source,
+};
+use source_item::SourceItem;
+
+use crate::app::Params;
+
+/// Marker of the line breaks in the flattened multi-line register.
+const LINE_BREAK_MARKER: &str = "⏎";
+
+/// A register dumped by the frontend.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+struct Entry {
+    /// Name of the register, e.g., `a`, `"` or `+`.
+    name: String,
+    content: String,
+}
+
+/// Returns the content of register in one line, the line breaks are replaced by the marker.
+fn flatten(content: &str) -> String {
+    content
+        .trim_end_matches(|c| c == '\n' || c == '\r')
+        .split('\n')
+        .map(|line| line.trim_end_matches('\r'))
+        .collect::<Vec<_>>()
+        .join(LINE_BREAK_MARKER)
+}
+
+/// Builds the item `name content`, only the content is used for matching.
+fn build_item(entry: &Entry) -> SourceItem {
+    let prefix = format!("{:<2} ", entry.name);
+    let match_text = flatten(&entry.content);
+    let offset = prefix.chars().count();
+    SourceItem::new(
+        format!("{}{}", prefix, match_text),
+        Some((match_text, offset)),
+        None,
+    )
+}
+
+/// Fuzzy filter the contents of registers, the empty registers are dropped.
+#[derive(StructOpt, Debug, Clone)]
+pub struct Registers {
+    /// Initial query string
+    #[structopt(index = 1, short, long)]
+    query: String,
+
+    /// JSON file of the dumped registers.
+    ///
+    /// [{"name": "a", "content": "first line\nsecond line\n"}]
+    #[structopt(long, parse(from_os_str = utility::paths::expand_os))]
+    input: PathBuf,
+}
+
+impl Registers {
+    fn read_entries(&self) -> Result<Vec<Entry>> {
+        let content = std::fs::read_to_string(&self.input)
+            .with_context(|| format!("Failed to read {}", self.input.display()))?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    /// Returns the items to filter and the register name of them.
+    fn collect_items(&self) -> Result<(Vec<SourceItem>, HashMap<String, String>)> {
+        let mut items = Vec::new();
+        let mut names = HashMap::new();
+        for entry in self
+            .read_entries()?
+            .into_iter()
+            .filter(|entry| !entry.content.is_empty())
+        {
+            let item = build_item(&entry);
+            names.insert(item.raw.clone(), entry.name);
+            items.push(item);
+        }
+        Ok((items, names))
+    }
+
+    /// Looks for matches of `query` in the contents of registers.
+    pub fn run(&self, params: Params) -> Result<()> {
+        let line_cutoff = params.line_cutoff();
+        let Params {
+            number, winwidth, ..
+        } = params;
+
+        let (items, names) = self.collect_items()?;
+
+        let ranked = filter::sync_run(
+            &self.query,
+            Source::List(items.into_iter()),
+            Algo::Fzy,
+            MatchType::Full,
+            vec![Bonus::None],
+            line_cutoff,
+        )?;
+
+        if let Some(number) = number {
+            let total = ranked.len();
+            let registers = ranked
+                .iter()
+                .take(number)
+                .filter_map(|(item, _, _)| names.get(&item.raw))
+                .collect::<Vec<_>>();
+            let shown = number.min(total);
+            let (lines, indices, truncated_map) = printer::process_top_items(
+                ranked.into_iter().take(number),
+                utility::tty::display_width(winwidth, 100),
+                None,
+            );
+            let mut payload = serde_json::json!({
+                "total": total,
+                "lines": lines,
+                "indices": indices,
+                "registers": registers,
+            });
+            if !truncated_map.is_empty() {
+                payload["truncated_map"] = serde_json::json!(truncated_map);
+            }
+            printer::Payload::new(total, shown).attach_to(&mut payload);
+            printer::println_payload(payload);
+        } else {
+            for (item, _, indices) in ranked.into_iter() {
+                let register = names.get(&item.raw);
+                let text = item.raw;
+                printer::println_payload(serde_json::json!({
+                    "text": text,
+                    "indices": indices,
+                    "register": register,
+                }));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_item() {
+        let entry = Entry {
+            name: "a".into(),
+            content: "fn main() {\r\n    foo();\n}\n".into(),
+        };
+        let item = build_item(&entry);
+        assert_eq!(item.raw, "a  fn main() {⏎    foo();⏎}");
+
+        let (_, indices) = Algo::Fzy
+            .apply_match("foo", &item, &MatchType::Full)
+            .unwrap();
+        assert_eq!(indices, vec![19, 20, 21]);
+    }
+}
@@ -0,0 +1,196 @@
+//! Recording of the maple invocations and the replay of them, see `--record`.
+//!
+//! Each invocation is appended to the record file as a line of JSON, the replay runs the
+//! recorded args again in the recorded working directory and compares the timing and the
+//! result count. The inputs have to be the same files or commands, stdin is not recorded.
+
+use std::fs::OpenOptions;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use structopt::StructOpt;
+
+use crate::app::Cmd;
+
+/// A recorded invocation of maple.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Record {
+    /// Args of maple, excluding the program itself and `--record`.
+    pub args: Vec<String>,
+    pub cwd: PathBuf,
+    /// Query of the filter or grep command if any.
+    pub query: Option<String>,
+    /// Seconds since the UNIX epoch.
+    pub started_at: u64,
+    pub elapsed_ms: u64,
+    /// Number of the payloads printed to stdout, counted the same as [`output_stats`].
+    pub payloads: usize,
+    /// `total` of the last payload having it.
+    pub total: Option<usize>,
+    pub success: bool,
+}
+
+/// Returns `args` without `--record <file>` or `--record=<file>`.
+fn strip_record_flag(mut args: impl Iterator<Item = String>) -> Vec<String> {
+    let mut stripped = Vec::new();
+    while let Some(arg) = args.next() {
+        if arg == "--record" {
+            args.next();
+        } else if !arg.starts_with("--record=") {
+            stripped.push(arg);
+        }
+    }
+    stripped
+}
+
+/// An invocation being recorded.
+pub struct Recording {
+    query: Option<String>,
+    started_at: u64,
+    started: Instant,
+}
+
+impl Recording {
+    pub fn start(cmd: &Cmd) -> Self {
+        Self {
+            query: cmd.query().map(Into::into),
+            started_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
+            started: Instant::now(),
+        }
+    }
+
+    /// Appends the record of this invocation to `record_file`.
+    pub fn finish(self, record_file: &Path, success: bool) -> Result<()> {
+        let printer::stats::PrintStats { payloads, total } = printer::stats::print_stats();
+        let record = Record {
+            args: strip_record_flag(std::env::args().skip(1)),
+            cwd: std::env::current_dir()?,
+            query: self.query,
+            started_at: self.started_at,
+            elapsed_ms: self.started.elapsed().as_millis() as u64,
+            payloads,
+            total,
+            success,
+        };
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(record_file)
+            .with_context(|| format!("Failed to open {}", record_file.display()))?;
+        writeln!(file, "{}", serde_json::to_string(&record)?)?;
+        Ok(())
+    }
+}
+
+/// Returns the number of the payloads and `total` of the last payload having it in the output
+/// of maple, the `Content-length` headers of the dyn filtering are skipped.
+///
+/// The lines spilled to a tempfile are not in the output, nor counted by the recording side.
+fn output_stats(stdout: &str) -> (usize, Option<usize>) {
+    let payloads = stdout
+        .lines()
+        .filter_map(|line| serde_json::from_str::<Value>(line).ok())
+        .filter(Value::is_object)
+        .collect::<Vec<_>>();
+    let total = payloads
+        .iter()
+        .rev()
+        .find_map(|payload| payload.get("total").and_then(Value::as_u64))
+        .map(|total| total as usize);
+    (payloads.len(), total)
+}
+
+/// Replay the invocations recorded by `--record`.
+#[derive(StructOpt, Debug, Clone)]
+pub struct Replay {
+    /// Record file written by `--record`.
+    #[structopt(index = 1, parse(from_os_str = utility::paths::expand_os))]
+    file: PathBuf,
+
+    /// Replay only the record of this 0-based index.
+    #[structopt(long)]
+    index: Option<usize>,
+}
+
+impl Replay {
+    fn replay(record: &Record) -> Result<Value> {
+        let started = Instant::now();
+        let output = Command::new(std::env::current_exe()?)
+            .args(&record.args)
+            .current_dir(&record.cwd)
+            .stdin(Stdio::null())
+            .output()?;
+        let elapsed_ms = started.elapsed().as_millis() as u64;
+        let (payloads, total) = output_stats(&String::from_utf8_lossy(&output.stdout));
+        Ok(serde_json::json!({
+            "elapsed_ms": elapsed_ms,
+            "payloads": payloads,
+            "total": total,
+            "success": output.status.success(),
+        }))
+    }
+
+    pub fn run(&self) -> Result<()> {
+        let file = std::fs::File::open(&self.file)
+            .with_context(|| format!("Failed to open {}", self.file.display()))?;
+        for (index, line) in BufReader::new(file).lines().enumerate() {
+            if self.index.map_or(false, |i| i != index) {
+                continue;
+            }
+            let record: Record = serde_json::from_str(&line?)
+                .with_context(|| format!("Invalid record at line {}", index + 1))?;
+            let replayed = Self::replay(&record)?;
+            let total_changed = replayed["total"] != serde_json::json!(record.total);
+            printer::println_payload(serde_json::json!({
+                "index": index,
+                "args": record.args,
+                "query": record.query,
+                "recorded": {
+                    "elapsed_ms": record.elapsed_ms,
+                    "payloads": record.payloads,
+                    "total": record.total,
+                    "success": record.success,
+                },
+                "replayed": replayed,
+                "total_changed": total_changed,
+            }));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_record_flag() {
+        let args = [
+            "--record",
+            "a.jsonl",
+            "--number",
+            "10",
+            "--record=b.jsonl",
+            "filter",
+        ];
+        assert_eq!(
+            strip_record_flag(args.iter().map(|arg| arg.to_string())),
+            vec!["--number", "10", "filter"]
+        );
+    }
+
+    #[test]
+    fn test_output_stats() {
+        let stdout = "Content-length: 14\n\n{\"total\":2}\n{\"text\":\"a\"}\n{\"total\":5}\n";
+        assert_eq!(output_stats(stdout), (3, Some(5)));
+        assert_eq!(output_stats("not json\n"), (0, None));
+    }
+}
@@ -25,7 +25,7 @@ pub struct Tags {
     query: String,
 
     /// The directory to generate recursive ctags.
-    #[structopt(index = 2, short, long, parse(from_os_str))]
+    #[structopt(index = 2, short, long, parse(from_os_str = utility::paths::expand_os))]
     dir: PathBuf,
 
     /// Specify the language.
@@ -33,7 +33,7 @@ pub struct Tags {
     languages: Option<String>,
 
     /// Read input from a cached grep tempfile, only absolute file path is supported.
-    #[structopt(long = "input", parse(from_os_str))]
+    #[structopt(long = "input", parse(from_os_str = utility::paths::expand_os))]
     input: Option<PathBuf>,
 
     /// Runs as the forerunner job, create the new cache entry.
@@ -86,6 +86,7 @@ impl Tags {
         Params {
             no_cache,
             icon_painter,
+            tiebreak,
             ..
         }: Params,
     ) -> Result<()> {
@@ -116,6 +117,8 @@ impl Tags {
         let cmd_args = cmd_args.iter().map(|x| x.as_str()).collect::<Vec<_>>();
 
         if self.forerunner {
+            // Wait for the other forerunner job creating the tags cache for the same directory.
+            let _cache_lock = CacheEntry::lock(&cmd_args, &self.dir)?;
             let (cache, total) = if no_cache {
                 create_tags_cache(&cmd_args, &self.dir)?
             } else if let Ok(cached_info) = cache_exists(&cmd_args, &self.dir) {
@@ -129,8 +132,9 @@ impl Tags {
             filter::dyn_run(
                 &self.query,
                 Source::List(formatted_tags_stream(&cmd_args, &self.dir)?.map(Into::into)),
-                FilterContext::new(None, Some(30), None, icon_painter, MatchType::TagName),
-                vec![Bonus::None],
+                FilterContext::new(None, Some(30), None, icon_painter, MatchType::TagName)
+                    .tiebreak(tiebreak),
+                vec![Bonus::Acronym],
             )?;
         }
 
@@ -0,0 +1,237 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::process::Command;
+
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use structopt::StructOpt;
+
+use filter::{
+    matcher::{Algo, Bonus, MatchType},
+    Source,
+};
+use source_item::SourceItem;
+
+use crate::app::Params;
+use crate::process::light::set_current_dir;
+use crate::tools::rg::{JsonLine, SearchResult};
+
+/// Returns the icon of `keyword`.
+fn keyword_icon(keyword: &str) -> char {
+    match keyword {
+        "TODO" => '\u{f0ae}',
+        "FIXME" => '\u{f188}',
+        "XXX" => '\u{f071}',
+        "HACK" => '\u{f0ad}',
+        _ => '\u{f02b}',
+    }
+}
+
+/// A comment containing one of the keywords.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+struct Entry {
+    keyword: String,
+    path: String,
+    lnum: u64,
+    /// 1-based byte column of the keyword.
+    col: usize,
+    text: String,
+}
+
+impl Entry {
+    fn from_json_line(line: &JsonLine) -> Option<Self> {
+        let data = &line.data;
+        let keyword = data.submatches.first()?.m.text.clone();
+        Some(Self {
+            keyword,
+            path: data.path().into(),
+            lnum: data.line_number(),
+            col: data.column() + 1,
+            text: data.line().trim().into(),
+        })
+    }
+
+    /// Builds the item `[icon ]KEYWORD path:lnum:text`, the keyword is not used for matching.
+    fn build_item(&self, enable_icon: bool) -> SourceItem {
+        let prefix = if enable_icon {
+            format!("{} {:<5} ", keyword_icon(&self.keyword), self.keyword)
+        } else {
+            format!("{:<5} ", self.keyword)
+        };
+        let match_text = format!("{}:{}:{}", self.path, self.lnum, self.text);
+        let offset = prefix.chars().count();
+        SourceItem::new(
+            format!("{}{}", prefix, match_text),
+            Some((match_text, offset)),
+            None,
+        )
+    }
+}
+
+/// Returns the regex of rg matching any of the whole word `keywords`.
+fn keywords_regex(keywords: &[String]) -> String {
+    format!(
+        r"\b({})\b",
+        keywords
+            .iter()
+            .map(|keyword| regex::escape(keyword))
+            .collect::<Vec<_>>()
+            .join("|")
+    )
+}
+
+/// Collect the TODO comments in the project, ranked by the priority of keyword then the score.
+#[derive(StructOpt, Debug, Clone)]
+pub struct Todos {
+    /// Initial query string
+    #[structopt(index = 1, short, long, default_value = "")]
+    query: String,
+
+    /// Specify the working directory of rg.
+    #[structopt(long = "cmd-dir", parse(from_os_str = utility::paths::expand_os))]
+    cmd_dir: Option<PathBuf>,
+
+    /// Keywords to search in the order of priority, matched case sensitively as whole words.
+    #[structopt(long, use_delimiter = true, default_value = "TODO,FIXME,XXX,HACK")]
+    keywords: Vec<String>,
+}
+
+impl Todos {
+    fn collect_entries(&self) -> Result<Vec<Entry>> {
+        let mut cmd = Command::new("rg");
+        cmd.args(&["--json", "--case-sensitive", "-e"])
+            .arg(keywords_regex(&self.keywords));
+        // Ref https://github.com/liuchengxu/vim-clap/issues/533
+        if cfg!(windows) {
+            cmd.arg(".");
+        }
+        set_current_dir(&mut cmd, self.cmd_dir.clone());
+
+        let output = cmd.output()?;
+        // Exit code 1 means no match.
+        if output.status.code() == Some(2) && output.stdout.is_empty() {
+            return Err(anyhow!(
+                "rg failed: {}",
+                String::from_utf8_lossy(&output.stderr)
+            ));
+        }
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let grep_lines = stdout.lines().collect::<Vec<_>>();
+        Ok(SearchResult::from_lines(&grep_lines)
+            .lines
+            .iter()
+            .filter(|line| !line.is_context())
+            .filter_map(Entry::from_json_line)
+            .collect())
+    }
+
+    /// Returns the priority of `keyword`, the smaller the higher.
+    fn priority(&self, keyword: &str) -> usize {
+        self.keywords
+            .iter()
+            .position(|k| k == keyword)
+            .unwrap_or(self.keywords.len())
+    }
+
+    pub fn run(&self, params: Params) -> Result<()> {
+        let line_cutoff = params.line_cutoff();
+        let Params {
+            number,
+            winwidth,
+            icon_painter,
+            ..
+        } = params;
+
+        let mut entries = HashMap::new();
+        let items = self
+            .collect_entries()?
+            .into_iter()
+            .map(|entry| {
+                let item = entry.build_item(icon_painter.is_some());
+                entries.insert(item.raw.clone(), entry);
+                item
+            })
+            .collect::<Vec<_>>();
+
+        let mut ranked = if self.query.is_empty() {
+            items
+                .into_iter()
+                .map(|item| (item, 0, Vec::new()))
+                .collect()
+        } else {
+            filter::sync_run(
+                &self.query,
+                Source::List(items.into_iter()),
+                Algo::Fzy,
+                MatchType::Full,
+                vec![Bonus::None],
+                line_cutoff,
+            )?
+        };
+        // The stable sort keeps the items of the same keyword ranked by the score.
+        ranked.sort_by_key(|(item, _, _)| self.priority(&entries[&item.raw].keyword));
+
+        if let Some(number) = number {
+            let total = ranked.len();
+            let entries = ranked
+                .iter()
+                .take(number)
+                .map(|(item, _, _)| &entries[&item.raw])
+                .collect::<Vec<_>>();
+            let (lines, indices, truncated_map) = printer::process_top_items(
+                ranked.into_iter().take(number),
+                utility::tty::display_width(winwidth, 100),
+                None,
+            );
+            let mut payload = serde_json::json!({
+                "total": total,
+                "lines": lines,
+                "indices": indices,
+                "entries": entries,
+            });
+            if !truncated_map.is_empty() {
+                payload["truncated_map"] = serde_json::json!(truncated_map);
+            }
+            printer::println_payload(payload);
+        } else {
+            for (item, _, indices) in ranked.into_iter() {
+                let entry = &entries[&item.raw];
+                printer::println_payload(serde_json::json!({
+                    "text": item.raw,
+                    "indices": indices,
+                    "entry": entry,
+                }));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_item() {
+        let line = r#"{"type":"match","data":{"path":{"text":"src/lib.rs"},"lines":{"text":"    // FIXME: handle the error\n"},"line_number":7,"absolute_offset":0,"submatches":[{"match":{"text":"FIXME"},"start":7,"end":12}]}}"#;
+        let result = SearchResult::from_lines(&[line]);
+        let entry = Entry::from_json_line(&result.lines[0]).unwrap();
+        assert_eq!(entry.keyword, "FIXME");
+        assert_eq!(entry.col, 8);
+        assert_eq!(entry.text, "// FIXME: handle the error");
+
+        let item = entry.build_item(false);
+        assert_eq!(item.raw, "FIXME src/lib.rs:7:// FIXME: handle the error");
+        let (_, indices) = Algo::Fzy
+            .apply_match("lib", &item, &MatchType::Full)
+            .unwrap();
+        assert_eq!(indices, vec![10, 11, 12]);
+
+        assert_eq!(
+            keywords_regex(&["TODO".into(), "C++".into()]),
+            r"\b(TODO|C\+\+)\b"
+        );
+    }
+}
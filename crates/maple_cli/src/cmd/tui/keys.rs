@@ -0,0 +1,129 @@
+//! Keys accepting the selection given by `--expect`, named the same with fzf.
+
+use std::str::FromStr;
+
+use anyhow::{anyhow, Error, Result};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+/// Key printed before the selected item once it's pressed, e.g., `ctrl-v`, `alt-enter` or `f2`.
+///
+/// The names and modifiers are case insensitive, the single char is not.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExpectKey {
+    /// Name given by the user, which is printed as is.
+    name: String,
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+/// Parses the lowercase name of a key other than a single char.
+fn parse_key_code(name: &str) -> Option<KeyCode> {
+    let code = match name {
+        "enter" | "return" => KeyCode::Enter,
+        "tab" => KeyCode::Tab,
+        "btab" | "shift-tab" => KeyCode::BackTab,
+        "esc" => KeyCode::Esc,
+        "space" => KeyCode::Char(' '),
+        "bspace" | "bs" => KeyCode::Backspace,
+        "del" => KeyCode::Delete,
+        "insert" => KeyCode::Insert,
+        "up" => KeyCode::Up,
+        "down" => KeyCode::Down,
+        "left" => KeyCode::Left,
+        "right" => KeyCode::Right,
+        "home" => KeyCode::Home,
+        "end" => KeyCode::End,
+        "pgup" | "page-up" => KeyCode::PageUp,
+        "pgdn" | "page-down" => KeyCode::PageDown,
+        _ => {
+            let n = name.strip_prefix('f')?.parse::<u8>().ok()?;
+            if !(1..=12).contains(&n) {
+                return None;
+            }
+            KeyCode::F(n)
+        }
+    };
+    Some(code)
+}
+
+impl FromStr for ExpectKey {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut key = s;
+        let mut modifiers = KeyModifiers::NONE;
+        loop {
+            let prefix = key.get(..5).unwrap_or(key).to_lowercase();
+            if prefix == "ctrl-" {
+                modifiers |= KeyModifiers::CONTROL;
+                key = &key[5..];
+            } else if prefix.starts_with("alt-") {
+                modifiers |= KeyModifiers::ALT;
+                key = &key[4..];
+            } else {
+                break;
+            }
+        }
+        let mut chars = key.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) => Some(KeyCode::Char(c)),
+            _ => parse_key_code(&key.to_lowercase()),
+        }
+        .ok_or_else(|| anyhow!("Unknown key: {}", s))?;
+        Ok(Self {
+            name: s.to_string(),
+            code,
+            modifiers,
+        })
+    }
+}
+
+impl ExpectKey {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns true if `key` is this key, the shift of an uppercase char is ignored.
+    pub fn matches(&self, key: &KeyEvent) -> bool {
+        let modifiers = key.modifiers & (KeyModifiers::CONTROL | KeyModifiers::ALT);
+        key.code == self.code && modifiers == self.modifiers
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(code: KeyCode, modifiers: KeyModifiers) -> KeyEvent {
+        KeyEvent::new(code, modifiers)
+    }
+
+    #[test]
+    fn test_expect_key() {
+        let ctrl_v = "ctrl-v".parse::<ExpectKey>().unwrap();
+        assert_eq!(ctrl_v.name(), "ctrl-v");
+        assert!(ctrl_v.matches(&key(KeyCode::Char('v'), KeyModifiers::CONTROL)));
+        assert!(!ctrl_v.matches(&key(KeyCode::Char('v'), KeyModifiers::NONE)));
+
+        let alt_enter = "alt-enter".parse::<ExpectKey>().unwrap();
+        assert!(alt_enter.matches(&key(KeyCode::Enter, KeyModifiers::ALT)));
+        assert!(!alt_enter.matches(&key(KeyCode::Enter, KeyModifiers::NONE)));
+
+        let f2 = "F2".parse::<ExpectKey>().unwrap();
+        assert_eq!(f2.name(), "F2");
+        assert!(f2.matches(&key(KeyCode::F(2), KeyModifiers::NONE)));
+
+        let upper = "X".parse::<ExpectKey>().unwrap();
+        assert!(upper.matches(&key(KeyCode::Char('X'), KeyModifiers::SHIFT)));
+        assert!(!upper.matches(&key(KeyCode::Char('x'), KeyModifiers::NONE)));
+
+        let ctrl_alt_x = "Ctrl-Alt-x".parse::<ExpectKey>().unwrap();
+        assert!(ctrl_alt_x.matches(&key(
+            KeyCode::Char('x'),
+            KeyModifiers::CONTROL | KeyModifiers::ALT
+        )));
+        assert!("f13".parse::<ExpectKey>().is_err());
+        assert!("ctrl-".parse::<ExpectKey>().is_err());
+        assert!("hyper-x".parse::<ExpectKey>().is_err());
+    }
+}
@@ -0,0 +1,236 @@
+//! Interactive fuzzy finder in the terminal, the selected line is printed to stdout.
+//!
+//! The screen is drawn on stderr so that the output can be piped, e.g., `vim $(fd | maple tui)`.
+//! The output and exit codes are compatible with fzf given `--print-query`, `--expect` and
+//! `--filter`, so that the scripts written for fzf can use maple instead.
+
+mod keys;
+mod preview;
+mod render;
+mod state;
+
+use std::io::Write;
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Result};
+use crossterm::{
+    event::{self, Event},
+    execute, terminal,
+};
+use structopt::StructOpt;
+
+use filter::{
+    matcher::{Algo, Bonus, MatchType},
+    subprocess, ExcludeFilter, Source,
+};
+use source_item::SourceItem;
+
+use crate::app::Params;
+
+use self::keys::ExpectKey;
+use self::state::{Action, MatchOptions, State};
+
+/// Exit code when nothing is selected, same with fzf.
+const NO_MATCH_EXIT_CODE: i32 = 1;
+
+/// Exit code when the finder is aborted, same with fzf.
+const ABORT_EXIT_CODE: i32 = 130;
+
+/// Outcome of the finder accepted by Enter or a key of `--expect`.
+#[derive(Debug)]
+struct Accepted {
+    query: String,
+    /// Name of the key of `--expect` pressed, `None` for Enter.
+    key: Option<String>,
+    selected: Option<String>,
+}
+
+/// Restores the terminal on drop, even if the finder errors out.
+struct TerminalGuard;
+
+impl TerminalGuard {
+    fn enter<W: Write>(w: &mut W) -> Result<Self> {
+        terminal::enable_raw_mode()?;
+        execute!(w, terminal::EnterAlternateScreen)?;
+        Ok(Self)
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let _ = execute!(std::io::stderr(), terminal::LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+/// Start the interactive fuzzy finder in the terminal.
+#[derive(StructOpt, Debug, Clone)]
+pub struct Tui {
+    /// Initial query string.
+    #[structopt(short, long, default_value = "")]
+    query: String,
+
+    /// Filter algorithm
+    #[structopt(short, long, possible_values = &Algo::variants(), case_insensitive = true)]
+    algo: Option<Algo>,
+
+    /// Shell command to produce the whole dataset that query is applied on.
+    #[structopt(short, long)]
+    cmd: Option<String>,
+
+    /// Working directory of shell command, also used for previewing the relative paths.
+    #[structopt(long, parse(from_os_str = utility::paths::expand_os))]
+    cmd_dir: Option<PathBuf>,
+
+    /// Read input from a file instead of stdin.
+    #[structopt(long, parse(from_os_str = utility::paths::expand_os))]
+    input: Option<PathBuf>,
+
+    /// Apply the filter on the full line content or parial of it.
+    #[structopt(short, long, possible_values = &MatchType::variants(), case_insensitive = true)]
+    match_type: Option<MatchType>,
+
+    /// Do not show the preview pane.
+    #[structopt(long)]
+    no_preview: bool,
+
+    /// Print the query as the first line of the output.
+    #[structopt(long)]
+    print_query: bool,
+
+    /// Comma-separated keys that accept the selection like Enter, e.g., `ctrl-v,ctrl-x`.
+    ///
+    /// The pressed key is printed before the selected line, an empty line for Enter.
+    #[structopt(long, use_delimiter = true)]
+    expect: Vec<ExpectKey>,
+
+    /// Print the lines matching this query and exit without the interactive finder, --query is
+    /// ignored.
+    #[structopt(short, long)]
+    filter: Option<String>,
+}
+
+impl Tui {
+    /// Reads all the items from the shell command, the input file or stdin.
+    fn collect_items(
+        &self,
+        exclude: &[String],
+        relative_to: Option<PathBuf>,
+        match_type: &MatchType,
+    ) -> Result<Vec<SourceItem>> {
+        let mut source: Source<Box<dyn Iterator<Item = SourceItem>>> =
+            if let Some(ref cmd_str) = self.cmd {
+                let mut exec = subprocess::Exec::shell(cmd_str);
+                if let Some(ref dir) = self.cmd_dir {
+                    exec = exec.cwd(dir);
+                }
+                exec.into()
+            } else if let Some(ref input) = self.input {
+                input.clone().into()
+            } else if atty::is(atty::Stream::Stdin) {
+                return Err(anyhow!(
+                    "No input, specify --cmd or --input, or pipe the input to stdin"
+                ));
+            } else {
+                Source::Stdin
+            };
+        if !exclude.is_empty() {
+            source = source.exclude(ExcludeFilter::new(exclude), match_type.clone())?;
+        }
+        if let Some(dir) = relative_to {
+            source = source.relative_to(dir, match_type.clone())?;
+        }
+        Ok(source.into_items()?.collect())
+    }
+
+    /// Runs the finder until an item is accepted or the finder is aborted.
+    fn run_finder(&self, state: &mut State) -> Result<Option<Accepted>> {
+        let mut stderr = std::io::stderr();
+        let _guard = TerminalGuard::enter(&mut stderr)?;
+
+        loop {
+            render::draw(
+                &mut stderr,
+                state,
+                self.cmd_dir.as_deref(),
+                !self.no_preview,
+                terminal::size()?,
+            )?;
+
+            // Resize and mouse events only need a redraw.
+            if let Event::Key(key) = event::read()? {
+                // The expected keys take precedence over the key bindings, same with fzf.
+                let expected = self.expect.iter().find(|expect| expect.matches(&key));
+                let action = match expected {
+                    Some(_) => Action::Accept,
+                    None => state.handle_key(key),
+                };
+                match action {
+                    Action::Continue => {}
+                    Action::Accept => {
+                        return Ok(Some(Accepted {
+                            query: state.query().to_string(),
+                            key: expected.map(|expect| expect.name().to_string()),
+                            selected: state.selected_item().map(|item| item.raw.clone()),
+                        }))
+                    }
+                    Action::Abort => return Ok(None),
+                }
+            }
+        }
+    }
+
+    pub fn run(&self, params: Params) -> Result<()> {
+        let line_cutoff = params.line_cutoff();
+        let Params {
+            exclude,
+            relative_to,
+            ..
+        } = params;
+
+        let match_type = self.match_type.clone().unwrap_or(MatchType::Full);
+        let items = self.collect_items(&exclude, relative_to, &match_type)?;
+
+        let mut state = State::new(
+            items,
+            MatchOptions {
+                algo: self.algo.clone().unwrap_or(Algo::Fzy),
+                match_type,
+                bonuses: vec![Bonus::None],
+                line_cutoff,
+            },
+            self.filter.clone().unwrap_or_else(|| self.query.clone()),
+        );
+
+        if self.filter.is_some() {
+            if self.print_query {
+                println!("{}", state.query());
+            }
+            let mut stdout = std::io::stdout();
+            for item in state.ranked_items() {
+                writeln!(stdout, "{}", item.raw)?;
+            }
+            if state.matched() == 0 {
+                std::process::exit(NO_MATCH_EXIT_CODE);
+            }
+            return Ok(());
+        }
+
+        let accepted = match self.run_finder(&mut state)? {
+            Some(accepted) => accepted,
+            None => std::process::exit(ABORT_EXIT_CODE),
+        };
+        if self.print_query {
+            println!("{}", accepted.query);
+        }
+        if !self.expect.is_empty() {
+            println!("{}", accepted.key.unwrap_or_default());
+        }
+        match accepted.selected {
+            Some(selected) => println!("{}", selected),
+            None => std::process::exit(NO_MATCH_EXIT_CODE),
+        }
+
+        Ok(())
+    }
+}
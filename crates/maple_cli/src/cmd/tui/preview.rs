@@ -0,0 +1,93 @@
+//! Preview of the selected line, the file content around the grep line or the file itself.
+
+use std::path::{Path, PathBuf};
+
+/// Content of the preview pane.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Preview {
+    pub title: String,
+    pub lines: Vec<String>,
+    /// Index of the line in `lines` to highlight.
+    pub highlight: Option<usize>,
+}
+
+fn resolve(path: PathBuf, cwd: Option<&Path>) -> PathBuf {
+    match cwd {
+        Some(dir) if path.is_relative() => dir.join(path),
+        _ => path,
+    }
+}
+
+/// Returns the preview of `line` in `height` rows, `cwd` is used for the relative paths.
+pub fn preview_of(line: &str, cwd: Option<&Path>, height: usize) -> Option<Preview> {
+    let size = height.saturating_sub(1);
+
+    if let Some((path, lnum, _col)) = pattern::extract_grep_position(line) {
+        let path = resolve(path, cwd);
+        if path.is_file() {
+            let (lines, hl_line) =
+                utility::read_preview_lines(&path, lnum.saturating_sub(1), size / 2).ok()?;
+            return Some(Preview {
+                title: format!("{}:{}", path.display(), lnum),
+                lines: lines.take(size).collect(),
+                highlight: Some(hl_line),
+            });
+        }
+    }
+
+    let path = resolve(line.trim().into(), cwd);
+    if path.is_file() {
+        Some(Preview {
+            title: path.display().to_string(),
+            lines: utility::read_first_lines(&path, size).ok()?.collect(),
+            highlight: None,
+        })
+    } else if path.is_dir() {
+        let mut entries = std::fs::read_dir(&path)
+            .ok()?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.file_name().to_string_lossy().into_owned())
+            .collect::<Vec<_>>();
+        entries.sort();
+        entries.truncate(size);
+        Some(Preview {
+            title: path.display().to_string(),
+            lines: entries,
+            highlight: None,
+        })
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_preview_grep_line() {
+        let dir = std::env::temp_dir().join(format!("clap_test_preview_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let content = (1..=20)
+            .map(|i| format!("line {}", i))
+            .collect::<Vec<_>>()
+            .join("\n");
+        std::fs::write(dir.join("foo.txt"), content).unwrap();
+
+        let preview = preview_of("foo.txt:10:1:line 10", Some(&dir), 5).unwrap();
+        assert_eq!(
+            preview.lines,
+            vec!["line 8", "line 9", "line 10", "line 11"]
+        );
+        assert_eq!(preview.highlight, Some(2));
+        assert_eq!(preview.lines[preview.highlight.unwrap()], "line 10");
+
+        let preview = preview_of("foo.txt", Some(&dir), 3).unwrap();
+        assert_eq!(preview.lines, vec!["line 1", "line 2"]);
+        assert_eq!(preview.highlight, None);
+
+        assert!(preview_of("not_exists.txt", Some(&dir), 3).is_none());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,189 @@
+//! Draws the prompt, the result list and the preview pane.
+
+use std::io::Write;
+use std::path::Path;
+
+use anyhow::Result;
+use crossterm::{
+    cursor::{MoveTo, Show},
+    queue,
+    style::{Attribute, Color, Print, SetAttribute, SetForegroundColor},
+};
+
+use super::preview::{self, Preview};
+use super::state::State;
+
+const PROMPT: &str = "> ";
+
+/// Rows taken by the prompt and the info line.
+const HEADER_ROWS: u16 = 2;
+
+/// The marker of the selected item.
+const SELECTED_MARKER: &str = "> ";
+
+/// The preview pane is not shown when the terminal is narrower than this.
+const MIN_PREVIEW_WIDTH: u16 = 60;
+
+/// The lines are truncated by `printer` only when there is enough room.
+const MIN_TRUNCATE_WIDTH: usize = 16;
+
+/// Replaces the control chars like tab with a space, the char indices are unchanged.
+fn sanitize(c: char) -> char {
+    if c.is_control() {
+        ' '
+    } else {
+        c
+    }
+}
+
+/// Clips `text` to `width` chars and pads it with spaces.
+fn fit(text: &str, width: usize) -> String {
+    let mut fitted = text.chars().take(width).map(sanitize).collect::<String>();
+    let len = fitted.chars().count();
+    fitted.extend(std::iter::repeat(' ').take(width - len));
+    fitted
+}
+
+/// Queues `text` of `width` chars with the chars at `indices` highlighted.
+fn queue_highlighted<W: Write>(
+    w: &mut W,
+    text: &str,
+    indices: &[usize],
+    width: usize,
+) -> Result<()> {
+    let text = fit(text, width);
+    for (idx, c) in text.chars().enumerate() {
+        if indices.contains(&idx) {
+            queue!(
+                w,
+                SetForegroundColor(Color::Green),
+                Print(c),
+                SetForegroundColor(Color::Reset)
+            )?;
+        } else {
+            queue!(w, Print(c))?;
+        }
+    }
+    Ok(())
+}
+
+fn draw_list<W: Write>(w: &mut W, state: &mut State, width: u16, height: u16) -> Result<()> {
+    let width = width as usize;
+    let text_width = width.saturating_sub(SELECTED_MARKER.len());
+
+    queue!(
+        w,
+        MoveTo(0, 0),
+        Print(fit(&format!("{}{}", PROMPT, state.query()), width)),
+        MoveTo(0, 1),
+        SetForegroundColor(Color::DarkGrey),
+        Print(fit(
+            &format!("  {}/{}", state.matched(), state.total()),
+            width
+        )),
+        SetForegroundColor(Color::Reset)
+    )?;
+
+    let list_height = height.saturating_sub(HEADER_ROWS) as usize;
+    state.scroll_to_selected(list_height);
+    let (lines, _) = printer::truncate_long_matched_lines(
+        state.visible(list_height).iter().cloned(),
+        text_width.max(MIN_TRUNCATE_WIDTH),
+        None,
+    );
+
+    let selected = state.selected_in_view();
+    for row in 0..list_height {
+        queue!(w, MoveTo(0, HEADER_ROWS + row as u16))?;
+        match lines.get(row) {
+            Some((text, _, indices)) if row == selected => {
+                queue!(w, SetAttribute(Attribute::Bold), Print(SELECTED_MARKER))?;
+                queue_highlighted(w, text, indices, text_width)?;
+                queue!(w, SetAttribute(Attribute::Reset))?;
+            }
+            Some((text, _, indices)) => {
+                queue!(w, Print(fit("", SELECTED_MARKER.len())))?;
+                queue_highlighted(w, text, indices, text_width)?;
+            }
+            None => queue!(w, Print(fit("", width)))?,
+        }
+    }
+
+    Ok(())
+}
+
+fn draw_preview<W: Write>(
+    w: &mut W,
+    preview: Option<Preview>,
+    col: u16,
+    width: u16,
+    height: u16,
+) -> Result<()> {
+    let width = width as usize;
+    let (title, lines, highlight) = match preview {
+        Some(Preview {
+            title,
+            lines,
+            highlight,
+        }) => (title, lines, highlight),
+        None => (String::new(), Vec::new(), None),
+    };
+
+    for row in 0..height {
+        queue!(w, MoveTo(col, row), Print('│'))?;
+        if row == 0 {
+            queue!(
+                w,
+                SetAttribute(Attribute::Bold),
+                Print(fit(&title, width)),
+                SetAttribute(Attribute::Reset)
+            )?;
+            continue;
+        }
+        let idx = row as usize - 1;
+        let line = lines.get(idx).map(String::as_str).unwrap_or_default();
+        if highlight == Some(idx) {
+            queue!(
+                w,
+                SetAttribute(Attribute::Reverse),
+                Print(fit(line, width)),
+                SetAttribute(Attribute::Reset)
+            )?;
+        } else {
+            queue!(w, Print(fit(line, width)))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Draws the whole screen of `width` x `height`.
+pub fn draw<W: Write>(
+    w: &mut W,
+    state: &mut State,
+    cwd: Option<&Path>,
+    enable_preview: bool,
+    (width, height): (u16, u16),
+) -> Result<()> {
+    let list_width = if enable_preview && width >= MIN_PREVIEW_WIDTH {
+        width / 2
+    } else {
+        width
+    };
+
+    draw_list(w, state, list_width, height)?;
+
+    if list_width < width {
+        let preview = state
+            .selected_item()
+            .and_then(|item| preview::preview_of(&item.raw, cwd, height as usize));
+        // One column for the border.
+        draw_preview(w, preview, list_width, width - list_width - 1, height)?;
+    }
+
+    let cursor_col = (PROMPT.len() + state.query().chars().count()).min(list_width as usize);
+    queue!(w, MoveTo(cursor_col as u16, 0), Show)?;
+    w.flush()?;
+
+    Ok(())
+}
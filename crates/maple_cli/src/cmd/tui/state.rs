@@ -0,0 +1,267 @@
+//! State of the interactive finder, kept apart from the terminal for testing.
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use filter::{
+    matcher::{Algo, Bonus, LineCutoff, MatchType},
+    FilterResult, Source,
+};
+use source_item::SourceItem;
+
+/// What to do after handling a key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    /// Keep running, the screen has to be redrawn.
+    Continue,
+    /// Quit and print the selected item.
+    Accept,
+    /// Quit without selecting anything.
+    Abort,
+}
+
+/// Options of the matcher applied on each query change.
+#[derive(Debug, Clone)]
+pub struct MatchOptions {
+    pub algo: Algo,
+    pub match_type: MatchType,
+    pub bonuses: Vec<Bonus>,
+    pub line_cutoff: Option<LineCutoff>,
+}
+
+#[derive(Debug)]
+pub struct State {
+    items: Vec<SourceItem>,
+    options: MatchOptions,
+    query: String,
+    ranked: Vec<FilterResult>,
+    /// Index of the selected item in `ranked`.
+    selected: usize,
+    /// Index of the first visible item in `ranked`.
+    offset: usize,
+}
+
+impl State {
+    pub fn new(items: Vec<SourceItem>, options: MatchOptions, query: String) -> Self {
+        let mut state = Self {
+            items,
+            options,
+            query,
+            ranked: Vec::new(),
+            selected: 0,
+            offset: 0,
+        };
+        state.refilter();
+        state
+    }
+
+    /// Reruns the matcher against all the items using the current query.
+    fn refilter(&mut self) {
+        self.ranked = if self.query.is_empty() {
+            self.items
+                .iter()
+                .cloned()
+                .map(|item| (item, 0, Vec::new()))
+                .collect()
+        } else {
+            filter::sync_run(
+                &self.query,
+                Source::List(self.items.iter().cloned()),
+                self.options.algo.clone(),
+                self.options.match_type.clone(),
+                self.options.bonuses.clone(),
+                self.options.line_cutoff.clone(),
+            )
+            .unwrap_or_default()
+        };
+        self.selected = 0;
+        self.offset = 0;
+    }
+
+    pub fn query(&self) -> &str {
+        &self.query
+    }
+
+    pub fn total(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn matched(&self) -> usize {
+        self.ranked.len()
+    }
+
+    /// Returns all the matched items in the ranked order.
+    pub fn ranked_items(&self) -> impl Iterator<Item = &SourceItem> {
+        self.ranked.iter().map(|(item, _, _)| item)
+    }
+
+    pub fn selected_item(&self) -> Option<&SourceItem> {
+        self.ranked.get(self.selected).map(|(item, _, _)| item)
+    }
+
+    /// Returns the position of the selected item in the visible items.
+    pub fn selected_in_view(&self) -> usize {
+        self.selected - self.offset
+    }
+
+    /// Scrolls the list so that the selected item is visible in `height` rows.
+    pub fn scroll_to_selected(&mut self, height: usize) {
+        if self.selected < self.offset {
+            self.offset = self.selected;
+        } else if height > 0 && self.selected >= self.offset + height {
+            self.offset = self.selected + 1 - height;
+        }
+    }
+
+    /// Returns the items visible in `height` rows.
+    pub fn visible(&self, height: usize) -> &[FilterResult] {
+        let end = (self.offset + height).min(self.ranked.len());
+        &self.ranked[self.offset.min(end)..end]
+    }
+
+    fn move_up(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    fn move_down(&mut self) {
+        if self.selected + 1 < self.ranked.len() {
+            self.selected += 1;
+        }
+    }
+
+    fn set_query(&mut self, query: String) {
+        if query != self.query {
+            self.query = query;
+            self.refilter();
+        }
+    }
+
+    /// Deletes the last word of the query and the whitespaces after it.
+    fn delete_word(&mut self) {
+        let query = self.query.trim_end();
+        let end = query
+            .rfind(char::is_whitespace)
+            .map(|idx| idx + 1)
+            .unwrap_or(0);
+        self.set_query(query[..end].to_string());
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) -> Action {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Esc => return Action::Abort,
+            KeyCode::Enter => return Action::Accept,
+            KeyCode::Up => self.move_up(),
+            KeyCode::Down => self.move_down(),
+            KeyCode::Backspace => {
+                let mut query = self.query.clone();
+                query.pop();
+                self.set_query(query);
+            }
+            KeyCode::Char(c) if ctrl => match c {
+                'c' | 'g' | 'q' => return Action::Abort,
+                'p' | 'k' => self.move_up(),
+                'n' | 'j' => self.move_down(),
+                'u' => self.set_query(String::new()),
+                'w' => self.delete_word(),
+                _ => {}
+            },
+            KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::ALT) => {
+                let mut query = self.query.clone();
+                query.push(c);
+                self.set_query(query);
+            }
+            _ => {}
+        }
+        Action::Continue
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_state(lines: &[&str]) -> State {
+        State::new(
+            lines.iter().map(|&line| line.into()).collect(),
+            MatchOptions {
+                algo: Algo::Fzy,
+                match_type: MatchType::Full,
+                bonuses: vec![Bonus::None],
+                line_cutoff: None,
+            },
+            String::new(),
+        )
+    }
+
+    fn type_str(state: &mut State, s: &str) {
+        for c in s.chars() {
+            state.handle_key(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE));
+        }
+    }
+
+    fn ctrl(c: char) -> KeyEvent {
+        KeyEvent::new(KeyCode::Char(c), KeyModifiers::CONTROL)
+    }
+
+    #[test]
+    fn test_typing_narrows_results() {
+        let mut state = new_state(&["src/main.rs", "README.md", "src/app.rs"]);
+        assert_eq!(state.matched(), 3);
+
+        type_str(&mut state, "app");
+        assert_eq!(state.query(), "app");
+        assert_eq!(state.matched(), 1);
+        assert_eq!(state.selected_item().unwrap().raw, "src/app.rs");
+
+        state.handle_key(KeyEvent::new(KeyCode::Backspace, KeyModifiers::NONE));
+        assert_eq!(state.query(), "ap");
+
+        state.handle_key(ctrl('u'));
+        assert_eq!(state.query(), "");
+        assert_eq!(state.matched(), 3);
+        assert_eq!(
+            state
+                .ranked_items()
+                .map(|item| item.raw.as_str())
+                .collect::<Vec<_>>(),
+            vec!["src/main.rs", "README.md", "src/app.rs"]
+        );
+    }
+
+    #[test]
+    fn test_delete_word() {
+        let mut state = new_state(&["foo bar"]);
+        type_str(&mut state, "foo bar  ");
+        state.handle_key(ctrl('w'));
+        assert_eq!(state.query(), "foo ");
+        state.handle_key(ctrl('w'));
+        assert_eq!(state.query(), "");
+    }
+
+    #[test]
+    fn test_navigation_and_scrolling() {
+        let mut state = new_state(&["a", "b", "c", "d", "e"]);
+        state.handle_key(ctrl('p'));
+        assert_eq!(state.selected_item().unwrap().raw, "a");
+
+        for _ in 0..10 {
+            state.handle_key(ctrl('n'));
+        }
+        assert_eq!(state.selected_item().unwrap().raw, "e");
+
+        state.scroll_to_selected(2);
+        let visible = state
+            .visible(2)
+            .iter()
+            .map(|(item, _, _)| item.raw.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(visible, vec!["d", "e"]);
+        assert_eq!(state.selected_in_view(), 1);
+
+        assert_eq!(
+            state.handle_key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)),
+            Action::Accept
+        );
+        assert_eq!(state.handle_key(ctrl('c')), Action::Abort);
+    }
+}
@@ -0,0 +1,22 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use structopt::StructOpt;
+
+use utility::println_json;
+
+use crate::cache_watcher;
+
+/// Keep the files and grep caches of a project up to date.
+#[derive(StructOpt, Debug, Clone)]
+pub struct Watch {
+    /// Watch the changes of the files in this directory.
+    #[structopt(long, parse(from_os_str = utility::paths::expand_os))]
+    dir: PathBuf,
+}
+
+impl Watch {
+    pub fn run(&self) -> Result<()> {
+        cache_watcher::watch(&self.dir, |updated| println_json!(updated))
+    }
+}
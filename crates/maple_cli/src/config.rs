@@ -0,0 +1,270 @@
+//! Config file of maple, `$XDG_CONFIG_HOME/vim-clap/config.toml` or
+//! `~/.config/vim-clap/config.toml` by default.
+//!
+//! ```toml
+//! # Number of threads used for filtering, defaults to the number of logical CPUs.
+//! threads = 4
+//!
+//! # Compression of the cache files: none, gzip or zstd.
+//! cache_compression = "zstd"
+//!
+//! # Provider run by `maple provider run my_todos`.
+//! [provider.my_todos]
+//! cmd = "rg TODO --vimgrep"
+//! match_type = "IgnoreFilePath"
+//! icon = "Grep"
+//!
+//! # Globs excluded from the results in addition to --exclude.
+//! exclude = ["*.min.js", "vendor"]
+//!
+//! # Extra flags of rg used by the grep provider.
+//! grep_flags = ["--hidden"]
+//!
+//! # Bonus of the filter if --bonus is not specified: filename or acronym.
+//! bonus = "filename"
+//!
+//! # Extra pattern of the built-in symbol extraction for python files.
+//! [[dumb_analyzer.python]]
+//! kind = "function"
+//! regex = '^\s*cp?def\s+\w+\s+(\w+)\s*\('
+//! ```
+//!
+//! The command line options take precedence over the config file.
+//!
+//! The `exclude`, `grep_flags` and `bonus` keys can also be overridden per project by a
+//! `.maple.toml` in the working directory or any of its ancestors, the other keys of it are
+//! ignored.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(default)]
+pub struct Config {
+    /// Size of the rayon thread pool, 0 means the default.
+    pub threads: Option<usize>,
+    /// Compression of the cache files, see [`utility::compression::Compression`].
+    pub cache_compression: Option<String>,
+    /// Providers declared by the user, keyed by the provider name.
+    #[serde(rename = "provider")]
+    pub providers: HashMap<String, ProviderConfig>,
+    /// Patterns of the built-in symbol extraction, keyed by the filetype.
+    pub dumb_analyzer: HashMap<String, Vec<SymbolPattern>>,
+    /// Globs excluded from the results in addition to --exclude.
+    pub exclude: Vec<String>,
+    /// Extra flags of rg used by the grep provider.
+    pub grep_flags: Vec<String>,
+    /// Bonus of the filter if --bonus is not specified.
+    pub bonus: Option<String>,
+}
+
+/// A user-defined provider, whose source is the output of a shell command.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ProviderConfig {
+    /// Shell command to produce the source.
+    pub cmd: String,
+    /// Match type of the source lines, see [`filter::matcher::MatchType`].
+    #[serde(default)]
+    pub match_type: Option<String>,
+    /// Icon painter of the source lines, see [`icon::IconPainter`].
+    #[serde(default)]
+    pub icon: Option<String>,
+}
+
+/// A pattern of the built-in symbol extraction, see [`crate::dumb_analyzer`].
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SymbolPattern {
+    /// Kind of the symbol, e.g., function.
+    pub kind: String,
+    /// Regex of the line defining the symbol, the first group is the symbol name.
+    pub regex: String,
+}
+
+/// Returns the default path of the config file.
+pub fn default_config_path() -> Option<PathBuf> {
+    std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .or_else(|| utility::paths::home_dir().map(|home| home.join(".config")))
+        .map(|dir| dir.join("vim-clap").join("config.toml"))
+}
+
+/// Name of the project config file.
+pub const PROJECT_CONFIG_FILE: &str = ".maple.toml";
+
+/// Flags of rg running the other commands, not allowed in the project config.
+const UNSAFE_GREP_FLAGS: &[&str] = &["--pre", "--pre-glob"];
+
+/// Returns the project config file found by walking up from `dir`.
+pub fn find_project_config(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .map(|dir| dir.join(PROJECT_CONFIG_FILE))
+        .find(|path| path.is_file())
+}
+
+fn read_config(path: &Path) -> Result<Config> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read the config file {}", path.display()))?;
+    toml::from_str(&content).with_context(|| format!("Invalid config file {}", path.display()))
+}
+
+/// Returns `grep_flags` without the [`UNSAFE_GREP_FLAGS`] and their values, which are either
+/// given by `=` or the next flag, e.g., `--pre=sh` or `--pre`, `sh`.
+fn safe_grep_flags(grep_flags: Vec<String>) -> Vec<String> {
+    let mut safe_flags = Vec::with_capacity(grep_flags.len());
+    let mut grep_flags = grep_flags.into_iter();
+    while let Some(flag) = grep_flags.next() {
+        let name = flag.split('=').next().unwrap_or_default();
+        if UNSAFE_GREP_FLAGS.contains(&name) {
+            let value = if flag.contains('=') {
+                None
+            } else {
+                grep_flags.next()
+            };
+            log::warn!(
+                "Ignored {} {} of the project config",
+                flag,
+                value.unwrap_or_default()
+            );
+        } else {
+            safe_flags.push(flag);
+        }
+    }
+    safe_flags
+}
+
+impl Config {
+    /// Loads the config from `path` if specified, otherwise from the default path.
+    ///
+    /// It's fine for the default config file to not exist.
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        let path = match path {
+            Some(path) => path.to_path_buf(),
+            None => match default_config_path() {
+                Some(path) if path.exists() => path,
+                _ => return Ok(Default::default()),
+            },
+        };
+
+        read_config(&path)
+    }
+
+    /// Loads the config like [`Config::load`], then applies the project config of `dir` if any.
+    ///
+    /// A malformed project config is ignored, which shouldn't break maple in the project.
+    pub fn load_for_dir(path: Option<&Path>, dir: &Path) -> Result<Self> {
+        let mut config = Self::load(path)?;
+        if let Some(project_config_path) = find_project_config(dir) {
+            match read_config(&project_config_path) {
+                Ok(project) => config.merge_project(project),
+                Err(e) => log::warn!("Ignored the project config: {:?}", e),
+            }
+        }
+        Ok(config)
+    }
+
+    /// Overrides the keys supported by the project config with those of `project`.
+    fn merge_project(&mut self, project: Config) {
+        if !project.exclude.is_empty() {
+            self.exclude = project.exclude;
+        }
+        if !project.grep_flags.is_empty() {
+            self.grep_flags = safe_grep_flags(project.grep_flags);
+        }
+        if project.bonus.is_some() {
+            self.bonus = project.bonus;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_config() {
+        let config: Config = toml::from_str("threads = 4").unwrap();
+        assert_eq!(config.threads, Some(4));
+
+        let config: Config = toml::from_str("").unwrap();
+        assert_eq!(config.threads, None);
+        assert_eq!(config.cache_compression, None);
+
+        let config: Config = toml::from_str(r#"cache_compression = "gzip""#).unwrap();
+        assert_eq!(config.cache_compression.as_deref(), Some("gzip"));
+
+        let config: Config = toml::from_str(
+            r#"
+            [provider.my_todos]
+            cmd = "rg TODO"
+            match_type = "IgnoreFilePath"
+            "#,
+        )
+        .unwrap();
+        let provider = &config.providers["my_todos"];
+        assert_eq!(provider.cmd, "rg TODO");
+        assert_eq!(provider.match_type.as_deref(), Some("IgnoreFilePath"));
+        assert_eq!(provider.icon, None);
+
+        let config: Config = toml::from_str(
+            r#"
+            [[dumb_analyzer.make]]
+            kind = "rule"
+            regex = '^(\w+)\s*:'
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.dumb_analyzer["make"][0].kind, "rule");
+    }
+
+    #[test]
+    fn test_project_config() {
+        let root = std::env::temp_dir().join(format!("clap_test_project_{}", std::process::id()));
+        let subdir = root.join("crates").join("foo");
+        std::fs::create_dir_all(&subdir).unwrap();
+        std::fs::write(
+            root.join(PROJECT_CONFIG_FILE),
+            r#"
+            exclude = ["vendor"]
+            grep_flags = ["--hidden", "--pre=sh"]
+            threads = 1
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            find_project_config(&subdir),
+            Some(root.join(PROJECT_CONFIG_FILE))
+        );
+
+        let mut config: Config = toml::from_str(
+            r#"
+            exclude = ["*.min.js"]
+            bonus = "filename"
+            "#,
+        )
+        .unwrap();
+        config.merge_project(read_config(&root.join(PROJECT_CONFIG_FILE)).unwrap());
+        assert_eq!(config.exclude, vec!["vendor"]);
+        assert_eq!(config.grep_flags, vec!["--hidden"]);
+        assert_eq!(config.bonus.as_deref(), Some("filename"));
+        assert_eq!(config.threads, None);
+
+        let flags = |flags: &[&str]| flags.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            safe_grep_flags(flags(&["--pre", "sh", "--hidden", "--pre-glob", "*.gz"])),
+            flags(&["--hidden"])
+        );
+
+        // The malformed project config is ignored.
+        std::fs::write(root.join(PROJECT_CONFIG_FILE), "exclude = [").unwrap();
+        let global_config = root.join("config.toml");
+        std::fs::write(&global_config, "exclude = [\"*.min.js\"]").unwrap();
+        let config = Config::load_for_dir(Some(&global_config), &subdir).unwrap();
+        assert_eq!(config.exclude, vec!["*.min.js"]);
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
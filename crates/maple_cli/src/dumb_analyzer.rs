@@ -0,0 +1,488 @@
+//! Lightweight symbol extraction by regex, for the environments without universal-ctags.
+//!
+//! Each language has a list of `(kind, regex)` patterns tried in order on every line, the first
+//! group of the regex is the symbol name. More patterns can be added per filetype in the config
+//! file, which are tried before the built-in ones:
+//!
+//! ```toml
+//! [[dumb_analyzer.python]]
+//! kind = "function"
+//! regex = '^\s*cp?def\s+\w+\s+(\w+)\s*\('
+//! ```
+
+use std::collections::HashMap;
+
+use anyhow::{anyhow, bail, Context, Result};
+use once_cell::sync::OnceCell;
+use regex::Regex;
+
+use crate::config::SymbolPattern;
+
+/// Patterns from the config file, keyed by the filetype.
+static USER_PATTERNS: OnceCell<HashMap<String, Vec<(String, Regex)>>> = OnceCell::new();
+
+/// Compiles the patterns of the config file, the invalid ones are logged and skipped.
+fn compile_user_patterns(
+    patterns: &HashMap<String, Vec<SymbolPattern>>,
+) -> HashMap<String, Vec<(String, Regex)>> {
+    patterns
+        .iter()
+        .map(|(filetype, patterns)| {
+            let patterns = patterns
+                .iter()
+                .filter_map(|pattern| match compile(&pattern.kind, &pattern.regex) {
+                    Ok(compiled) => Some(compiled),
+                    Err(e) => {
+                        log::warn!(
+                            "Skipped the {} pattern of {}: {:?}",
+                            pattern.kind,
+                            filetype,
+                            e
+                        );
+                        None
+                    }
+                })
+                .collect();
+            (filetype.clone(), patterns)
+        })
+        .collect()
+}
+
+/// Sets the patterns of the config file, which take precedence over the built-in ones.
+pub fn set_user_patterns(patterns: &HashMap<String, Vec<SymbolPattern>>) {
+    let _ = USER_PATTERNS.set(compile_user_patterns(patterns));
+}
+
+fn compile(kind: &str, regex: &str) -> Result<(String, Regex)> {
+    let re = Regex::new(regex)
+        .with_context(|| format!("Invalid regex of the {} symbol: {}", kind, regex))?;
+    if re.captures_len() < 2 {
+        bail!("No group of the symbol name in the regex {}", regex);
+    }
+    Ok((kind.to_string(), re))
+}
+
+/// Returns the built-in patterns of `filetype` in the form of (kind, regex).
+fn builtin_patterns(filetype: &str) -> Option<&'static [(&'static str, &'static str)]> {
+    let patterns: &[(&str, &str)] = match filetype {
+        "rust" => &[
+            (
+                "function",
+                r#"^\s*(?:pub(?:\([^)]*\))?\s+)?(?:const\s+)?(?:async\s+)?(?:unsafe\s+)?(?:extern\s+"[^"]*"\s+)?fn\s+(\w+)"#,
+            ),
+            ("struct", r"^\s*(?:pub(?:\([^)]*\))?\s+)?struct\s+(\w+)"),
+            ("enum", r"^\s*(?:pub(?:\([^)]*\))?\s+)?enum\s+(\w+)"),
+            (
+                "trait",
+                r"^\s*(?:pub(?:\([^)]*\))?\s+)?(?:unsafe\s+)?trait\s+(\w+)",
+            ),
+            ("type", r"^\s*(?:pub(?:\([^)]*\))?\s+)?type\s+(\w+)"),
+            ("module", r"^\s*(?:pub(?:\([^)]*\))?\s+)?mod\s+(\w+)"),
+            ("macro", r"^\s*macro_rules!\s+(\w+)"),
+            (
+                "constant",
+                r"^\s*(?:pub(?:\([^)]*\))?\s+)?(?:const|static)\s+(?:mut\s+)?(\w+)\s*:",
+            ),
+        ],
+        "python" => &[
+            ("class", r"^\s*class\s+(\w+)"),
+            ("function", r"^\s*(?:async\s+)?def\s+(\w+)"),
+        ],
+        "go" => &[
+            ("function", r"^func\s+(?:\([^)]*\)\s*)?(\w+)"),
+            ("type", r"^type\s+(\w+)"),
+        ],
+        "javascript" | "javascriptreact" | "typescript" | "typescriptreact" => &[
+            (
+                "function",
+                r"^\s*(?:export\s+)?(?:default\s+)?(?:async\s+)?function\s*\*?\s*(\w+)",
+            ),
+            (
+                "class",
+                r"^\s*(?:export\s+)?(?:default\s+)?(?:abstract\s+)?class\s+(\w+)",
+            ),
+            ("interface", r"^\s*(?:export\s+)?interface\s+(\w+)"),
+            (
+                "function",
+                r"^\s*(?:export\s+)?(?:const|let|var)\s+(\w+)\s*=\s*(?:async\s*)?(?:function|\([^)]*\)\s*=>|\w+\s*=>)",
+            ),
+        ],
+        "vim" => &[
+            ("function", r"^\s*fu(?:nction)?!?\s+([\w:#.<>]+)"),
+            ("command", r"^\s*com(?:mand)?!?\s+(?:-\S+\s+)*([A-Z]\w*)"),
+        ],
+        "lua" => &[("function", r"^\s*(?:local\s+)?function\s+([\w.:]+)")],
+        "sh" | "zsh" => &[("function", r"^\s*(?:function\s+)?(\w+)\s*\(\)")],
+        // The definitions start at the first column unlike the statements.
+        "c" => &[
+            ("macro", r"^\s*#\s*define\s+(\w+)"),
+            ("struct", r"^\s*(?:typedef\s+)?struct\s+(\w+)\s*(?:\{|$)"),
+            ("enum", r"^\s*(?:typedef\s+)?enum\s+(\w+)\s*(?:\{|$)"),
+            ("typedef", r"^\s*typedef\s+.*\b(\w+)\s*;"),
+            (
+                "function",
+                r"^(?:(?:static|inline|extern|const|unsigned|signed|struct)\s+)*\w+[\s*]+(\w+)\s*\([^;]*$",
+            ),
+        ],
+        "cpp" => &[
+            ("macro", r"^\s*#\s*define\s+(\w+)"),
+            ("namespace", r"^\s*namespace\s+(\w+)"),
+            (
+                "class",
+                r"^\s*(?:template\s*<.*>\s*)?class\s+(\w+)\s*(?:[:{]|$)",
+            ),
+            (
+                "struct",
+                r"^\s*(?:template\s*<.*>\s*)?(?:typedef\s+)?struct\s+(\w+)\s*(?:[:{]|$)",
+            ),
+            ("enum", r"^\s*(?:typedef\s+)?enum\s+(?:class\s+)?(\w+)"),
+            ("function", r"^(?:[\w:<>*&]+[\s*&]+)+([\w:~]+)\s*\([^;]*$"),
+        ],
+        "java" => &[
+            (
+                "class",
+                r"^\s*(?:(?:public|protected|private|abstract|static|final|sealed)\s+)*class\s+(\w+)",
+            ),
+            (
+                "interface",
+                r"^\s*(?:(?:public|protected|private|abstract|static|sealed)\s+)*(?:interface|@interface)\s+(\w+)",
+            ),
+            (
+                "enum",
+                r"^\s*(?:(?:public|protected|private|static)\s+)*enum\s+(\w+)",
+            ),
+            (
+                "method",
+                r"^\s*(?:(?:public|protected|private|static|final|abstract|synchronized|native|default)\s+)+[\w<>\[\],.?\s]*?(\w+)\s*\(",
+            ),
+        ],
+        "kotlin" => &[
+            (
+                "class",
+                r"^\s*(?:(?:public|private|protected|internal|open|abstract|sealed|data|enum|inner|annotation)\s+)*(?:class|object)\s+(\w+)",
+            ),
+            (
+                "interface",
+                r"^\s*(?:(?:public|private|protected|internal|sealed|fun)\s+)*interface\s+(\w+)",
+            ),
+            (
+                "function",
+                r"^\s*(?:(?:public|private|protected|internal|override|open|abstract|suspend|inline|operator|infix|tailrec)\s+)*fun\s+(?:<[^>]*>\s*)?(?:[\w.]+\.)?(\w+)",
+            ),
+        ],
+        "swift" => &[
+            (
+                "function",
+                r"^\s*(?:(?:public|private|fileprivate|internal|open|static|class|override|mutating|final|@\w+)\s+)*func\s+(\w+)",
+            ),
+            (
+                "class",
+                r"^\s*(?:(?:public|private|fileprivate|internal|open|final)\s+)*class\s+(\w+)",
+            ),
+            (
+                "struct",
+                r"^\s*(?:(?:public|private|fileprivate|internal)\s+)*struct\s+(\w+)",
+            ),
+            (
+                "enum",
+                r"^\s*(?:(?:public|private|fileprivate|internal|indirect)\s+)*enum\s+(\w+)",
+            ),
+            (
+                "interface",
+                r"^\s*(?:(?:public|private|fileprivate|internal)\s+)*protocol\s+(\w+)",
+            ),
+        ],
+        "cs" => &[
+            ("namespace", r"^\s*namespace\s+([\w.]+)"),
+            (
+                "class",
+                r"^\s*(?:(?:public|private|protected|internal|static|abstract|sealed|partial)\s+)*class\s+(\w+)",
+            ),
+            (
+                "interface",
+                r"^\s*(?:(?:public|private|protected|internal|partial)\s+)*interface\s+(\w+)",
+            ),
+            (
+                "struct",
+                r"^\s*(?:(?:public|private|protected|internal|readonly|partial)\s+)*struct\s+(\w+)",
+            ),
+            (
+                "enum",
+                r"^\s*(?:(?:public|private|protected|internal)\s+)*enum\s+(\w+)",
+            ),
+            (
+                "method",
+                r"^\s*(?:(?:public|private|protected|internal|static|virtual|override|abstract|async|sealed|extern)\s+)+[\w<>\[\],.?\s]*?(\w+)\s*\(",
+            ),
+        ],
+        "scala" => &[
+            (
+                "class",
+                r"^\s*(?:(?:case|abstract|final|sealed|private|protected|implicit)\s+)*class\s+(\w+)",
+            ),
+            (
+                "trait",
+                r"^\s*(?:(?:sealed|private|protected)\s+)*trait\s+(\w+)",
+            ),
+            (
+                "module",
+                r"^\s*(?:(?:case|private|protected)\s+)*object\s+(\w+)",
+            ),
+            (
+                "function",
+                r"^\s*(?:(?:private|protected|override|final|implicit|inline)\s+)*def\s+(\w+)",
+            ),
+        ],
+        "ruby" => &[
+            ("class", r"^\s*class\s+([\w:]+)"),
+            ("module", r"^\s*module\s+([\w:]+)"),
+            ("method", r"^\s*def\s+(?:self\.)?(\w+[?!=]?)"),
+        ],
+        "php" => &[
+            (
+                "class",
+                r"^\s*(?:(?:abstract|final|readonly)\s+)*class\s+(\w+)",
+            ),
+            ("interface", r"^\s*interface\s+(\w+)"),
+            ("trait", r"^\s*trait\s+(\w+)"),
+            (
+                "function",
+                r"^\s*(?:(?:public|private|protected|static|abstract|final)\s+)*function\s+&?(\w+)",
+            ),
+        ],
+        "haskell" => &[
+            (
+                "type",
+                r"^(?:data|newtype|type)\s+(?:family\s+|instance\s+)?(\w+)",
+            ),
+            ("class", r"^class\s+(?:.*=>\s*)?(\w+)"),
+            ("function", r"^(\w+)\s*::"),
+        ],
+        "elixir" => &[
+            ("module", r"^\s*defmodule\s+([\w.]+)"),
+            ("macro", r"^\s*defmacrop?\s+(\w+[?!]?)"),
+            ("function", r"^\s*defp?\s+(\w+[?!]?)"),
+        ],
+        _ => return None,
+    };
+    Some(patterns)
+}
+
+/// A symbol found by [`Analyzer`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Symbol {
+    pub name: String,
+    pub kind: String,
+    /// 1-based line number.
+    pub line: usize,
+}
+
+/// Symbol extraction of a filetype.
+#[derive(Debug, Clone)]
+pub struct Analyzer {
+    patterns: Vec<(String, Regex)>,
+}
+
+impl Analyzer {
+    /// Creates the analyzer of `filetype`, the patterns of config file are tried first.
+    pub fn new(filetype: &str) -> Result<Self> {
+        let user_patterns = USER_PATTERNS
+            .get()
+            .and_then(|patterns| patterns.get(filetype))
+            .cloned()
+            .unwrap_or_default();
+        Self::with_patterns(filetype, user_patterns)
+    }
+
+    fn with_patterns(filetype: &str, mut patterns: Vec<(String, Regex)>) -> Result<Self> {
+        if let Some(builtin) = builtin_patterns(filetype) {
+            for (kind, regex) in builtin {
+                patterns.push(compile(kind, regex)?);
+            }
+        }
+        if patterns.is_empty() {
+            return Err(anyhow!("No symbol patterns for {}", filetype));
+        }
+        Ok(Self { patterns })
+    }
+
+    /// Returns the kind and name of the symbol defined in `line`, the first pattern wins.
+    pub fn symbol_of<'a>(&'a self, line: &str) -> Option<(&'a str, String)> {
+        self.patterns.iter().find_map(|(kind, re)| {
+            re.captures(line)
+                .and_then(|caps| caps.get(1))
+                .map(|name| (kind.as_str(), name.as_str().to_string()))
+        })
+    }
+
+    /// Returns the symbols of `content` line by line, at most one symbol per line.
+    pub fn analyze(&self, content: &str) -> Vec<Symbol> {
+        content
+            .lines()
+            .enumerate()
+            .filter_map(|(idx, line)| {
+                self.symbol_of(line).map(|(kind, name)| Symbol {
+                    name,
+                    kind: kind.to_string(),
+                    line: idx + 1,
+                })
+            })
+            .collect()
+    }
+
+    /// Returns the kind of the definition of `word` in `line`, e.g., for telling the
+    /// definitions from the usages of dumb-jump.
+    ///
+    /// The qualified names like `Foo::bar` or `clap#foo` are compared by the last segment.
+    pub fn definition_kind<'a>(&'a self, line: &str, word: &str) -> Option<&'a str> {
+        self.symbol_of(line).and_then(|(kind, name)| {
+            let last_segment = name.rsplit(|c| matches!(c, ':' | '.' | '#')).next();
+            if name == word || last_segment == Some(word) {
+                Some(kind)
+            } else {
+                None
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn symbols(filetype: &str, content: &str) -> Vec<(String, String, usize)> {
+        Analyzer::new(filetype)
+            .unwrap()
+            .analyze(content)
+            .into_iter()
+            .map(|symbol| (symbol.name, symbol.kind, symbol.line))
+            .collect()
+    }
+
+    #[test]
+    fn test_analyze() {
+        let content = r#"
+pub(crate) struct Foo {
+    bar: usize,
+}
+
+impl Foo {
+    pub async fn new() -> Self {
+        todo!()
+    }
+}
+
+macro_rules! foo {
+    () => {};
+}
+
+const MAX_LEN: usize = 1;
+"#;
+        assert_eq!(
+            symbols("rust", content),
+            vec![
+                ("Foo".into(), "struct".into(), 2),
+                ("new".into(), "function".into(), 7),
+                ("foo".into(), "macro".into(), 12),
+                ("MAX_LEN".into(), "constant".into(), 16),
+            ]
+        );
+
+        let tags = symbols(
+            "vim",
+            "function! clap#foo() abort\ncommand! -nargs=* Clap call x()",
+        );
+        assert_eq!(tags[0].0, "clap#foo");
+        assert_eq!(tags[1].0, "Clap");
+
+        let content = r#"
+#define MAX_LEN 10
+static int parse(const char *s) {
+    if (s == NULL) {
+        return 0;
+    }
+}
+"#;
+        assert_eq!(
+            symbols("c", content),
+            vec![
+                ("MAX_LEN".into(), "macro".into(), 2),
+                ("parse".into(), "function".into(), 3),
+            ]
+        );
+
+        let content = r#"
+public class Foo {
+    private static List<String> bar(int x) {
+        return baz(x);
+    }
+}
+"#;
+        assert_eq!(
+            symbols("java", content),
+            vec![
+                ("Foo".into(), "class".into(), 2),
+                ("bar".into(), "method".into(), 3),
+            ]
+        );
+
+        assert_eq!(
+            symbols("ruby", "module Clap\n  def self.run?\n  end\nend"),
+            vec![
+                ("Clap".into(), "module".into(), 1),
+                ("run?".into(), "method".into(), 2),
+            ]
+        );
+
+        assert!(Analyzer::new("cobol").is_err());
+    }
+
+    #[test]
+    fn test_user_patterns() {
+        let user_patterns = vec![compile("function", r"^\s*cpdef\s+\w+\s+(\w+)").unwrap()];
+        let analyzer = Analyzer::with_patterns("python", user_patterns).unwrap();
+        assert_eq!(
+            analyzer.symbol_of("cpdef int fib(int n):"),
+            Some(("function", "fib".into()))
+        );
+        assert_eq!(
+            analyzer.symbol_of("class Foo:"),
+            Some(("class", "Foo".into()))
+        );
+
+        // A new filetype.
+        let user_patterns = vec![compile("rule", r"^(\w+)\s*:").unwrap()];
+        let analyzer = Analyzer::with_patterns("make", user_patterns).unwrap();
+        assert_eq!(
+            analyzer.definition_kind("build: deps", "build"),
+            Some("rule")
+        );
+        assert_eq!(analyzer.definition_kind("build: deps", "deps"), None);
+
+        assert!(compile("function", r"^def \w+").is_err());
+        assert!(compile("function", r"^def (\w+").is_err());
+
+        let pattern = |regex: &str| SymbolPattern {
+            kind: "function".into(),
+            regex: regex.into(),
+        };
+        let mut patterns = HashMap::new();
+        patterns.insert(
+            "python".to_string(),
+            vec![pattern(r"^def (\w+"), pattern(r"^\s*cpdef\s+\w+\s+(\w+)")],
+        );
+        let compiled = compile_user_patterns(&patterns);
+        assert_eq!(compiled["python"].len(), 1);
+        assert_eq!(compiled["python"][0].1.as_str(), r"^\s*cpdef\s+\w+\s+(\w+)");
+    }
+
+    #[test]
+    fn test_definition_kind() {
+        let analyzer = Analyzer::new("cpp").unwrap();
+        assert_eq!(
+            analyzer.definition_kind("void Foo::bar(int x) {", "bar"),
+            Some("function")
+        );
+        assert_eq!(analyzer.definition_kind("  bar(1);", "bar"), None);
+    }
+}
@@ -0,0 +1,22 @@
+//! Handler of Ctrl-C and SIGTERM during the sync filtering, see [`filter::interrupt`].
+
+use printer::warning::Warning;
+
+/// Exit code of a process terminated by SIGINT.
+const INTERRUPTED_EXIT_CODE: i32 = 130;
+
+/// Lets the first Ctrl-C or kill stop the sync filtering and print the partial results, the
+/// second one exits immediately.
+pub fn catch_interrupt() {
+    let result = ctrlc::set_handler(|| {
+        if filter::interrupt::is_interrupted() {
+            std::process::exit(INTERRUPTED_EXIT_CODE);
+        }
+        log::debug!("Interrupted, printing the partial results");
+        printer::warning::warn(Warning::Interrupted);
+        filter::interrupt::interrupt();
+    });
+    if let Err(e) = result {
+        log::debug!("Failed to set the interrupt handler: {}", e);
+    }
+}
@@ -1,15 +1,23 @@
 mod app;
+mod cache_watcher;
+mod config;
+mod dumb_analyzer;
+mod interrupt;
 mod logger;
+mod params;
 mod process;
+mod session_cache;
 mod stdio_server;
 mod tools;
 
+pub mod api;
 pub mod cmd;
 /// Re-exports.
 pub use {
     anyhow::{Context, Result},
-    app::{Cmd, Maple},
+    app::{Cmd, Maple, Params},
     filter::{subprocess, Source},
     icon::IconPainter,
+    params::{ParamsBuilder, ParamsError},
     structopt::StructOpt,
 };
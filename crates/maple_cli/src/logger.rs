@@ -8,6 +8,13 @@ use log4rs::{
     encode::pattern::PatternEncoder,
 };
 
+/// Returns the log level specified by `RUST_LOG`, e.g., `RUST_LOG=trace`, debug by default.
+fn max_level(rust_log: Option<&str>) -> LevelFilter {
+    rust_log
+        .and_then(|level| level.trim().parse().ok())
+        .unwrap_or(LevelFilter::Debug)
+}
+
 pub fn init<P: AsRef<Path>>(log_path: P) -> Result<()> {
     let encoder = PatternEncoder::new(
         "{date(%Y-%m-%d %H:%M:%S)} {level} {thread} {file}:{line} {message}{n}",
@@ -20,14 +27,13 @@ pub fn init<P: AsRef<Path>>(log_path: P) -> Result<()> {
         .append(false)
         .build(log_path.as_ref())?;
 
-    // Log Trace level output to file where trace is the default level
-    // and the programmatically specified level to stderr.
+    // Log Debug level output to file by default, which can be changed by RUST_LOG.
     let config = Config::builder()
         .appender(Appender::builder().build("vim-clap", Box::new(log_file)))
         .build(
             Root::builder()
                 .appender("vim-clap")
-                .build(LevelFilter::Debug),
+                .build(max_level(std::env::var("RUST_LOG").ok().as_deref())),
         )?;
 
     // Use this to change log levels at runtime.
@@ -41,3 +47,16 @@ pub fn init<P: AsRef<Path>>(log_path: P) -> Result<()> {
     );
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_max_level() {
+        assert_eq!(max_level(None), LevelFilter::Debug);
+        assert_eq!(max_level(Some("TRACE")), LevelFilter::Trace);
+        assert_eq!(max_level(Some("off")), LevelFilter::Off);
+        assert_eq!(max_level(Some("maple=info")), LevelFilter::Debug);
+    }
+}
@@ -0,0 +1,305 @@
+//! Validation of the combinations of the command line options.
+//!
+//! The invalid combinations are reported as [`ParamsError`] before running the subcommand, which
+//! is printed as the `error` of the JSON output too, so that the frontend can show it to the user
+//! instead of the results being silently wrong.
+
+use serde::Serialize;
+
+use crate::app::{Cmd, Params};
+
+/// An invalid combination of the command line options.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct ParamsError {
+    /// The flags involved, e.g., `--sync` and `--grep-cmd`.
+    pub flags: Vec<&'static str>,
+    pub message: String,
+}
+
+impl ParamsError {
+    pub fn new(flags: &[&'static str], message: impl Into<String>) -> Self {
+        Self {
+            flags: flags.to_vec(),
+            message: message.into(),
+        }
+    }
+
+    /// Prints the error as the `error` of JSON.
+    pub fn print(&self) {
+        printer::println_payload(serde_json::json!({ "error": self }));
+    }
+}
+
+impl std::fmt::Display for ParamsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for ParamsError {}
+
+/// Builder of [`Params`] parsed from the command line, which are validated against the
+/// subcommand by [`Self::build`].
+#[derive(Debug, Default)]
+pub struct ParamsBuilder {
+    params: Params,
+}
+
+impl From<Params> for ParamsBuilder {
+    fn from(params: Params) -> Self {
+        Self { params }
+    }
+}
+
+impl ParamsBuilder {
+    /// Returns the params if they are valid, along with the options of `cmd`.
+    pub fn build(self, cmd: &Cmd) -> Result<Params, ParamsError> {
+        let params = self.params;
+        if params.number == Some(0) {
+            return Err(ParamsError::new(
+                &["--number"],
+                "--number must be greater than 0",
+            ));
+        }
+        if params.max_line_len == Some(0) {
+            return Err(ParamsError::new(
+                &["--max-line-len"],
+                "--max-line-len must be greater than 0",
+            ));
+        }
+        cmd.validate(&params)?;
+        Ok(params)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::Maple;
+    use structopt::StructOpt;
+
+    fn build(args: &[&str]) -> Result<Params, ParamsError> {
+        let maple = Maple::from_iter(std::iter::once("maple").chain(args.iter().copied()));
+        ParamsBuilder::from(maple.params).build(&maple.command)
+    }
+
+    #[test]
+    fn test_build_params() {
+        assert_eq!(
+            build(&["--number", "10", "version"]).unwrap().number,
+            Some(10)
+        );
+        assert_eq!(
+            build(&["--number", "0", "version"]).unwrap_err().flags,
+            vec!["--number"]
+        );
+        assert_eq!(
+            build(&["grep", "foo", "--sync"]).unwrap_err().flags,
+            vec!["--sync", "--grep-cmd"]
+        );
+        assert_eq!(
+            build(&["grep", "foo", "--ignore-case", "--case-sensitive"])
+                .unwrap_err()
+                .flags,
+            vec!["--ignore-case", "--case-sensitive"]
+        );
+        assert_eq!(
+            build(&["filter", "foo", "--profile"]).unwrap_err().flags,
+            vec!["--profile", "--sync"]
+        );
+        assert!(ParamsBuilder::default().build(&Cmd::Version).is_ok());
+    }
+
+    #[test]
+    fn test_filter_session_id_without_sync() {
+        assert_eq!(
+            build(&["filter", "foo", "--session-id", "1"])
+                .unwrap_err()
+                .flags,
+            vec!["--session-id", "--sync"]
+        );
+    }
+
+    #[test]
+    fn test_filter_stdin_tee_without_sync() {
+        assert_eq!(
+            build(&["filter", "foo", "--stdin-tee"]).unwrap_err().flags,
+            vec!["--stdin-tee", "--sync"]
+        );
+    }
+
+    #[test]
+    fn test_filter_queries_file_without_sync() {
+        assert_eq!(
+            build(&["filter", "foo", "--queries-file", "/tmp/queries"])
+                .unwrap_err()
+                .flags,
+            vec!["--queries-file", "--sync"]
+        );
+    }
+
+    #[test]
+    fn test_filter_score_cache_without_sync() {
+        assert_eq!(
+            build(&["filter", "foo", "--score-cache", "--input", "/tmp/input"])
+                .unwrap_err()
+                .flags,
+            vec!["--score-cache", "--sync"]
+        );
+    }
+
+    #[test]
+    fn test_filter_indices_only_without_sync() {
+        assert_eq!(
+            build(&["filter", "foo", "--indices-only"])
+                .unwrap_err()
+                .flags,
+            vec!["--indices-only", "--sync"]
+        );
+    }
+
+    #[test]
+    fn test_filter_score_cache_without_input() {
+        assert_eq!(
+            build(&["filter", "foo", "--sync", "--score-cache"])
+                .unwrap_err()
+                .flags,
+            vec!["--score-cache", "--input"]
+        );
+    }
+
+    #[test]
+    fn test_filter_score_cache_with_display_delimiter() {
+        assert_eq!(
+            build(&[
+                "filter",
+                "foo",
+                "--sync",
+                "--score-cache",
+                "--input",
+                "/tmp/input",
+                "--display-delimiter",
+                ","
+            ])
+            .unwrap_err()
+            .flags,
+            vec!["--score-cache", "--display-delimiter"]
+        );
+    }
+
+    #[test]
+    fn test_filter_stable_order_without_session_id() {
+        assert_eq!(
+            build(&["filter", "foo", "--sync", "--stable-order"])
+                .unwrap_err()
+                .flags,
+            vec!["--stable-order", "--session-id"]
+        );
+    }
+
+    #[test]
+    fn test_filter_stable_order_not_sorted_by_score() {
+        assert_eq!(
+            build(&[
+                "filter",
+                "foo",
+                "--sync",
+                "--session-id",
+                "1",
+                "--stable-order",
+                "--sort-by",
+                "path"
+            ])
+            .unwrap_err()
+            .flags,
+            vec!["--stable-order", "--sort-by"]
+        );
+    }
+
+    #[test]
+    fn test_filter_snapshot_with_sync() {
+        assert_eq!(
+            build(&["filter", "foo", "--sync", "--snapshot", "100"])
+                .unwrap_err()
+                .flags,
+            vec!["--snapshot", "--sync"]
+        );
+    }
+
+    #[test]
+    fn test_filter_follow_with_sync() {
+        assert_eq!(
+            build(&[
+                "filter",
+                "foo",
+                "--sync",
+                "--follow",
+                "--input",
+                "/tmp/input"
+            ])
+            .unwrap_err()
+            .flags,
+            vec!["--follow", "--sync"]
+        );
+    }
+
+    #[test]
+    fn test_filter_follow_without_input() {
+        assert_eq!(
+            build(&["filter", "foo", "--follow"]).unwrap_err().flags,
+            vec!["--follow", "--input"]
+        );
+    }
+
+    #[test]
+    fn test_grep_context_without_sync() {
+        assert_eq!(
+            build(&["grep", "foo", "-C", "2"]).unwrap_err().flags,
+            vec!["--context", "--sync"]
+        );
+    }
+
+    #[test]
+    fn test_grep_after_context_without_sync() {
+        assert_eq!(
+            build(&["grep", "foo", "-A", "2"]).unwrap_err().flags,
+            vec!["--after-context", "--sync"]
+        );
+    }
+
+    #[test]
+    fn test_grep_before_context_without_sync() {
+        assert_eq!(
+            build(&["grep", "foo", "-B", "2"]).unwrap_err().flags,
+            vec!["--before-context", "--sync"]
+        );
+    }
+
+    #[test]
+    fn test_grep_session_id_without_sync() {
+        assert_eq!(
+            build(&["grep", "foo", "--session-id", "1"])
+                .unwrap_err()
+                .flags,
+            vec!["--session-id", "--sync"]
+        );
+    }
+
+    #[test]
+    fn test_grep_sort_by_with_sync() {
+        assert_eq!(
+            build(&[
+                "grep",
+                "foo",
+                "--sync",
+                "--grep-cmd",
+                "rg",
+                "--sort-by",
+                "path"
+            ])
+            .unwrap_err()
+            .flags,
+            vec!["--sort-by", "--sync"]
+        );
+    }
+}
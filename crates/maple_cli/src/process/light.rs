@@ -1,16 +1,33 @@
 //! Wrapper of std `Command` with some optimization about the output.
 
 use std::fs::File;
-use std::io::Write;
-use std::path::PathBuf;
-use std::process::{Command, Output};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::Duration;
 
 use anyhow::{anyhow, Result};
+use structopt::StructOpt;
 
+use filter::subprocess::Exec;
 use icon::IconPainter;
-use utility::{get_cached_entry, println_json, read_first_lines, remove_dir_contents};
+use printer::warning::Warning;
+use utility::{println_json, read_first_lines};
 
-use crate::cmd::cache::CacheEntry;
+use crate::cmd::cache::{cache_exists, CacheEntry, CacheMeta};
+
+/// Maximum number of the stderr lines reported as a warning.
+const MAX_STDERR_LINES: usize = 20;
+
+/// Interval of checking the interruption while the command produces no output.
+const INTERRUPT_CHECK_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Returns the last [`MAX_STDERR_LINES`] lines of `stderr`, the latest progress info is kept.
+fn stderr_tail(stderr: &str) -> String {
+    let lines = stderr.trim_end().lines().collect::<Vec<_>>();
+    lines[lines.len().saturating_sub(MAX_STDERR_LINES)..].join("\n")
+}
 
 /// Remove the last element if it's empty string.
 #[inline]
@@ -25,14 +42,103 @@ fn trim_trailing(lines: &mut Vec<String>) {
 
 pub fn set_current_dir(cmd: &mut Command, cmd_dir: Option<PathBuf>) {
     if let Some(cmd_dir) = cmd_dir {
+        // The verbatim path is not supported by cmd as the working directory.
+        let mut cmd_dir = utility::paths::simplified(&cmd_dir).into_owned();
         // If cmd_dir is not a directory, use its parent as current dir.
-        if cmd_dir.is_dir() {
-            cmd.current_dir(cmd_dir);
-        } else {
-            let mut cmd_dir = cmd_dir;
+        if !utility::paths::long_path(&cmd_dir).is_dir() {
             cmd_dir.pop();
-            cmd.current_dir(cmd_dir);
         }
+        cmd.current_dir(cmd_dir);
+    }
+}
+
+/// Parses the `KEY=VALUE` of `--env`, VALUE can be empty.
+fn parse_env_var(s: &str) -> std::result::Result<(String, String), String> {
+    match s.split_once('=') {
+        Some((key, value)) if !key.is_empty() => Ok((key.into(), value.into())),
+        _ => Err(format!("invalid KEY=VALUE: {}", s)),
+    }
+}
+
+/// Environment of the spawned command, which is inherited from maple by default.
+#[derive(StructOpt, Debug, Clone, Default)]
+pub struct EnvOptions {
+    /// Set the environment variable KEY=VALUE for the command, can be specified multiple times.
+    ///
+    /// e.g., `--env RIPGREP_CONFIG_PATH= --env GIT_OPTIONAL_LOCKS=0`.
+    #[structopt(long = "env", number_of_values = 1, parse(try_from_str = parse_env_var))]
+    pub vars: Vec<(String, String)>,
+
+    /// Do not inherit the environment, only the variables of `--env` are set for the command.
+    ///
+    /// Pass `PATH` via `--env` if the command can't be found without it.
+    #[structopt(long = "clear-env")]
+    pub clear: bool,
+}
+
+impl EnvOptions {
+    /// Returns true if the command simply inherits the environment of maple.
+    pub fn is_inherited(&self) -> bool {
+        !self.clear && self.vars.is_empty()
+    }
+
+    pub fn apply(&self, cmd: &mut Command) {
+        if self.clear {
+            cmd.env_clear();
+        }
+        cmd.envs(self.vars.iter().map(|(key, value)| (key, value)));
+    }
+
+    pub fn apply_to_exec(&self, exec: Exec) -> Exec {
+        let exec = if self.clear { exec.env_clear() } else { exec };
+        self.vars
+            .iter()
+            .fold(exec, |exec, (key, value)| exec.env(key, value))
+    }
+
+    /// Returns the extra args for identifying the cache, the output may vary with the environment.
+    pub fn cache_args(&self) -> Vec<String> {
+        let mut args = self
+            .vars
+            .iter()
+            .map(|(key, value)| format!("--env={}={}", key, value))
+            .collect::<Vec<_>>();
+        if self.clear {
+            args.push("--clear-env".into());
+        }
+        args
+    }
+}
+
+/// Lowers the scheduling priority of the process spawned by `cmd`.
+///
+/// Used by the background jobs, e.g., the forerunner job creating the cache, so that they
+/// don't compete with the editor for CPU time.
+pub fn set_low_priority(cmd: &mut Command) {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+
+        /// Niceness increment of the spawned process.
+        const NICE_INCREMENT: libc::c_int = 10;
+
+        // SAFETY: `nice` is async-signal-safe, the failure is ignored so that the command is
+        // still executed with the normal priority.
+        unsafe {
+            cmd.pre_exec(|| {
+                libc::nice(NICE_INCREMENT);
+                Ok(())
+            });
+        }
+    }
+
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+
+        const BELOW_NORMAL_PRIORITY_CLASS: u32 = 0x0000_4000;
+
+        cmd.creation_flags(BELOW_NORMAL_PRIORITY_CLASS);
     }
 }
 
@@ -47,6 +153,31 @@ pub struct ExecutedInfo {
     pub using_cache: bool,
     /// Optional temp cache file for the whole output.
     pub tempfile: Option<PathBuf>,
+    /// Set if the command failed after producing some output, which is then partial.
+    pub failure: Option<Failure>,
+}
+
+/// Failure of a command which is not fatal since the output is not empty.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Failure {
+    /// Exit code of the command, `None` if it was terminated by a signal.
+    pub exit_code: Option<i32>,
+    /// Last lines of the stderr.
+    pub error_summary: String,
+}
+
+impl Failure {
+    /// Returns true if the output is complete despite the failure, i.e., rg exited with 1 for
+    /// no match, the other exit codes are the errors which may have left part of the output.
+    pub fn is_complete_output(&self) -> bool {
+        matches!(self.exit_code, Some(0) | Some(1))
+    }
+
+    /// Adds `exit_code` and `error_summary` to the results `payload`.
+    pub fn attach_to(&self, payload: &mut serde_json::Value) {
+        payload["exit_code"] = serde_json::json!(self.exit_code);
+        payload["error_summary"] = serde_json::json!(self.error_summary);
+    }
 }
 
 impl ExecutedInfo {
@@ -57,23 +188,24 @@ impl ExecutedInfo {
             tempfile,
             total,
             lines,
+            failure,
         } = self;
 
-        if self.using_cache {
-            if self.tempfile.is_some() {
-                if self.lines.is_empty() {
-                    println_json!(using_cache, tempfile, total);
-                } else {
-                    println_json!(using_cache, tempfile, total, lines);
-                }
-            } else {
-                println_json!(total, lines);
+        let mut payload = serde_json::json!({ "total": total });
+        // The lines have been printed already, e.g., by `LightCommand::execute_tee`.
+        if !(tempfile.is_some() && lines.is_empty()) {
+            payload["lines"] = serde_json::json!(lines);
+        }
+        if tempfile.is_some() {
+            payload["tempfile"] = serde_json::json!(tempfile);
+            if *using_cache {
+                payload["using_cache"] = serde_json::json!(using_cache);
             }
-        } else if self.tempfile.is_some() {
-            println_json!(tempfile, total, lines);
-        } else {
-            println_json!(total, lines);
         }
+        if let Some(failure) = failure {
+            failure.attach_to(&mut payload);
+        }
+        printer::println_payload(payload);
     }
 }
 
@@ -86,6 +218,8 @@ pub struct CommandEnv {
     pub output: Option<String>,
     pub icon_painter: Option<IconPainter>,
     pub output_threshold: usize,
+    /// Print the top `number` lines as soon as they are read and always cache the whole output.
+    pub tee_output: bool,
 }
 
 impl Default for CommandEnv {
@@ -97,6 +231,7 @@ impl Default for CommandEnv {
             output: None,
             icon_painter: None,
             output_threshold: 100_000usize,
+            tee_output: false,
         }
     }
 }
@@ -150,14 +285,8 @@ impl CommandEnv {
     pub fn do_cache(&self, cmd_stdout: &[u8], args: &[&str]) -> Result<PathBuf> {
         let tempfile = self.new_cache_entry(args)?;
 
-        // Remove the other outdated cache file if there are any.
-        //
-        // There should be only one cache file in parent_dir at this moment.
-        if let Some(parent_dir) = tempfile.parent() {
-            remove_dir_contents(&parent_dir.to_path_buf())?;
-        }
-
-        File::create(&tempfile)?.write_all(cmd_stdout)?;
+        let meta = CacheMeta::new(args, self.dir.as_deref(), self.total);
+        CacheEntry::write(&tempfile, cmd_stdout, &meta)?;
 
         // FIXME find the nth newline index of stdout.
         // let _end = std::cmp::min(cmd_stdout.len(), 500);
@@ -203,18 +332,199 @@ impl<'a> LightCommand<'a> {
         }
     }
 
-    /// Collect the output of command, exit directly if any error happened.
-    fn output(&mut self) -> Result<Output> {
+    /// Collect the output of command, exit directly if it failed without any output.
+    fn output(&mut self) -> Result<(Output, Option<Failure>)> {
+        let started = std::time::Instant::now();
         let cmd_output = self.cmd.output()?;
+        log::debug!(
+            "Executed {:?} in {:?}, output: {} bytes",
+            self.cmd,
+            started.elapsed(),
+            cmd_output.stdout.len()
+        );
+        let failure = Self::check_failure(&cmd_output);
+        Ok((cmd_output, failure))
+    }
 
-        // vim-clap does not handle the stderr stream, we just pass the error info via stdout.
-        if !cmd_output.status.success() && !cmd_output.stderr.is_empty() {
-            let error = format!("{}", String::from_utf8_lossy(&cmd_output.stderr));
+    /// vim-clap does not handle the stderr stream, we just pass the error info via stdout.
+    ///
+    /// The stderr of a command exited successfully is not an error, which is reported as the
+    /// `warnings` of the results instead. The output of a failed command, e.g., permission
+    /// denied on part of the tree, is kept as the partial results along with the [`Failure`].
+    fn check_failure(cmd_output: &Output) -> Option<Failure> {
+        if cmd_output.stderr.is_empty() {
+            return None;
+        }
+        let stderr = String::from_utf8_lossy(&cmd_output.stderr);
+        if cmd_output.status.success() {
+            printer::warning::warn(Warning::Stderr {
+                message: stderr_tail(&stderr),
+            });
+            None
+        } else if cmd_output.stdout.is_empty() {
+            let error = format!("{}", stderr);
             println_json!(error);
             std::process::exit(1);
+        } else {
+            Some(Failure {
+                exit_code: cmd_output.status.code(),
+                error_summary: stderr_tail(&stderr),
+            })
+        }
+    }
+
+    /// Prints the top `number` lines as soon as they are read and caches the whole output, see
+    /// [`Self::execute_tee`].
+    pub fn tee_output(mut self, tee_output: bool) -> Self {
+        self.env.tee_output = tee_output;
+        self
+    }
+
+    /// Collect the output of command while writing the stdout to the partial cache entry.
+    ///
+    /// The partial cache entry can be read by the other maple process before the command finishes.
+    /// `on_read` is called with the stdout read so far after each read.
+    fn output_with_partial_cache(
+        &mut self,
+        partial: &Path,
+        mut on_read: impl FnMut(&[u8]),
+    ) -> Result<(Output, Option<Failure>)> {
+        let mut child = self
+            .cmd
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+
+        // Read stderr in another thread in case of the pipe is full and the child gets blocked.
+        let mut child_stderr = child.stderr.take();
+        let stderr_reader = std::thread::spawn(move || {
+            let mut stderr = Vec::new();
+            if let Some(ref mut child_stderr) = child_stderr {
+                let _ = child_stderr.read_to_end(&mut stderr);
+            }
+            stderr
+        });
+
+        let mut stdout = Vec::new();
+        let mut partial_file = File::create(partial)?;
+        if let Some(mut child_stdout) = child.stdout.take() {
+            let mut buf = [0u8; 8192];
+            loop {
+                let n = child_stdout.read(&mut buf)?;
+                if n == 0 {
+                    break;
+                }
+                partial_file.write_all(&buf[..n])?;
+                stdout.extend_from_slice(&buf[..n]);
+                on_read(&stdout);
+            }
+        }
+
+        let status = child.wait()?;
+        let stderr = stderr_reader
+            .join()
+            .map_err(|e| anyhow!("failed to read stderr: {:?}", e))?;
+
+        let cmd_output = Output {
+            status,
+            stdout,
+            stderr,
+        };
+
+        if !cmd_output.status.success() && !cmd_output.stderr.is_empty() {
+            let _ = std::fs::remove_file(partial);
         }
+        let failure = Self::check_failure(&cmd_output);
 
-        Ok(cmd_output)
+        Ok((cmd_output, failure))
+    }
+
+    /// Collect the output of command until it finishes or the filtering is interrupted, see
+    /// [`filter::interrupt`].
+    ///
+    /// The interrupted command is killed, the complete lines read so far are kept as the
+    /// partial output, which is not a [`Failure`].
+    fn output_interruptible(&mut self) -> Result<(Output, Option<Failure>)> {
+        let mut child = self
+            .cmd
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+
+        // Read stderr in another thread in case of the pipe is full and the child gets blocked.
+        let mut child_stderr = child.stderr.take();
+        let stderr_reader = std::thread::spawn(move || {
+            let mut stderr = Vec::new();
+            if let Some(ref mut child_stderr) = child_stderr {
+                let _ = child_stderr.read_to_end(&mut stderr);
+            }
+            stderr
+        });
+
+        // The blocking reads of stdout are moved to another thread as well, so that the
+        // interruption is noticed even if the command keeps silent for a long time.
+        let (tx, rx) = mpsc::channel();
+        let mut child_stdout = child.stdout.take();
+        std::thread::spawn(move || {
+            if let Some(ref mut child_stdout) = child_stdout {
+                let mut buf = [0u8; 8192];
+                loop {
+                    match child_stdout.read(&mut buf) {
+                        Ok(0) | Err(_) => break,
+                        Ok(n) => {
+                            if tx.send(buf[..n].to_vec()).is_err() {
+                                break;
+                            }
+                        }
+                    }
+                }
+            }
+        });
+
+        let mut stdout = Vec::new();
+        let mut interrupted = false;
+        loop {
+            if filter::interrupt::is_interrupted() {
+                let _ = child.kill();
+                interrupted = true;
+                break;
+            }
+            match rx.recv_timeout(INTERRUPT_CHECK_INTERVAL) {
+                Ok(chunk) => stdout.extend_from_slice(&chunk),
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+        }
+
+        let status = child.wait()?;
+        let stderr = stderr_reader
+            .join()
+            .map_err(|e| anyhow!("failed to read stderr: {:?}", e))?;
+
+        if interrupted {
+            log::debug!("Killed {:?} on interrupt", self.cmd);
+            // The last line may be cut in the middle.
+            let complete = stdout
+                .iter()
+                .rposition(|&b| b == b'\n')
+                .map_or(0, |i| i + 1);
+            stdout.truncate(complete);
+            let cmd_output = Output {
+                status,
+                stdout,
+                stderr,
+            };
+            return Ok((cmd_output, None));
+        }
+
+        let cmd_output = Output {
+            status,
+            stdout,
+            stderr,
+        };
+        let failure = Self::check_failure(&cmd_output);
+
+        Ok((cmd_output, failure))
     }
 
     /// Normally we only care about the top N items and number of total results if it's not a
@@ -231,6 +541,7 @@ impl<'a> LightCommand<'a> {
                 lines,
                 using_cache: false,
                 tempfile: None,
+                failure: None,
             });
         }
         Err(anyhow!(
@@ -266,25 +577,24 @@ impl<'a> LightCommand<'a> {
         args: &[&str],
         cmd_dir: PathBuf,
     ) -> Result<ExecutedInfo> {
-        if let Ok(cached_entry) = get_cached_entry(args, &cmd_dir) {
-            if let Ok(total) = CacheEntry::get_total(&cached_entry) {
-                let tempfile = cached_entry.path();
-                let lines = if let Ok(lines_iter) = read_first_lines(&tempfile, 100) {
-                    if let Some(ref painter) = self.env.icon_painter {
-                        lines_iter.map(|x| painter.paint(&x)).collect()
-                    } else {
-                        lines_iter.collect()
-                    }
+        if let Ok((tempfile, total)) = cache_exists(args, &cmd_dir) {
+            log::debug!("Using the cache {} of {:?}", tempfile.display(), args);
+            let lines = if let Ok(lines_iter) = read_first_lines(&tempfile, 100) {
+                if let Some(ref painter) = self.env.icon_painter {
+                    lines_iter.map(|x| painter.paint(&x)).collect()
                 } else {
-                    vec![]
-                };
-                return Ok(ExecutedInfo {
-                    using_cache: true,
-                    total,
-                    tempfile: Some(tempfile),
-                    lines,
-                });
-            }
+                    lines_iter.collect()
+                }
+            } else {
+                vec![]
+            };
+            return Ok(ExecutedInfo {
+                using_cache: true,
+                total,
+                tempfile: Some(tempfile),
+                lines,
+                failure: None,
+            });
         }
 
         self.env.dir = Some(cmd_dir);
@@ -292,6 +602,125 @@ impl<'a> LightCommand<'a> {
         self.execute(args)
     }
 
+    /// Execute the command and write its output to a partial cache entry at the same time.
+    ///
+    /// Same with [`Self::execute`], the partial cache entry is kept only if the output
+    /// threshold exceeds.
+    pub fn execute_with_partial_cache(&mut self, args: &[&str]) -> Result<ExecutedInfo> {
+        let partial = CacheEntry::partial(args, self.env.dir.clone())?;
+        let (cmd_output, failure) = self.output_with_partial_cache(&partial, |_| {})?;
+        let cmd_stdout = &cmd_output.stdout;
+
+        self.env.total = bytecount::count(cmd_stdout, b'\n');
+
+        // The partial results of a failed command are not cached.
+        let tempfile = if failure.is_some() {
+            None
+        } else if self.env.should_do_cache() {
+            let tempfile = self.env.new_cache_entry(args)?;
+            let meta = CacheMeta::new(args, self.env.dir.as_deref(), self.env.total);
+            CacheEntry::finish_partial(&partial, &tempfile, &meta)?;
+            Some(tempfile)
+        } else {
+            std::fs::remove_file(&partial)?;
+            None
+        };
+
+        if let Ok(executed_info) = self.minimalize_job_overhead(cmd_stdout) {
+            return Ok(ExecutedInfo {
+                failure,
+                ..executed_info
+            });
+        }
+
+        let stdout_str = String::from_utf8_lossy(cmd_stdout);
+        let lines = self.try_prepend_icon(stdout_str.split('\n'));
+        let total = self.env.total;
+
+        Ok(ExecutedInfo {
+            total,
+            lines,
+            tempfile,
+            using_cache: false,
+            failure,
+        })
+    }
+
+    /// Execute the command and write its whole output to the cache entry as it streams, the top
+    /// `number` lines are printed as soon as they are read.
+    ///
+    /// Unlike [`Self::execute`], the output is cached regardless of the output threshold unless
+    /// the command fails, the returned info has no lines if they have been printed.
+    fn execute_tee(&mut self, args: &[&str], number: usize) -> Result<ExecutedInfo> {
+        let partial = CacheEntry::partial(args, self.env.dir.clone())?;
+
+        let env = self.env.clone();
+        let mut printed = false;
+        let (cmd_output, failure) = self.output_with_partial_cache(&partial, |stdout| {
+            if !printed && bytecount::count(stdout, b'\n') >= number {
+                let stdout_str = String::from_utf8_lossy(stdout);
+                let lines = env.try_paint_icon(stdout_str.split('\n').take(number));
+                // The final payload with the total and the cache file follows.
+                printer::println_payload(serde_json::json!({ "lines": lines, "finished": false }));
+                printed = true;
+            }
+        })?;
+        let cmd_stdout = &cmd_output.stdout;
+
+        self.env.total = bytecount::count(cmd_stdout, b'\n');
+
+        // The partial results of a failed command are not cached.
+        let tempfile = if failure.is_some() {
+            let _ = std::fs::remove_file(&partial);
+            None
+        } else {
+            let tempfile = self.env.new_cache_entry(args)?;
+            let meta = CacheMeta::new(args, self.env.dir.as_deref(), self.env.total);
+            CacheEntry::finish_partial(&partial, &tempfile, &meta)?;
+            Some(tempfile)
+        };
+
+        // The partial results of a failed command are returned as usual since there is no cache.
+        let lines = if printed && tempfile.is_some() {
+            Vec::new()
+        } else {
+            let stdout_str = String::from_utf8_lossy(cmd_stdout);
+            self.try_prepend_icon(stdout_str.split('\n').take(number))
+        };
+        let total = self.env.total;
+
+        Ok(ExecutedInfo {
+            total,
+            lines,
+            tempfile,
+            using_cache: false,
+            failure,
+        })
+    }
+
+    /// Execute the command until it finishes or the filtering is interrupted, see
+    /// [`Self::output_interruptible`].
+    ///
+    /// The whole output read is returned, neither truncated to `self.number` nor cached.
+    pub fn execute_interruptible(&mut self) -> Result<ExecutedInfo> {
+        let (cmd_output, failure) = self.output_interruptible()?;
+        let cmd_stdout = &cmd_output.stdout;
+
+        self.env.total = bytecount::count(cmd_stdout, b'\n');
+
+        let stdout_str = String::from_utf8_lossy(cmd_stdout);
+        let lines = self.try_prepend_icon(stdout_str.split('\n'));
+        let total = self.env.total;
+
+        Ok(ExecutedInfo {
+            total,
+            lines,
+            tempfile: None,
+            using_cache: false,
+            failure,
+        })
+    }
+
     /// Execute the command directly and capture the output.
     ///
     /// Truncate the results to `self.number` if specified,
@@ -299,17 +728,28 @@ impl<'a> LightCommand<'a> {
     /// a tempfile if they are more than `self.output_threshold`.
     /// This cached tempfile can be reused on the following runs.
     pub fn execute(&mut self, args: &[&str]) -> Result<ExecutedInfo> {
-        let cmd_output = self.output()?;
+        if let (true, Some(number)) = (self.env.tee_output, self.env.number) {
+            return self.execute_tee(args, number);
+        }
+
+        let (cmd_output, failure) = self.output()?;
         let cmd_stdout = &cmd_output.stdout;
 
         self.env.total = bytecount::count(cmd_stdout, b'\n');
 
         if let Ok(executed_info) = self.minimalize_job_overhead(cmd_stdout) {
-            return Ok(executed_info);
+            return Ok(ExecutedInfo {
+                failure,
+                ..executed_info
+            });
         }
 
-        // Write the output to a tempfile if the lines are too many.
-        let (stdout_str, tempfile) = self.try_cache(&cmd_stdout, args)?;
+        // Write the output to a tempfile if the lines are too many, unless it's partial.
+        let (stdout_str, tempfile) = if failure.is_some() {
+            (String::from_utf8_lossy(cmd_stdout).into(), None)
+        } else {
+            self.try_cache(&cmd_stdout, args)?
+        };
         let lines = self.try_prepend_icon(stdout_str.split('\n'));
         let total = self.env.total;
 
@@ -318,6 +758,56 @@ impl<'a> LightCommand<'a> {
             lines,
             tempfile,
             using_cache: false,
+            failure,
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stderr_tail() {
+        assert_eq!(stderr_tail("warning: foo\n"), "warning: foo");
+        let stderr = (0..30).map(|i| format!("{}%\n", i)).collect::<String>();
+        let tail = stderr_tail(&stderr);
+        assert_eq!(tail.lines().count(), MAX_STDERR_LINES);
+        assert!(tail.starts_with("10%") && tail.ends_with("29%"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_execute_interruptible() {
+        let mut cmd = Command::new("sh");
+        cmd.args(&["-c", "echo foo; sleep 0.1; echo bar"]);
+        let executed = LightCommand::new_grep(&mut cmd, None, None, None, None)
+            .execute_interruptible()
+            .unwrap();
+        assert_eq!(executed.total, 2);
+        assert_eq!(executed.lines, vec!["foo", "bar"]);
+        assert!(executed.failure.is_none());
+    }
+
+    #[test]
+    fn test_failure() {
+        let failure = |exit_code| Failure {
+            exit_code,
+            error_summary: "Permission denied".into(),
+        };
+        assert!(failure(Some(1)).is_complete_output());
+        assert!(!failure(Some(2)).is_complete_output());
+        assert!(!failure(None).is_complete_output());
+
+        let mut payload = serde_json::json!({ "total": 1 });
+        failure(Some(2)).attach_to(&mut payload);
+        assert_eq!(
+            payload,
+            serde_json::json!({
+                "total": 1,
+                "exit_code": 2,
+                "error_summary": "Permission denied"
+            })
+        );
+    }
+}
@@ -0,0 +1,222 @@
+//! Session-scoped cache of the computed results, keyed by the provider and query.
+//!
+//! The frontend passes the same `--session-id` for all the queries of a session, once the
+//! query goes back to a previous one, e.g., by backspacing, the results are loaded from
+//! the disk instead of being computed again. The source is assumed to be unchanged during
+//! a session.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use anyhow::Result;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use filter::FilterResult;
+use source_item::SourceItem;
+use utility::{cache_writer::write_atomically, calculate_hash, clap_cache_dir};
+
+const SESSIONS_DIR: &str = "sessions";
+
+/// File of the order of the last results in a session, see [`SessionCache::stabilize_order`].
+const ORDER_FILE: &str = "last_order.json";
+
+/// Maximum number of the last results whose order is recorded.
+const MAX_ORDERED_RESULTS: usize = 1000;
+
+/// Returns the id of the item that stays the same across the queries.
+fn stable_id(item: &SourceItem) -> u64 {
+    calculate_hash(&(item.line_number, &item.raw))
+}
+
+/// Keeps the chars that are safe to be used in a directory name.
+pub(crate) fn sanitize(session_id: &str) -> String {
+    session_id
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// Serializable form of [`FilterResult`], the match text is no longer needed after ranking.
+#[derive(Serialize, Deserialize)]
+struct CachedResult {
+    raw: String,
+    display_text: Option<String>,
+    lossy: bool,
+    #[serde(default)]
+    line_number: Option<usize>,
+    #[serde(default)]
+    user_data: Option<serde_json::Value>,
+    score: i64,
+    indices: Vec<usize>,
+}
+
+impl From<&FilterResult> for CachedResult {
+    fn from((item, score, indices): &FilterResult) -> Self {
+        Self {
+            raw: item.raw.clone(),
+            display_text: item.display_text.clone(),
+            lossy: item.lossy,
+            line_number: item.line_number,
+            user_data: item.user_data.clone(),
+            score: *score,
+            indices: indices.clone(),
+        }
+    }
+}
+
+impl From<CachedResult> for FilterResult {
+    fn from(cached: CachedResult) -> Self {
+        let mut item = SourceItem::new(cached.raw, None, cached.display_text);
+        item.lossy = cached.lossy;
+        item.line_number = cached.line_number;
+        item.user_data = cached.user_data;
+        (item, cached.score, cached.indices)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SessionCache {
+    dir: PathBuf,
+}
+
+impl SessionCache {
+    /// `provider` separates the entries of different subcommands in the same session.
+    pub fn new(session_id: &str, provider: &str) -> Self {
+        let mut dir = clap_cache_dir();
+        dir.push(SESSIONS_DIR);
+        dir.push(sanitize(session_id));
+        dir.push(provider);
+        Self { dir }
+    }
+
+    /// `options` are the rest arguments that affect the results besides `query`.
+    fn entry_path(&self, query: &str, options: &str) -> PathBuf {
+        self.dir.join(format!(
+            "{}_{}.json",
+            calculate_hash(&query),
+            calculate_hash(&options)
+        ))
+    }
+
+    pub fn load<T: DeserializeOwned>(&self, query: &str, options: &str) -> Option<T> {
+        let content = std::fs::read(self.entry_path(query, options)).ok()?;
+        serde_json::from_slice(&content).ok()
+    }
+
+    pub fn store<T: Serialize>(&self, query: &str, options: &str, value: &T) -> Result<()> {
+        std::fs::create_dir_all(&self.dir)?;
+        write_atomically(&self.entry_path(query, options), serde_json::to_vec(value)?)
+    }
+
+    /// Returns the ranked results of `query` computed before in this session.
+    pub fn load_ranked(&self, query: &str, options: &str) -> Option<Vec<FilterResult>> {
+        self.load::<Vec<CachedResult>>(query, options)
+            .map(|cached| cached.into_iter().map(Into::into).collect())
+    }
+
+    pub fn store_ranked(&self, query: &str, options: &str, ranked: &[FilterResult]) -> Result<()> {
+        let cached = ranked.iter().map(Into::into).collect::<Vec<CachedResult>>();
+        self.store(query, options, &cached)
+    }
+
+    /// Keeps the relative order of the results of the same score as in the last results of this
+    /// session, so that they are not reshuffled while typing, then records the order of the top
+    /// `number` results for the next time.
+    ///
+    /// The results not in the last results follow the ones that are, in the original order.
+    pub fn stabilize_order(
+        &self,
+        mut ranked: Vec<FilterResult>,
+        number: Option<usize>,
+    ) -> Vec<FilterResult> {
+        let order_path = self.dir.join(ORDER_FILE);
+        let last_order = std::fs::read(&order_path)
+            .ok()
+            .and_then(|content| serde_json::from_slice::<Vec<u64>>(&content).ok());
+        if let Some(last_order) = last_order {
+            let positions = last_order
+                .into_iter()
+                .enumerate()
+                .map(|(pos, id)| (id, pos))
+                .collect::<HashMap<_, _>>();
+            // The id of each item is hashed once instead of on every comparison.
+            ranked.sort_by_cached_key(|(item, score, _)| {
+                let position = positions
+                    .get(&stable_id(item))
+                    .copied()
+                    .unwrap_or(usize::MAX);
+                (std::cmp::Reverse(*score), position)
+            });
+        }
+
+        let order = ranked
+            .iter()
+            .take(
+                number
+                    .unwrap_or(MAX_ORDERED_RESULTS)
+                    .min(MAX_ORDERED_RESULTS),
+            )
+            .map(|(item, _, _)| stable_id(item))
+            .collect::<Vec<_>>();
+        let stored = std::fs::create_dir_all(&self.dir)
+            .map_err(anyhow::Error::from)
+            .and_then(|_| write_atomically(&order_path, serde_json::to_vec(&order)?));
+        if let Err(e) = stored {
+            log::error!("Failed to write the order of the results: {:?}", e);
+        }
+
+        ranked
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_session_cache() {
+        let session_id = format!("test/{}", std::process::id());
+        let cache = SessionCache::new(&session_id, "filter");
+        assert!(!cache.dir.to_string_lossy().contains("test/"));
+
+        let mut item: SourceItem = "src/lib.rs".into();
+        item.lossy = true;
+        let ranked = vec![(item, 42, vec![4, 5, 6])];
+        cache.store_ranked("lib", "Fzy", &ranked).unwrap();
+
+        let loaded = cache.load_ranked("lib", "Fzy").unwrap();
+        assert_eq!(loaded.len(), 1);
+        assert_eq!(loaded[0].0.raw, "src/lib.rs");
+        assert!(loaded[0].0.lossy);
+        assert_eq!((loaded[0].1, &loaded[0].2), (42, &vec![4, 5, 6]));
+
+        assert!(cache.load_ranked("lib", "SubString").is_none());
+        assert!(cache.load_ranked("li", "Fzy").is_none());
+
+        let ranked = |lines: &[(&str, i64)]| {
+            lines
+                .iter()
+                .map(|&(line, score)| (line.into(), score, Vec::new()))
+                .collect::<Vec<FilterResult>>()
+        };
+        let lines = |ranked: Vec<FilterResult>| {
+            ranked
+                .into_iter()
+                .map(|(item, _, _)| item.raw)
+                .collect::<Vec<_>>()
+        };
+        cache.stabilize_order(ranked(&[("b", 1), ("a", 1)]), None);
+        // `b` still goes before `a`, the new `c` follows them.
+        let stabilized =
+            cache.stabilize_order(ranked(&[("c", 1), ("a", 1), ("b", 1), ("d", 2)]), None);
+        assert_eq!(lines(stabilized), vec!["d", "b", "a", "c"]);
+
+        std::fs::remove_dir_all(cache.dir.parent().unwrap()).unwrap();
+    }
+}
@@ -64,6 +64,14 @@ fn write_response<T: Serialize>(msg: T) {
     }
 }
 
+/// Starts keeping the caches of project `cwd` up to date in the background.
+fn handle_watch_message(msg: Message) {
+    match msg.params.get("cwd").and_then(|x| x.as_str()) {
+        Some(cwd) => crate::cache_watcher::spawn_watcher(cwd.into()),
+        None => error!("Missing cwd in the watch message: {:?}", msg),
+    }
+}
+
 fn loop_read_rpc_message(reader: impl BufRead, sink: &Sender<String>) {
     let mut reader = reader;
     loop {
@@ -95,6 +103,7 @@ fn loop_handle_rpc_message(rx: &Receiver<String>) {
                 "init_ext_map" => message_handlers::parse_filetypedetect(msg),
                 "filer" => filer::handle_filer_message(msg),
                 "dumb_jump" => dumb_jump::handle_dumb_jump_message(msg),
+                "watch" => handle_watch_message(msg),
                 "filer/on_init" => session_manager.new_session(msg.session_id, msg, FilerSession),
                 "filer/on_move" => session_manager.send(msg.session_id, OnMove(msg)),
                 "on_init" => session_manager.new_session(msg.session_id, msg, GeneralSession),
@@ -1,6 +1,7 @@
 use std::path::PathBuf;
 use std::sync::{atomic::AtomicBool, Arc, Mutex};
 
+use super::delta::ResultItem;
 use super::*;
 use crate::stdio_server::types::ProviderId;
 
@@ -18,6 +19,10 @@ pub struct SessionContext {
     pub runtimepath: Option<String>,
     pub is_running: Arc<Mutex<AtomicBool>>,
     pub source_list: Arc<Mutex<Option<Vec<String>>>>,
+    /// Send only the delta from the previous result list in the on_typed response.
+    pub enable_delta: bool,
+    /// Result list sent in the last on_typed response, used for computing the delta.
+    pub last_result: Arc<Mutex<Vec<ResultItem>>>,
 }
 
 impl SessionContext {
@@ -56,6 +61,12 @@ impl From<Message> for SessionContext {
             .and_then(|x| x.as_u64())
             .unwrap_or(DEFAULT_PREVIEW_WINHEIGHT);
 
+        let enable_delta = msg
+            .params
+            .get("enable_delta")
+            .and_then(|x| x.as_bool())
+            .unwrap_or(false);
+
         let start_buffer_path = msg
             .params
             .get("source_fpath")
@@ -73,6 +84,8 @@ impl From<Message> for SessionContext {
             start_buffer_path,
             is_running: Arc::new(Mutex::new(true.into())),
             source_list: Arc::new(Mutex::new(None)),
+            enable_delta,
+            last_result: Arc::new(Mutex::new(Vec::new())),
         }
     }
 }
@@ -0,0 +1,212 @@
+//! Computes the delta between the result lists sent to the client consecutively.
+//!
+//! Each item is identified by its stable id, the position of the line in the source list, so
+//! that the client only has to redraw the items that are actually changed.
+
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+/// Position of the line in the source list.
+pub type ItemId = usize;
+
+/// A displayed item in the result window.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResultItem {
+    pub id: ItemId,
+    pub line: String,
+    pub indices: Vec<usize>,
+}
+
+/// A new item, displayed at `index` of the result window.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct Insertion {
+    pub id: ItemId,
+    pub index: usize,
+    pub line: String,
+    pub indices: Vec<usize>,
+}
+
+/// The item displayed at `from` previously is displayed at `to` now.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct Move {
+    pub id: ItemId,
+    pub from: usize,
+    pub to: usize,
+}
+
+/// The displayed line or the highlight of a retained item is changed, e.g., the line is
+/// truncated differently for the new matches.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct Update {
+    pub id: ItemId,
+    pub line: String,
+    pub indices: Vec<usize>,
+}
+
+/// Delta from the previous result list to the current one.
+///
+/// The retained items that are neither moved nor updated stay where they were, `len` is the
+/// size of the current result list.
+#[derive(Serialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct Delta {
+    pub len: usize,
+    pub removed: Vec<ItemId>,
+    pub inserted: Vec<Insertion>,
+    pub moved: Vec<Move>,
+    pub updated: Vec<Update>,
+}
+
+impl Delta {
+    /// Computes the delta from `previous` to `current`.
+    pub fn between(previous: &[ResultItem], current: &[ResultItem]) -> Self {
+        let previous_positions = previous
+            .iter()
+            .enumerate()
+            .map(|(idx, item)| (item.id, (idx, item)))
+            .collect::<HashMap<_, _>>();
+
+        let current_ids = current.iter().map(|item| item.id).collect::<Vec<_>>();
+
+        let removed = previous
+            .iter()
+            .map(|item| item.id)
+            .filter(|id| !current_ids.contains(id))
+            .collect();
+
+        let mut delta = Self {
+            len: current.len(),
+            removed,
+            ..Default::default()
+        };
+
+        for (to, item) in current.iter().enumerate() {
+            match previous_positions.get(&item.id) {
+                Some(&(from, previous_item)) => {
+                    if from != to {
+                        delta.moved.push(Move {
+                            id: item.id,
+                            from,
+                            to,
+                        });
+                    }
+                    if previous_item.line != item.line || previous_item.indices != item.indices {
+                        delta.updated.push(Update {
+                            id: item.id,
+                            line: item.line.clone(),
+                            indices: item.indices.clone(),
+                        });
+                    }
+                }
+                None => delta.inserted.push(Insertion {
+                    id: item.id,
+                    index: to,
+                    line: item.line.clone(),
+                    indices: item.indices.clone(),
+                }),
+            }
+        }
+
+        delta
+    }
+}
+
+/// Returns the stable ids of `lines`, which are the matched lines of `source_list`.
+///
+/// The duplicate lines are assigned with their positions in order.
+pub fn stable_ids<'a>(
+    source_list: &[String],
+    lines: impl IntoIterator<Item = &'a str>,
+) -> Vec<ItemId> {
+    let mut positions: HashMap<&str, Vec<ItemId>> = HashMap::new();
+    for (id, line) in source_list.iter().enumerate().rev() {
+        positions.entry(line.as_str()).or_default().push(id);
+    }
+
+    lines
+        .into_iter()
+        .map(|line| {
+            positions
+                .get_mut(line)
+                .and_then(|ids| ids.pop())
+                .unwrap_or(source_list.len())
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(id: ItemId, indices: Vec<usize>) -> ResultItem {
+        ResultItem {
+            id,
+            line: format!("line {}", id),
+            indices,
+        }
+    }
+
+    #[test]
+    fn test_delta_between() {
+        let previous = vec![item(1, vec![0]), item(2, vec![0]), item(3, vec![0])];
+        let current = vec![item(3, vec![0]), item(2, vec![1]), item(4, vec![2])];
+
+        let delta = Delta::between(&previous, &current);
+        assert_eq!(delta.len, 3);
+        assert_eq!(delta.removed, vec![1]);
+        assert_eq!(
+            delta.inserted,
+            vec![Insertion {
+                id: 4,
+                index: 2,
+                line: "line 4".into(),
+                indices: vec![2]
+            }]
+        );
+        assert_eq!(
+            delta.moved,
+            vec![Move {
+                id: 3,
+                from: 2,
+                to: 0
+            }]
+        );
+        assert_eq!(
+            delta.updated,
+            vec![Update {
+                id: 2,
+                line: "line 2".into(),
+                indices: vec![1]
+            }]
+        );
+
+        let mut truncated = current.clone();
+        truncated[0].line = "..ne 3".into();
+        assert_eq!(
+            Delta::between(&current, &truncated).updated,
+            vec![Update {
+                id: 3,
+                line: "..ne 3".into(),
+                indices: vec![0]
+            }]
+        );
+
+        assert_eq!(
+            Delta::between(&current, &current),
+            Delta {
+                len: 3,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_stable_ids() {
+        let source_list = vec!["foo".into(), "bar".into(), "foo".into()];
+        assert_eq!(
+            stable_ids(&source_list, vec!["foo", "bar", "foo"]),
+            vec![0, 1, 2]
+        );
+        assert_eq!(stable_ids(&source_list, vec!["bar", "foo"]), vec![1, 0]);
+    }
+}
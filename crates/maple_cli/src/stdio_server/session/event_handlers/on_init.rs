@@ -3,7 +3,7 @@ use serde_json::json;
 
 use crate::process::tokio::TokioCommand;
 use crate::stdio_server::{
-    session::{EventHandler, Session},
+    session::{delta::ResultItem, EventHandler, Session},
     write_response,
 };
 
@@ -35,6 +35,19 @@ pub async fn run<T: EventHandler>(
           "lines": response_lines,
         }}));
 
+        if session.context.enable_delta {
+            // The initial lines are the first lines of the source list.
+            *session.context.last_result.lock().unwrap() = response_lines
+                .into_iter()
+                .enumerate()
+                .map(|(id, line)| ResultItem {
+                    id,
+                    line,
+                    indices: Vec::new(),
+                })
+                .collect();
+        }
+
         let mut session = session;
         session.set_source_list(lines);
     }
@@ -14,7 +14,9 @@ use crate::stdio_server::{
 
 #[inline]
 pub fn as_absolute_path<P: AsRef<Path>>(path: P) -> Result<String> {
-    std::fs::canonicalize(path.as_ref())?
+    let absolute = std::fs::canonicalize(path.as_ref())?;
+    utility::paths::simplified(&absolute)
+        .into_owned()
         .into_os_string()
         .into_string()
         .map_err(|e| anyhow!("{:?}, path:{}", e, path.as_ref().display()))
@@ -109,11 +111,7 @@ impl OnMove {
             "files" | "git_files" => Self::Files(build_abs_path(&context.cwd, curline)),
             "history" => {
                 if curline.starts_with('~') {
-                    // I know std::env::home_dir() is incorrect in some rare cases[1], but dirs crate has been archived.
-                    //
-                    // [1] https://www.reddit.com/r/rust/comments/ga7f56/why_dirs_and_directories_repositories_have_been/fsjbsac/
-                    #[allow(deprecated)]
-                    let mut path = std::env::home_dir().context("failed to get home_dir")?;
+                    let mut path = utility::paths::home_dir().context("failed to get home_dir")?;
                     path.push(&curline[2..]);
                     Self::History(path)
                 } else {
@@ -191,6 +189,13 @@ impl OnMove {
     }
 }
 
+/// Adds the filetype of `path` as `syntax` to the preview result, used for highlighting.
+fn add_syntax(result: &mut serde_json::Value, path: &Path) {
+    if let Some(filetype) = filetype::detect(path) {
+        result["syntax"] = filetype.into();
+    }
+}
+
 pub struct OnMoveHandler<'a> {
     pub msg_id: u64,
     pub provider_id: ProviderId,
@@ -313,12 +318,14 @@ impl<'a> OnMoveHandler<'a> {
                     "<== message(out) sending event: on_move, msg_id:{}, provider_id:{}, lines: {:?}",
                     self.msg_id, self.provider_id, lines
                 );
-                self.send_response(json!({
+                let mut result = json!({
                   "event": "on_move",
                   "lines": lines,
                   "fname": fname,
                   "hi_lnum": hi_lnum
-                }));
+                });
+                add_syntax(&mut result, path.as_ref());
+                self.send_response(result);
             }
             Err(err) => {
                 error!(
@@ -363,11 +370,13 @@ impl<'a> OnMoveHandler<'a> {
         let lines = std::iter::once(abs_path.clone())
             .chain(self.truncate_preview_lines(lines_iter))
             .collect::<Vec<_>>();
-        self.send_response(json!({
+        let mut result = json!({
           "event": "on_move",
           "lines": lines,
           "fname": abs_path
-        }));
+        });
+        add_syntax(&mut result, path.as_ref());
+        self.send_response(result);
         Ok(())
     }
 
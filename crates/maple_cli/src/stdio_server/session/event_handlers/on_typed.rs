@@ -3,7 +3,13 @@ use serde_json::json;
 
 use filter::matcher::{Algo, Bonus, MatchType};
 
-use crate::stdio_server::{session::SessionContext, write_response, Message};
+use crate::stdio_server::{
+    session::{
+        delta::{stable_ids, Delta, ResultItem},
+        SessionContext,
+    },
+    write_response, Message,
+};
 
 pub fn handle_on_typed(msg: Message, context: &SessionContext) {
     debug!("recv OnTyped event: {:?}", msg);
@@ -28,10 +34,22 @@ pub fn handle_on_typed(msg: Message, context: &SessionContext) {
             _ => Bonus::None,
         };
         let lines_info =
-            filter::sync_run(&query, source, Algo::Fzy, match_type, vec![bonus]).unwrap();
+            filter::sync_run(&query, source, Algo::Fzy, match_type, vec![bonus], None).unwrap();
 
         let total = lines_info.len();
 
+        let ids = if context.enable_delta {
+            stable_ids(
+                source_list,
+                lines_info
+                    .iter()
+                    .take(30)
+                    .map(|(item, _, _)| item.raw.as_str()),
+            )
+        } else {
+            Vec::new()
+        };
+
         let (lines, indices, truncated_map) = printer::process_top_items(
             lines_info.into_iter().take(30),
             context.display_winwidth as usize,
@@ -48,11 +66,35 @@ pub fn handle_on_typed(msg: Message, context: &SessionContext) {
             write_response(json!({
             "id": msg_id,
             "provider_id": context.provider_id,
-            "result": result
+            "result": printer::prepare_payload(result)
             }));
         };
 
-        if truncated_map.is_empty() {
+        if context.enable_delta {
+            let current = ids
+                .into_iter()
+                .zip(lines.into_iter().zip(indices.into_iter()))
+                .map(|(id, (line, indices))| ResultItem { id, line, indices })
+                .collect::<Vec<_>>();
+            let mut last_result = context.last_result.lock().unwrap();
+            let delta = Delta::between(&last_result, &current);
+            *last_result = current;
+
+            if truncated_map.is_empty() {
+                send_response(json!({
+                  "event": "on_typed",
+                  "total": total,
+                  "delta": delta,
+                }));
+            } else {
+                send_response(json!({
+                  "event": "on_typed",
+                  "total": total,
+                  "delta": delta,
+                  "truncated_map": truncated_map,
+                }));
+            }
+        } else if truncated_map.is_empty() {
             send_response(json!({
               "event": "on_typed",
               "total": total,
@@ -1,4 +1,5 @@
 mod context;
+pub mod delta;
 pub mod event_handlers;
 mod manager;
 pub mod message_handlers;
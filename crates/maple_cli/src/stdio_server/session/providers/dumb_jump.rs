@@ -13,7 +13,8 @@ pub fn handle_dumb_jump_message(msg: Message) {
 
         let dumb_jump = DumbJump {
             word: input,
-            extension,
+            extension: Some(extension),
+            lang: None,
             kind: None,
             cmd_dir: Some(cwd.into()),
         };
@@ -22,6 +23,7 @@ pub fn handle_dumb_jump_message(msg: Message) {
             Ok(Lines {
                 mut lines,
                 mut indices,
+                ..
             }) => {
                 let total = lines.len();
                 // Only show the top 200 items.
@@ -0,0 +1,65 @@
+//! Case options of grep, applied to both rg and the fuzzy matcher.
+
+use structopt::StructOpt;
+
+use filter::matcher::CaseMatching;
+
+use crate::params::ParamsError;
+
+#[derive(StructOpt, Debug, Clone, Default)]
+pub struct CaseOptions {
+    /// Match case insensitively, delegate to --ignore-case option of rg.
+    #[structopt(long)]
+    pub ignore_case: bool,
+
+    /// Match case sensitively only if the query contains an uppercase char, the default.
+    #[structopt(long)]
+    pub smart_case: bool,
+
+    /// Match case sensitively, delegate to --case-sensitive option of rg.
+    #[structopt(long)]
+    pub case_sensitive: bool,
+}
+
+impl CaseOptions {
+    /// Only one of the case options can be specified.
+    pub fn validate(&self) -> Result<(), ParamsError> {
+        let flags = [
+            (self.ignore_case, "--ignore-case"),
+            (self.smart_case, "--smart-case"),
+            (self.case_sensitive, "--case-sensitive"),
+        ]
+        .iter()
+        .filter(|(enabled, _)| *enabled)
+        .map(|(_, flag)| *flag)
+        .collect::<Vec<_>>();
+        if flags.len() > 1 {
+            return Err(ParamsError::new(
+                &flags,
+                format!("{} can't be specified together", flags.join(" and ")),
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn case_matching(&self) -> CaseMatching {
+        if self.ignore_case {
+            CaseMatching::Ignore
+        } else if self.case_sensitive {
+            CaseMatching::Respect
+        } else {
+            CaseMatching::Smart
+        }
+    }
+
+    /// Returns the options of rg, which are also part of the cache key.
+    ///
+    /// No option for the smart case as rg is always run with --smart-case by default.
+    pub fn flags(&self) -> Vec<&'static str> {
+        match self.case_matching() {
+            CaseMatching::Ignore => vec!["--ignore-case"],
+            CaseMatching::Smart => Vec::new(),
+            CaseMatching::Respect => vec!["--case-sensitive"],
+        }
+    }
+}
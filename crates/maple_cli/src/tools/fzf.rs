@@ -0,0 +1,234 @@
+//! Shim of the fzf command line flags, mapped onto the options of `maple filter`.
+//!
+//! Supported flags:
+//!
+//! - `-e`, `--exact`: the substring matching.
+//! - `-i`, `+i`: the case insensitive and case sensitive matching.
+//! - `-n`, `--nth`: match the given fields only, e.g., `2`, `-1`, `2..`, `..3`.
+//! - `-d`, `--delimiter`: delimiter of the fields, a literal string instead of a regex.
+//! - `--tiebreak`: criteria of the results of the same score, `length`, `begin`, `end` or `index`.
+
+use anyhow::{anyhow, Result};
+
+use filter::{matcher::CaseMatching, Tiebreak};
+use source_item::{DisplayIndexMap, SourceItem};
+
+use crate::tools::shell_words;
+
+/// Field index expression of `--nth`, 1-based and negative from the end.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct FieldRange {
+    start: Option<i64>,
+    end: Option<i64>,
+}
+
+impl FieldRange {
+    fn parse(expr: &str) -> Result<Self> {
+        let parse_index = |index: &str| -> Result<Option<i64>> {
+            match index {
+                "" => Ok(None),
+                index => match index.parse() {
+                    Ok(0) | Err(_) => Err(anyhow!("Invalid field index: {}", expr)),
+                    Ok(index) => Ok(Some(index)),
+                },
+            }
+        };
+        match expr.find("..") {
+            Some(pos) => Ok(Self {
+                start: parse_index(&expr[..pos])?,
+                end: parse_index(&expr[pos + 2..])?,
+            }),
+            None => {
+                let index = parse_index(expr)?.ok_or_else(|| anyhow!("Empty field index"))?;
+                Ok(Self {
+                    start: Some(index),
+                    end: Some(index),
+                })
+            }
+        }
+    }
+
+    /// Returns the 0-based inclusive range of the fields given their count.
+    fn resolve(&self, count: usize) -> Option<(usize, usize)> {
+        let count = count as i64;
+        let to_index = |index: i64| if index < 0 { count + index } else { index - 1 };
+        let start = self.start.map_or(0, to_index).max(0);
+        let end = self.end.map_or(count - 1, to_index).min(count - 1);
+        if start > end {
+            None
+        } else {
+            Some((start as usize, end as usize))
+        }
+    }
+}
+
+/// Fields of the line matched against the query, see `--nth`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fields {
+    ranges: Vec<FieldRange>,
+    delimiter: Option<String>,
+}
+
+impl Fields {
+    /// Returns the byte spans of the fields of `line`, separated by the delimiter or the
+    /// whitespaces.
+    fn spans(&self, line: &str) -> Vec<(usize, usize)> {
+        match self.delimiter {
+            Some(ref delimiter) => {
+                let mut spans = Vec::new();
+                let mut start = 0;
+                for (pos, _) in line.match_indices(delimiter.as_str()) {
+                    spans.push((start, pos));
+                    start = pos + delimiter.len();
+                }
+                spans.push((start, line.len()));
+                spans
+            }
+            None => line
+                .split_whitespace()
+                .map(|field| {
+                    let start = field.as_ptr() as usize - line.as_ptr() as usize;
+                    (start, start + field.len())
+                })
+                .collect(),
+        }
+    }
+
+    /// Sets the selected fields of `item` as its text for matching.
+    pub fn apply(&self, mut item: SourceItem) -> SourceItem {
+        let spans = self.spans(&item.raw);
+        let mut selected = self
+            .ranges
+            .iter()
+            .filter_map(|range| range.resolve(spans.len()))
+            .flat_map(|(start, end)| start..=end)
+            .collect::<Vec<_>>();
+        selected.sort_unstable();
+        selected.dedup();
+
+        let (first, last) = match (selected.first(), selected.last()) {
+            (Some(&first), Some(&last)) => (first, last),
+            // No field to match, the item never matches a query.
+            _ => {
+                item.match_text = Some((String::new(), 0));
+                return item;
+            }
+        };
+
+        if last - first + 1 == selected.len() {
+            let (start, end) = (spans[first].0, spans[last].1);
+            let offset = item.raw[..start].chars().count();
+            item.match_text = Some((item.raw[start..end].into(), offset));
+            item
+        } else {
+            // The selected fields joined by a space, mapped back onto the whole line.
+            let mut filter_text = String::new();
+            let mut index_map = Vec::new();
+            for &idx in &selected {
+                if !filter_text.is_empty() {
+                    filter_text.push(' ');
+                    index_map.push(None);
+                }
+                let (start, end) = spans[idx];
+                let offset = item.raw[..start].chars().count();
+                let field = &item.raw[start..end];
+                filter_text.push_str(field);
+                index_map.extend((offset..offset + field.chars().count()).map(Some));
+            }
+            item.match_text = Some((filter_text, 0));
+            item.display_index_map = Some(DisplayIndexMap::Explicit(index_map));
+            item
+        }
+    }
+}
+
+/// Options parsed from the fzf flags.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FzfArgs {
+    pub exact: bool,
+    pub case_matching: Option<CaseMatching>,
+    pub fields: Option<Fields>,
+    pub tiebreak: Vec<Tiebreak>,
+}
+
+impl FzfArgs {
+    pub fn parse(args: &str) -> Result<Self> {
+        let mut fzf_args = Self::default();
+        let mut nth = None;
+        let mut delimiter = None;
+
+        let mut words = shell_words::split(args)?.into_iter();
+        while let Some(word) = words.next() {
+            let (flag, inline_value) = match word.find('=') {
+                Some(pos) if word.starts_with("--") => {
+                    (word[..pos].to_string(), Some(word[pos + 1..].to_string()))
+                }
+                _ => (word, None),
+            };
+            let mut value = || {
+                inline_value
+                    .clone()
+                    .or_else(|| words.next())
+                    .ok_or_else(|| anyhow!("Missing the value of {}", flag))
+            };
+            match flag.as_str() {
+                "-e" | "--exact" => fzf_args.exact = true,
+                "+e" | "--no-exact" => fzf_args.exact = false,
+                "-i" => fzf_args.case_matching = Some(CaseMatching::Ignore),
+                "+i" => fzf_args.case_matching = Some(CaseMatching::Respect),
+                "-n" | "--nth" => nth = Some(value()?),
+                "-d" | "--delimiter" => delimiter = Some(value()?),
+                "--tiebreak" => {
+                    fzf_args.tiebreak =
+                        value()?.split(',').map(str::parse).collect::<Result<_>>()?
+                }
+                _ => return Err(anyhow!("Unsupported fzf flag: {}", flag)),
+            }
+        }
+
+        if let Some(nth) = nth {
+            fzf_args.fields = Some(Fields {
+                ranges: nth
+                    .split(',')
+                    .map(FieldRange::parse)
+                    .collect::<Result<_>>()?,
+                delimiter,
+            });
+        }
+
+        Ok(fzf_args)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_fzf_args() {
+        let args = FzfArgs::parse(r#"-e +i --nth=2.. -d ':' --tiebreak length,index"#).unwrap();
+        assert!(args.exact);
+        assert_eq!(args.case_matching, Some(CaseMatching::Respect));
+        assert_eq!(args.tiebreak, vec![Tiebreak::Length, Tiebreak::Index]);
+
+        let item = args.fields.unwrap().apply("src/lib.rs:12:fn main()".into());
+        assert_eq!(item.match_text, Some(("12:fn main()".into(), 11)));
+
+        assert!(FzfArgs::parse("--multi").is_err());
+        assert!(FzfArgs::parse("--nth 0").is_err());
+    }
+
+    #[test]
+    fn test_select_fields() {
+        let fields = FzfArgs::parse("-n 1,-1").unwrap().fields.unwrap();
+        let item = fields.apply("foo  bar baz".into());
+        assert_eq!(item.match_text, Some(("foo baz".into(), 0)));
+        assert_eq!(item.display_indices(vec![0, 4]), vec![0, 9]);
+
+        let fields = FzfArgs::parse("--nth=..2").unwrap().fields.unwrap();
+        assert_eq!(
+            fields.apply("foo  bar baz".into()).match_text,
+            Some(("foo  bar".into(), 0))
+        );
+    }
+}
@@ -0,0 +1,93 @@
+//! Parses the output of git log in a structured pretty format.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+
+use crate::process::light::set_current_dir;
+
+/// Separator of the fields in [`LOG_FORMAT`], which is unlikely in any of them.
+const FIELD_SEPARATOR: char = '\u{1f}';
+
+/// Full hash, short hash, author, date and subject of each commit.
+const LOG_FORMAT: &str = "--format=%H%x1f%h%x1f%an%x1f%ad%x1f%s";
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct Commit {
+    pub hash: String,
+    pub short_hash: String,
+    pub author: String,
+    /// Author date in the format of `YYYY-MM-DD`.
+    pub date: String,
+    pub subject: String,
+}
+
+impl Commit {
+    /// Returns the line for displaying, same with the commits provider of Vim.
+    ///
+    /// 2021-03-14 1a2b3c4 Add the commits provider (Author)
+    pub fn display_line(&self) -> String {
+        format!(
+            "{} {} {} ({})",
+            self.date, self.short_hash, self.subject, self.author
+        )
+    }
+}
+
+/// Parses the output of git log in [`LOG_FORMAT`], the malformed lines are skipped.
+pub fn parse_log(output: &str) -> Vec<Commit> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.splitn(5, FIELD_SEPARATOR);
+            Some(Commit {
+                hash: fields.next()?.into(),
+                short_hash: fields.next()?.into(),
+                author: fields.next()?.into(),
+                date: fields.next()?.into(),
+                subject: fields.next()?.into(),
+            })
+        })
+        .collect()
+}
+
+/// Returns the commits of the repo at `cmd_dir`, only the ones touching `file` if given.
+pub fn log(cmd_dir: Option<PathBuf>, file: Option<&Path>) -> Result<Vec<Commit>> {
+    let mut cmd = Command::new("git");
+    cmd.args(&["log", "--color=never", "--date=short", LOG_FORMAT]);
+    if let Some(file) = file {
+        cmd.args(&["--follow", "--"]).arg(file);
+    }
+    set_current_dir(&mut cmd, cmd_dir);
+
+    let output = cmd.output()?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "git log failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+
+    Ok(parse_log(&String::from_utf8_lossy(&output.stdout)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_log() {
+        let output =
+            "1a2b3c4d5e6f\u{1f}1a2b3c4\u{1f}Alice\u{1f}2021-03-14\u{1f}Fix a|b \u{1f} parsing\n\
+                      malformed line\n";
+        let commits = parse_log(output);
+        assert_eq!(commits.len(), 1);
+        assert_eq!(commits[0].subject, "Fix a|b \u{1f} parsing");
+        assert_eq!(
+            commits[0].display_line(),
+            "2021-03-14 1a2b3c4 Fix a|b \u{1f} parsing (Alice)"
+        );
+    }
+}
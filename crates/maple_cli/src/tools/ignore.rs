@@ -0,0 +1,167 @@
+//! Options of searching the hidden and ignored files, shared by rg and fd.
+
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use serde::{Deserialize, Serialize};
+use structopt::StructOpt;
+
+/// Names of the ignore files respected by rg or fd.
+const IGNORE_FILES: &[&str] = &[".gitignore", ".ignore", ".rgignore", ".fdignore"];
+
+/// An ignore file and the time it was last modified.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct IgnoreFile {
+    pub path: PathBuf,
+    /// Unix timestamp in milliseconds.
+    pub mtime: u64,
+}
+
+impl IgnoreFile {
+    fn read(path: PathBuf) -> Option<Self> {
+        let mtime = std::fs::metadata(&path)
+            .and_then(|metadata| metadata.modified())
+            .ok()?
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or_default();
+        Some(Self { path, mtime })
+    }
+}
+
+/// Returns the ignore files influencing the search in `dir`, i.e., the ones in `dir` and its
+/// ancestors, including `.git/info/exclude`.
+///
+/// The ignore files in the subdirectories are not included as finding them takes a full walk.
+pub fn ignore_files(dir: &Path) -> Vec<IgnoreFile> {
+    dir.ancestors()
+        .flat_map(|dir| {
+            IGNORE_FILES
+                .iter()
+                .map(move |name| dir.join(name))
+                .chain(std::iter::once(
+                    dir.join(".git").join("info").join("exclude"),
+                ))
+        })
+        .filter_map(IgnoreFile::read)
+        .collect()
+}
+
+#[derive(StructOpt, Debug, Clone, Default)]
+pub struct IgnoreOptions {
+    /// Search the hidden files and directories, delegate to --hidden option of rg or fd.
+    #[structopt(long)]
+    pub hidden: bool,
+
+    /// Do not respect the ignore files, e.g., .gitignore, delegate to --no-ignore option of rg
+    /// or fd.
+    #[structopt(long)]
+    pub no_ignore: bool,
+}
+
+/// Returns true if the program of `cmd` is rg or fd, which accept the same ignore options.
+pub fn is_rg_or_fd(cmd: &str) -> bool {
+    cmd.split_whitespace()
+        .next()
+        .and_then(|program| Path::new(program).file_stem())
+        .and_then(std::ffi::OsStr::to_str)
+        .map(|program| matches!(program, "rg" | "fd" | "fdfind"))
+        .unwrap_or(false)
+}
+
+impl IgnoreOptions {
+    /// Returns the options of rg or fd.
+    pub fn flags(&self) -> Vec<&'static str> {
+        let mut flags = Vec::new();
+        if self.hidden {
+            flags.push("--hidden");
+        }
+        if self.no_ignore {
+            flags.push("--no-ignore");
+        }
+        flags
+    }
+
+    /// Returns the options if the program of `cmd` is rg or fd, the other commands may not
+    /// accept them.
+    pub fn flags_for(&self, cmd: &str) -> Vec<&'static str> {
+        if is_rg_or_fd(cmd) {
+            self.flags()
+        } else {
+            Vec::new()
+        }
+    }
+
+    /// Adds the options to the shell command `cmd` if it's a rg or fd command.
+    ///
+    /// The options present in `cmd` already are not added again, the other commands are
+    /// returned as is.
+    pub fn apply_to_cmd(&self, cmd: &str) -> String {
+        if !is_rg_or_fd(cmd) {
+            return cmd.into();
+        }
+        let mut words = cmd.splitn(2, char::is_whitespace);
+        let program = words.next().unwrap_or_default();
+        let rest = words.next().unwrap_or_default();
+        let flags = self
+            .flags()
+            .into_iter()
+            .filter(|flag| !rest.split_whitespace().any(|word| word == *flag))
+            .collect::<Vec<_>>();
+        std::iter::once(program)
+            .chain(flags)
+            .chain(std::iter::once(rest).filter(|rest| !rest.is_empty()))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ignore_files() {
+        let dir = std::env::temp_dir().join(format!("clap_test_ignore_{}", std::process::id()));
+        let sub_dir = dir.join("sub");
+        std::fs::create_dir_all(&sub_dir).unwrap();
+        std::fs::write(dir.join(".rgignore"), "target\n").unwrap();
+        std::fs::write(sub_dir.join(".ignore"), "*.log\n").unwrap();
+
+        let paths = ignore_files(&sub_dir)
+            .into_iter()
+            .map(|ignore_file| ignore_file.path)
+            .filter(|path| path.starts_with(&dir))
+            .collect::<Vec<_>>();
+        assert_eq!(paths, vec![sub_dir.join(".ignore"), dir.join(".rgignore")]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_apply_to_cmd() {
+        let options = IgnoreOptions {
+            hidden: true,
+            no_ignore: true,
+        };
+        assert_eq!(
+            options.apply_to_cmd("fd --type f"),
+            "fd --hidden --no-ignore --type f"
+        );
+        assert_eq!(
+            options.apply_to_cmd("rg --files --hidden"),
+            "rg --no-ignore --files --hidden"
+        );
+        assert_eq!(options.apply_to_cmd("rg"), "rg --hidden --no-ignore");
+        assert_eq!(options.apply_to_cmd("git ls-files"), "git ls-files");
+        assert_eq!(
+            IgnoreOptions::default().apply_to_cmd("fd --type f"),
+            "fd --type f"
+        );
+        assert_eq!(
+            options.flags_for("/usr/bin/rg --vimgrep"),
+            vec!["--hidden", "--no-ignore"]
+        );
+        assert!(options.flags_for("grep -rn").is_empty());
+    }
+}
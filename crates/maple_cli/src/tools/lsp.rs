@@ -0,0 +1,369 @@
+//! Minimal client of the language server protocol, only for the requests issued by maple.
+//!
+//! The language server is spawned as a child process and talked to over its stdio, each message
+//! is a JSON-RPC object prefixed with the `Content-Length` header.
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+use crossbeam_channel::Receiver;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+/// Time to wait for the response of a request, indexing a big project can take a while.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Reads a message of the language server, `None` if the server has exited.
+fn read_message<R: BufRead>(reader: &mut R) -> Result<Option<Value>> {
+    let mut content_length = None;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some(len) = header.strip_prefix("Content-Length:") {
+            content_length = Some(len.trim().parse::<usize>()?);
+        }
+    }
+    let content_length = content_length.ok_or_else(|| anyhow!("Missing Content-Length"))?;
+    let mut content = vec![0; content_length];
+    reader.read_exact(&mut content)?;
+    Ok(Some(serde_json::from_slice(&content)?))
+}
+
+/// Returns the `file://` URI of the absolute `path`.
+pub fn path_to_uri(path: &Path) -> String {
+    let path = path.to_string_lossy().replace('\\', "/");
+    let mut uri = String::from("file://");
+    if !path.starts_with('/') {
+        uri.push('/');
+    }
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'/' | b'-' | b'_' | b'.' | b'~' | b':' => {
+                uri.push(byte as char)
+            }
+            _ => uri.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    uri
+}
+
+/// Returns the path of the `file://` URI, `None` for the other schemes.
+pub fn uri_to_path(uri: &str) -> Option<PathBuf> {
+    let encoded = uri.strip_prefix("file://")?.as_bytes();
+    let mut bytes = Vec::with_capacity(encoded.len());
+    let mut idx = 0;
+    while idx < encoded.len() {
+        let decoded = match encoded[idx] {
+            b'%' => encoded
+                .get(idx + 1..idx + 3)
+                .and_then(|hex| std::str::from_utf8(hex).ok())
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok()),
+            _ => None,
+        };
+        match decoded {
+            Some(byte) => {
+                bytes.push(byte);
+                idx += 3;
+            }
+            None => {
+                bytes.push(encoded[idx]);
+                idx += 1;
+            }
+        }
+    }
+    let path = String::from_utf8_lossy(&bytes).into_owned();
+    // `/C:/foo` on Windows.
+    if path.len() > 2 && path.as_bytes()[2] == b':' && path.starts_with('/') {
+        Some(path[1..].into())
+    } else {
+        Some(path.into())
+    }
+}
+
+/// Client of a language server running as the child process.
+pub struct LspClient {
+    child: Child,
+    stdin: ChildStdin,
+    messages: Receiver<Value>,
+    next_id: u64,
+}
+
+impl LspClient {
+    /// Spawns the language server `server_cmd` and initializes it with the workspace `root`.
+    pub fn start(server_cmd: &str, root: &Path) -> Result<Self> {
+        let mut words = server_cmd.split_whitespace();
+        let program = words
+            .next()
+            .ok_or_else(|| anyhow!("Empty server command"))?;
+        let mut child = Command::new(program)
+            .args(words)
+            .current_dir(root)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .with_context(|| format!("Failed to spawn the language server {}", server_cmd))?;
+
+        let stdin = child
+            .stdin
+            .take()
+            .context("No stdin of the language server")?;
+        let stdout = child
+            .stdout
+            .take()
+            .context("No stdout of the language server")?;
+        let (sender, messages) = crossbeam_channel::unbounded();
+        std::thread::spawn(move || {
+            let mut reader = BufReader::new(stdout);
+            while let Ok(Some(message)) = read_message(&mut reader) {
+                if sender.send(message).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let mut client = Self {
+            child,
+            stdin,
+            messages,
+            next_id: 0,
+        };
+
+        let root_uri = path_to_uri(root);
+        let name = root
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        client.request(
+            "initialize",
+            json!({
+                "processId": std::process::id(),
+                "rootPath": root,
+                "rootUri": root_uri,
+                "workspaceFolders": [{ "uri": root_uri, "name": name }],
+                "capabilities": {
+                    "workspace": { "symbol": { "dynamicRegistration": false } }
+                },
+            }),
+        )?;
+        client.notify("initialized", json!({}))?;
+
+        Ok(client)
+    }
+
+    fn send(&mut self, message: Value) -> Result<()> {
+        let content = message.to_string();
+        write!(
+            self.stdin,
+            "Content-Length: {}\r\n\r\n{}",
+            content.len(),
+            content
+        )?;
+        self.stdin.flush()?;
+        Ok(())
+    }
+
+    pub fn notify(&mut self, method: &str, params: Value) -> Result<()> {
+        self.send(json!({ "jsonrpc": "2.0", "method": method, "params": params }))
+    }
+
+    /// Sends the request and returns its result.
+    ///
+    /// The requests of the server in the meanwhile are replied with null, the notifications are
+    /// ignored.
+    pub fn request(&mut self, method: &str, params: Value) -> Result<Value> {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.send(json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }))?;
+
+        loop {
+            let message = self
+                .messages
+                .recv_timeout(REQUEST_TIMEOUT)
+                .map_err(|e| anyhow!("No response of {}: {}", method, e))?;
+            match (message.get("id"), message.get("method")) {
+                (Some(request_id), Some(_)) => {
+                    let request_id = request_id.clone();
+                    self.send(json!({ "jsonrpc": "2.0", "id": request_id, "result": null }))?;
+                }
+                (Some(response_id), None) if *response_id == json!(id) => {
+                    if let Some(error) = message.get("error") {
+                        return Err(anyhow!("{} failed: {}", method, error));
+                    }
+                    return Ok(message.get("result").cloned().unwrap_or(Value::Null));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Asks the language server to exit.
+    pub fn shutdown(mut self) -> Result<()> {
+        self.request("shutdown", Value::Null)?;
+        self.notify("exit", Value::Null)
+    }
+}
+
+impl Drop for LspClient {
+    fn drop(&mut self) {
+        // The server not exiting in time is killed.
+        if let Ok(None) = self.child.try_wait() {
+            std::thread::sleep(Duration::from_millis(100));
+            if let Ok(None) = self.child.try_wait() {
+                let _ = self.child.kill();
+                let _ = self.child.wait();
+            }
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+struct Position {
+    line: usize,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+struct Range {
+    start: Position,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+struct Location {
+    uri: String,
+    #[serde(default)]
+    range: Option<Range>,
+}
+
+/// A symbol of the `workspace/symbol` response.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct SymbolInformation {
+    name: String,
+    kind: u32,
+    location: Location,
+    #[serde(default, rename = "containerName")]
+    container_name: Option<String>,
+}
+
+/// Returns the name of `SymbolKind`, same with the kind of ctags if there is one.
+fn symbol_kind_name(kind: u32) -> &'static str {
+    match kind {
+        1 => "file",
+        2 => "module",
+        3 => "namespace",
+        4 => "package",
+        5 => "class",
+        6 => "method",
+        7 => "property",
+        8 => "field",
+        9 => "constructor",
+        10 => "enum",
+        11 => "interface",
+        12 => "function",
+        13 => "variable",
+        14 => "constant",
+        15 => "string",
+        16 => "number",
+        17 => "boolean",
+        18 => "array",
+        19 => "object",
+        20 => "key",
+        21 => "null",
+        22 => "enumerator",
+        23 => "struct",
+        24 => "event",
+        25 => "operator",
+        26 => "typeparameter",
+        _ => "unknown",
+    }
+}
+
+impl SymbolInformation {
+    /// Builds the line for displaying the symbol in the format of the project tags, the path is
+    /// relative to `root` if possible.
+    pub fn display_line(&self, root: &Path) -> String {
+        let path = uri_to_path(&self.location.uri)
+            .map(|path| match path.strip_prefix(root) {
+                Ok(relative) => relative.to_path_buf(),
+                Err(_) => path,
+            })
+            .map(|path| path.to_string_lossy().into_owned())
+            .unwrap_or_else(|| self.location.uri.clone());
+        let line = self
+            .location
+            .range
+            .as_ref()
+            .map_or(1, |range| range.start.line + 1);
+        format!(
+            "{text:<text_width$} {kind:<kind_width$} {container}",
+            text = format!("{}:{}", self.name, line),
+            text_width = 30,
+            kind = format!("[{}@{}]", symbol_kind_name(self.kind), path),
+            kind_width = 30,
+            container = self.container_name.as_deref().unwrap_or_default(),
+        )
+        .trim_end()
+        .into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_message() {
+        let content = r#"{"jsonrpc":"2.0","id":0,"result":null}"#;
+        let input = format!(
+            "Content-Length: {}\r\nContent-Type: application/vscode-jsonrpc\r\n\r\n{}",
+            content.len(),
+            content
+        );
+        let mut reader = BufReader::new(input.as_bytes());
+        let message = read_message(&mut reader).unwrap().unwrap();
+        assert_eq!(message["id"], 0);
+        assert!(read_message(&mut reader).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_uri() {
+        let path = Path::new("/home/user/my project/src/lib.rs");
+        let uri = path_to_uri(path);
+        assert_eq!(uri, "file:///home/user/my%20project/src/lib.rs");
+        assert_eq!(uri_to_path(&uri).unwrap(), path);
+        assert_eq!(
+            uri_to_path("file:///C:/foo/bar.rs").unwrap(),
+            PathBuf::from("C:/foo/bar.rs")
+        );
+        assert_eq!(uri_to_path("jdt://contents/foo"), None);
+    }
+
+    #[test]
+    fn test_symbol_display_line() {
+        let symbol: SymbolInformation = serde_json::from_value(json!({
+            "name": "Matcher",
+            "kind": 23,
+            "location": {
+                "uri": "file:///project/crates/matcher/src/lib.rs",
+                "range": { "start": { "line": 52, "character": 0 }, "end": { "line": 52, "character": 4 } }
+            },
+            "containerName": "matcher"
+        }))
+        .unwrap();
+        assert_eq!(
+            symbol.display_line(Path::new("/project")),
+            format!(
+                "{:<30} {:<30} matcher",
+                "Matcher:53", "[struct@crates/matcher/src/lib.rs]"
+            )
+        );
+    }
+}
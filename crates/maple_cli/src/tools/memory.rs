@@ -0,0 +1,38 @@
+//! Limit of the matches retained by the dyn filtering given the available memory.
+
+use sysinfo::{RefreshKind, System, SystemExt};
+
+/// Estimated bytes taken by a retained match, i.e., the item, its score and indices.
+const BYTES_PER_MATCH: u64 = 512;
+
+/// Only a quarter of the available memory is spent on the retained matches.
+const MEMORY_SHARE: u64 = 4;
+
+/// The limit is never lower than this even if the memory is scarce.
+const MIN_LIMIT: usize = 10_000;
+
+/// Returns the number of the matches fitting in the share of `available` bytes.
+fn limit_for(available: u64) -> usize {
+    ((available / MEMORY_SHARE / BYTES_PER_MATCH) as usize).max(MIN_LIMIT)
+}
+
+/// Returns the limit given the available memory of the system, `None` if it's unknown.
+pub fn auto_limit() -> Option<usize> {
+    let system = System::new_with_specifics(RefreshKind::new().with_memory());
+    // In KiB.
+    match system.available_memory() {
+        0 => None,
+        available => Some(limit_for(available * 1024)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_limit_for() {
+        assert_eq!(limit_for(8 * 1024 * 1024 * 1024), 4 * 1024 * 1024);
+        assert_eq!(limit_for(1024), MIN_LIMIT);
+    }
+}
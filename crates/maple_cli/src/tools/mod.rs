@@ -1,2 +1,10 @@
+pub mod case;
 pub mod ctags;
+pub mod fzf;
+pub mod git;
+pub mod ignore;
+pub mod lsp;
+pub mod memory;
+pub mod path_set;
 pub mod rg;
+pub mod shell_words;
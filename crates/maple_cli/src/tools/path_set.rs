@@ -0,0 +1,72 @@
+//! Restricting rg to a set of paths listed in a file, e.g., the output of `git diff --name-only`.
+
+use std::path::Path;
+
+use anyhow::{Context, Result};
+
+/// The paths searched by rg instead of the whole working directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PathSet {
+    /// Sorted and deduplicated, so that the hash does not depend on the order of the listing.
+    paths: Vec<String>,
+}
+
+impl PathSet {
+    /// Parses the paths of `content`, one per line, relative to `dir`.
+    ///
+    /// The blank lines and the paths not existing in `dir` are skipped, e.g., the deleted files
+    /// listed by `git diff --name-only`, which would fail rg otherwise.
+    fn parse(content: &str, dir: &Path) -> Self {
+        let mut paths = content
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && dir.join(line).exists())
+            .map(Into::into)
+            .collect::<Vec<String>>();
+        paths.sort();
+        paths.dedup();
+        Self { paths }
+    }
+
+    /// Reads the paths listed in `paths_file`, relative to `dir`.
+    pub fn read(paths_file: &Path, dir: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(paths_file)
+            .with_context(|| format!("Failed to read the paths file {}", paths_file.display()))?;
+        Ok(Self::parse(&content, dir))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.paths.is_empty()
+    }
+
+    pub fn paths(&self) -> impl Iterator<Item = &str> {
+        self.paths.iter().map(String::as_str)
+    }
+
+    /// Returns the extra arg of the cache key identifying this path set.
+    ///
+    /// The paths themselves are not part of the key since the cache directory is named after it.
+    pub fn cache_arg(&self) -> String {
+        format!("--paths={:x}", utility::calculate_hash(&self.paths))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_path_set() {
+        let dir = std::env::current_dir().unwrap();
+        let path_set = PathSet::parse("src/lib.rs\n\nCargo.toml\nsrc/lib.rs\nno_such_file\n", &dir);
+        assert_eq!(
+            path_set.paths().collect::<Vec<_>>(),
+            vec!["Cargo.toml", "src/lib.rs"]
+        );
+        assert_eq!(
+            path_set.cache_arg(),
+            PathSet::parse("Cargo.toml\nsrc/lib.rs\n", &dir).cache_arg()
+        );
+        assert!(PathSet::parse("no_such_file\n", &dir).is_empty());
+    }
+}
@@ -1,46 +1,253 @@
 //! This module requires the executable rg with `--json` and `--pcre2` is installed in the system.
 
-use serde::Deserialize;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+/// A message of rg's --json output.
+///
+/// Ref https://docs.rs/grep-printer/0.1.6/grep_printer/struct.JSON.html
+#[derive(Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(tag = "type", content = "data", rename_all = "lowercase")]
+pub enum Message {
+    /// Start searching a file.
+    Begin(Begin),
+    /// Finish searching a file.
+    End(End),
+    /// A matched line.
+    Match(Match),
+    /// A context line, enabled by `-A`, `-B` or `-C`.
+    Context(Match),
+    /// Statistics of the whole search, the last message.
+    Summary(Summary),
+}
+
+#[derive(Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Begin {
+    pub path: Text,
+}
+
+#[derive(Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct End {
+    pub path: Text,
+    pub binary_offset: Option<u64>,
+    pub stats: Stats,
+}
+
+#[derive(Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Summary {
+    pub elapsed_total: Elapsed,
+    pub stats: Stats,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Elapsed {
+    pub secs: u64,
+    pub nanos: u32,
+    pub human: String,
+}
+
+/// Search statistics of a file or the whole search.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Stats {
+    pub elapsed: Elapsed,
+    pub searches: u64,
+    pub searches_with_match: u64,
+    pub bytes_searched: u64,
+    pub bytes_printed: u64,
+    pub matched_lines: u64,
+    pub matches: u64,
+}
+
+/// Match counts of a single file.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct FileMatches {
+    pub path: String,
+    pub matched_lines: u64,
+    pub matches: u64,
+}
+
+/// Position of the first submatch in a matched line.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MatchPosition {
+    /// 1-based byte column of the match start, as used by `cursor()` of Vim.
+    pub column: usize,
+    /// Byte length of the match.
+    pub length: usize,
+}
+
+/// A context line of a matched line.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ContextLine {
+    pub line_number: u64,
+    pub text: String,
+}
+
+/// The context lines before and after a matched line.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct MatchContext {
+    pub before: Vec<ContextLine>,
+    pub after: Vec<ContextLine>,
+}
+
+/// Attaches the context lines in `lines` to the matched lines, the matched lines are returned
+/// along with their context.
+///
+/// A context line right after a match is the trailing context of it unless it has `after`
+/// context lines already, the others are the leading context of the next match in the same
+/// file, since rg merges the context lines shared by the adjacent matches.
+pub fn attach_contexts(lines: Vec<JsonLine>, after: usize) -> Vec<(JsonLine, MatchContext)> {
+    let mut matches: Vec<(JsonLine, MatchContext)> = Vec::new();
+    let mut pending: Vec<Match> = Vec::new();
+    for line in lines {
+        if !line.is_context() {
+            let before = std::mem::take(&mut pending)
+                .into_iter()
+                .filter(|context| context.path == line.data.path)
+                .map(|context| context.context_line())
+                .collect();
+            matches.push((
+                line,
+                MatchContext {
+                    before,
+                    after: Vec::new(),
+                },
+            ));
+            continue;
+        }
+        match matches.last_mut() {
+            Some((last, context))
+                if pending.is_empty()
+                    && context.after.len() < after
+                    && last.data.path == line.data.path =>
+            {
+                context.after.push(line.data.context_line())
+            }
+            _ => pending.push(line.data),
+        }
+    }
+    matches
+}
+
+/// Everything collected from the whole output of rg --json.
+#[derive(Clone, Debug, Default)]
+pub struct SearchResult {
+    /// The matched and context lines in the output order.
+    pub lines: Vec<JsonLine>,
+    /// Match counts per file, only the files having a match are included.
+    pub file_matches: Vec<FileMatches>,
+    /// Statistics of the whole search, `None` if the output is incomplete.
+    pub stats: Option<Stats>,
+}
+
+impl SearchResult {
+    /// Decodes the output lines of rg --json, the undecodable lines are ignored.
+    pub fn from_lines<T: AsRef<str>>(lines: &[T]) -> Self {
+        let mut result = Self::default();
+
+        for message in lines
+            .iter()
+            .filter_map(|line| serde_json::from_str::<Message>(line.as_ref()).ok())
+        {
+            match message {
+                Message::Begin(_) => {}
+                Message::Match(data) => result.lines.push(JsonLine::new_match(data)),
+                Message::Context(data) => result.lines.push(JsonLine::new_context(data)),
+                Message::End(End { path, stats, .. }) => {
+                    if stats.matched_lines > 0 {
+                        result.file_matches.push(FileMatches {
+                            path: path.text,
+                            matched_lines: stats.matched_lines,
+                            matches: stats.matches,
+                        });
+                    }
+                }
+                Message::Summary(Summary { stats, .. }) => result.stats = Some(stats),
+            }
+        }
+
+        result
+    }
+}
+
+/// Kind of a line printed by rg --json, the other messages are not lines.
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LineKind {
+    Match,
+    Context,
+}
 
 /// This struct represents the line content of rg's --json.
 #[derive(Deserialize, Clone, Debug, PartialEq, Eq)]
 pub struct JsonLine {
     #[serde(rename = "type")]
-    pub ty: String,
+    pub kind: LineKind,
     pub data: Match,
 }
 
 impl JsonLine {
-    /// Returns the formatted String like using rg's -vimgrep option.
-    pub fn grep_line_format(&self, enable_icon: bool) -> String {
+    pub fn new_match(data: Match) -> Self {
+        Self {
+            kind: LineKind::Match,
+            data,
+        }
+    }
+
+    pub fn new_context(data: Match) -> Self {
+        Self {
+            kind: LineKind::Context,
+            data,
+        }
+    }
+
+    /// Returns true if this is a context line instead of a matched line.
+    pub fn is_context(&self) -> bool {
+        self.kind == LineKind::Context
+    }
+
+    /// Rewrites the file path relative to `dir` if the file is in `dir`.
+    pub fn relative_to(&mut self, dir: &Path) {
+        if let Some(relative) = utility::strip_dir_prefix(&self.data.path.text, dir) {
+            let relative = relative.to_string();
+            self.data.path.text = relative;
+        }
+    }
+
+    /// Returns the part before the line content in the formatted grep line.
+    ///
+    /// [icon ]filepath:line_number:column:
+    fn grep_line_prefix(&self, enable_icon: bool) -> String {
         let maybe_icon = if enable_icon {
             format!("{} ", icon::icon_for(&self.data.path.text))
         } else {
             Default::default()
         };
         format!(
-            "{}{}:{}:{}:{}",
+            "{}{}:{}:{}:",
             maybe_icon,
             self.data.path(),
             self.data.line_number(),
             self.data.column(),
-            self.data.line(),
         )
     }
 
+    /// Returns the formatted String like using rg's -vimgrep option.
+    pub fn grep_line_format(&self, enable_icon: bool) -> String {
+        format!("{}{}", self.grep_line_prefix(enable_icon), self.data.line())
+    }
+
+    /// Returns the byte offset of the line content in the formatted grep line.
     pub fn grep_line_offset(&self, enable_icon: bool) -> usize {
-        // filepath:line_number:column:text, 3 extra `:` in the formatted String.
-        let fixed_offset = if enable_icon { 3 + 4 } else { 3 };
-        self.data.path().len()
-            + self.data.line_number().to_string().len()
-            + self.data.column().to_string().len()
-            + fixed_offset
+        // The icon is not always of the same byte length.
+        self.grep_line_prefix(enable_icon).len()
     }
 
+    /// Returns the formatted grep line and the byte indices of all the submatches in it.
     pub fn build_grep_line(&self, enable_icon: bool) -> (String, Vec<usize>) {
-        let formatted = self.grep_line_format(enable_icon);
-        let indices = self.data.match_indices(self.grep_line_offset(enable_icon));
-        (formatted, indices)
+        let prefix = self.grep_line_prefix(enable_icon);
+        let indices = self.data.match_indices(prefix.len());
+        (format!("{}{}", prefix, self.data.line()), indices)
     }
 
     /// NOTE: [`pattern::DUMB_JUMP_LINE`] must be updated accordingly once the format is changed.
@@ -100,12 +307,20 @@ impl PartialEq for Match {
 }
 
 impl Match {
+    /// Returns the byte indices of all the submatches, `offset` is added to each of them.
+    ///
+    /// The part of submatch beyond the trimmed line, e.g., the trailing newline, is dropped.
     pub fn match_indices(&self, offset: usize) -> Vec<usize> {
-        self.submatches
+        let line_len = self.line().len();
+        let mut indices = self
+            .submatches
             .iter()
-            .map(|s| s.match_indices(offset))
+            .map(|s| range(s.start.min(line_len), s.end.min(line_len), offset))
             .flatten()
-            .collect()
+            .collect::<Vec<_>>();
+        indices.sort_unstable();
+        indices.dedup();
+        indices
     }
 
     pub fn match_indices_for_dumb_jump(&self, offset: usize, search_word: &Word) -> Vec<usize> {
@@ -124,13 +339,35 @@ impl Match {
         self.line_number.unwrap_or_default()
     }
 
+    /// Returns the start of first submatch, 0 for the context line which has no submatches.
     pub fn column(&self) -> usize {
-        self.submatches[0].start
+        self.submatches.first().map(|s| s.start).unwrap_or_default()
     }
 
     pub fn line(&self) -> &str {
         self.lines.text.trim_end()
     }
+
+    fn context_line(&self) -> ContextLine {
+        ContextLine {
+            line_number: self.line_number(),
+            text: self.line().into(),
+        }
+    }
+
+    /// Returns the position of the first submatch, `None` for the context line.
+    ///
+    /// The part of submatch beyond the trimmed line, e.g., the trailing newline, is dropped.
+    pub fn match_position(&self) -> Option<MatchPosition> {
+        let line_len = self.line().len();
+        self.submatches.first().map(|s| {
+            let start = s.start.min(line_len);
+            MatchPosition {
+                column: start + 1,
+                length: s.end.min(line_len) - start,
+            }
+        })
+    }
 }
 
 #[derive(Deserialize, Clone, Debug, PartialEq, Eq)]
@@ -194,3 +431,94 @@ fn test_search_word_is_a_word() {
     let mat = re.find(line).unwrap();
     assert_eq!(mat.start(), 18);
 }
+
+#[test]
+fn test_build_grep_line_with_multiple_submatches() {
+    let line = r#"{"type":"match","data":{"path":{"text":"src/lib.rs"},"lines":{"text":"let 中 = foo(foo);\n"},"line_number":2,"absolute_offset":7,"submatches":[{"match":{"text":"foo"},"start":10,"end":13},{"match":{"text":"foo"},"start":14,"end":17}]}}"#;
+    let line: JsonLine = serde_json::from_str(line).unwrap();
+
+    let (formatted, indices) = line.build_grep_line(false);
+    assert_eq!(formatted, "src/lib.rs:2:10:let 中 = foo(foo);");
+    let highlighted = indices
+        .iter()
+        .map(|i| formatted.as_bytes()[*i])
+        .collect::<Vec<_>>();
+    assert_eq!(highlighted, b"foofoo");
+
+    let (formatted, indices) = line.build_grep_line(true);
+    let offset = line.grep_line_offset(true);
+    assert_eq!(&formatted[offset..], "let 中 = foo(foo);");
+    assert_eq!(indices[0], offset + 10);
+    assert_eq!(indices.len(), 6);
+}
+
+#[test]
+fn test_search_result_from_lines() {
+    let lines = [
+        r#"{"type":"begin","data":{"path":{"text":"src/lib.rs"}}}"#,
+        r#"{"type":"context","data":{"path":{"text":"src/lib.rs"},"lines":{"text":"// foo\n"},"line_number":1,"absolute_offset":0,"submatches":[]}}"#,
+        r#"{"type":"match","data":{"path":{"text":"src/lib.rs"},"lines":{"text":"fn foo() { foo }\n"},"line_number":2,"absolute_offset":7,"submatches":[{"match":{"text":"foo"},"start":3,"end":6},{"match":{"text":"foo"},"start":11,"end":14}]}}"#,
+        r#"{"type":"end","data":{"path":{"text":"src/lib.rs"},"binary_offset":null,"stats":{"elapsed":{"secs":0,"nanos":1000,"human":"0.000001s"},"searches":1,"searches_with_match":1,"bytes_searched":24,"bytes_printed":512,"matched_lines":1,"matches":2}}}"#,
+        r#"{"type":"begin","data":{"path":{"text":"src/main.rs"}}}"#,
+        r#"{"type":"end","data":{"path":{"text":"src/main.rs"},"binary_offset":null,"stats":{"elapsed":{"secs":0,"nanos":1000,"human":"0.000001s"},"searches":1,"searches_with_match":0,"bytes_searched":10,"bytes_printed":0,"matched_lines":0,"matches":0}}}"#,
+        r#"{"data":{"elapsed_total":{"human":"0.002s","nanos":2000000,"secs":0},"stats":{"bytes_printed":512,"bytes_searched":34,"elapsed":{"human":"0.000002s","nanos":2000,"secs":0},"matched_lines":1,"matches":2,"searches":2,"searches_with_match":1}},"type":"summary"}"#,
+    ];
+
+    let result = SearchResult::from_lines(&lines);
+
+    assert_eq!(result.lines.len(), 2);
+    assert!(result.lines[0].is_context());
+    assert_eq!(result.lines[0].data.column(), 0);
+    assert!(!result.lines[1].is_context());
+    assert_eq!(result.lines[1].data.column(), 3);
+    assert_eq!(
+        result.file_matches,
+        vec![FileMatches {
+            path: "src/lib.rs".into(),
+            matched_lines: 1,
+            matches: 2,
+        }]
+    );
+    let stats = result.stats.unwrap();
+    assert_eq!((stats.searches, stats.matches), (2, 2));
+}
+
+#[test]
+fn test_attach_contexts() {
+    let line = |kind: LineKind, path: &str, line_number: u64| JsonLine {
+        kind,
+        data: Match {
+            path: Text { text: path.into() },
+            lines: Text {
+                text: format!("line {}\n", line_number),
+            },
+            line_number: Some(line_number),
+            absolute_offset: 0,
+            submatches: Vec::new(),
+        },
+    };
+    let lines = vec![
+        line(LineKind::Context, "a.rs", 1),
+        line(LineKind::Match, "a.rs", 2),
+        line(LineKind::Context, "a.rs", 3),
+        line(LineKind::Context, "a.rs", 4),
+        line(LineKind::Match, "a.rs", 5),
+        line(LineKind::Context, "a.rs", 6),
+        line(LineKind::Match, "b.rs", 1),
+    ];
+
+    let attached = attach_contexts(lines, 1);
+    let line_numbers = |lines: &[ContextLine]| {
+        lines
+            .iter()
+            .map(|line| line.line_number)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(attached.len(), 3);
+    assert_eq!(line_numbers(&attached[0].1.before), vec![1]);
+    assert_eq!(line_numbers(&attached[0].1.after), vec![3]);
+    assert_eq!(line_numbers(&attached[1].1.before), vec![4]);
+    assert_eq!(line_numbers(&attached[1].1.after), vec![6]);
+    assert_eq!(attached[2].1, MatchContext::default());
+    assert_eq!(attached[1].1.after[0].text, "line 6");
+}
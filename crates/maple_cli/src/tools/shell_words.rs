@@ -0,0 +1,93 @@
+//! Splitting a string into the words like a shell, for the options typed in the provider input.
+//!
+//! Different from a shell, a backslash outside the quotes only escapes a whitespace, quote or
+//! backslash and is kept otherwise, so that a regex like `fn\s+run` can be typed as is.
+
+use std::ops::Range;
+
+use anyhow::{anyhow, Result};
+
+/// Splits `s` into the words, the quotes and escaping backslashes are removed.
+pub fn split(s: &str) -> Result<Vec<String>> {
+    Ok(split_spanned(s)?
+        .into_iter()
+        .map(|(word, _)| word)
+        .collect())
+}
+
+/// Same with [`split`], but each word comes with the byte range of its raw text in `s`, which
+/// includes the quotes and escaping backslashes.
+pub fn split_spanned(s: &str) -> Result<Vec<(String, Range<usize>)>> {
+    let mut words = Vec::new();
+    let mut word = None::<(String, usize)>;
+    let mut quote = None;
+    let mut chars = s.char_indices().peekable();
+    while let Some((idx, c)) = chars.next() {
+        if word.is_none() && (quote.is_some() || !c.is_whitespace()) {
+            word = Some((String::new(), idx));
+        }
+        let pushed = match (quote, c) {
+            (Some(q), c) if c == q => {
+                quote = None;
+                None
+            }
+            (Some('"'), '\\') => Some(
+                chars
+                    .next_if(|&(_, next)| next == '"' || next == '\\')
+                    .map_or(c, |(_, escaped)| escaped),
+            ),
+            (None, '\\') => {
+                let &(_, next) = chars.peek().ok_or_else(|| anyhow!("Trailing backslash"))?;
+                if next.is_whitespace() || next == '"' || next == '\'' || next == '\\' {
+                    chars.next();
+                    Some(next)
+                } else {
+                    Some(c)
+                }
+            }
+            (Some(_), c) => Some(c),
+            (None, '\'') | (None, '"') => {
+                quote = Some(c);
+                None
+            }
+            (None, c) if c.is_whitespace() => {
+                if let Some((word, start)) = word.take() {
+                    words.push((word, start..idx));
+                }
+                None
+            }
+            (None, c) => Some(c),
+        };
+        if let (Some(c), Some((word, _))) = (pushed, word.as_mut()) {
+            word.push(c);
+        }
+    }
+    if quote.is_some() {
+        return Err(anyhow!("Unterminated quote in {}", s));
+    }
+    words.extend(word.map(|(word, start)| (word, start..s.len())));
+    Ok(words)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split() {
+        assert_eq!(
+            split(r#""fn run" --glob *.rs"#).unwrap(),
+            vec!["fn run", "--glob", "*.rs"]
+        );
+        assert_eq!(
+            split(r#"fn\s+run 'a\b' "say \"hi\"" a\ b ''"#).unwrap(),
+            vec![r"fn\s+run", r"a\b", r#"say "hi""#, "a b", ""]
+        );
+        assert_eq!(
+            split_spanned(r#" "fn run"  -w "#).unwrap(),
+            vec![("fn run".to_string(), 1..9), ("-w".to_string(), 11..13)]
+        );
+        assert!(split(r#""fn run"#).is_err());
+        assert!(split(r"foo\").is_err());
+    }
+}
@@ -20,7 +20,7 @@ pub fn match_and_score_with_positions(needle: &str, haystack: &str) -> Option<Ma
     let haystack = if needle.chars().any(|c| c.is_uppercase()) {
         haystack
     } else {
-        lowercased = haystack.to_lowercase();
+        lowercased = lowercase_chars(haystack);
         &lowercased
     };
 
@@ -42,6 +42,16 @@ pub fn match_and_score_with_positions(needle: &str, haystack: &str) -> Option<Ma
         .map(|needle_length| score_with_positions(needle, needle_length, haystack))
 }
 
+/// Lowercases each char of `s` to exactly one char.
+///
+/// `str::to_lowercase()` may turn one char into several ones, e.g., `İ`, which breaks
+/// the positions calculated on the lowercased haystack.
+fn lowercase_chars(s: &str) -> String {
+    s.chars()
+        .map(|c| c.to_lowercase().next().unwrap_or(c))
+        .collect()
+}
+
 /// Searches for needle's chars in the haystack.
 /// Returns `None` if haystack doesn't hold all needle's chars.
 /// Returns `Some(len)` with needle's length otherwise.
@@ -55,7 +65,7 @@ pub fn match_and_score_with_positions(needle: &str, haystack: &str) -> Option<Ma
 /// ```
 #[inline]
 fn matches(needle: &str, haystack: &str) -> Option<usize> {
-    if needle.is_empty() || needle == haystack {
+    if needle.is_empty() {
         return Some(0);
     }
 
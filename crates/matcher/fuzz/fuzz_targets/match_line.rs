@@ -0,0 +1,42 @@
+//! Runs all the matcher algorithms on arbitrary lines and checks the invariants of the result.
+//!
+//! The input is split at the first `\n` into the query and the line.
+//!
+//! Run with `cargo fuzz run match_line` in `crates/matcher`.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+use matcher::invariants::check_match_result;
+use matcher::{Algo, Bonus, MatchType, Matcher};
+use source_item::SourceItem;
+
+fuzz_target!(|data: &[u8]| {
+    let input = match std::str::from_utf8(data) {
+        Ok(input) => input,
+        Err(_) => return,
+    };
+
+    let (query, line) = match input.find('\n') {
+        Some(idx) => (&input[..idx], &input[idx + 1..]),
+        None => return,
+    };
+
+    let item: SourceItem = line.into();
+
+    for algo in &[Algo::Fzy, Algo::Skim, Algo::SubString, Algo::Auto] {
+        for match_type in &[
+            MatchType::Full,
+            MatchType::TagName,
+            MatchType::FileName,
+            MatchType::IgnoreFilePath,
+        ] {
+            let matcher = Matcher::new(algo.clone(), match_type.clone(), Bonus::FileName);
+            let result = matcher.do_match(&item, query);
+            if let Err(e) = check_match_result(line, query, &result) {
+                panic!("{:?} {:?}: {}", algo, match_type, e);
+            }
+        }
+    }
+});
@@ -2,34 +2,95 @@ use structopt::clap::arg_enum;
 
 use source_item::{MatchTextFor, MatchType};
 
+use crate::grapheme::match_graphemes;
+use crate::normalize;
+use crate::prefilter;
+use crate::query::parse_alternatives;
 use crate::MatchResult;
 
 // Implement arg_enum for using it in the command line arguments.
 arg_enum! {
   /// Supported line oriented String match algorithm.
+  ///
+  /// `Auto` picks an algorithm per item, see [`Algo::auto_select`].
   #[derive(Debug, Clone)]
   pub enum Algo {
       Skim,
       Fzy,
       SubString,
+      Auto,
   }
 }
 
+/// The text longer than this is considered as a text line rather than a path by `Algo::Auto`.
+const AUTO_MAX_PATH_LEN: usize = 64;
+
 impl Algo {
+    /// Returns the algorithm used by `Algo::Auto` for `text`.
+    ///
+    /// fzy works better for the short paths where the matches on the path components are
+    /// favored, while skim works better for the long text lines, e.g., the content of grep lines.
+    pub fn auto_select(text: &str) -> Self {
+        let is_path_like =
+            text.contains(|c: char| c == '/' || c == '\\') || !text.contains(char::is_whitespace);
+        if text.len() <= AUTO_MAX_PATH_LEN && is_path_like {
+            Self::Fzy
+        } else {
+            Self::Skim
+        }
+    }
+
+    fn match_chars(&self, text: &str, query: &str) -> MatchResult {
+        match self {
+            Self::Fzy => fzy::fuzzy_indices(text, query),
+            Self::Skim => skim::fuzzy_indices(text, query),
+            Self::SubString => substring::substr_indices(text, query),
+            Self::Auto => Self::auto_select(text).match_chars(text, query),
+        }
+    }
+
+    /// Matches with the diacritics folded if `--normalize` is on, otherwise matches by the
+    /// grapheme clusters if `query` contains the non-ASCII chars.
+    ///
+    /// The line missing any char of `query` is rejected by [`prefilter::may_match`] first.
+    fn match_text(&self, text: &str, query: &str) -> MatchResult {
+        if !prefilter::may_match(text, query) {
+            return None;
+        }
+        let match_chars = |text: &str, query: &str| self.match_chars(text, query);
+        if normalize::is_enabled() {
+            if let Some(result) = normalize::match_folded(text, query, match_chars) {
+                return result;
+            }
+        } else if !query.is_ascii() {
+            if let Some(result) = match_graphemes(text, query, match_chars) {
+                return result;
+            }
+        }
+        self.match_chars(text, query)
+    }
+
+    /// Matches the item against `query`.
+    ///
+    /// For the query with alternatives(`foo|bar`), the best result of all the matched
+    /// alternatives is returned.
     pub fn apply_match<'a, T: MatchTextFor<'a>>(
         &self,
         query: &str,
         item: &T,
         match_type: &MatchType,
     ) -> MatchResult {
-        item.match_text_for(match_type).and_then(|(text, offset)| {
-            let res = match self {
-                Self::Fzy => fzy::fuzzy_indices(text, query),
-                Self::Skim => skim::fuzzy_indices(text, query),
-                Self::SubString => substring::substr_indices(text, query),
-            };
-            res.map(|(score, indices)| (score, indices.into_iter().map(|x| x + offset).collect()))
-        })
+        item.match_text_for(match_type)
+            .and_then(|(text, offset)| self.apply_match_on_text(query, text, offset))
+    }
+
+    /// Matches `text` against `query`, `offset` is added to the matched indices.
+    pub fn apply_match_on_text(&self, query: &str, text: &str, offset: usize) -> MatchResult {
+        let res = parse_alternatives(query)
+            .into_iter()
+            .filter_map(|alternative| self.match_text(text, alternative))
+            .max_by_key(|(score, _)| *score);
+        res.map(|(score, indices)| (score, indices.into_iter().map(|x| x + offset).collect()))
     }
 }
 
@@ -54,45 +115,40 @@ pub mod fzy {
 }
 
 pub mod substring {
-    fn find_start_at(slice: &str, start_at: usize, pat: &str) -> Option<usize> {
-        slice[start_at..].find(pat).map(|i| start_at + i)
+    /// Lowercases `c` to exactly one char so that the char positions are not shifted.
+    #[inline]
+    fn lowercase_char(c: char) -> char {
+        c.to_lowercase().next().unwrap_or(c)
     }
 
-    fn _substr_indices_impl(haystack: &str, niddle: &str) -> Option<(f64, Vec<usize>)> {
-        let niddle = niddle.to_lowercase();
-
-        match find_start_at(haystack, 0, &niddle) {
-            Some(idx) => {
-                let mut positions = Vec::new();
-
-                // For build without overflow checks this could be written as
-                // `let mut pos = idx - 1;` with `|| { pos += 1; pos }` closure.
-                let mut pos = idx;
-                positions.resize_with(
-                    niddle.len(),
-                    // Simple endless iterator for `idx..` range. Even though it's endless,
-                    // it will iterate only `sub_niddle.len()` times.
-                    || {
-                        pos += 1;
-                        pos - 1
-                    },
-                );
-
-                if positions.is_empty() {
-                    return None;
-                }
-
-                let calc_score = || {
-                    let last_pos = positions.last().unwrap();
-                    let match_len = (last_pos + 1 - positions[0]) as f64;
-
-                    (2f64 / (positions[0] + 1) as f64) + 1f64 / (last_pos + 1) as f64 - match_len
-                };
-
-                Some((calc_score(), positions))
-            }
-            None => None,
+    /// Returns the char index of the first occurrence of `niddle` in `haystack`.
+    fn find_chars(haystack: &[char], niddle: &[char]) -> Option<usize> {
+        if niddle.len() > haystack.len() {
+            return None;
         }
+        haystack
+            .windows(niddle.len())
+            .position(|window| window == niddle)
+    }
+
+    fn _substr_indices_impl(haystack: &[char], niddle: &str) -> Option<(f64, Vec<usize>)> {
+        let niddle = niddle.chars().map(lowercase_char).collect::<Vec<_>>();
+
+        if niddle.is_empty() {
+            return None;
+        }
+
+        let idx = find_chars(haystack, &niddle)?;
+        let positions = (idx..idx + niddle.len()).collect::<Vec<_>>();
+
+        let calc_score = || {
+            let last_pos = positions.last().unwrap();
+            let match_len = (last_pos + 1 - positions[0]) as f64;
+
+            (2f64 / (positions[0] + 1) as f64) + 1f64 / (last_pos + 1) as f64 - match_len
+        };
+
+        Some((calc_score(), positions))
     }
 
     fn unordered_substr_indices_impl(haystack: &str, niddle: &str) -> Option<(f64, Vec<usize>)> {
@@ -101,13 +157,13 @@ pub mod substring {
             return None;
         }
 
-        let haystack = haystack.to_lowercase();
-        let haystack = haystack.as_str();
+        // The positions are counted in chars.
+        let haystack = haystack.chars().map(lowercase_char).collect::<Vec<_>>();
 
         let mut total_score = 0f64;
         let mut positions = Vec::new();
         for sub_niddle in niddle.split_whitespace() {
-            if let Some((score, indices)) = _substr_indices_impl(haystack, &sub_niddle) {
+            if let Some((score, indices)) = _substr_indices_impl(&haystack, &sub_niddle) {
                 total_score += score;
                 positions.extend_from_slice(&indices);
             } else {
@@ -120,6 +176,8 @@ pub mod substring {
         }
 
         positions.sort_unstable();
+        // The overlapped sub-niddles can match the same positions.
+        positions.dedup();
 
         Some((total_score, positions))
     }
@@ -129,6 +187,13 @@ pub mod substring {
             .map(|(score, positions)| (score as i64, positions))
     }
 
+    #[test]
+    fn test_substr_char_indices() {
+        assert_eq!(substr_indices("中文/src", "src"), Some((-2, vec![3, 4, 5])));
+        assert_eq!(substr_indices("İx", "x"), Some((0, vec![1])));
+        assert_eq!(substr_indices("abc", "ab bc"), Some((0, vec![0, 1, 2])));
+    }
+
     #[test]
     fn test_substr() {
         assert_eq!(
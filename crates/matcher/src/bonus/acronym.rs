@@ -0,0 +1,107 @@
+//! Add a bonus to the candidates whose word initials spell the acronym query.
+//!
+//! An all-uppercase query like `FBR` is taken as an acronym, which strongly prefers
+//! `FooBarReader` and `foo_bar_reader` to the other candidates merely containing these chars.
+
+use source_item::SourceItem;
+
+use crate::Score;
+
+/// Returns true if `query` is an acronym, i.e., at least two uppercase letters or digits with
+/// at least one letter.
+pub fn is_acronym(query: &str) -> bool {
+    query.len() >= 2
+        && query
+            .chars()
+            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit())
+        && query.chars().any(|c| c.is_ascii_uppercase())
+}
+
+/// Returns the uppercase initials of the words in `identifier`, which are split by the case
+/// changes, `_` and `-`, e.g., `HS` of `HTTPServer`.
+fn initials(identifier: &str) -> String {
+    let chars = identifier.chars().collect::<Vec<_>>();
+    let mut initials = String::new();
+    for (idx, &c) in chars.iter().enumerate() {
+        if !c.is_alphanumeric() {
+            continue;
+        }
+        let is_initial = match idx.checked_sub(1).map(|prev| chars[prev]) {
+            None => true,
+            Some(prev) if !prev.is_alphanumeric() => true,
+            Some(prev) => {
+                c.is_uppercase()
+                    && (!prev.is_uppercase()
+                        || chars.get(idx + 1).map_or(false, |next| next.is_lowercase()))
+            }
+        };
+        if is_initial {
+            initials.extend(c.to_uppercase());
+        }
+    }
+    initials
+}
+
+/// Returns 2 if the initials of an identifier in `text` are exactly the acronym `query`, 1 if
+/// they contain it, otherwise 0.
+fn spelling_of(text: &str, query: &str) -> u8 {
+    text.split(|c: char| !(c.is_alphanumeric() || c == '_' || c == '-'))
+        .map(|identifier| {
+            let initials = initials(identifier);
+            if initials == query {
+                2
+            } else if initials.contains(query) {
+                1
+            } else {
+                0
+            }
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+/// Returns true if the acronym `query` is spelled by the initials of an identifier in `text`.
+pub(crate) fn spells(text: &str, query: &str) -> bool {
+    spelling_of(text, query) > 0
+}
+
+pub fn calc_bonus(item: &SourceItem, query: &str, base_score: Score) -> Score {
+    if !is_acronym(query) {
+        return 0;
+    }
+    match spelling_of(item.match_text(), query) {
+        2 => base_score,
+        1 => base_score / 2,
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_initials() {
+        assert_eq!(initials("FooBarReader"), "FBR");
+        assert_eq!(initials("foo_bar_reader"), "FBR");
+        assert_eq!(initials("foo-bar-reader"), "FBR");
+        assert_eq!(initials("_foo_bar"), "FB");
+        assert_eq!(initials("HTTPServer"), "HS");
+        assert_eq!(initials("parseJSON"), "PJ");
+
+        assert!(is_acronym("FBR"));
+        assert!(is_acronym("V2S"));
+        assert!(!is_acronym("F"));
+        assert!(!is_acronym("FBr"));
+        assert!(!is_acronym("12"));
+    }
+
+    #[test]
+    fn test_acronym_bonus() {
+        assert_eq!(calc_bonus(&"struct FooBarReader {".into(), "FBR", 100), 100);
+        assert_eq!(calc_bonus(&"fn foo_bar_reader()".into(), "FBR", 100), 100);
+        assert_eq!(calc_bonus(&"MyFooBarReader".into(), "FBR", 100), 50);
+        assert_eq!(calc_bonus(&"fbr_other".into(), "FBR", 100), 0);
+        assert_eq!(calc_bonus(&"FooBarReader".into(), "fbr", 100), 0);
+    }
+}
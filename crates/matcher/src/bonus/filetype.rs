@@ -0,0 +1,123 @@
+//! Add a bonus to the files relevant to the filetype of current buffer.
+//!
+//! For instance, the `.rs` files are ranked higher than the vendored `.js` files when editing
+//! a Rust file.
+
+use std::path::Path;
+
+use source_item::SourceItem;
+
+use crate::Score;
+
+/// Returns the file extensions related to the Vim `filetype`.
+///
+/// The filetype itself is used as the extension if not listed here.
+fn related_extensions(filetype: &str) -> Option<&'static [&'static str]> {
+    let extensions: &'static [&'static str] = match filetype {
+        "c" => &["c", "h"],
+        "cpp" => &["cpp", "cc", "cxx", "c++", "hpp", "hh", "hxx", "h"],
+        "cs" => &["cs"],
+        "elixir" => &["ex", "exs"],
+        "erlang" => &["erl", "hrl"],
+        "go" => &["go"],
+        "haskell" => &["hs", "lhs"],
+        "javascript" => &["js", "mjs", "cjs", "jsx"],
+        "javascriptreact" => &["jsx", "js"],
+        "kotlin" => &["kt", "kts"],
+        "markdown" => &["md", "markdown"],
+        "objc" => &["m", "h"],
+        "ocaml" => &["ml", "mli"],
+        "python" => &["py", "pyi"],
+        "ruby" => &["rb"],
+        "rust" => &["rs"],
+        "sh" | "bash" => &["sh", "bash"],
+        "typescript" => &["ts", "tsx"],
+        "typescriptreact" => &["tsx", "ts"],
+        "vim" => &["vim"],
+        "yaml" => &["yaml", "yml"],
+        _ => return None,
+    };
+    Some(extensions)
+}
+
+#[derive(Debug, Clone)]
+pub struct FileType {
+    extensions: Vec<String>,
+}
+
+impl FileType {
+    pub fn new(filetype: &str) -> Self {
+        let filetype = filetype.to_lowercase();
+        let extensions = match related_extensions(&filetype) {
+            Some(extensions) => extensions.iter().map(|s| s.to_string()).collect(),
+            None => vec![filetype],
+        };
+        Self { extensions }
+    }
+
+    /// Returns true if the file `path` has one of the related extensions.
+    fn is_relevant(&self, path: &str) -> bool {
+        Path::new(path)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| {
+                self.extensions
+                    .iter()
+                    .any(|related| related.eq_ignore_ascii_case(ext))
+            })
+            .unwrap_or(false)
+    }
+
+    pub fn calc_bonus(&self, item: &SourceItem, base_score: Score) -> Score {
+        if self.is_relevant(item.raw.trim_end()) {
+            base_score / 4
+        } else {
+            0
+        }
+    }
+}
+
+impl From<String> for FileType {
+    fn from(filetype: String) -> Self {
+        Self::new(&filetype)
+    }
+}
+
+impl From<&String> for FileType {
+    fn from(filetype: &String) -> Self {
+        Self::new(filetype)
+    }
+}
+
+impl From<&str> for FileType {
+    fn from(filetype: &str) -> Self {
+        Self::new(filetype)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_related_extensions() {
+        let c: FileType = "c".into();
+        assert!(c.is_relevant("src/main.c"));
+        assert!(c.is_relevant("include/foo.H"));
+        assert!(!c.is_relevant("src/main.rs"));
+
+        let zig: FileType = "zig".into();
+        assert!(zig.is_relevant("build.zig"));
+        assert!(!zig.is_relevant("zig"));
+    }
+
+    #[test]
+    fn test_filetype_bonus() {
+        let rust: FileType = "rust".into();
+        assert_eq!(
+            rust.calc_bonus(&"crates/matcher/src/lib.rs".into(), 100),
+            25
+        );
+        assert_eq!(rust.calc_bonus(&"vendor/jquery/lib.js".into(), 100), 0);
+    }
+}
@@ -1,9 +1,12 @@
+pub mod acronym;
+pub mod filetype;
 pub mod language;
 pub mod recent_files;
 
 use source_item::SourceItem;
 
 use crate::Score;
+use filetype::FileType;
 use language::Language;
 use recent_files::RecentFiles;
 
@@ -18,9 +21,16 @@ pub enum Bonus {
     /// Give a bonus to for the keywords if the language type is known.
     Language(Language),
 
+    /// Give a bonus to the files relevant to the filetype of current buffer.
+    FileType(FileType),
+
     /// Give a bonus if the item is in the list of recently opened files.
     RecentFiles(RecentFiles),
 
+    /// Give a bonus if the word initials spell the all-uppercase query, e.g., `FBR` for
+    /// `foo_bar_reader`, which is matched despite the smart case.
+    Acronym,
+
     /// No additional bonus.
     None,
 }
@@ -42,6 +52,7 @@ impl From<&str> for Bonus {
         match b.to_lowercase().as_str() {
             "none" => Self::None,
             "filename" => Self::FileName,
+            "acronym" => Self::Acronym,
             _ => Self::None,
         }
     }
@@ -54,11 +65,16 @@ impl From<&String> for Bonus {
 }
 
 fn bonus_for_filename(item: &SourceItem, score: Score, indices: &[usize]) -> Score {
-    if let Some((_, idx)) = pattern::file_name_only(&item.raw) {
-        if item.raw.len() > idx {
+    // The matched indices are in the coordinate of display text.
+    let line = item.display_text();
+    if let Some((_, idx)) = pattern::file_name_only(line) {
+        // The indices are counted in chars.
+        let idx = line[..idx].chars().count();
+        let line_len = line.chars().count();
+        if line_len > idx {
             let hits_filename = indices.iter().filter(|x| **x >= idx).count();
             // bonus = base_score * len(matched elements in filename) / len(filename)
-            score * hits_filename as i64 / (item.raw.len() - idx) as i64
+            score * hits_filename as i64 / (line_len - idx) as i64
         } else {
             0
         }
@@ -69,7 +85,13 @@ fn bonus_for_filename(item: &SourceItem, score: Score, indices: &[usize]) -> Sco
 
 impl Bonus {
     /// Calculates the bonus score given the match result of base algorithm.
-    pub fn bonus_for(&self, item: &SourceItem, score: Score, indices: &[usize]) -> Score {
+    pub fn bonus_for(
+        &self,
+        item: &SourceItem,
+        query: &str,
+        score: Score,
+        indices: &[usize],
+    ) -> Score {
         // Ignore the long line.
         if item.raw.len() > 1024 {
             return 0;
@@ -79,6 +101,8 @@ impl Bonus {
             Bonus::FileName => bonus_for_filename(item, score, indices),
             Bonus::RecentFiles(recent_files) => recent_files.calc_bonus(item, score),
             Bonus::Language(language) => language.calc_bonus(item, score),
+            Bonus::FileType(filetype) => filetype.calc_bonus(item, score),
+            Bonus::Acronym => acronym::calc_bonus(item, query, score),
             Bonus::None => 0,
         }
     }
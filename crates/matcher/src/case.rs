@@ -0,0 +1,80 @@
+//! Case sensitivity of the matching.
+//!
+//! The base algorithms match case insensitively, the results of a case sensitive query are
+//! checked against the case of the matched chars afterwards.
+
+use structopt::clap::arg_enum;
+
+use crate::query::parse_alternatives;
+
+arg_enum! {
+  /// How the case of the query is respected, same with the case options of rg.
+  ///
+  /// `Smart` matches case sensitively only if the query contains an uppercase char.
+  #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+  pub enum CaseMatching {
+      Ignore,
+      Smart,
+      Respect,
+  }
+}
+
+impl Default for CaseMatching {
+    fn default() -> Self {
+        Self::Smart
+    }
+}
+
+impl CaseMatching {
+    pub fn is_sensitive(&self, query: &str) -> bool {
+        match self {
+            Self::Ignore => false,
+            Self::Smart => query.chars().any(char::is_uppercase),
+            Self::Respect => true,
+        }
+    }
+}
+
+/// Returns true if the chars of `text` at `indices` contain the chars of any alternative of
+/// `query` in order with the exact case.
+///
+/// The result without indices is accepted as it can't be checked, e.g., fzy gives up the
+/// extremely long line.
+pub(crate) fn matches_case(text: &str, indices: &[usize], query: &str) -> bool {
+    if indices.is_empty() {
+        return true;
+    }
+
+    let chars = text.chars().collect::<Vec<_>>();
+    let matched = indices
+        .iter()
+        .filter_map(|&idx| chars.get(idx).copied())
+        .collect::<Vec<_>>();
+
+    parse_alternatives(query).into_iter().any(|alternative| {
+        let mut matched = matched.iter();
+        alternative
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .all(|q| matched.any(|&m| m == q))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches_case() {
+        assert!(CaseMatching::Smart.is_sensitive("Foo"));
+        assert!(!CaseMatching::Smart.is_sensitive("foo"));
+        assert!(!CaseMatching::Ignore.is_sensitive("Foo"));
+        assert!(CaseMatching::Respect.is_sensitive("foo"));
+
+        assert!(matches_case("fn Foo()", &[3, 4, 5], "Foo"));
+        assert!(!matches_case("fn foo()", &[3, 4, 5], "Foo"));
+        assert!(matches_case("fn foo()", &[3, 4, 5], "Bar|foo"));
+        assert!(matches_case("fn Foo bar()", &[3, 4, 5, 7, 8, 9], "Foo bar"));
+        assert!(matches_case("fn foo()", &[], "Foo"));
+    }
+}
@@ -0,0 +1,96 @@
+//! Matching over the grapheme clusters instead of chars.
+//!
+//! A user-perceived character can consist of multiple chars, e.g., `é` written as `e` and the
+//! combining acute accent. Matching such text by chars can match a part of the cluster only,
+//! which is not what the user sees in Vim. Each cluster of multiple chars is replaced by a
+//! single symbol char before running the match algorithm, so that it's matched as a whole.
+
+use std::collections::HashMap;
+
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::MatchResult;
+
+/// Start of the supplementary private use area, which is used as the symbols of the clusters.
+const SYMBOL_START: u32 = 0xF0000;
+
+/// Matches `text` against `query` by the grapheme clusters with `match_chars`.
+///
+/// Returns `None` if neither of them has a cluster of multiple chars, in which case matching
+/// by chars is the same.
+pub(crate) fn match_graphemes(
+    text: &str,
+    query: &str,
+    match_chars: impl Fn(&str, &str) -> MatchResult,
+) -> Option<MatchResult> {
+    let text_clusters = text.graphemes(true).collect::<Vec<_>>();
+    let query_clusters = query.graphemes(true).collect::<Vec<_>>();
+
+    let is_multi_char = |cluster: &&str| cluster.chars().nth(1).is_some();
+    if !text_clusters.iter().any(is_multi_char) && !query_clusters.iter().any(is_multi_char) {
+        return None;
+    }
+
+    // The clusters of different cases share the same symbol like the chars.
+    let mut symbols = HashMap::new();
+    let mut to_symbols = |clusters: &[&str]| {
+        clusters
+            .iter()
+            .map(|cluster| {
+                if !is_multi_char(cluster) {
+                    return cluster.chars().next().unwrap_or_default();
+                }
+                let next_symbol = SYMBOL_START + symbols.len() as u32;
+                *symbols
+                    .entry(cluster.to_lowercase())
+                    .or_insert_with(|| std::char::from_u32(next_symbol).unwrap_or_default())
+            })
+            .collect::<String>()
+    };
+
+    let text_symbols = to_symbols(&text_clusters);
+    let query_symbols = to_symbols(&query_clusters);
+
+    // Char index of the start of each cluster in `text`.
+    let mut cluster_starts = Vec::with_capacity(text_clusters.len() + 1);
+    let mut char_idx = 0;
+    for cluster in text_clusters.iter() {
+        cluster_starts.push(char_idx);
+        char_idx += cluster.chars().count();
+    }
+    cluster_starts.push(char_idx);
+
+    Some(
+        match_chars(&text_symbols, &query_symbols).map(|(score, indices)| {
+            let indices = indices
+                .into_iter()
+                .flat_map(|idx| cluster_starts[idx]..cluster_starts[idx + 1])
+                .collect();
+            (score, indices)
+        }),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::substring::substr_indices;
+
+    #[test]
+    fn test_match_graphemes() {
+        // `é` is decomposed into `e` and the combining acute accent.
+        let text = "exe\u{301}cute";
+        let (_, indices) = match_graphemes(text, "e\u{301}", substr_indices)
+            .unwrap()
+            .unwrap();
+        assert_eq!(indices, vec![2, 3]);
+
+        // The plain `e` does not match a part of `é`.
+        assert_eq!(
+            match_graphemes("xe\u{301}", "xe", substr_indices).unwrap(),
+            None
+        );
+
+        assert!(match_graphemes("中文", "中", substr_indices).is_none());
+    }
+}
@@ -0,0 +1,104 @@
+//! Invariants of the match result, shared by the property tests and the fuzz target.
+//!
+//! For a match result of `query` on `line`:
+//!
+//! - the matched indices are strictly increasing.
+//! - the matched indices are within the bounds of `line`, counted in chars.
+//! - the chars at the matched indices are in `query`, ignoring case.
+
+use crate::MatchResult;
+
+#[inline]
+fn lowercase_char(c: char) -> char {
+    c.to_lowercase().next().unwrap_or(c)
+}
+
+/// Checks the match result of `query` on `line`, returns the violated invariant if any.
+pub fn check_match_result(line: &str, query: &str, result: &MatchResult) -> Result<(), String> {
+    let indices = match result {
+        Some((_, indices)) => indices,
+        None => return Ok(()),
+    };
+
+    if indices.windows(2).any(|w| w[0] >= w[1]) {
+        return Err(format!("indices {:?} are not strictly increasing", indices));
+    }
+
+    let line_chars = line.chars().collect::<Vec<_>>();
+    let query_chars = query.chars().map(lowercase_char).collect::<Vec<_>>();
+
+    for &idx in indices {
+        let c = line_chars
+            .get(idx)
+            .ok_or_else(|| format!("index {} is out of the bounds of {:?}", idx, line))?;
+        if !query_chars.contains(&lowercase_char(*c)) {
+            return Err(format!(
+                "char {:?} at index {} of {:?} is not in the query {:?}",
+                c, idx, line, query
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Algo, MatchType};
+    use proptest::prelude::*;
+    use source_item::SourceItem;
+
+    fn all_algos() -> Vec<Algo> {
+        vec![Algo::Fzy, Algo::Skim, Algo::SubString, Algo::Auto]
+    }
+
+    #[test]
+    fn test_check_match_result() {
+        assert!(check_match_result("abc", "ac", &Some((0, vec![0, 2]))).is_ok());
+        assert!(check_match_result("abc", "ac", &Some((0, vec![2, 0]))).is_err());
+        assert!(check_match_result("abc", "ac", &Some((0, vec![0, 3]))).is_err());
+        assert!(check_match_result("abc", "ac", &Some((0, vec![0, 1]))).is_err());
+        assert!(check_match_result("中文", "文", &Some((0, vec![1]))).is_ok());
+    }
+
+    proptest! {
+        #[test]
+        fn full_match_invariants(line in "\\PC{0,64}", query in "\\PC{0,8}") {
+            let item: SourceItem = line.as_str().into();
+            for algo in all_algos() {
+                let result = algo.apply_match(&query, &item, &MatchType::Full);
+                prop_assert_eq!(check_match_result(&line, &query, &result), Ok(()));
+            }
+        }
+
+        #[test]
+        fn grep_line_invariants(
+            path in "[\\PC&&[^:]]{1,16}",
+            lnum in 1usize..1000,
+            col in 1usize..100,
+            content in "\\PC{0,48}",
+            query in "\\PC{1,8}",
+        ) {
+            let line = format!("{}:{}:{}:{}", path, lnum, col, content);
+            let item: SourceItem = line.as_str().into();
+            for algo in all_algos() {
+                for match_type in &[MatchType::IgnoreFilePath, MatchType::FileName] {
+                    let result = algo.apply_match(&query, &item, match_type);
+                    prop_assert_eq!(check_match_result(&line, &query, &result), Ok(()));
+                }
+            }
+        }
+
+        #[test]
+        fn exact_match_scores_highest(query in "[a-z0-9_/.]{1,16}", suffix in "[a-z0-9_/. ]{1,16}") {
+            let exact: SourceItem = query.as_str().into();
+            let longer: SourceItem = format!("{}{}", query, suffix).into();
+            for algo in &[Algo::Fzy, Algo::SubString] {
+                let (exact_score, _) = algo.apply_match(&query, &exact, &MatchType::Full).unwrap();
+                let (longer_score, _) = algo.apply_match(&query, &longer, &MatchType::Full).unwrap();
+                prop_assert!(exact_score >= longer_score);
+            }
+        }
+    }
+}
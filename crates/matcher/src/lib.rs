@@ -19,12 +19,23 @@
 
 mod algo;
 mod bonus;
+mod case;
+mod grapheme;
+pub mod invariants;
+mod long_line;
+pub mod normalize;
+mod prefilter;
+mod query;
+pub mod typo;
 
 use source_item::SourceItem;
 
 pub use self::algo::*;
 pub use self::bonus::language::Language;
 pub use self::bonus::Bonus;
+pub use self::case::CaseMatching;
+pub use self::long_line::{LineCutoff, LongLineStrategy};
+pub use self::query::parse_alternatives;
 pub use source_item::MatchType;
 
 /// Score of base matching algorithm(fzy, skim, etc).
@@ -38,10 +49,15 @@ pub type MatchResult = Option<(Score, Vec<usize>)>;
 ///   * `match_type`: represents the way of extracting the matching piece from the raw line.
 ///   * `algo`: algorithm used for matching the text.
 ///   * `bonus`: add a bonus to the result of base `algo`.
+///   * `line_cutoff`: score only a part of the extremely long line.
+///   * `case_matching`: whether the case of query is respected.
+#[derive(Debug)]
 pub struct Matcher {
     match_type: MatchType,
     algo: Algo,
     bonuses: Vec<Bonus>,
+    line_cutoff: Option<LineCutoff>,
+    case_matching: CaseMatching,
 }
 
 impl Matcher {
@@ -51,6 +67,8 @@ impl Matcher {
             algo,
             match_type,
             bonuses: vec![bonus],
+            line_cutoff: None,
+            case_matching: CaseMatching::default(),
         }
     }
 
@@ -59,25 +77,124 @@ impl Matcher {
             algo,
             match_type,
             bonuses,
+            line_cutoff: None,
+            case_matching: CaseMatching::default(),
         }
     }
 
+    /// Sets the cutoff for the long line.
+    pub fn line_cutoff(mut self, line_cutoff: Option<LineCutoff>) -> Self {
+        self.line_cutoff = line_cutoff;
+        self
+    }
+
+    /// Sets the case sensitivity of the matching.
+    pub fn case_matching(mut self, case_matching: CaseMatching) -> Self {
+        self.case_matching = case_matching;
+        self
+    }
+
+    /// Returns true if `text` is spelled by the acronym `query` with [`Bonus::Acronym`], which
+    /// is accepted regardless of the case.
+    fn accepts_acronym(&self, text: &str, query: &str) -> bool {
+        bonus::acronym::is_acronym(query)
+            && self.bonuses.iter().any(|b| matches!(b, Bonus::Acronym))
+            && bonus::acronym::spells(text, query)
+    }
+
+    /// Returns true if the lines matched by `query` are always matched by any prefix of it, so
+    /// that `query` only has to be matched against the matches of its prefix.
+    ///
+    /// Not the case for the alternatives, which match more lines as the query grows, nor for the
+    /// case sensitive query, whose matched indices may change the case check.
+    pub fn narrows_by_prefix(&self, query: &str) -> bool {
+        !query.contains(query::ALTERNATION_SEPARATOR) && !self.case_matching.is_sensitive(query)
+    }
+
     /// Match the item without considering the bonus.
     #[inline]
     pub fn base_match(&self, item: &SourceItem, query: &str) -> MatchResult {
-        self.algo.apply_match(query, item, &self.match_type)
+        let (text, offset) = item.match_text_for(&self.match_type)?;
+        self.base_match_text(text, offset, query)
+    }
+
+    /// Matches the text extracted from a line, `offset` is added to the matched indices.
+    fn base_match_text(&self, text: &str, offset: usize, query: &str) -> MatchResult {
+        let (text, offset) = match self.line_cutoff {
+            Some(ref line_cutoff) => {
+                let (window, window_offset) = line_cutoff.window(text, query)?;
+                (window, offset + window_offset)
+            }
+            None => (text, offset),
+        };
+
+        let (score, indices) = match self.case_matching {
+            // The base algorithms are insensitive to the lowercase query.
+            CaseMatching::Ignore => {
+                self.algo
+                    .apply_match_on_text(&query.to_lowercase(), text, 0)?
+            }
+            case_matching => {
+                let result = self.algo.apply_match_on_text(query, text, 0)?;
+                if case_matching.is_sensitive(query)
+                    && !case::matches_case(text, &result.1, query)
+                    && !(case_matching == CaseMatching::Smart && self.accepts_acronym(text, query))
+                {
+                    return None;
+                }
+                result
+            }
+        };
+
+        Some((score, indices.into_iter().map(|x| x + offset).collect()))
     }
 
     /// Actually performs the matching algorithm.
+    ///
+    /// The returned indices are in the coordinate of the display text.
     pub fn do_match(&self, item: &SourceItem, query: &str) -> MatchResult {
-        self.base_match(item, query).map(|(score, indices)| {
-            let total_bonus_score: Score = self
-                .bonuses
-                .iter()
-                .map(|b| b.bonus_for(item, score, &indices))
-                .sum();
-            (score + total_bonus_score, indices)
-        })
+        self.base_match(item, query)
+            .map(|(score, indices)| self.add_bonuses(item, query, score, indices))
+    }
+
+    /// Matches the plain `line` borrowed from the source, e.g., the content of a file, the item
+    /// is only created for the matched line.
+    ///
+    /// Same result with [`Self::do_match`] on the item of `line`.
+    pub fn match_line(&self, line: &str, query: &str) -> Option<(SourceItem, Score, Vec<usize>)> {
+        let (text, offset) = source_item::line_match_text_for(line, &self.match_type)?;
+        let (score, indices) = self.base_match_text(text, offset, query)?;
+        let item = SourceItem::from(line);
+        let (score, indices) = self.add_bonuses(&item, query, score, indices);
+        Some((item, score, indices))
+    }
+
+    fn add_bonuses(
+        &self,
+        item: &SourceItem,
+        query: &str,
+        score: Score,
+        indices: Vec<usize>,
+    ) -> (Score, Vec<usize>) {
+        let indices = item.display_indices(indices);
+        let total_bonus_score: Score = self
+            .bonuses
+            .iter()
+            .map(|b| b.bonus_for(item, query, score, &indices))
+            .sum();
+        (score + total_bonus_score, indices)
+    }
+
+    /// Matches the item against the variants of `query` with one typo fixed, for the item
+    /// failed to match strictly.
+    ///
+    /// The best result is penalized by [`typo::TYPO_PENALTY`].
+    pub fn typo_match(&self, item: &SourceItem, query: &str) -> MatchResult {
+        typo::typo_variants(query)
+            .iter()
+            .filter_map(|variant| self.do_match(item, variant))
+            .max_by_key(|(score, _)| *score)
+            .map(|(score, indices)| (score - typo::TYPO_PENALTY, indices))
     }
 }
 
@@ -112,6 +229,143 @@ mod tests {
         assert_eq!(origin_indices, indices);
     }
 
+    #[test]
+    fn test_alternation() {
+        let matcher = Matcher::new(Algo::Fzy, MatchType::Full, Bonus::None);
+        let item: SourceItem = "autoload/clap/provider/files.vim".into();
+
+        assert!(matcher.do_match(&item, "rs|lua").is_none());
+
+        let (score, indices) = matcher.do_match(&item, "rs|files").unwrap();
+        assert_eq!((score, indices), matcher.do_match(&item, "files").unwrap());
+
+        let (best_score, _) = matcher.do_match(&item, "fls|files").unwrap();
+        let (fls_score, _) = matcher.do_match(&item, "fls").unwrap();
+        assert!(best_score >= fls_score);
+        assert_eq!(best_score, score);
+    }
+
+    #[test]
+    fn test_auto_algo() {
+        let path = "crates/maple_cli/src/lib.rs";
+        let text = "skim is used for the long text lines containing a lot of words, e.g. comments";
+        assert!(matches!(Algo::auto_select(path), Algo::Fzy));
+        assert!(matches!(Algo::auto_select("println_json"), Algo::Fzy));
+        assert!(matches!(Algo::auto_select("let foo = bar;"), Algo::Skim));
+        assert!(matches!(Algo::auto_select(text), Algo::Skim));
+
+        let item: SourceItem = path.into();
+        assert_eq!(
+            Algo::Auto.apply_match("lib", &item, &MatchType::Full),
+            Algo::Fzy.apply_match("lib", &item, &MatchType::Full)
+        );
+        let item: SourceItem = text.into();
+        assert_eq!(
+            Algo::Auto.apply_match("skim", &item, &MatchType::Full),
+            Algo::Skim.apply_match("skim", &item, &MatchType::Full)
+        );
+    }
+
+    #[test]
+    fn test_case_matching() {
+        let item: SourceItem = "src/Matcher.rs".into();
+        let matcher = |case_matching| {
+            Matcher::new(Algo::Fzy, MatchType::Full, Bonus::None).case_matching(case_matching)
+        };
+
+        assert!(matcher(CaseMatching::Smart)
+            .do_match(&item, "Match")
+            .is_some());
+        assert!(matcher(CaseMatching::Smart)
+            .do_match(&item, "match")
+            .is_some());
+        assert!(matcher(CaseMatching::Smart)
+            .do_match(&item, "MATCH")
+            .is_none());
+        assert!(matcher(CaseMatching::Ignore)
+            .do_match(&item, "MATCH")
+            .is_some());
+        assert!(matcher(CaseMatching::Respect)
+            .do_match(&item, "match")
+            .is_none());
+    }
+
+    #[test]
+    fn test_typo_match() {
+        let item: SourceItem = "src/matcher.rs".into();
+        let matcher = Matcher::new(Algo::SubString, MatchType::Full, Bonus::None);
+        assert!(matcher.do_match(&item, "mtacher").is_none());
+        let (score, indices) = matcher.typo_match(&item, "mtacher").unwrap();
+        let (strict_score, strict_indices) = matcher.do_match(&item, "matcher").unwrap();
+        assert_eq!(score, strict_score - typo::TYPO_PENALTY);
+        assert_eq!(indices, strict_indices);
+
+        let matcher = Matcher::new(Algo::Fzy, MatchType::Full, Bonus::None);
+        assert!(matcher.do_match(&item, "matxher").is_none());
+        assert!(matcher.typo_match(&item, "matxher").is_some());
+        assert!(matcher.typo_match(&item, "mxtxher").is_none());
+    }
+
+    #[test]
+    fn test_line_cutoff() {
+        let line: SourceItem = format!("{}needle", "x".repeat(2000)).into();
+        let matcher = Matcher::new(Algo::Fzy, MatchType::Full, Bonus::None);
+
+        let prefix_matcher = Matcher::new(Algo::Fzy, MatchType::Full, Bonus::None)
+            .line_cutoff(Some(LineCutoff::new(100, LongLineStrategy::Prefix)));
+        assert!(prefix_matcher.do_match(&line, "needle").is_none());
+
+        let window_matcher = Matcher::new(Algo::Fzy, MatchType::Full, Bonus::None)
+            .line_cutoff(Some(LineCutoff::new(100, LongLineStrategy::Window)));
+        let (_, indices) = window_matcher.do_match(&line, "needle").unwrap();
+        assert_eq!(indices, (2000..2006).collect::<Vec<_>>());
+        // fzy gives up the line longer than 1024 chars.
+        assert!(matcher.do_match(&line, "needle").unwrap().1.is_empty());
+    }
+
+    #[test]
+    fn test_match_line() {
+        let lines = [
+            "crates/matcher/src/lib.rs:12:5:pub fn match_line()",
+            "crates/matcher/src/lib.rs",
+            "match_line	crates/matcher/src/lib.rs	/^pub fn match_line/;\"	f",
+        ];
+        for match_type in &[
+            MatchType::Full,
+            MatchType::FileName,
+            MatchType::IgnoreFilePath,
+            MatchType::TagName,
+        ] {
+            let matcher = Matcher::new(Algo::Fzy, match_type.clone(), Bonus::FileName);
+            for line in &lines {
+                for query in &["mline", "lib", "xyz"] {
+                    assert_eq!(
+                        matcher
+                            .match_line(line, query)
+                            .map(|(item, score, indices)| (item.raw, score, indices)),
+                        matcher
+                            .do_match(&(*line).into(), query)
+                            .map(|(score, indices)| (line.to_string(), score, indices))
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_acronym_bonus() {
+        let item: SourceItem = "fn foo_bar_reader()".into();
+        let matcher = Matcher::new(Algo::Fzy, MatchType::Full, Bonus::None);
+        assert!(matcher.do_match(&item, "FBR").is_none());
+
+        let matcher = Matcher::new(Algo::Fzy, MatchType::Full, Bonus::Acronym);
+        let (base_score, _) = matcher.base_match(&item, "FBR").unwrap();
+        let (score, _) = matcher.do_match(&item, "FBR").unwrap();
+        assert_eq!(score, base_score * 2);
+        let item: SourceItem = "fn xfbr()".into();
+        assert!(matcher.do_match(&item, "FBR").is_none());
+    }
+
     #[test]
     fn test_filename_bonus() {
         let lines = vec![
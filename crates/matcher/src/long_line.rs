@@ -0,0 +1,137 @@
+//! Guards the matcher against the extremely long lines, e.g., minified JS or data files.
+//!
+//! Only a part of the line longer than the cutoff is scored, the matched indices are still
+//! in the coordinate of the whole line so that the printer can truncate the line around them.
+
+use structopt::clap::arg_enum;
+
+use crate::query::parse_alternatives;
+
+/// Number of chars kept before the first candidate in the window.
+const WINDOW_LEADING_CHARS: usize = 16;
+
+arg_enum! {
+  /// The part of the long line to score.
+  ///
+  /// - `Prefix`: score the leading chars up to the cutoff only.
+  /// - `Window`: score a window of the cutoff length around the first candidate of query.
+  #[derive(Debug, Clone)]
+  pub enum LongLineStrategy {
+      Prefix,
+      Window,
+  }
+}
+
+impl Default for LongLineStrategy {
+    fn default() -> Self {
+        Self::Prefix
+    }
+}
+
+/// Cutoff for the long line, specified by `--max-line-len`.
+#[derive(Debug, Clone)]
+pub struct LineCutoff {
+    max_len: usize,
+    strategy: LongLineStrategy,
+}
+
+impl LineCutoff {
+    pub fn new(max_len: usize, strategy: LongLineStrategy) -> Self {
+        Self { max_len, strategy }
+    }
+
+    /// Returns the part of `text` to score and its char offset in `text`.
+    ///
+    /// Returns `None` if the line can not match `query` at all.
+    pub fn window<'a>(&self, text: &'a str, query: &str) -> Option<(&'a str, usize)> {
+        // Fast path, the char count is never larger than the byte length.
+        if text.len() <= self.max_len {
+            return Some((text, 0));
+        }
+
+        let char_count = text.chars().count();
+        if char_count <= self.max_len {
+            return Some((text, 0));
+        }
+
+        let start = match self.strategy {
+            LongLineStrategy::Prefix => 0,
+            LongLineStrategy::Window => {
+                let first_candidate = first_candidate(text, query)?;
+                first_candidate
+                    .saturating_sub(WINDOW_LEADING_CHARS.min(self.max_len / 2))
+                    .min(char_count - self.max_len)
+            }
+        };
+
+        let byte_index = |char_idx: usize| {
+            text.char_indices()
+                .nth(char_idx)
+                .map(|(idx, _)| idx)
+                .unwrap_or_else(|| text.len())
+        };
+
+        let (byte_start, byte_end) = (byte_index(start), byte_index(start + self.max_len));
+
+        Some((&text[byte_start..byte_end], start))
+    }
+}
+
+/// Returns the char index of the earliest char in `text` that starts any alternative of `query`.
+fn first_candidate(text: &str, query: &str) -> Option<usize> {
+    let first_chars = parse_alternatives(query)
+        .into_iter()
+        .filter_map(|alternative| alternative.chars().find(|c| !c.is_whitespace()))
+        .flat_map(char::to_lowercase)
+        .collect::<Vec<_>>();
+
+    if first_chars.is_empty() {
+        return Some(0);
+    }
+
+    text.chars()
+        .position(|c| c.to_lowercase().any(|c| first_chars.contains(&c)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_short_line() {
+        let cutoff = LineCutoff::new(10, LongLineStrategy::Window);
+        assert_eq!(cutoff.window("short", "zzz"), Some(("short", 0)));
+        assert_eq!(
+            cutoff.window("中文中文中文", "zzz"),
+            Some(("中文中文中文", 0))
+        );
+    }
+
+    #[test]
+    fn test_prefix() {
+        let cutoff = LineCutoff::new(4, LongLineStrategy::Prefix);
+        assert_eq!(cutoff.window("abcdefgh", "fg"), Some(("abcd", 0)));
+        assert_eq!(cutoff.window("中文中文中文", "文"), Some(("中文中文", 0)));
+    }
+
+    #[test]
+    fn test_window() {
+        let line = format!("{}needle{}", "x".repeat(100), "y".repeat(100));
+        let cutoff = LineCutoff::new(40, LongLineStrategy::Window);
+        let (window, offset) = cutoff.window(&line, "Needle").unwrap();
+        assert_eq!(offset, 100 - WINDOW_LEADING_CHARS);
+        assert_eq!(window.len(), 40);
+        assert!(window.contains("needle"));
+
+        // The window is never beyond the end of line.
+        let (window, offset) = cutoff.window(&line, "y|needle").unwrap();
+        assert_eq!((window.len(), offset), (40, 100 - WINDOW_LEADING_CHARS));
+        let (window, offset) = cutoff.window(&line, "yy").unwrap();
+        assert_eq!((window.len(), offset), (40, 106 - WINDOW_LEADING_CHARS));
+        let line = format!("{}y", "x".repeat(100));
+        let (window, offset) = cutoff.window(&line, "y").unwrap();
+        assert_eq!((window.len(), offset), (40, 61));
+
+        assert!(cutoff.window(&line, "z").is_none());
+    }
+}
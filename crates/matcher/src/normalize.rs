@@ -0,0 +1,92 @@
+//! Diacritic-insensitive matching, e.g., `e` in the query matches `é`, `è` and `ê`.
+//!
+//! Both the text and query are folded by the NFKD decomposition with the combining marks
+//! removed before matching, the matched indices are mapped back onto the original text.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
+
+use crate::MatchResult;
+
+static NORMALIZE: AtomicBool = AtomicBool::new(false);
+
+/// Sets whether to fold the diacritics when matching in this process.
+pub fn set_normalize(enable: bool) {
+    NORMALIZE.store(enable, Ordering::Relaxed);
+}
+
+pub fn is_enabled() -> bool {
+    NORMALIZE.load(Ordering::Relaxed)
+}
+
+/// Returns the folded `text` and the char index in `text` of each folded char.
+fn fold(text: &str) -> (String, Vec<usize>) {
+    let mut folded = String::with_capacity(text.len());
+    let mut origins = Vec::with_capacity(text.len());
+    for (idx, c) in text.chars().enumerate() {
+        if c.is_ascii() {
+            folded.push(c);
+            origins.push(idx);
+            continue;
+        }
+        for decomposed in std::iter::once(c).nfkd().filter(|c| !is_combining_mark(*c)) {
+            folded.push(decomposed);
+            origins.push(idx);
+        }
+    }
+    (folded, origins)
+}
+
+/// Matches the folded `text` against the folded `query` with `match_chars`.
+///
+/// Returns `None` if both of them are ASCII, which are the same after folding.
+pub(crate) fn match_folded(
+    text: &str,
+    query: &str,
+    match_chars: impl Fn(&str, &str) -> MatchResult,
+) -> Option<MatchResult> {
+    if text.is_ascii() && query.is_ascii() {
+        return None;
+    }
+
+    let (folded_text, origins) = fold(text);
+    let (folded_query, _) = fold(query);
+
+    Some(
+        match_chars(&folded_text, &folded_query).map(|(score, indices)| {
+            let mut indices = indices
+                .into_iter()
+                .map(|idx| origins[idx])
+                .collect::<Vec<_>>();
+            // The chars decomposed from the same char, e.g., `ﬁ`, are merged.
+            indices.dedup();
+            (score, indices)
+        }),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::substring::substr_indices;
+
+    #[test]
+    fn test_match_folded() {
+        let (_, indices) = match_folded("café crème", "cafe creme", substr_indices)
+            .unwrap()
+            .unwrap();
+        assert_eq!(indices, (0..10).filter(|i| *i != 4).collect::<Vec<_>>());
+
+        let (_, indices) = match_folded("ﬁle", "file", substr_indices)
+            .unwrap()
+            .unwrap();
+        assert_eq!(indices, vec![0, 1, 2]);
+
+        // The accented query matches the unaccented text too.
+        assert!(match_folded("resume", "résumé", substr_indices)
+            .unwrap()
+            .is_some());
+        assert!(match_folded("resume", "resume", substr_indices).is_none());
+    }
+}
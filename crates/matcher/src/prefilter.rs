@@ -0,0 +1,66 @@
+//! Cheap check of whether a line can be matched at all, before running the scoring algorithm.
+//!
+//! The base algorithms require every char of the query to be in the line, in order, ignoring
+//! the case. The prefilter checks the weaker condition that every char of the query appears
+//! in the line regardless of the order, using a bitmask of the ASCII chars of each side, which
+//! rejects most of the lines of a large source in a single byte scan.
+
+/// Bitmask of the lowercased ASCII chars of `s`, `None` if `s` is not ASCII.
+///
+/// The whitespaces are not counted, which separate the words of the substring query.
+#[inline]
+fn ascii_mask(s: &str) -> Option<u128> {
+    let mut mask = 0u128;
+    for &byte in s.as_bytes() {
+        if !byte.is_ascii() {
+            return None;
+        }
+        if !byte.is_ascii_whitespace() {
+            mask |= 1u128 << byte.to_ascii_lowercase();
+        }
+    }
+    Some(mask)
+}
+
+/// Returns false if `text` can not be matched by `query` for sure.
+///
+/// Only the ASCII text and query are checked, the others, e.g., with the diacritics folded by
+/// `--normalize`, are always passed to the base algorithm.
+#[inline]
+pub(crate) fn may_match(text: &str, query: &str) -> bool {
+    match (ascii_mask(query), ascii_mask(text)) {
+        (Some(query_mask), Some(text_mask)) => query_mask & !text_mask == 0,
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algo::{fzy, skim, substring};
+    use proptest::prelude::*;
+
+    #[test]
+    fn test_may_match() {
+        assert!(may_match("src/lib.rs", "SRC"));
+        assert!(may_match("src/lib.rs", "bil"));
+        assert!(may_match("src/lib.rs", "sr li"));
+        assert!(may_match("src/lib.rs", ""));
+        assert!(!may_match("src/lib.rs", "srx"));
+        // Not ASCII.
+        assert!(may_match("中文/src", "cx"));
+        assert!(may_match("src", "é"));
+    }
+
+    proptest! {
+        #[test]
+        fn never_rejects_a_match(line in "[ -~]{0,48}", query in "[ -~]{1,6}") {
+            let matched = fzy::fuzzy_indices(&line, &query).is_some()
+                || skim::fuzzy_indices(&line, &query).is_some()
+                || substring::substr_indices(&line, &query).is_some();
+            if matched {
+                prop_assert!(may_match(&line, &query));
+            }
+        }
+    }
+}
@@ -0,0 +1,37 @@
+//! Parses the query string into the alternatives for matching.
+
+/// Separator of the query alternatives, `foo|bar` matches the line containing `foo` or `bar`.
+pub const ALTERNATION_SEPARATOR: char = '|';
+
+/// Returns the alternatives of `query`.
+///
+/// A line matches the query if any of the alternatives matches. The empty alternatives are
+/// ignored, the query itself is returned if there is no non-empty alternative.
+pub fn parse_alternatives(query: &str) -> Vec<&str> {
+    let alternatives = query
+        .split(ALTERNATION_SEPARATOR)
+        .map(str::trim)
+        .filter(|alternative| !alternative.is_empty())
+        .collect::<Vec<_>>();
+
+    if alternatives.is_empty() {
+        vec![query]
+    } else {
+        alternatives
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_alternatives() {
+        assert_eq!(parse_alternatives("foo"), vec!["foo"]);
+        assert_eq!(parse_alternatives("foo|bar"), vec!["foo", "bar"]);
+        assert_eq!(parse_alternatives(" .rs | .vim |"), vec![".rs", ".vim"]);
+        assert_eq!(parse_alternatives("sr bl|lib"), vec!["sr bl", "lib"]);
+        assert_eq!(parse_alternatives("|"), vec!["|"]);
+        assert_eq!(parse_alternatives(""), vec![""]);
+    }
+}
@@ -0,0 +1,67 @@
+//! Typo tolerance, a query with one transposed, wrong or extra char can still match.
+//!
+//! The typos are fixed by the variants of query with two adjacent chars swapped or one char
+//! removed, the latter covers a wrong char for the fuzzy algorithms. The variants are only tried
+//! as a fallback pass when the strict pass yields fewer than [`MIN_STRICT_MATCHES`] results.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::Score;
+
+static TYPO_TOLERANT: AtomicBool = AtomicBool::new(false);
+
+/// The typos are not tolerated if the strict pass has at least this many results.
+pub const MIN_STRICT_MATCHES: usize = 5;
+
+/// Score penalty of a match with the typo fixed.
+pub const TYPO_PENALTY: Score = 100;
+
+/// The shorter queries are too loose to tolerate a typo.
+const MIN_QUERY_LEN: usize = 3;
+
+/// Sets whether to tolerate one typo in the query when matching in this process.
+pub fn set_typo_tolerant(enable: bool) {
+    TYPO_TOLERANT.store(enable, Ordering::Relaxed);
+}
+
+pub fn is_enabled() -> bool {
+    TYPO_TOLERANT.load(Ordering::Relaxed)
+}
+
+/// Returns the distinct variants of `query` with one typo fixed.
+pub(crate) fn typo_variants(query: &str) -> Vec<String> {
+    let chars = query.chars().collect::<Vec<_>>();
+    if chars.len() < MIN_QUERY_LEN {
+        return Vec::new();
+    }
+
+    let mut variants: Vec<String> = Vec::new();
+    let mut push = |variant: String| {
+        if variant != query && !variants.contains(&variant) {
+            variants.push(variant);
+        }
+    };
+    for idx in 0..chars.len() - 1 {
+        let mut swapped = chars.clone();
+        swapped.swap(idx, idx + 1);
+        push(swapped.into_iter().collect());
+    }
+    for idx in 0..chars.len() {
+        let mut removed = chars.clone();
+        removed.remove(idx);
+        push(removed.into_iter().collect());
+    }
+    variants
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_typo_variants() {
+        assert_eq!(typo_variants("abc"), vec!["bac", "acb", "bc", "ac", "ab"]);
+        assert_eq!(typo_variants("aab"), vec!["aba", "ab", "aa"]);
+        assert!(typo_variants("ab").is_empty());
+    }
+}
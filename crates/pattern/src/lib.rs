@@ -55,6 +55,16 @@ pub fn extract_grep_position(line: &str) -> Option<(PathBuf, usize, usize)> {
     Some((fpath, lnum, col))
 }
 
+/// Returns a tuple of (fpath, lnum, col, byte offset of the line content).
+pub fn parse_grep_line(line: &str) -> Option<(&str, usize, usize, usize)> {
+    let cap = GREP_POS.captures(line)?;
+    let fpath = cap.get(1).map(|x| x.as_str())?;
+    let str2nr = |idx: usize| cap.get(idx).map(|x| x.as_str()).and_then(parse_lnum);
+    let lnum = str2nr(2)?;
+    let col = str2nr(3)?;
+    Some((fpath, lnum, col, cap.get(0)?.end()))
+}
+
 /// Returns a tuple of (fpath, lnum, col).
 pub fn extract_jump_line_info(line: &str) -> Option<(&str, PathBuf, usize, usize)> {
     let cap = DUMB_JUMP_LINE.captures(line)?;
@@ -82,11 +92,10 @@ pub fn extract_fpath_from_grep_line(line: &str) -> Option<&str> {
 /// Returns the file name of files entry.
 #[inline]
 pub fn file_name_only(line: &str) -> Option<(&str, usize)> {
-    let fpath: std::path::PathBuf = line.into();
-    fpath
-        .file_name()
-        .map(|x| x.to_string_lossy().into_owned())
-        .map(|fname| (&line[line.len() - fname.len()..], line.len() - fname.len()))
+    let fname = std::path::Path::new(line).file_name()?.to_str()?;
+    // The file name is not always the tail of line, e.g., `foo/bar/` or `foo/bar/.`.
+    line.rfind(fname)
+        .map(|offset| (&line[offset..offset + fname.len()], offset))
 }
 
 fn parse_lnum(lnum: &str) -> Option<usize> {
@@ -163,6 +172,17 @@ mod tests {
         println!("{:?}", extract_jump_line_info(line));
     }
 
+    #[test]
+    fn test_file_name_only() {
+        assert_eq!(
+            file_name_only("crates/pattern/src/lib.rs"),
+            Some(("lib.rs", 19))
+        );
+        assert_eq!(file_name_only("中文/src/"), Some(("src", 7)));
+        assert_eq!(file_name_only("中/."), Some(("中", 0)));
+        assert_eq!(file_name_only("/"), None);
+    }
+
     #[test]
     fn test_tag_name_only() {
         let line = "<Backspace>:60       [map]           inoremap <silent> <buffer> <Backspace> <C-R>=clap#handler#bs_action()<CR>  ftplugin/clap_input.vim";
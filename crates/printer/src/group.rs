@@ -0,0 +1,194 @@
+//! Groups the grep lines by file, each file header is followed by its matched lines.
+//!
+//! The results are still flat lines for displaying, `entries` tells the frontend how to
+//! collapse them.
+//!
+//! ```text
+//! crates/printer/src/lib.rs           GroupEntry::File
+//!   12:5:use icon::IconPainter;       GroupEntry::Line
+//!   40:9:    let icon = ...           GroupEntry::Line
+//! ```
+
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+/// Leading whitespaces of the matched lines under the file header.
+pub const GROUP_INDENT: &str = "  ";
+
+/// Unit of the highlight indices of the grep lines.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndexUnit {
+    /// Byte offsets, e.g., the submatches of rg.
+    Byte,
+    /// Char offsets, e.g., the matched indices of the fuzzy matcher.
+    Char,
+}
+
+/// Metadata of each line in the grouped results.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum GroupEntry {
+    /// Header of the lines in `path`.
+    File { path: String, count: usize },
+    /// Matched line under the file header, `indent` is the length of the leading whitespaces.
+    Line {
+        path: String,
+        lnum: usize,
+        col: usize,
+        indent: usize,
+    },
+    /// The line that is not a grep line, kept as it is.
+    Plain,
+}
+
+#[derive(Debug, Default)]
+pub struct GroupedLines {
+    pub lines: Vec<String>,
+    pub indices: Vec<Vec<usize>>,
+    pub entries: Vec<GroupEntry>,
+}
+
+/// Groups the grep `lines` by file.
+///
+/// The files are ordered by their first line in `lines`, so are the lines of a file,
+/// which means the ranking is respected. The lines that are not grep lines are put at the end.
+///
+/// A file icon is prepended to the file headers if `enable_icon` is true.
+pub fn group_by_file(
+    lines: Vec<String>,
+    indices: Vec<Vec<usize>>,
+    unit: IndexUnit,
+    enable_icon: bool,
+) -> GroupedLines {
+    // (path, [(index of line, lnum, col)])
+    let mut groups: Vec<(&str, Vec<(usize, usize, usize)>)> = Vec::new();
+    let mut group_of_path: HashMap<&str, usize> = HashMap::new();
+    let mut plain_lines = Vec::new();
+
+    for (idx, line) in lines.iter().enumerate() {
+        match pattern::parse_grep_line(line) {
+            Some((path, lnum, col, _)) => {
+                let group = *group_of_path.entry(path).or_insert_with(|| {
+                    groups.push((path, Vec::new()));
+                    groups.len() - 1
+                });
+                groups[group].1.push((idx, lnum, col));
+            }
+            None => plain_lines.push(idx),
+        }
+    }
+
+    let mut grouped = GroupedLines::default();
+
+    for (path, members) in groups {
+        grouped.lines.push(if enable_icon {
+            format!("{} {}", icon::icon_for(path), path)
+        } else {
+            path.to_string()
+        });
+        grouped.indices.push(Vec::new());
+        grouped.entries.push(GroupEntry::File {
+            path: path.to_string(),
+            count: members.len(),
+        });
+
+        for (idx, lnum, col) in members {
+            let line = &lines[idx];
+            // Strip `path:`, the rest `lnum:col:line` is displayed under the header.
+            let stripped = &line[..path.len() + 1];
+            let stripped_len = match unit {
+                IndexUnit::Byte => stripped.len(),
+                IndexUnit::Char => stripped.chars().count(),
+            };
+
+            grouped
+                .lines
+                .push(format!("{}{}", GROUP_INDENT, &line[stripped.len()..]));
+            grouped.indices.push(
+                indices[idx]
+                    .iter()
+                    .filter(|&&i| i >= stripped_len)
+                    .map(|i| i - stripped_len + GROUP_INDENT.len())
+                    .collect(),
+            );
+            grouped.entries.push(GroupEntry::Line {
+                path: path.to_string(),
+                lnum,
+                col,
+                indent: GROUP_INDENT.len(),
+            });
+        }
+    }
+
+    for idx in plain_lines {
+        grouped.lines.push(lines[idx].clone());
+        grouped.indices.push(indices[idx].clone());
+        grouped.entries.push(GroupEntry::Plain);
+    }
+
+    grouped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_group_by_file() {
+        let lines = vec![
+            "src/lib.rs:2:1:fn foo() {}".to_string(),
+            "src/main.rs:10:5:    foo();".to_string(),
+            "not a grep line".to_string(),
+            "src/lib.rs:8:4:    foo".to_string(),
+        ];
+        let indices = vec![vec![18, 19, 20], vec![21, 22, 23], vec![], vec![19, 20, 21]];
+
+        let grouped = group_by_file(lines, indices, IndexUnit::Byte, false);
+
+        assert_eq!(
+            grouped.lines,
+            vec![
+                "src/lib.rs",
+                "  2:1:fn foo() {}",
+                "  8:4:    foo",
+                "src/main.rs",
+                "  10:5:    foo();",
+                "not a grep line",
+            ]
+        );
+        assert_eq!(grouped.lines[1].get(9..12), Some("foo"));
+        assert_eq!(grouped.indices[1], vec![9, 10, 11]);
+        assert_eq!(grouped.indices[2], vec![10, 11, 12]);
+        assert_eq!(grouped.indices[4], vec![11, 12, 13]);
+        assert_eq!(
+            grouped.entries[0],
+            GroupEntry::File {
+                path: "src/lib.rs".into(),
+                count: 2
+            }
+        );
+        assert_eq!(
+            grouped.entries[4],
+            GroupEntry::Line {
+                path: "src/main.rs".into(),
+                lnum: 10,
+                col: 5,
+                indent: 2
+            }
+        );
+        assert_eq!(grouped.entries[5], GroupEntry::Plain);
+    }
+
+    #[test]
+    fn test_group_char_indices() {
+        let lines = vec!["中文/a.rs:1:1:foo".to_string()];
+        // `foo` starts at char 12 and byte 16.
+        let grouped = group_by_file(lines.clone(), vec![vec![12]], IndexUnit::Char, false);
+        assert_eq!(grouped.lines[1], "  1:1:foo");
+        assert_eq!(grouped.indices[1], vec![6]);
+
+        let grouped = group_by_file(lines, vec![vec![16]], IndexUnit::Byte, false);
+        assert_eq!(grouped.indices[1], vec![6]);
+    }
+}
@@ -0,0 +1,117 @@
+//! Format of the matched indices in the printed results.
+//!
+//! The indices can be printed as coalesced ranges instead, which is much smaller for the long
+//! contiguous matches and cheaper for Vim to apply the highlights.
+
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use serde_json::Value;
+
+static USE_RANGES: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndexFormat {
+    /// `indices`: the individual matched indices.
+    Indices,
+    /// `indices_ranges`: `[start, end]` pairs of the matched indices, `end` is exclusive.
+    Ranges,
+}
+
+impl Default for IndexFormat {
+    fn default() -> Self {
+        Self::Indices
+    }
+}
+
+impl FromStr for IndexFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "indices" => Ok(Self::Indices),
+            "ranges" => Ok(Self::Ranges),
+            _ => Err(format!(
+                "Unknown index format: {}, possible values: indices, ranges",
+                s
+            )),
+        }
+    }
+}
+
+/// Sets the index format of the results printed by this process.
+pub fn set_index_format(index_format: IndexFormat) {
+    USE_RANGES.store(index_format == IndexFormat::Ranges, Ordering::Relaxed);
+}
+
+pub fn index_format() -> IndexFormat {
+    if USE_RANGES.load(Ordering::Relaxed) {
+        IndexFormat::Ranges
+    } else {
+        IndexFormat::Indices
+    }
+}
+
+/// Coalesces the adjacent indices into `[start, end]` pairs, `end` is exclusive.
+pub fn coalesce_ranges(indices: &[usize]) -> Vec<[usize; 2]> {
+    let mut ranges: Vec<[usize; 2]> = Vec::new();
+    for &idx in indices {
+        match ranges.last_mut() {
+            Some(last) if last[1] == idx => last[1] = idx + 1,
+            _ => ranges.push([idx, idx + 1]),
+        }
+    }
+    ranges
+}
+
+/// Converts the `indices` of one line or of a list of lines into ranges.
+fn into_ranges(indices: Value) -> Value {
+    match indices {
+        Value::Array(items) if items.iter().all(Value::is_array) => {
+            Value::Array(items.into_iter().map(into_ranges).collect())
+        }
+        indices => {
+            let indices: Vec<usize> = serde_json::from_value(indices).unwrap_or_default();
+            serde_json::json!(coalesce_ranges(&indices))
+        }
+    }
+}
+
+/// Replaces `indices` of `payload` with `indices_ranges` if the index format is ranges.
+pub fn apply(payload: &mut Value) {
+    if index_format() != IndexFormat::Ranges {
+        return;
+    }
+    if let Some(payload) = payload.as_object_mut() {
+        if let Some(indices) = payload.remove("indices") {
+            payload.insert("indices_ranges".into(), into_ranges(indices));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_coalesce_ranges() {
+        assert_eq!(coalesce_ranges(&[]), Vec::<[usize; 2]>::new());
+        assert_eq!(coalesce_ranges(&[3]), vec![[3, 4]]);
+        assert_eq!(
+            coalesce_ranges(&[0, 1, 2, 5, 7, 8]),
+            vec![[0, 3], [5, 6], [7, 9]]
+        );
+    }
+
+    #[test]
+    fn test_into_ranges() {
+        assert_eq!(
+            into_ranges(serde_json::json!([[0, 1, 2], [], [4, 6]])),
+            serde_json::json!([[[0, 3]], [], [[4, 5], [6, 7]]])
+        );
+        assert_eq!(
+            into_ranges(serde_json::json!([1, 2, 3])),
+            serde_json::json!([[1, 4]])
+        );
+    }
+}
@@ -1,13 +1,32 @@
 //! This crate provides the feature of diplaying the information of filtered lines
 //! by printing them to stdout in JSON format.
 
+pub mod group;
+pub mod index_format;
+mod offset;
+pub mod output_format;
+mod payload;
+mod progress;
+pub mod shorten_path;
+pub mod stats;
+mod truncation;
+pub mod warning;
+pub mod writer;
+
 use std::collections::HashMap;
 
 use icon::{IconPainter, ICON_LEN};
 use source_item::SourceItem;
-use utility::{println_json, println_json_with_length};
 
-pub const DOTS: &str = "..";
+use self::group::{GroupedLines, IndexUnit};
+use self::writer::ChunkedWriter;
+
+pub use self::offset::LineOffset;
+pub use self::payload::Payload;
+pub use self::progress::Progress;
+pub use self::truncation::{
+    truncate_grep_lines, truncate_lines, truncate_long_matched_lines, DOTS,
+};
 
 /// Line number of Vim is 1-based.
 pub type VimLineNumber = usize;
@@ -26,222 +45,386 @@ pub type LinesTruncatedMap = HashMap<VimLineNumber, String>;
 /// Tuple of (matched line text, filtering score, indices of matched elements)
 pub type FilterResult = (SourceItem, i64, Vec<usize>);
 
-/// sign column width 2
-#[cfg(not(test))]
-const WINWIDTH_OFFSET: usize = 4;
-
-#[cfg(test)]
-const WINWIDTH_OFFSET: usize = 0;
-
-// https://stackoverflow.com/questions/51982999/slice-a-string-containing-unicode-chars
-#[inline]
-fn utf8_str_slice(line: &str, start: usize, end: usize) -> String {
-    line.chars().take(end).skip(start).collect()
+/// Returns the line numbers of the items decoded lossily from invalid UTF-8.
+fn lossy_line_numbers(top_list: &[FilterResult]) -> Vec<VimLineNumber> {
+    top_list
+        .iter()
+        .enumerate()
+        .filter(|(_, (item, _, _))| item.lossy)
+        .map(|(idx, _)| idx + 1)
+        .collect()
 }
 
-fn truncate_line_impl(
-    winwidth: usize,
-    line: &str,
-    indices: &[usize],
-    skipped: Option<usize>,
-) -> Option<(String, Vec<usize>)> {
-    let last_idx = indices.last()?;
-    if *last_idx > winwidth {
-        let mut start = *last_idx - winwidth;
-        if start >= indices[0] || (indices.len() > 1 && *last_idx - start > winwidth) {
-            start = indices[0];
-        }
-        let line_len = line.len();
-        // [--------------------------]
-        // [-----------------------------------------------------------------xx--x--]
-        for _ in 0..3 {
-            if indices[0] - start >= DOTS.len() && line_len - start >= winwidth {
-                start += DOTS.len();
-            } else {
-                break;
-            }
-        }
-        let trailing_dist = line_len - last_idx;
-        if trailing_dist < indices[0] - start {
-            start += trailing_dist;
-        }
-        let end = line.len();
-        let left_truncated = if let Some(n) = skipped {
-            let icon: String = line.chars().take(n).collect();
-            format!("{}{}{}", icon, DOTS, utf8_str_slice(&line, start, end))
-        } else {
-            format!("{}{}", DOTS, utf8_str_slice(&line, start, end))
-        };
-
-        let offset = line_len.saturating_sub(left_truncated.len());
-
-        let left_truncated_len = left_truncated.len();
-
-        let (truncated, max_index) = if left_truncated_len > winwidth {
-            if left_truncated_len == winwidth + 1 {
-                (
-                    format!("{}.", utf8_str_slice(&left_truncated, 0, winwidth - 1)),
-                    winwidth - 1,
-                )
-            } else {
-                (
-                    format!(
-                        "{}{}",
-                        utf8_str_slice(&left_truncated, 0, winwidth - 2),
-                        DOTS
-                    ),
-                    winwidth - 2,
-                )
-            }
-        } else {
-            (left_truncated, winwidth)
-        };
-
-        let truncated_indices = indices
-            .iter()
-            .map(|x| x - offset)
-            .take_while(|x| *x < max_index)
-            .collect::<Vec<_>>();
+/// Returns the line numbers of the items in their source file, `None` unless all of them are
+/// read from a file.
+fn source_line_numbers(top_list: &[FilterResult]) -> Option<Vec<usize>> {
+    top_list
+        .iter()
+        .map(|(item, _, _)| item.line_number)
+        .collect::<Option<Vec<_>>>()
+        .filter(|line_numbers| !line_numbers.is_empty())
+}
 
-        Some((truncated, truncated_indices))
+/// Returns the user data of the items, `None` unless any of them has one.
+fn items_user_data(top_list: &[FilterResult]) -> Option<Vec<serde_json::Value>> {
+    if top_list.iter().any(|(item, _, _)| item.user_data.is_some()) {
+        Some(
+            top_list
+                .iter()
+                .map(|(item, _, _)| item.user_data.clone().unwrap_or_default())
+                .collect(),
+        )
     } else {
         None
     }
 }
 
-/// Long matched lines can cause the matched items invisible.
-///
-/// # Arguments
+/// Returns the info of the truncated top items ranked by the filtering score.
 ///
-/// - winwidth: width of the display window.
-/// - skipped: number of skipped chars, used when need to skip the leading icons.
-pub fn truncate_long_matched_lines<T>(
-    lines: impl IntoIterator<Item = (SourceItem, T, Vec<usize>)>,
-    winwidth: usize,
-    skipped: Option<usize>,
-) -> (Vec<(String, T, Vec<usize>)>, LinesTruncatedMap) {
-    let mut truncated_map = HashMap::new();
-    let mut lnum = 0usize;
-    let winwidth = winwidth - WINWIDTH_OFFSET;
-    let lines = lines
-        .into_iter()
-        .map(|(item, score, indices)| {
-            let line = item.display_text.unwrap_or(item.raw);
-            lnum += 1;
-
-            if let Some((truncated, truncated_indices)) =
-                truncate_line_impl(winwidth, &line, &indices, skipped)
-            {
-                truncated_map.insert(lnum, line);
-                (truncated, score, truncated_indices)
-            } else {
-                (line, score, indices)
-            }
-        })
-        .collect::<Vec<_>>();
-    (lines, truncated_map)
-}
-
-pub fn truncate_grep_lines(
-    lines: impl IntoIterator<Item = String>,
-    indices: impl IntoIterator<Item = Vec<usize>>,
+/// The indices are remapped onto the printed lines by their [`LineOffset`] at once, including
+/// the icon prefix.
+pub fn process_top_items<T>(
+    top_list: impl IntoIterator<Item = (SourceItem, T, Vec<usize>)>,
     winwidth: usize,
-    skipped: Option<usize>,
+    icon_painter: Option<IconPainter>,
 ) -> (Vec<String>, Vec<Vec<usize>>, LinesTruncatedMap) {
     let mut truncated_map = HashMap::new();
-    let mut lnum = 0usize;
-    let winwidth = winwidth - WINWIDTH_OFFSET;
-    let (lines, indices): (Vec<String>, Vec<Vec<usize>>) = lines
+    let (lines, indices) = top_list
         .into_iter()
-        .zip(indices.into_iter())
-        .map(|(line, indices)| {
-            lnum += 1;
-
-            if let Some((truncated, truncated_indices)) =
-                truncate_line_impl(winwidth, &line, &indices, skipped)
-            {
-                truncated_map.insert(lnum, line);
-                (truncated, truncated_indices)
-            } else {
-                (line, indices)
+        .enumerate()
+        .map(|(idx, (item, _, indices))| {
+            let (text, indices, offset, full_line) =
+                truncation::truncate_item(item, indices, winwidth, None);
+            let (line, offset) = match icon_painter {
+                Some(ref painter) => {
+                    let iconized = match full_line {
+                        Some(ref origin_text) => {
+                            format!("{} {}", painter.get_icon(origin_text), text)
+                        }
+                        None => painter.paint(&text),
+                    };
+                    (iconized, offset.with_prefix(ICON_LEN))
+                }
+                None => (text, offset),
+            };
+            if let Some(full_line) = full_line {
+                truncated_map.insert(idx + 1, full_line);
             }
+            (line, offset.apply(&indices))
         })
         .unzip();
     (lines, indices, truncated_map)
 }
 
-/// Returns the info of the truncated top items ranked by the filtering score.
-pub fn process_top_items<T>(
-    top_list: impl IntoIterator<Item = (SourceItem, T, Vec<usize>)>,
-    winwidth: usize,
-    icon_painter: Option<IconPainter>,
-) -> (Vec<String>, Vec<Vec<usize>>, LinesTruncatedMap) {
-    let (truncated_lines, truncated_map) = truncate_long_matched_lines(top_list, winwidth, None);
-    if let Some(painter) = icon_painter {
-        let (lines, indices): (Vec<_>, Vec<Vec<usize>>) = truncated_lines
-            .into_iter()
-            .enumerate()
-            .map(|(idx, (text, _, idxs))| {
-                let iconized = if let Some(origin_text) = truncated_map.get(&(idx + 1)) {
-                    format!("{} {}", painter.get_icon(origin_text), text)
-                } else {
-                    painter.paint(&text)
-                };
-                (iconized, idxs.iter().map(|x| x + ICON_LEN).collect())
-            })
-            .unzip();
+/// Serializes the JSON `payload` of the results in the configured index format.
+///
+/// The counts of [`Payload`] are attached if the payload has `total` or `lines`, so are the
+/// pending warnings if it has `total`.
+fn format_payload(payload: serde_json::Value) -> String {
+    attach_info(payload).to_string()
+}
 
-        (lines, indices, truncated_map)
-    } else {
-        let (lines, indices): (Vec<_>, Vec<_>) = truncated_lines
-            .into_iter()
-            .map(|(text, _, idxs)| (text, idxs))
-            .unzip();
+fn attach_info(mut payload: serde_json::Value) -> serde_json::Value {
+    stats::observe(&payload);
+    warning::attach(&mut payload);
+    Payload::attach(&mut payload);
+    index_format::apply(&mut payload);
+    payload
+}
 
-        (lines, indices, truncated_map)
+/// Attaches the counts and warnings to the JSON `payload` and applies the index format, for
+/// the payloads not printed by [`println_payload`], e.g., the responses of the RPC server.
+///
+/// The payload is counted as printed to stdout by the caller, see [`stats::print_stats`].
+pub fn prepare_payload(payload: serde_json::Value) -> serde_json::Value {
+    stats::count_printed(1);
+    attach_info(payload)
+}
+
+/// Returns the output of the JSON `payload` in the output format, `None` if there is nothing to
+/// print, e.g., the human output of no results.
+///
+/// The results are rendered as the colored lines with the human output format, the warnings go
+/// to stderr then.
+fn format_output(mut payload: serde_json::Value) -> Option<String> {
+    if output_format::output_format() == output_format::OutputFormat::Human {
+        stats::observe(&payload);
+        warning::attach(&mut payload);
+        if let Some(warnings) = payload.get("warnings") {
+            eprintln!("{}", warnings);
+        }
+        if let Some(rendered) = output_format::render(&payload) {
+            return Some(rendered).filter(|rendered| !rendered.is_empty());
+        }
+    }
+    Some(format_payload(payload))
+}
+
+/// Prints the JSON `payload` of the results to stdout in the output format.
+pub fn println_payload(payload: serde_json::Value) {
+    if let Some(output) = format_output(payload) {
+        stats::count_printed(1);
+        println!("{}", output);
     }
 }
 
+/// Same with [`println_payload`] but prefixed with the `Content-length` header.
+pub fn println_payload_with_length(payload: serde_json::Value) {
+    stats::count_printed(1);
+    let payload = format_payload(payload);
+    println!("Content-length: {}\n\n{}", payload.len(), payload);
+}
+
 /// Prints the results of filter::sync_run() to stdout.
 pub fn print_sync_filter_results(
     ranked: Vec<FilterResult>,
     number: Option<usize>,
     winwidth: usize,
     icon_painter: Option<IconPainter>,
+) -> std::io::Result<()> {
+    print_sync_filter_results_with(ranked, number, winwidth, icon_painter, serde_json::Map::new)
+}
+
+/// Prints the line numbers in the input of the top `number` results and their matched indices
+/// only, for the frontend holding the lines already.
+pub fn print_indices_only(
+    ranked: Vec<FilterResult>,
+    number: Option<usize>,
+    extra: serde_json::Map<String, serde_json::Value>,
 ) {
+    let total = ranked.len();
+    let (line_numbers, indices): (Vec<_>, Vec<_>) = ranked
+        .into_iter()
+        .take(number.unwrap_or(total))
+        .map(|(item, _, indices)| (item.line_number, indices))
+        .unzip();
+    let mut payload = serde_json::json!({
+        "total": total,
+        "line_numbers": line_numbers,
+        "indices": indices,
+    });
+    if let Some(payload) = payload.as_object_mut() {
+        payload.extend(extra);
+    }
+    println_payload(payload);
+}
+
+/// Prints the results of filter::sync_run() to stdout with the `extra` fields attached.
+///
+/// The `extra` fields are built right before they are printed, as the last line if `number`
+/// is None.
+///
+/// The lines printed without `number` are spilled to a tempfile past the threshold of
+/// [`writer::set_spill_threshold`], only `total` and the `tempfile` are printed then. Both the
+/// lines and the spilled ones are in the output format.
+///
+/// Stdout closed by the reader is not an error.
+pub fn print_sync_filter_results_with(
+    ranked: Vec<FilterResult>,
+    number: Option<usize>,
+    winwidth: usize,
+    icon_painter: Option<IconPainter>,
+    extra: impl FnOnce() -> serde_json::Map<String, serde_json::Value>,
+) -> std::io::Result<()> {
     if let Some(number) = number {
         let total = ranked.len();
+        let lossy_lines = lossy_line_numbers(&ranked[..number.min(total)]);
+        let line_numbers = source_line_numbers(&ranked[..number.min(total)]);
+        let user_data = items_user_data(&ranked[..number.min(total)]);
         let (lines, indices, truncated_map) =
             process_top_items(ranked.into_iter().take(number), winwidth, icon_painter);
-        if truncated_map.is_empty() {
-            println_json!(total, lines, indices);
-        } else {
-            println_json!(total, lines, indices, truncated_map);
+        let mut payload = serde_json::json!({
+            "total": total,
+            "lines": lines,
+            "indices": indices,
+        });
+        if let Some(payload) = payload.as_object_mut() {
+            if !truncated_map.is_empty() {
+                payload.insert("truncated_map".into(), serde_json::json!(truncated_map));
+            }
+            if !lossy_lines.is_empty() {
+                payload.insert("lossy_lines".into(), serde_json::json!(lossy_lines));
+            }
+            if let Some(line_numbers) = line_numbers {
+                payload.insert("line_numbers".into(), serde_json::json!(line_numbers));
+            }
+            if let Some(user_data) = user_data {
+                payload.insert("user_data".into(), serde_json::json!(user_data));
+            }
+            payload.extend(extra());
         }
+        println_payload(payload);
     } else {
-        for (item, _, indices) in ranked.into_iter() {
-            let text = item.display_text.unwrap_or(item.raw);
-            println_json!(text, indices);
+        let total = ranked.len();
+        let mut writer = ChunkedWriter::stdout();
+        // Number of the lines written, which are only printed to stdout if not spilled.
+        let mut written_lines = 0;
+        let written = ranked
+            .into_iter()
+            .try_for_each(|(item, _, indices)| {
+                let lossy = item.lossy;
+                let line_number = item.line_number;
+                let user_data = item.user_data;
+                let text = item.display_text.unwrap_or(item.raw);
+                let mut payload = serde_json::json!({ "text": text, "indices": indices });
+                if lossy {
+                    payload["lossy"] = serde_json::json!(lossy);
+                }
+                if let Some(line_number) = line_number {
+                    payload["line_number"] = serde_json::json!(line_number);
+                }
+                if let Some(user_data) = user_data {
+                    payload["user_data"] = user_data;
+                }
+                match format_output(payload) {
+                    Some(output) => {
+                        written_lines += 1;
+                        writer.write_line(&output)
+                    }
+                    None => Ok(()),
+                }
+            })
+            .and_then(|()| {
+                let extra = extra();
+                if extra.is_empty() {
+                    Ok(())
+                } else {
+                    written_lines += 1;
+                    writer.write_line(&serde_json::Value::Object(extra).to_string())
+                }
+            })
+            .and_then(|()| writer.finish());
+        match written {
+            // The spilled lines are in the same output format as printed.
+            Ok(Some(tempfile)) => {
+                println_payload(serde_json::json!({ "total": total, "tempfile": tempfile }))
+            }
+            Ok(None) => stats::count_printed(written_lines),
+            // Stdout is closed by the reader.
+            Err(e) if e.kind() == std::io::ErrorKind::BrokenPipe => {}
+            Err(e) => return Err(e),
         }
     }
+    Ok(())
 }
 
-/// Prints the results of filter::dyn_run() to stdout.
+/// Prints the results of filter::dyn_run() to stdout, tagged with the `progress` of the stream.
 pub fn print_dyn_filter_results(
     ranked: Vec<FilterResult>,
     total: usize,
     number: usize,
     winwidth: usize,
     icon_painter: Option<IconPainter>,
+    progress: Progress,
 ) {
+    let lossy_lines = lossy_line_numbers(&ranked[..number.min(ranked.len())]);
+    let line_numbers = source_line_numbers(&ranked[..number.min(ranked.len())]);
+    let user_data = items_user_data(&ranked[..number.min(ranked.len())]);
     let (lines, indices, truncated_map) =
         process_top_items(ranked.into_iter().take(number), winwidth, icon_painter);
 
-    if truncated_map.is_empty() {
-        println_json_with_length!(total, lines, indices);
-    } else {
-        println_json_with_length!(total, lines, indices, truncated_map);
+    let mut payload = serde_json::json!({
+        "total": total,
+        "lines": lines,
+        "indices": indices,
+    });
+    if !truncated_map.is_empty() {
+        payload["truncated_map"] = serde_json::json!(truncated_map);
+    }
+    if !lossy_lines.is_empty() {
+        payload["lossy_lines"] = serde_json::json!(lossy_lines);
     }
+    if let Some(line_numbers) = line_numbers {
+        payload["line_numbers"] = serde_json::json!(line_numbers);
+    }
+    if let Some(user_data) = user_data {
+        payload["user_data"] = serde_json::json!(user_data);
+    }
+    progress.attach(&mut payload);
+    println_payload_with_length(payload);
+}
+
+/// Prints the lines appended to the followed file that match the query to stdout.
+///
+/// The lines are printed in the given order, i.e., each batch is ranked on its own by the caller.
+pub fn print_appended_filter_results(
+    appended: Vec<FilterResult>,
+    winwidth: usize,
+    icon_painter: Option<IconPainter>,
+) {
+    let lossy_lines = lossy_line_numbers(&appended);
+    let line_numbers = source_line_numbers(&appended);
+    let user_data = items_user_data(&appended);
+    let (lines, indices, truncated_map) = process_top_items(appended, winwidth, icon_painter);
+
+    let mut payload = serde_json::json!({
+        "appended": lines,
+        "indices": indices,
+    });
+    if !truncated_map.is_empty() {
+        payload["truncated_map"] = serde_json::json!(truncated_map);
+    }
+    if !lossy_lines.is_empty() {
+        payload["lossy_lines"] = serde_json::json!(lossy_lines);
+    }
+    if let Some(line_numbers) = line_numbers {
+        payload["line_numbers"] = serde_json::json!(line_numbers);
+    }
+    if let Some(user_data) = user_data {
+        payload["user_data"] = serde_json::json!(user_data);
+    }
+    println_payload_with_length(payload);
+}
+
+/// Prints the results of filter::dyn_run() grouped by the file of grep lines to stdout.
+///
+/// The icon is only added to the file headers, see [`print_dyn_filter_results`] for `progress`.
+pub fn print_grouped_dyn_filter_results(
+    ranked: Vec<FilterResult>,
+    total: usize,
+    number: usize,
+    winwidth: usize,
+    icon_painter: Option<IconPainter>,
+    progress: Progress,
+) {
+    let shown = number.min(ranked.len());
+    let (lines, indices): (Vec<_>, Vec<_>) = ranked
+        .into_iter()
+        .take(number)
+        .map(|(item, _, indices)| (item.display_text.unwrap_or(item.raw), indices))
+        .unzip();
+
+    let GroupedLines {
+        lines,
+        indices,
+        entries,
+    } = group::group_by_file(lines, indices, IndexUnit::Char, icon_painter.is_some());
+
+    let (truncated_lines, truncated_map) = truncate_long_matched_lines(
+        lines
+            .into_iter()
+            .zip(indices)
+            .map(|(line, indices)| (line.into(), (), indices)),
+        winwidth,
+        None,
+    );
+    let (lines, indices): (Vec<_>, Vec<_>) = truncated_lines
+        .into_iter()
+        .map(|(text, _, idxs)| (text, idxs))
+        .unzip();
+
+    let mut payload = serde_json::json!({
+        "total": total,
+        "shown": shown,
+        "lines": lines,
+        "indices": indices,
+        "entries": entries,
+    });
+    if !truncated_map.is_empty() {
+        payload["truncated_map"] = serde_json::json!(truncated_map);
+    }
+    progress.attach(&mut payload);
+    println_payload_with_length(payload);
 }
 
 #[cfg(test)]
@@ -392,11 +575,36 @@ mod tests {
     }
 
     #[test]
-    fn test_print_multibyte_string_slice() {
-        let multibyte_str = "README.md:23:1:Gourinath Banda. “Scalable Real-Time Kernel for Small Embedded Systems”. En- glish. PhD thesis. Denmark: University of Southern Denmark, June 2003. URL: http://citeseerx.ist.psu.edu/viewdoc/download;jsessionid=84D11348847CDC13691DFAED09883FCB?doi=10.1.1.118.1909&rep=rep1&type=pdf.";
-        let start = 33;
-        let end = 300;
-        let expected = "Scalable Real-Time Kernel for Small Embedded Systems”. En- glish. PhD thesis. Denmark: University of Southern Denmark, June 2003. URL: http://citeseerx.ist.psu.edu/viewdoc/download;jsessionid=84D11348847CDC13691DFAED09883FCB?doi=10.1.1.118.1909&rep=rep1&type=pdf.";
-        assert_eq!(expected, utf8_str_slice(multibyte_str, start, end));
+    fn test_items_user_data() {
+        let mut item = SourceItem::from("foo");
+        item.user_data = Some(serde_json::json!({ "lnum": 12 }));
+        let top_list = vec![(item, 0, vec![]), ("bar".into(), 0, vec![])];
+        assert_eq!(
+            items_user_data(&top_list),
+            Some(vec![
+                serde_json::json!({ "lnum": 12 }),
+                serde_json::Value::Null
+            ])
+        );
+        assert_eq!(items_user_data(&top_list[1..]), None);
+    }
+
+    #[test]
+    fn test_process_top_items_offsets() {
+        let line = format!("{}needle", "a".repeat(40));
+        let top_list = || vec![(SourceItem::from(line.as_str()), 0, (40..46).collect())];
+
+        let (lines, indices, truncated_map) = process_top_items(top_list(), 20, None);
+        assert_eq!(lines[0], format!("..{}needle", "a".repeat(12)));
+        assert_eq!(indices[0], (14..20).collect::<Vec<_>>());
+        assert_eq!(truncated_map.get(&1), Some(&line));
+
+        // The icon prefix is counted in the same offset as the truncation.
+        let (lines, indices, _) = process_top_items(top_list(), 20, Some(IconPainter::File));
+        assert!(lines[0].ends_with(&format!("..{}needle", "a".repeat(12))));
+        assert_eq!(
+            indices[0],
+            (14 + ICON_LEN..20 + ICON_LEN).collect::<Vec<_>>()
+        );
     }
 }
@@ -0,0 +1,128 @@
+//! Mapping of the matched indices from the original line onto the printed line.
+//!
+//! A printed line is the original line with some leading chars replaced, e.g., by the dots of
+//! the truncation, some trailing chars cut off and a prefix prepended, e.g., the icon. All the
+//! changes are recorded in one [`LineOffset`] and the indices are remapped once by it, instead of
+//! being shifted by each step.
+
+/// Offset of the char indices of a line after the truncation and the prefix.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct LineOffset {
+    /// Number of the leading chars of the original line removed.
+    removed: usize,
+    /// Number of the chars in front of the rest of the original line in the printed line.
+    prepended: usize,
+    /// The indices from this one of the printed line are cut off, unlimited if `None`.
+    visible_end: Option<usize>,
+}
+
+impl LineOffset {
+    /// Offset of the line with `width` chars prepended, e.g., the icon.
+    pub fn prefix(width: usize) -> Self {
+        Self {
+            prepended: width,
+            ..Default::default()
+        }
+    }
+
+    /// Offset of the line whose first `removed` chars are replaced with `prepended` chars and
+    /// the chars from `visible_end` of the result are cut off.
+    pub(crate) fn truncated(removed: usize, prepended: usize, visible_end: usize) -> Self {
+        Self {
+            removed,
+            prepended,
+            visible_end: Some(visible_end),
+        }
+    }
+
+    /// Returns the offset with `width` more chars prepended to the printed line.
+    pub fn with_prefix(self, width: usize) -> Self {
+        Self {
+            removed: self.removed,
+            prepended: self.prepended + width,
+            visible_end: self.visible_end.map(|end| end + width),
+        }
+    }
+
+    /// Maps the char `indices` of the original line onto the printed line, the indices of the
+    /// removed or cut off chars are dropped.
+    pub fn apply(&self, indices: &[usize]) -> Vec<usize> {
+        indices
+            .iter()
+            .filter(|&&idx| idx >= self.removed)
+            .map(|idx| idx - self.removed + self.prepended)
+            .take_while(|&idx| self.visible_end.map_or(true, |end| idx < end))
+            .collect()
+    }
+
+    /// Same with [`Self::apply`] but the indices are the byte offsets, e.g., from rg's
+    /// submatches, `printed` is the printed line of `original`.
+    pub fn apply_bytes(&self, original: &str, printed: &str, byte_indices: &[usize]) -> Vec<usize> {
+        if *self == Self::default() {
+            return byte_indices.to_vec();
+        }
+        let char_indices = byte_indices_to_char_indices(original, byte_indices);
+        char_indices_to_byte_indices(printed, &self.apply(&char_indices))
+    }
+}
+
+/// Converts the byte indices of `line` to char indices, the bytes of a char are merged.
+pub(crate) fn byte_indices_to_char_indices(line: &str, byte_indices: &[usize]) -> Vec<usize> {
+    let mut char_indices = line
+        .char_indices()
+        .enumerate()
+        .filter(|(_, (byte_idx, c))| {
+            byte_indices
+                .iter()
+                .any(|i| *i >= *byte_idx && *i < byte_idx + c.len_utf8())
+        })
+        .map(|(char_idx, _)| char_idx)
+        .collect::<Vec<_>>();
+    char_indices.dedup();
+    char_indices
+}
+
+/// Converts the char indices of `line` to byte indices, a char is expanded to all its bytes.
+fn char_indices_to_byte_indices(line: &str, char_indices: &[usize]) -> Vec<usize> {
+    line.char_indices()
+        .enumerate()
+        .filter(|(char_idx, _)| char_indices.contains(char_idx))
+        .flat_map(|(_, (byte_idx, c))| byte_idx..byte_idx + c.len_utf8())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_offset() {
+        assert_eq!(LineOffset::default().apply(&[0, 3]), vec![0, 3]);
+        assert_eq!(LineOffset::prefix(4).apply(&[0, 3]), vec![4, 7]);
+        assert!(LineOffset::prefix(4).apply(&[]).is_empty());
+
+        // `aaaaaneedle` printed as `..aneed..`.
+        let offset = LineOffset::truncated(4, 2, 7);
+        assert_eq!(offset.apply(&[3, 5, 6, 7, 8, 9]), vec![3, 4, 5, 6]);
+        // Then the icon is prepended.
+        assert_eq!(
+            offset.with_prefix(4).apply(&[3, 5, 6, 7, 8, 9]),
+            vec![7, 8, 9, 10]
+        );
+    }
+
+    #[test]
+    fn test_line_offset_bytes() {
+        // `中中needle` printed as `..needle`.
+        let original = "中中needle";
+        let offset = LineOffset::truncated(2, 2, 8);
+        assert_eq!(
+            offset.apply_bytes(original, "..needle", &[0, 1, 2, 6, 7]),
+            vec![2, 3]
+        );
+        assert_eq!(
+            LineOffset::default().apply_bytes(original, original, &[0, 1, 2]),
+            vec![0, 1, 2]
+        );
+    }
+}
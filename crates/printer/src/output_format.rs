@@ -0,0 +1,175 @@
+//! Format of the printed results, JSON for Vim or colored text for the standalone use in a shell.
+
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use serde_json::Value;
+
+static HUMAN: AtomicBool = AtomicBool::new(false);
+
+const MATCHED_STYLE: &str = "\x1b[1;31m";
+const PATH_STYLE: &str = "\x1b[2m";
+const RESET: &str = "\x1b[0m";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// The JSON payloads.
+    Json,
+    /// The lines of results with the matched chars highlighted and the paths dimmed.
+    Human,
+}
+
+impl Default for OutputFormat {
+    fn default() -> Self {
+        Self::Json
+    }
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "json" => Ok(Self::Json),
+            "human" => Ok(Self::Human),
+            _ => Err(format!(
+                "Unknown output format: {}, possible values: json, human",
+                s
+            )),
+        }
+    }
+}
+
+/// Sets the output format of the results printed by this process.
+pub fn set_output_format(output_format: OutputFormat) {
+    HUMAN.store(output_format == OutputFormat::Human, Ordering::Relaxed);
+}
+
+pub fn output_format() -> OutputFormat {
+    if HUMAN.load(Ordering::Relaxed) {
+        OutputFormat::Human
+    } else {
+        OutputFormat::Json
+    }
+}
+
+/// Returns the number of the leading chars of `line` that are the path, i.e., `path:lnum:col:`
+/// of the grep line or the directory of the file path.
+fn path_prefix_len(line: &str) -> usize {
+    let mut fields = line.splitn(4, ':');
+    if let (Some(path), Some(lnum), Some(rest)) = (fields.next(), fields.next(), fields.next()) {
+        if !path.is_empty() && !lnum.is_empty() && lnum.chars().all(|c| c.is_ascii_digit()) {
+            let col_len = match fields.next() {
+                Some(_) if !rest.is_empty() && rest.chars().all(|c| c.is_ascii_digit()) => {
+                    rest.chars().count() + 1
+                }
+                _ => 0,
+            };
+            return path.chars().count() + lnum.chars().count() + 2 + col_len;
+        }
+    }
+    if line.contains(char::is_whitespace) {
+        return 0;
+    }
+    line.rfind(|c| c == '/' || c == '\\')
+        .map_or(0, |pos| line[..=pos].chars().count())
+}
+
+/// Returns `line` with the chars at `indices` highlighted and the path dimmed.
+pub fn highlight(line: &str, indices: &[usize]) -> String {
+    let path_len = path_prefix_len(line);
+    let mut highlighted = String::with_capacity(line.len());
+    let mut current = None;
+    for (idx, c) in line.chars().enumerate() {
+        let style = if indices.contains(&idx) {
+            Some(MATCHED_STYLE)
+        } else if idx < path_len {
+            Some(PATH_STYLE)
+        } else {
+            None
+        };
+        if style != current {
+            if current.is_some() {
+                highlighted.push_str(RESET);
+            }
+            if let Some(style) = style {
+                highlighted.push_str(style);
+            }
+            current = style;
+        }
+        highlighted.push(c);
+    }
+    if current.is_some() {
+        highlighted.push_str(RESET);
+    }
+    highlighted
+}
+
+fn indices_of(indices: Option<&Value>) -> Vec<usize> {
+    indices
+        .and_then(|indices| serde_json::from_value(indices.clone()).ok())
+        .unwrap_or_default()
+}
+
+/// Renders the results of `payload` as the colored lines, `None` if it has no results.
+///
+/// The results spilled to a tempfile are rendered as the path of the tempfile.
+pub fn render(payload: &Value) -> Option<String> {
+    if let Some(Value::Array(lines)) = payload.get("lines") {
+        let indices = payload
+            .get("indices")
+            .and_then(Value::as_array)
+            .cloned()
+            .unwrap_or_default();
+        let rendered = lines
+            .iter()
+            .enumerate()
+            .map(|(idx, line)| {
+                highlight(
+                    line.as_str().unwrap_or_default(),
+                    &indices_of(indices.get(idx)),
+                )
+            })
+            .collect::<Vec<_>>();
+        return Some(rendered.join("\n"));
+    }
+    if let Some(tempfile) = payload.get("tempfile").and_then(Value::as_str) {
+        let total = payload
+            .get("total")
+            .and_then(Value::as_u64)
+            .unwrap_or_default();
+        return Some(format!("{} results written to {}", total, tempfile));
+    }
+    payload
+        .get("text")
+        .and_then(Value::as_str)
+        .map(|text| highlight(text, &indices_of(payload.get("indices"))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_highlight() {
+        assert_eq!(
+            highlight("src/lib.rs", &[4, 5]),
+            "\x1b[2msrc/\x1b[0m\x1b[1;31mli\x1b[0mb.rs"
+        );
+        assert_eq!(path_prefix_len("src/lib.rs:12:5:fn main() {}"), 16);
+        assert_eq!(path_prefix_len("src/lib.rs:12:fn main() {}"), 14);
+        assert_eq!(path_prefix_len("fn main() {}"), 0);
+
+        let payload =
+            serde_json::json!({ "total": 2, "lines": ["foo", "bar"], "indices": [[0], []] });
+        assert_eq!(
+            render(&payload),
+            Some("\x1b[1;31mf\x1b[0moo\nbar".to_string())
+        );
+        assert_eq!(render(&serde_json::json!({ "total": 0 })), None);
+        assert_eq!(
+            render(&serde_json::json!({ "total": 2, "tempfile": "/tmp/1_1.txt" })),
+            Some("2 results written to /tmp/1_1.txt".to_string())
+        );
+    }
+}
@@ -0,0 +1,119 @@
+//! Counts attached to the results of every subcommand.
+//!
+//! The results of filter, exec, grep and the cached responses always have `total`, `shown`
+//! and `truncated` with the same meaning, so that the frontend can handle them uniformly.
+
+use serde::Serialize;
+use serde_json::Value;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Payload {
+    /// Number of all the results, e.g., the matched items or the lines of command output.
+    pub total: usize,
+    /// Number of the results in `lines`.
+    pub shown: usize,
+    /// Whether some of the results are not in `lines`.
+    pub truncated: bool,
+}
+
+impl Payload {
+    pub fn new(total: usize, shown: usize) -> Self {
+        Self {
+            total,
+            shown,
+            truncated: shown < total,
+        }
+    }
+
+    /// Returns the counts of the JSON `payload` inferred from its `total` and `lines`.
+    ///
+    /// `shown` given explicitly takes precedence over the length of `lines`, e.g., the lines
+    /// grouped by file contain the file headers. `None` if the payload has neither `total` nor
+    /// `lines`, e.g., a single line result.
+    fn infer(payload: &Value) -> Option<Self> {
+        let shown = payload
+            .get("shown")
+            .and_then(|shown| shown.as_u64())
+            .map(|shown| shown as usize)
+            .or_else(|| {
+                payload
+                    .get("lines")
+                    .and_then(|lines| lines.as_array())
+                    .map(|lines| lines.len())
+            });
+        let total = payload
+            .get("total")
+            .and_then(|total| total.as_u64())
+            .map(|total| total as usize);
+        match (total, shown) {
+            (None, None) => None,
+            (total, shown) => {
+                let shown = shown.unwrap_or_default();
+                Some(Self::new(total.unwrap_or(shown), shown))
+            }
+        }
+    }
+
+    /// Sets the count fields of the JSON `payload` to these counts, which are kept as they are
+    /// by [`crate::println_payload`] later.
+    pub fn attach_to(self, payload: &mut Value) {
+        if let (Ok(Value::Object(counts)), Some(fields)) =
+            (serde_json::to_value(self), payload.as_object_mut())
+        {
+            fields.extend(counts);
+        }
+    }
+
+    /// Sets the count fields of the JSON `payload` to the counts inferred from it, unless they
+    /// are attached explicitly.
+    pub(crate) fn attach(payload: &mut Value) {
+        if payload.get("truncated").is_some() {
+            return;
+        }
+        if let Some(counts) = Self::infer(payload) {
+            counts.attach_to(payload);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_attach() {
+        let mut payload = json!({ "total": 3, "lines": ["a", "b"] });
+        Payload::attach(&mut payload);
+        assert_eq!(
+            payload,
+            json!({ "total": 3, "shown": 2, "truncated": true, "lines": ["a", "b"] })
+        );
+
+        let mut payload = json!({ "lines": ["a"] });
+        Payload::attach(&mut payload);
+        assert_eq!(
+            payload,
+            json!({ "total": 1, "shown": 1, "truncated": false, "lines": ["a"] })
+        );
+
+        let mut payload = json!({ "total": 5, "tempfile": "/tmp/x" });
+        Payload::attach(&mut payload);
+        assert_eq!(payload["shown"], json!(0));
+        assert_eq!(payload["truncated"], json!(true));
+
+        let mut payload = json!({ "total": 4, "shown": 4, "lines": ["a.rs", "a", "b"] });
+        Payload::attach(&mut payload);
+        assert_eq!(payload["truncated"], json!(false));
+
+        let mut payload = json!({ "text": "a", "indices": [0] });
+        Payload::attach(&mut payload);
+        assert_eq!(payload, json!({ "text": "a", "indices": [0] }));
+
+        let mut payload = json!({ "total": 9, "lines": ["a.rs", "a"] });
+        Payload::new(9, 1).attach_to(&mut payload);
+        Payload::attach(&mut payload);
+        assert_eq!(payload["shown"], json!(1));
+        assert_eq!(payload["truncated"], json!(true));
+    }
+}
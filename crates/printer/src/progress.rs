@@ -0,0 +1,81 @@
+//! Tags of the top results streamed in dyn mode.
+//!
+//! The top results are refined and sent again and again while the source streams in, the
+//! snapshot of a greater `sequence` always covers more lines of the source, so the frontend can
+//! simply drop the stale snapshots arriving late.
+
+use serde_json::Value;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Progress {
+    /// Position of the snapshot in the stream, starting from 0.
+    pub sequence: usize,
+    /// Whether the source is complete, i.e., the snapshot is final.
+    pub finished: bool,
+    /// Hint of typing debounce for the frontend, see `filter::suggested_debounce_ms`.
+    pub suggested_debounce_ms: Option<u64>,
+    /// Whether some matches have been dropped for the memory, see `filter::set_max_retained`.
+    pub limited: bool,
+}
+
+impl Progress {
+    /// Returns the tags of a snapshot sent while the source is still being read.
+    pub fn partial(sequence: usize) -> Self {
+        Self {
+            sequence,
+            finished: false,
+            suggested_debounce_ms: None,
+            limited: false,
+        }
+    }
+
+    /// Returns the tags of the final snapshot.
+    pub fn finished(sequence: usize, suggested_debounce_ms: u64) -> Self {
+        Self {
+            sequence,
+            finished: true,
+            suggested_debounce_ms: Some(suggested_debounce_ms),
+            limited: false,
+        }
+    }
+
+    pub fn limited(mut self, limited: bool) -> Self {
+        self.limited = limited;
+        self
+    }
+
+    /// Adds the tags to the JSON `payload`.
+    pub fn attach(&self, payload: &mut Value) {
+        payload["sequence"] = serde_json::json!(self.sequence);
+        payload["finished"] = serde_json::json!(self.finished);
+        if let Some(suggested_debounce_ms) = self.suggested_debounce_ms {
+            payload["suggested_debounce_ms"] = serde_json::json!(suggested_debounce_ms);
+        }
+        if self.limited {
+            payload["limited"] = serde_json::json!(true);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_attach() {
+        let mut payload = json!({ "total": 1 });
+        Progress::partial(0).attach(&mut payload);
+        assert_eq!(
+            payload,
+            json!({ "total": 1, "sequence": 0, "finished": false })
+        );
+
+        let mut payload = json!({ "total": 1 });
+        Progress::finished(2, 20).attach(&mut payload);
+        assert_eq!(
+            payload,
+            json!({ "total": 1, "sequence": 2, "finished": true, "suggested_debounce_ms": 20 })
+        );
+    }
+}
@@ -0,0 +1,117 @@
+//! Shortening the paths for display in the narrow windows.
+//!
+//! The intermediate directories are abbreviated to one char, the parent directory and file
+//! name are kept, e.g., `~/projects/vim-clap/src/main.rs` is displayed as `~/p/v/src/main.rs`.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static SHORTEN_PATH: AtomicBool = AtomicBool::new(false);
+
+/// Sets whether to shorten the paths printed by this process.
+pub fn set_shorten_path(enable: bool) {
+    SHORTEN_PATH.store(enable, Ordering::Relaxed);
+}
+
+fn is_separator(c: char) -> bool {
+    c == '/' || c == std::path::MAIN_SEPARATOR
+}
+
+/// Returns the shortened `path` and the char `indices` remapped onto it if `--shorten-path`
+/// is on, `None` if nothing can be shortened.
+///
+/// Only the text looking like a path, i.e., having a separator and no whitespace, is
+/// shortened. The indices in the abbreviated part of a directory are dropped.
+pub fn try_shorten(path: &str, indices: &[usize]) -> Option<(String, Vec<usize>)> {
+    if !SHORTEN_PATH.load(Ordering::Relaxed) {
+        return None;
+    }
+    shorten_with_home(path, indices, std::env::var("HOME").ok().as_deref())
+}
+
+fn shorten_with_home(
+    path: &str,
+    indices: &[usize],
+    home: Option<&str>,
+) -> Option<(String, Vec<usize>)> {
+    if path.contains(char::is_whitespace) {
+        return None;
+    }
+
+    let chars = path.chars().collect::<Vec<_>>();
+
+    // The kept chars and their indices in `path`.
+    let mut kept: Vec<(char, Option<usize>)> = Vec::with_capacity(chars.len());
+
+    let start = match home.filter(|home| !home.is_empty()) {
+        Some(home)
+            if path
+                .strip_prefix(home)
+                .map_or(false, |r| r.starts_with(is_separator)) =>
+        {
+            kept.push(('~', None));
+            home.chars().count()
+        }
+        _ => 0,
+    };
+
+    let separators = (start..chars.len())
+        .filter(|&i| is_separator(chars[i]))
+        .collect::<Vec<_>>();
+
+    // The components after it are kept as is.
+    let kept_from = if separators.len() >= 2 {
+        separators[separators.len() - 2]
+    } else {
+        start
+    };
+
+    let mut component_start = start;
+    for (i, &c) in chars.iter().enumerate().skip(start) {
+        if is_separator(c) {
+            kept.push((c, Some(i)));
+            component_start = i + 1;
+            continue;
+        }
+        let pos = i - component_start;
+        // Keep one more char for the hidden directories, e.g., `.config` => `.c`.
+        if i > kept_from || pos == 0 || (pos == 1 && chars[component_start] == '.') {
+            kept.push((c, Some(i)));
+        }
+    }
+
+    if start == 0 && kept.len() == chars.len() {
+        return None;
+    }
+
+    let shortened = kept.iter().map(|(c, _)| c).collect::<String>();
+    let shortened_indices = indices
+        .iter()
+        .filter_map(|idx| kept.iter().position(|(_, origin)| *origin == Some(*idx)))
+        .collect();
+
+    Some((shortened, shortened_indices))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shorten_path() {
+        let path = "/home/clap/projects/vim-clap/src/main.rs";
+        let (shortened, indices) = shorten_with_home(path, &[11, 12, 29, 33], None).unwrap();
+        assert_eq!(shortened, "/h/c/p/v/src/main.rs");
+        // `r` of projects is dropped.
+        assert_eq!(indices, vec![5, 9, 13]);
+
+        let (shortened, _) = shorten_with_home(path, &[], Some("/home/clap")).unwrap();
+        assert_eq!(shortened, "~/p/v/src/main.rs");
+
+        let (shortened, _) =
+            shorten_with_home("/home/clap/.config/nvim/init.vim", &[], Some("/home/clap")).unwrap();
+        assert_eq!(shortened, "~/.c/nvim/init.vim");
+
+        assert!(shorten_with_home("src/main.rs", &[], None).is_none());
+        assert!(shorten_with_home("a/b c/d/e", &[], None).is_none());
+    }
+}
@@ -0,0 +1,54 @@
+//! Statistics of the payloads printed by this process, e.g., for recording the session.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use serde_json::Value;
+
+static PAYLOADS: AtomicUsize = AtomicUsize::new(0);
+
+/// `total` of the last payload having it plus one, 0 if there is none.
+static LAST_TOTAL: AtomicUsize = AtomicUsize::new(0);
+
+/// Statistics of the payloads printed so far.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PrintStats {
+    /// Number of the payloads printed to stdout, i.e., the JSON lines, the lines spilled to a
+    /// tempfile are not counted.
+    pub payloads: usize,
+    /// `total` of the last payload having it.
+    pub total: Option<usize>,
+}
+
+/// Counts `n` payloads printed to stdout.
+pub(crate) fn count_printed(n: usize) {
+    PAYLOADS.fetch_add(n, Ordering::Relaxed);
+}
+
+/// Records `total` of `payload` if any, whether it's printed to stdout or spilled.
+pub(crate) fn observe(payload: &Value) {
+    if let Some(total) = payload.get("total").and_then(Value::as_u64) {
+        LAST_TOTAL.store(total as usize + 1, Ordering::Relaxed);
+    }
+}
+
+pub fn print_stats() -> PrintStats {
+    PrintStats {
+        payloads: PAYLOADS.load(Ordering::Relaxed),
+        total: LAST_TOTAL.load(Ordering::Relaxed).checked_sub(1),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_print_stats() {
+        observe(&serde_json::json!({ "total": 3, "lines": ["a"] }));
+        observe(&serde_json::json!({ "text": "a" }));
+        count_printed(2);
+        let stats = print_stats();
+        assert!(stats.payloads >= 2);
+        assert_eq!(stats.total, Some(3));
+    }
+}
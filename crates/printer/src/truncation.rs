@@ -0,0 +1,359 @@
+//! Truncation of the long lines to fit in the display window.
+//!
+//! A line is truncated around its matched chars, the indices of the matched chars are remapped
+//! to the truncated line by its [`LineOffset`], all the subcommands share this offset math.
+
+use std::collections::HashMap;
+
+use source_item::SourceItem;
+
+use crate::offset::{byte_indices_to_char_indices, LineOffset};
+use crate::{shorten_path, LinesTruncatedMap};
+
+pub const DOTS: &str = "..";
+
+/// sign column width 2, plus the icon prefix of the default width 2.
+#[cfg(not(test))]
+const WINWIDTH_OFFSET: usize = 4;
+
+#[cfg(test)]
+const WINWIDTH_OFFSET: usize = 0;
+
+/// Returns the width available for the text of a line in the window of `winwidth`, the icon
+/// prefix wider than the default one, e.g., with `--ambiwidth double`, takes more cells.
+fn text_width(winwidth: usize) -> usize {
+    let extra_icon_width =
+        icon::width::icon_width().saturating_sub(icon::width::DEFAULT_ICON_WIDTH);
+    winwidth.saturating_sub(WINWIDTH_OFFSET + extra_icon_width)
+}
+
+// https://stackoverflow.com/questions/51982999/slice-a-string-containing-unicode-chars
+#[inline]
+pub(crate) fn utf8_str_slice(line: &str, start: usize, end: usize) -> String {
+    line.chars().take(end).skip(start).collect()
+}
+
+/// Returns the truncated line and its offset if `line` is too long for the matched chars at
+/// `indices` to be visible.
+fn truncate_line_impl(
+    winwidth: usize,
+    line: &str,
+    indices: &[usize],
+    skipped: Option<usize>,
+) -> Option<(String, LineOffset)> {
+    let last_idx = indices.last()?;
+    // The prefix is kept in front of the dots, the rest of line is fit in the remaining width.
+    let text_width = winwidth.saturating_sub(skipped.unwrap_or(0));
+    if *last_idx > winwidth {
+        let mut start = *last_idx - text_width;
+        if start >= indices[0] || (indices.len() > 1 && *last_idx - start > text_width) {
+            start = indices[0];
+        }
+        // The indices are counted in chars.
+        let line_len = line.chars().count();
+        // [--------------------------]
+        // [-----------------------------------------------------------------xx--x--]
+        for _ in 0..3 {
+            if indices[0] - start >= DOTS.len() && line_len - start >= text_width {
+                start += DOTS.len();
+            } else {
+                break;
+            }
+        }
+        let trailing_dist = line_len - last_idx;
+        if trailing_dist < indices[0] - start {
+            start += trailing_dist;
+        }
+        let end = line_len;
+        let left_truncated = if let Some(n) = skipped {
+            let icon: String = line.chars().take(n).collect();
+            format!("{}{}{}", icon, DOTS, utf8_str_slice(&line, start, end))
+        } else {
+            format!("{}{}", DOTS, utf8_str_slice(&line, start, end))
+        };
+
+        let prepended = skipped.unwrap_or(0) + DOTS.len();
+        let left_truncated_len = left_truncated.chars().count();
+
+        let (truncated, max_index) = if left_truncated_len > winwidth {
+            if left_truncated_len == winwidth + 1 {
+                (
+                    format!("{}.", utf8_str_slice(&left_truncated, 0, winwidth - 1)),
+                    winwidth - 1,
+                )
+            } else {
+                (
+                    format!(
+                        "{}{}",
+                        utf8_str_slice(&left_truncated, 0, winwidth - 2),
+                        DOTS
+                    ),
+                    winwidth - 2,
+                )
+            }
+        } else {
+            (left_truncated, winwidth)
+        };
+
+        // The chars before `start` are replaced by the prefix and dots, which can be longer
+        // than the replaced chars.
+        Some((
+            truncated,
+            LineOffset::truncated(start, prepended, max_index),
+        ))
+    } else {
+        None
+    }
+}
+
+/// Returns the line to display of `item`, shortened and truncated if necessary, and the offset
+/// of `indices` on it, the original line is returned too if it's changed.
+///
+/// The indices of the shortened path are remapped before the truncation.
+pub(crate) fn truncate_item(
+    item: SourceItem,
+    indices: Vec<usize>,
+    winwidth: usize,
+    skipped: Option<usize>,
+) -> (String, Vec<usize>, LineOffset, Option<String>) {
+    let winwidth = text_width(winwidth);
+    let line = item.display_text.unwrap_or(item.raw);
+    if let Some((shortened, shortened_indices)) = shorten_path::try_shorten(&line, &indices) {
+        // The full path is kept in the truncated map like the truncated lines.
+        let (text, offset) = truncate_line_impl(winwidth, &shortened, &shortened_indices, skipped)
+            .unwrap_or((shortened, LineOffset::default()));
+        (text, shortened_indices, offset, Some(line))
+    } else if let Some((truncated, offset)) = truncate_line_impl(winwidth, &line, &indices, skipped)
+    {
+        (truncated, indices, offset, Some(line))
+    } else {
+        (line, indices, LineOffset::default(), None)
+    }
+}
+
+/// Long matched lines can cause the matched items invisible.
+///
+/// # Arguments
+///
+/// - winwidth: width of the display window.
+/// - skipped: number of skipped chars, used when need to skip the leading icons.
+pub fn truncate_long_matched_lines<T>(
+    lines: impl IntoIterator<Item = (SourceItem, T, Vec<usize>)>,
+    winwidth: usize,
+    skipped: Option<usize>,
+) -> (Vec<(String, T, Vec<usize>)>, LinesTruncatedMap) {
+    let mut truncated_map = HashMap::new();
+    let lines = lines
+        .into_iter()
+        .enumerate()
+        .map(|(idx, (item, score, indices))| {
+            let (text, indices, offset, full_line) =
+                truncate_item(item, indices, winwidth, skipped);
+            if let Some(full_line) = full_line {
+                truncated_map.insert(idx + 1, full_line);
+            }
+            (text, score, offset.apply(&indices))
+        })
+        .collect::<Vec<_>>();
+    (lines, truncated_map)
+}
+
+/// Truncates the lines longer than `width` so that the matched chars at `indices` are visible.
+///
+/// The indices are the char indices of each line, the returned ones are the char indices in the
+/// truncated lines, the matched chars cut off on the right are dropped. The first `prefix_width`
+/// chars, e.g., the icon, are kept when a line is truncated on the left. The full lines of the
+/// truncated ones are returned in the map by their 1-based line number.
+///
+/// # Examples
+///
+/// ```
+/// let line = format!("{}needle", "a".repeat(40));
+/// let (lines, indices, truncated_map) =
+///     printer::truncate_lines(vec![line.clone()], vec![(40..46).collect()], 24, None);
+/// assert!(lines[0].starts_with(".."));
+/// assert_eq!(truncated_map.get(&1), Some(&line));
+/// ```
+pub fn truncate_lines(
+    lines: impl IntoIterator<Item = String>,
+    indices: impl IntoIterator<Item = Vec<usize>>,
+    width: usize,
+    prefix_width: Option<usize>,
+) -> (Vec<String>, Vec<Vec<usize>>, LinesTruncatedMap) {
+    let mut truncated_map = HashMap::new();
+    let width = text_width(width);
+    let (lines, indices): (Vec<String>, Vec<Vec<usize>>) = lines
+        .into_iter()
+        .zip(indices.into_iter())
+        .enumerate()
+        .map(|(idx, (line, indices))| {
+            match truncate_line_impl(width, &line, &indices, prefix_width) {
+                Some((truncated, offset)) => {
+                    let truncated_indices = offset.apply(&indices);
+                    truncated_map.insert(idx + 1, line);
+                    (truncated, truncated_indices)
+                }
+                None => (line, indices),
+            }
+        })
+        .unzip();
+    (lines, indices, truncated_map)
+}
+
+/// Same with [`truncate_lines`] but the indices are the byte offsets, e.g., from rg's submatches.
+///
+/// The truncation is done in chars, the returned indices are still byte offsets in the
+/// truncated line.
+pub fn truncate_grep_lines(
+    lines: impl IntoIterator<Item = String>,
+    indices: impl IntoIterator<Item = Vec<usize>>,
+    winwidth: usize,
+    skipped: Option<usize>,
+) -> (Vec<String>, Vec<Vec<usize>>, LinesTruncatedMap) {
+    let mut truncated_map = HashMap::new();
+    let width = text_width(winwidth);
+    let (lines, indices): (Vec<String>, Vec<Vec<usize>>) = lines
+        .into_iter()
+        .zip(indices.into_iter())
+        .enumerate()
+        .map(|(idx, (line, byte_indices))| {
+            let char_indices = byte_indices_to_char_indices(&line, &byte_indices);
+            match truncate_line_impl(width, &line, &char_indices, skipped) {
+                Some((truncated, offset)) => {
+                    let truncated_indices = offset.apply_bytes(&line, &truncated, &byte_indices);
+                    truncated_map.insert(idx + 1, line);
+                    (truncated, truncated_indices)
+                }
+                None => (line, byte_indices),
+            }
+        })
+        .unzip();
+    (lines, indices, truncated_map)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns the chars of `line` at `indices`.
+    fn highlighted(line: &str, indices: &[usize]) -> String {
+        indices
+            .iter()
+            .map(|idx| line.chars().nth(*idx).unwrap())
+            .collect()
+    }
+
+    fn truncate_one(
+        line: &str,
+        indices: Vec<usize>,
+        width: usize,
+        prefix_width: Option<usize>,
+    ) -> (String, Vec<usize>, Option<String>) {
+        let (mut lines, mut indices, truncated_map) =
+            truncate_lines(vec![line.to_string()], vec![indices], width, prefix_width);
+        (
+            lines.remove(0),
+            indices.remove(0),
+            truncated_map.get(&1).cloned(),
+        )
+    }
+
+    #[test]
+    fn test_truncate_short_line() {
+        let (line, indices, full) = truncate_one("src/lib.rs", vec![4, 5, 6], 20, None);
+        assert_eq!(line, "src/lib.rs");
+        assert_eq!(indices, vec![4, 5, 6]);
+        assert!(full.is_none());
+
+        let (line, indices, full) = truncate_one("src/lib.rs", vec![], 5, None);
+        assert_eq!(line, "src/lib.rs");
+        assert!(indices.is_empty());
+        assert!(full.is_none());
+    }
+
+    #[test]
+    fn test_truncate_ascii_line() {
+        let raw = format!("{}needle", "a".repeat(40));
+        let (line, indices, full) = truncate_one(&raw, (40..46).collect(), 20, None);
+        assert_eq!(line, format!("..{}needle", "a".repeat(12)));
+        assert_eq!(indices, (14..20).collect::<Vec<_>>());
+        assert_eq!(full, Some(raw));
+    }
+
+    #[test]
+    fn test_truncate_multibyte_line() {
+        let raw = format!("{}needle{}", "中".repeat(40), "文".repeat(3));
+        let (line, indices, full) = truncate_one(&raw, (40..46).collect(), 20, None);
+        assert!(line.starts_with(DOTS));
+        assert!(line.chars().count() <= 20);
+        assert_eq!(highlighted(&line, &indices), "needle");
+        assert_eq!(full, Some(raw));
+    }
+
+    #[test]
+    fn test_truncate_with_icon_prefix() {
+        let raw = format!("X {}needle", "a".repeat(40));
+        let (line, indices, _) = truncate_one(&raw, (42..48).collect(), 20, Some(2));
+        assert!(line.starts_with("X .."));
+        assert!(line.chars().count() <= 20);
+        assert_eq!(highlighted(&line, &indices), "needle");
+
+        let raw = format!("中 {}needle", "文".repeat(40));
+        let (line, indices, _) = truncate_one(&raw, (42..48).collect(), 20, Some(2));
+        assert!(line.starts_with("中 .."));
+        assert_eq!(highlighted(&line, &indices), "needle");
+    }
+
+    #[test]
+    fn test_truncate_remaps_indices() {
+        // Truncated on the right only, the indices are shifted by the dots prepended.
+        let raw = format!("needle{}", "a".repeat(40));
+        let (line, indices, _) = truncate_one(&raw, vec![0, 45], 20, None);
+        assert!(line.ends_with(DOTS));
+        assert_eq!(line.chars().count(), 20);
+        assert_eq!(indices, vec![2]);
+        assert_eq!(highlighted(&line, &indices), "n");
+
+        // The matched chars cut off on the right are dropped.
+        let raw = format!("{}ne{}dle", "a".repeat(30), "b".repeat(30));
+        let (line, indices, _) = truncate_one(&raw, vec![30, 31, 62, 63, 64], 20, None);
+        assert!(line.chars().count() <= 20);
+        assert!("nedle".starts_with(&highlighted(&line, &indices)));
+    }
+
+    #[test]
+    fn test_truncate_grep_lines_with_byte_indices() {
+        let line = format!(
+            "src/lib.rs:1:1:{}needle{}",
+            "中".repeat(60),
+            "文".repeat(10)
+        );
+        let start = line.find("needle").unwrap();
+        let indices = (start..start + "needle".len()).collect::<Vec<_>>();
+
+        let (lines, indices, truncated_map) =
+            truncate_grep_lines(vec![line.clone()], vec![indices], 50, None);
+
+        assert_eq!(truncated_map.get(&1), Some(&line));
+        let highlighted = indices[0]
+            .iter()
+            .map(|i| lines[0].as_bytes()[*i])
+            .collect::<Vec<_>>();
+        assert_eq!(highlighted, b"needle");
+
+        // The byte indices of the short line are kept as is.
+        let (_, indices, truncated_map) =
+            truncate_grep_lines(vec!["a.rs:1:1:中x".into()], vec![vec![9, 10]], 50, None);
+        assert_eq!(indices, vec![vec![9, 10]]);
+        assert!(truncated_map.is_empty());
+    }
+
+    #[test]
+    fn test_print_multibyte_string_slice() {
+        let multibyte_str = "README.md:23:1:Gourinath Banda. “Scalable Real-Time Kernel for Small Embedded Systems”. En- glish. PhD thesis. Denmark: University of Southern Denmark, June 2003. URL: http://citeseerx.ist.psu.edu/viewdoc/download;jsessionid=84D11348847CDC13691DFAED09883FCB?doi=10.1.1.118.1909&rep=rep1&type=pdf.";
+        let start = 33;
+        let end = 300;
+        let expected = "Scalable Real-Time Kernel for Small Embedded Systems”. En- glish. PhD thesis. Denmark: University of Southern Denmark, June 2003. URL: http://citeseerx.ist.psu.edu/viewdoc/download;jsessionid=84D11348847CDC13691DFAED09883FCB?doi=10.1.1.118.1909&rep=rep1&type=pdf.";
+        assert_eq!(expected, utf8_str_slice(multibyte_str, start, end));
+    }
+}
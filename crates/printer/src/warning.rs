@@ -0,0 +1,68 @@
+//! Warnings about the source of results, attached to the next printed result payload.
+
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use serde_json::Value;
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Warning {
+    /// The lines containing NUL of a file, which is probably binary, are skipped.
+    BinaryLinesSkipped { path: PathBuf, count: usize },
+    /// A command exited successfully but wrote to stderr, e.g., the progress info.
+    Stderr { message: String },
+    /// The filtering was interrupted, only the results collected before are shown.
+    Interrupted,
+}
+
+static PENDING: Lazy<Mutex<Vec<Warning>>> = Lazy::new(Default::default);
+
+/// Reports `warning`, which is sent along with the results printed next.
+pub fn warn(warning: Warning) {
+    if let Ok(mut pending) = PENDING.lock() {
+        pending.push(warning);
+    }
+}
+
+/// Moves the pending warnings to `warnings` of the JSON `payload` if it has `total`.
+pub(crate) fn attach(payload: &mut Value) {
+    if payload.get("total").is_none() {
+        return;
+    }
+    let warnings = match PENDING.lock() {
+        Ok(mut pending) if !pending.is_empty() => std::mem::take(&mut *pending),
+        _ => return,
+    };
+    payload["warnings"] = serde_json::json!(warnings);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_attach() {
+        warn(Warning::BinaryLinesSkipped {
+            path: "a.bin".into(),
+            count: 2,
+        });
+
+        let mut line = serde_json::json!({ "text": "a" });
+        attach(&mut line);
+        assert!(line.get("warnings").is_none());
+
+        let mut payload = serde_json::json!({ "total": 1 });
+        attach(&mut payload);
+        assert_eq!(
+            payload["warnings"],
+            serde_json::json!([{ "kind": "binary_lines_skipped", "path": "a.bin", "count": 2 }])
+        );
+
+        let mut payload = serde_json::json!({ "total": 1 });
+        attach(&mut payload);
+        assert!(payload.get("warnings").is_none());
+    }
+}
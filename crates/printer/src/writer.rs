@@ -0,0 +1,187 @@
+//! Writer of the results printed line by line to stdout.
+//!
+//! The lines are written in bounded chunks, so a slow reader of stdout blocks the writer instead
+//! of the pending output piling up in memory. Once the output exceeds the threshold set by
+//! [`set_spill_threshold`], it's spilled to a tempfile and only the path of the tempfile is
+//! returned. The tempfiles older than [`SPILL_MAX_AGE`] are removed on the next spill.
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, SystemTime};
+
+/// Size of the pieces flushed to stdout.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// The spilled output is read by the frontend right after it's printed, the older ones are
+/// left behind.
+pub const SPILL_MAX_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// 0 means never spilling the output.
+static SPILL_THRESHOLD: AtomicUsize = AtomicUsize::new(0);
+
+/// Sets the size in bytes beyond which the output printed by this process is spilled to a
+/// tempfile.
+pub fn set_spill_threshold(threshold: Option<usize>) {
+    SPILL_THRESHOLD.store(threshold.unwrap_or(0), Ordering::Relaxed);
+}
+
+fn spill_threshold() -> Option<usize> {
+    match SPILL_THRESHOLD.load(Ordering::Relaxed) {
+        0 => None,
+        threshold => Some(threshold),
+    }
+}
+
+/// Removes the spilled output in `dir` modified more than `max_age` ago.
+fn remove_stale_spills(dir: &Path, max_age: Duration) {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+    for path in entries.filter_map(|e| e.ok()).map(|e| e.path()) {
+        let is_stale = std::fs::metadata(&path)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|modified| modified.elapsed().ok())
+            .map_or(false, |age| age > max_age);
+        if is_stale {
+            // It may be removed by another process at the same time.
+            let _ = std::fs::remove_file(&path);
+        }
+    }
+}
+
+/// Returns a path of the spilled output not used by the other processes.
+fn spill_path() -> io::Result<PathBuf> {
+    let mut dir = utility::clap_cache_dir();
+    dir.push("output");
+    std::fs::create_dir_all(&dir)?;
+    remove_stale_spills(&dir, SPILL_MAX_AGE);
+    let nanos = SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or_default();
+    dir.push(format!("{}_{}.txt", std::process::id(), nanos));
+    Ok(dir)
+}
+
+#[derive(Debug)]
+pub struct ChunkedWriter<W: Write> {
+    out: W,
+    /// Lines not written yet, at most the spill threshold or [`CHUNK_SIZE`].
+    pending: Vec<u8>,
+    spill_threshold: Option<usize>,
+    spilled: Option<(PathBuf, BufWriter<File>)>,
+}
+
+impl ChunkedWriter<io::Stdout> {
+    /// Creates a writer of stdout with the spill threshold of this process.
+    pub fn stdout() -> Self {
+        Self::new(io::stdout(), spill_threshold())
+    }
+}
+
+impl<W: Write> ChunkedWriter<W> {
+    pub fn new(out: W, spill_threshold: Option<usize>) -> Self {
+        Self {
+            out,
+            pending: Vec::with_capacity(CHUNK_SIZE),
+            spill_threshold,
+            spilled: None,
+        }
+    }
+
+    pub fn write_line(&mut self, line: &str) -> io::Result<()> {
+        if let Some((_, ref mut file)) = self.spilled {
+            return writeln!(file, "{}", line);
+        }
+
+        self.pending.extend_from_slice(line.as_bytes());
+        self.pending.push(b'\n');
+
+        // The output can't be spilled once part of it is written to `out`, hence it's kept in
+        // memory until either the threshold is exceeded or the output is complete.
+        match self.spill_threshold {
+            Some(threshold) if self.pending.len() > threshold => {
+                let path = spill_path()?;
+                let mut file = BufWriter::new(File::create(&path)?);
+                file.write_all(&self.pending)?;
+                self.pending = Vec::new();
+                self.spilled = Some((path, file));
+            }
+            Some(_) => {}
+            None if self.pending.len() >= CHUNK_SIZE => self.flush_pending()?,
+            None => {}
+        }
+
+        Ok(())
+    }
+
+    fn flush_pending(&mut self) -> io::Result<()> {
+        for chunk in self.pending.chunks(CHUNK_SIZE) {
+            self.out.write_all(chunk)?;
+            self.out.flush()?;
+        }
+        self.pending.clear();
+        Ok(())
+    }
+
+    /// Writes the rest of output, returns the path of tempfile if the output has been spilled.
+    pub fn finish(mut self) -> io::Result<Option<PathBuf>> {
+        match self.spilled.take() {
+            Some((path, mut file)) => {
+                file.flush()?;
+                Ok(Some(path))
+            }
+            None => {
+                self.flush_pending()?;
+                Ok(None)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chunked_writer() {
+        let mut out = Vec::new();
+        let mut writer = ChunkedWriter::new(&mut out, None);
+        for _ in 0..CHUNK_SIZE {
+            writer.write_line("a").unwrap();
+        }
+        assert_eq!(writer.finish().unwrap(), None);
+        assert_eq!(out.len(), CHUNK_SIZE * 2);
+
+        let mut out = Vec::new();
+        let mut writer = ChunkedWriter::new(&mut out, Some(4));
+        writer.write_line("ab").unwrap();
+        writer.write_line("cd").unwrap();
+        writer.write_line("ef").unwrap();
+        let path = writer.finish().unwrap().unwrap();
+        assert!(out.is_empty());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "ab\ncd\nef\n");
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_remove_stale_spills() {
+        let dir =
+            std::env::temp_dir().join(format!("clap_test_stale_spills_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let spilled = dir.join("1_1.txt");
+        std::fs::write(&spilled, "a\n").unwrap();
+
+        remove_stale_spills(&dir, SPILL_MAX_AGE);
+        assert!(spilled.exists());
+        std::thread::sleep(Duration::from_millis(10));
+        remove_stale_spills(&dir, Duration::from_millis(1));
+        assert!(!spilled.exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
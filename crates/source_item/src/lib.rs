@@ -1,10 +1,73 @@
+use std::borrow::Cow;
+
 use structopt::clap::arg_enum;
 
 use pattern::{file_name_only, strip_grep_filepath, tag_name_only};
 
 /// A tuple of match text piece (matching_text, offset_of_matching_text).
+///
+/// The offset is counted in chars, same with the matched indices.
 pub type MatchText<'a> = (&'a str, usize);
 
+/// Converts the byte offset in `line` to the char offset.
+#[inline]
+fn char_offset(line: &str, byte_offset: usize) -> usize {
+    line[..byte_offset].chars().count()
+}
+
+/// Aligning the texts whose lengths multiply beyond this is too expensive.
+const MAX_ALIGN_CELLS: usize = 1 << 20;
+
+/// Maps each char of `filter_text` to the char of `display_text` it's displayed as.
+///
+/// The chars are aligned along the longest common subsequence, the chars absent in
+/// `display_text` are mapped to `None`.
+fn align_chars(filter_text: &str, display_text: &str) -> Vec<Option<usize>> {
+    let a = filter_text.chars().collect::<Vec<_>>();
+    let b = display_text.chars().collect::<Vec<_>>();
+    let mut map = vec![None; a.len()];
+    if (a.len() + 1) * (b.len() + 1) > MAX_ALIGN_CELLS {
+        return map;
+    }
+
+    // lcs[i * w + j] is the length of LCS of a[i..] and b[j..].
+    let w = b.len() + 1;
+    let mut lcs = vec![0u32; (a.len() + 1) * w];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i * w + j] = if a[i] == b[j] {
+                lcs[(i + 1) * w + j + 1] + 1
+            } else {
+                lcs[(i + 1) * w + j].max(lcs[i * w + j + 1])
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        if a[i] == b[j] {
+            map[i] = Some(j);
+            i += 1;
+            j += 1;
+        } else if lcs[(i + 1) * w + j] >= lcs[i * w + j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    map
+}
+
+/// Maps the matched indices of the match text onto the display text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DisplayIndexMap {
+    /// The chars are aligned along the longest common subsequence when the indices are
+    /// converted, which is only done for the printed items since the alignment is expensive.
+    Aligned,
+    /// The display char of each char of the match text.
+    Explicit(Vec<Option<usize>>),
+}
+
 arg_enum! {
   #[derive(Debug, Clone)]
   pub enum MatchType {
@@ -55,6 +118,15 @@ pub struct SourceItem {
     pub raw: String,
     pub match_text: Option<(String, usize)>,
     pub display_text: Option<String>,
+    /// True if `raw` is decoded lossily from a line containing invalid UTF-8.
+    pub lossy: bool,
+    /// Maps the matched indices onto `display_text` when it's not aligned with `match_text`.
+    pub display_index_map: Option<DisplayIndexMap>,
+    /// 1-based line number of the item in the source file.
+    pub line_number: Option<usize>,
+    /// Opaque data of the provider, carried through the filtering untouched and returned along
+    /// with the item, e.g., the location to jump to.
+    pub user_data: Option<serde_json::Value>,
 }
 
 impl From<&str> for SourceItem {
@@ -63,6 +135,10 @@ impl From<&str> for SourceItem {
             raw: s.into(),
             display_text: None,
             match_text: None,
+            lossy: false,
+            display_index_map: None,
+            line_number: None,
+            user_data: None,
         }
     }
 }
@@ -84,6 +160,42 @@ impl SourceItem {
             raw,
             display_text,
             match_text,
+            lossy: false,
+            display_index_map: None,
+            line_number: None,
+            user_data: None,
+        }
+    }
+
+    /// Constructs `SourceItem` that is matched against `filter_text` but displayed as
+    /// `display_text`, e.g., filter on the full path but display the shortened one.
+    ///
+    /// The matched indices are remapped onto `display_text` by [`Self::display_indices`], the
+    /// matched chars absent in `display_text` are not highlighted.
+    pub fn with_display(raw: String, filter_text: String, display_text: String) -> Self {
+        Self {
+            raw,
+            display_text: Some(display_text),
+            match_text: Some((filter_text, 0)),
+            lossy: false,
+            display_index_map: Some(DisplayIndexMap::Aligned),
+            line_number: None,
+            user_data: None,
+        }
+    }
+
+    /// Constructs `SourceItem` from the bytes of a line.
+    ///
+    /// The invalid UTF-8 sequences are replaced with `U+FFFD` and the item is marked as lossy,
+    /// the matcher runs against the decoded text.
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        match String::from_utf8_lossy(bytes) {
+            Cow::Borrowed(line) => line.into(),
+            Cow::Owned(line) => {
+                let mut item: Self = line.into();
+                item.lossy = true;
+                item
+            }
         }
     }
 
@@ -103,15 +215,66 @@ impl SourceItem {
         }
     }
 
+    /// Converts the matched indices to the indices of the display text.
+    pub fn display_indices(&self, indices: Vec<usize>) -> Vec<usize> {
+        let map_with = |map: &[Option<usize>]| {
+            indices
+                .iter()
+                .filter_map(|&idx| map.get(idx).copied().flatten())
+                .collect()
+        };
+        match self.display_index_map {
+            Some(DisplayIndexMap::Aligned) => {
+                map_with(&align_chars(self.match_text(), self.display_text()))
+            }
+            Some(DisplayIndexMap::Explicit(ref map)) => map_with(map),
+            None => indices,
+        }
+    }
+
     pub fn match_text_for(&self, match_ty: &MatchType) -> Option<MatchText> {
         if let Some((ref text, offset)) = self.match_text {
             return Some((text, offset));
         }
-        match match_ty {
-            MatchType::Full => Some((self.raw.as_str(), 0)),
-            MatchType::TagName => tag_name_only(self.raw.as_str()).map(|s| (s, 0)),
-            MatchType::FileName => file_name_only(self.raw.as_str()),
-            MatchType::IgnoreFilePath => strip_grep_filepath(self.raw.as_str()),
+        line_match_text_for(&self.raw, match_ty)
+    }
+}
+
+/// Returns the text of `line` to match for `match_ty`, same with [`SourceItem::match_text_for`]
+/// of the plain item of `line`, without creating the item.
+pub fn line_match_text_for<'a>(line: &'a str, match_ty: &MatchType) -> Option<MatchText<'a>> {
+    match match_ty {
+        MatchType::Full => Some((line, 0)),
+        MatchType::TagName => tag_name_only(line).map(|s| (s, 0)),
+        MatchType::FileName => {
+            file_name_only(line).map(|(text, offset)| (text, char_offset(line, offset)))
         }
+        MatchType::IgnoreFilePath => {
+            strip_grep_filepath(line).map(|(text, offset)| (text, char_offset(line, offset)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display_indices() {
+        let item = SourceItem::with_display(
+            "src/components/foo.rs".into(),
+            "src/components/foo.rs".into(),
+            "s/c/foo.rs [+]".into(),
+        );
+        // s, c, f, o, o
+        assert_eq!(
+            item.display_indices(vec![0, 4, 15, 16, 17]),
+            vec![0, 2, 4, 5, 6]
+        );
+        // `rc` is elided in the display text.
+        assert_eq!(item.display_indices(vec![1, 2, 19]), vec![8]);
+
+        let item: SourceItem = "foo".into();
+        assert_eq!(item.display_indices(vec![0, 2]), vec![0, 2]);
     }
 }
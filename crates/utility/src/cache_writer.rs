@@ -0,0 +1,250 @@
+//! Writes the cache files atomically and guards the concurrent cache writers.
+
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+use anyhow::{anyhow, Result};
+
+/// Lock file older than this is considered as left by a crashed writer if the liveness of its
+/// writer can't be told, see [`is_alive`].
+const STALE_LOCK_AGE: Duration = Duration::from_secs(10 * 60);
+
+const LOCK_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Returns the temp file path used for writing `path`.
+///
+/// The temp file is in the same directory of `path` so that the rename is atomic.
+pub fn temp_path_for(path: &Path) -> PathBuf {
+    let file_name = path
+        .file_name()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    path.with_file_name(format!(".{}.{}.tmp", file_name, std::process::id()))
+}
+
+/// Returns true if `path` is a temp file created by [`write_atomically`].
+pub fn is_temp_file(path: &Path) -> bool {
+    path.file_name()
+        .and_then(std::ffi::OsStr::to_str)
+        .map(|name| name.starts_with('.') && name.ends_with(".tmp"))
+        .unwrap_or(false)
+}
+
+/// Returns the pid of the process writing the temp file `path`, see [`temp_path_for`].
+pub fn temp_file_writer(path: &Path) -> Option<u32> {
+    let name = path.file_name()?.to_str()?.strip_suffix(".tmp")?;
+    name.rsplit('.').next()?.parse().ok()
+}
+
+/// Returns true if the process `pid` is still running.
+///
+/// Always true on the platforms other than unix, where it can't be told.
+#[cfg(unix)]
+pub fn is_alive(pid: u32) -> bool {
+    // SAFETY: signal 0 only checks the existence of the process.
+    unsafe { libc::kill(pid as libc::pid_t, 0) == 0 }
+}
+
+#[cfg(not(unix))]
+pub fn is_alive(_pid: u32) -> bool {
+    true
+}
+
+/// Writes `contents` to a temp file first and then renames it to `path`.
+///
+/// The readers of `path` never see a half-written file.
+pub fn write_atomically<T: AsRef<[u8]>>(path: &Path, contents: T) -> Result<()> {
+    let temp_path = temp_path_for(path);
+
+    let write_temp = || -> Result<()> {
+        let mut temp_file = File::create(&temp_path)?;
+        temp_file.write_all(contents.as_ref())?;
+        temp_file.sync_all()?;
+        Ok(())
+    };
+
+    if let Err(e) = write_temp() {
+        let _ = std::fs::remove_file(&temp_path);
+        return Err(e);
+    }
+
+    std::fs::rename(&temp_path, path)?;
+
+    Ok(())
+}
+
+/// Returns the path of the lock file for the cache directory `cache_dir`.
+fn lock_path_for(cache_dir: &Path) -> PathBuf {
+    cache_dir.with_extension("lock")
+}
+
+fn read_holder(lock_path: &Path) -> Option<u32> {
+    std::fs::read_to_string(lock_path).ok()?.trim().parse().ok()
+}
+
+/// Returns true if the lock is left by a writer which is gone.
+///
+/// The lock of a live writer is never stale however long it takes, e.g., rg on a huge project.
+fn is_stale(lock_path: &Path) -> bool {
+    match read_holder(lock_path) {
+        Some(pid) if cfg!(unix) => !is_alive(pid),
+        // The pid is not written yet or the liveness can't be told on this platform.
+        _ => std::fs::metadata(lock_path)
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+            .map(|age| age > STALE_LOCK_AGE)
+            .unwrap_or(false),
+    }
+}
+
+/// An advisory lock of a cache directory, released on drop.
+///
+/// Only one cache writer is allowed for a cache directory at the same time.
+#[derive(Debug)]
+pub struct CacheLock {
+    path: PathBuf,
+}
+
+impl CacheLock {
+    /// Tries to acquire the lock of `cache_dir`.
+    ///
+    /// Returns `None` if the lock is being held by another process.
+    pub fn try_acquire(cache_dir: &Path) -> Result<Option<Self>> {
+        let path = lock_path_for(cache_dir);
+
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        if is_stale(&path) {
+            let _ = std::fs::remove_file(&path);
+        }
+
+        match OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(mut lock_file) => {
+                write!(lock_file, "{}", std::process::id())?;
+                Ok(Some(Self { path }))
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Returns the pid of the process holding the lock of `cache_dir`.
+    pub fn holder(cache_dir: &Path) -> Option<u32> {
+        read_holder(&lock_path_for(cache_dir))
+    }
+
+    /// Returns true if the lock of `cache_dir` is held by another live process.
+    pub fn is_held_by_other(cache_dir: &Path) -> bool {
+        let lock_path = lock_path_for(cache_dir);
+        match read_holder(&lock_path) {
+            Some(pid) => pid != std::process::id() && !is_stale(&lock_path),
+            None => lock_path.exists() && !is_stale(&lock_path),
+        }
+    }
+
+    /// Releases the lock of `cache_dir` held by another process, e.g., which has been killed.
+    pub fn force_release(cache_dir: &Path) -> Result<()> {
+        match std::fs::remove_file(lock_path_for(cache_dir)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Acquires the lock of `cache_dir`, waiting until it's released by the other process.
+    pub fn acquire(cache_dir: &Path, timeout: Duration) -> Result<Self> {
+        let start = Instant::now();
+        loop {
+            if let Some(lock) = Self::try_acquire(cache_dir)? {
+                return Ok(lock);
+            }
+            if start.elapsed() > timeout {
+                return Err(anyhow!(
+                    "Timed out waiting for the cache lock of {}",
+                    cache_dir.display()
+                ));
+            }
+            std::thread::sleep(LOCK_POLL_INTERVAL);
+        }
+    }
+}
+
+impl Drop for CacheLock {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cache_lock() {
+        let mut cache_dir = std::env::temp_dir();
+        cache_dir.push(format!("clap_test_lock_{}", std::process::id()));
+
+        let lock = CacheLock::try_acquire(&cache_dir).unwrap();
+        assert!(lock.is_some());
+        assert!(CacheLock::try_acquire(&cache_dir).unwrap().is_none());
+
+        assert_eq!(CacheLock::holder(&cache_dir), Some(std::process::id()));
+
+        drop(lock);
+        assert_eq!(CacheLock::holder(&cache_dir), None);
+        let lock = CacheLock::try_acquire(&cache_dir).unwrap();
+        assert!(lock.is_some());
+
+        CacheLock::force_release(&cache_dir).unwrap();
+        assert!(CacheLock::try_acquire(&cache_dir).unwrap().is_some());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_stale_lock() {
+        let cache_dir =
+            std::env::temp_dir().join(format!("clap_test_stale_lock_{}", std::process::id()));
+
+        let mut child = std::process::Command::new("true").spawn().unwrap();
+        let dead_pid = child.id();
+        child.wait().unwrap();
+        std::fs::write(lock_path_for(&cache_dir), dead_pid.to_string()).unwrap();
+        assert!(!CacheLock::is_held_by_other(&cache_dir));
+        let lock = CacheLock::try_acquire(&cache_dir).unwrap();
+        assert!(lock.is_some());
+        drop(lock);
+
+        // The lock of a live writer is kept regardless of its age.
+        let mut child = std::process::Command::new("sleep")
+            .arg("5")
+            .spawn()
+            .unwrap();
+        std::fs::write(lock_path_for(&cache_dir), child.id().to_string()).unwrap();
+        assert!(CacheLock::is_held_by_other(&cache_dir));
+        assert!(CacheLock::try_acquire(&cache_dir).unwrap().is_none());
+        child.kill().unwrap();
+        child.wait().unwrap();
+        CacheLock::force_release(&cache_dir).unwrap();
+    }
+
+    #[test]
+    fn test_temp_file_writer() {
+        let path = temp_path_for(Path::new("/tmp/foo.json"));
+        assert!(is_temp_file(&path));
+        assert_eq!(temp_file_writer(&path), Some(std::process::id()));
+        assert_eq!(temp_file_writer(Path::new("/tmp/foo.json")), None);
+    }
+
+    #[test]
+    fn test_write_atomically() {
+        let path = std::env::temp_dir().join(format!("clap_test_atomic_{}", std::process::id()));
+        write_atomically(&path, "hello").unwrap();
+        assert!(!temp_path_for(&path).exists());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "hello");
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,143 @@
+//! Transparent compression of the cache files.
+//!
+//! The compressed cache file keeps its name, the compression format is detected from the magic
+//! bytes when reading it, so the cache files written with different settings can coexist.
+
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::Path;
+use std::str::FromStr;
+
+use anyhow::{anyhow, Result};
+
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+
+/// Compression format of the cache files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    None,
+    Gzip,
+    Zstd,
+}
+
+impl Default for Compression {
+    fn default() -> Self {
+        Self::None
+    }
+}
+
+impl FromStr for Compression {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "none" => Ok(Self::None),
+            "gzip" | "gz" => Ok(Self::Gzip),
+            "zstd" | "zst" => Ok(Self::Zstd),
+            _ => Err(anyhow!(
+                "Unknown cache compression: {}, possible values: none, gzip, zstd",
+                s
+            )),
+        }
+    }
+}
+
+impl Compression {
+    /// Detects the compression format given the first bytes of a file.
+    pub fn detect(header: &[u8]) -> Self {
+        if header.starts_with(GZIP_MAGIC) {
+            Self::Gzip
+        } else if header.starts_with(ZSTD_MAGIC) {
+            Self::Zstd
+        } else {
+            Self::None
+        }
+    }
+
+    /// Compresses `contents` in this format.
+    pub fn compress(self, contents: &[u8]) -> io::Result<Vec<u8>> {
+        match self {
+            Self::None => Ok(contents.to_vec()),
+            #[cfg(feature = "compression")]
+            Self::Gzip => {
+                use std::io::Write;
+
+                let mut encoder =
+                    flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast());
+                encoder.write_all(contents)?;
+                encoder.finish()
+            }
+            #[cfg(feature = "compression")]
+            Self::Zstd => zstd::stream::encode_all(contents, zstd::DEFAULT_COMPRESSION_LEVEL),
+            #[cfg(not(feature = "compression"))]
+            _ => Err(unsupported(self)),
+        }
+    }
+}
+
+#[cfg(not(feature = "compression"))]
+fn unsupported(compression: Compression) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Other,
+        format!(
+            "{:?} compression is not supported in this build",
+            compression
+        ),
+    )
+}
+
+/// Opens `path` for reading, the compressed file is decompressed transparently.
+pub fn open_decompressed(path: &Path) -> io::Result<Box<dyn Read>> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let compression = Compression::detect(reader.fill_buf()?);
+    match compression {
+        Compression::None => Ok(Box::new(reader)),
+        #[cfg(feature = "compression")]
+        Compression::Gzip => Ok(Box::new(flate2::bufread::MultiGzDecoder::new(reader))),
+        #[cfg(feature = "compression")]
+        Compression::Zstd => Ok(Box::new(zstd::stream::read::Decoder::with_buffer(reader)?)),
+        #[cfg(not(feature = "compression"))]
+        _ => Err(unsupported(compression)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(Compression::detect(b"\x1f\x8b\x08\x00"), Compression::Gzip);
+        assert_eq!(
+            Compression::detect(b"\x28\xb5\x2f\xfd\x00"),
+            Compression::Zstd
+        );
+        assert_eq!(Compression::detect(b"src/lib.rs:1:1:"), Compression::None);
+        assert_eq!(Compression::detect(b""), Compression::None);
+        assert_eq!("GZIP".parse::<Compression>().unwrap(), Compression::Gzip);
+        assert!("lz4".parse::<Compression>().is_err());
+    }
+
+    #[cfg(feature = "compression")]
+    #[test]
+    fn test_roundtrip() {
+        for compression in &[Compression::None, Compression::Gzip, Compression::Zstd] {
+            let path = std::env::temp_dir().join(format!(
+                "clap_test_compression_{:?}_{}",
+                compression,
+                std::process::id()
+            ));
+            let contents = b"src/lib.rs:1:1:foo\nsrc/main.rs:2:3:bar\n";
+            std::fs::write(&path, compression.compress(contents).unwrap()).unwrap();
+
+            let mut decompressed = Vec::new();
+            open_decompressed(&path)
+                .unwrap()
+                .read_to_end(&mut decompressed)
+                .unwrap();
+            assert_eq!(decompressed, contents);
+            std::fs::remove_file(&path).unwrap();
+        }
+    }
+}
@@ -0,0 +1,152 @@
+//! Layout of the cache directory.
+//!
+//! The cache directory is placed in the platform cache dir, i.e., `$XDG_CACHE_HOME` or
+//! `~/.cache` on Linux, `~/Library/Caches` on macOS and `%LOCALAPPDATA%` on Windows, the system
+//! temp dir is used if none of them is available.
+//!
+//! //  vim.clap
+//! //  ├── projects
+//! //  │   ├── <hash of project root>
+//! //  │   │   ├── root                  -- path of the project root
+//! //  │   │   ├── <arg1_arg2_arg3>      -- cache entries of a command
+//! //  │   │   └── jobs                  -- records of the forerunner jobs
+//! //  │   └── no_cmd_dir
+//! //  ├── history
+//! //  │   └── <provider>                -- previous queries of a provider
+//! //  ├── scores
+//! //  │   └── <hash of source file>     -- memoized matches of the queries on a source file
+//! //  └── ...                           -- caches not bound to a project, e.g., stdin, session
+
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::{calculate_hash, paths, CLAP_CACHE};
+
+/// Directory containing the caches of each project.
+const PROJECTS_DIR: &str = "projects";
+
+/// Project directory of the caches of commands run without a working directory.
+const NO_CMD_DIR: &str = "no_cmd_dir";
+
+/// File in a project directory storing the path of project root.
+const ROOT_FILE: &str = "root";
+
+/// Returns the platform cache dir given the environment variable lookup.
+fn base_cache_dir_with<F>(lookup: F) -> Option<PathBuf>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let absolute = |var: &str| {
+        lookup(var)
+            .map(PathBuf::from)
+            .filter(|path| path.is_absolute())
+    };
+
+    if cfg!(windows) {
+        absolute("LOCALAPPDATA")
+    } else if cfg!(target_os = "macos") {
+        absolute("HOME").map(|home| home.join("Library").join("Caches"))
+    } else {
+        absolute("XDG_CACHE_HOME").or_else(|| absolute("HOME").map(|home| home.join(".cache")))
+    }
+}
+
+/// Returns the root cache directory of clap.
+pub fn clap_cache_dir() -> PathBuf {
+    let mut dir =
+        base_cache_dir_with(|var| std::env::var(var).ok()).unwrap_or_else(std::env::temp_dir);
+    dir.push(CLAP_CACHE);
+    dir
+}
+
+/// Returns the cache directory of the project whose root is `root`.
+///
+/// The normalized path is hashed to avoid the possible issue of using a path as the directory
+/// name, e.g., the verbatim path on Windows.
+pub fn project_cache_dir(root: &Path) -> PathBuf {
+    let mut dir = clap_cache_dir();
+    dir.push(PROJECTS_DIR);
+    dir.push(format!("{}", calculate_hash(&paths::normalize(root))));
+    dir
+}
+
+/// Returns the cache directory of the commands that are not run in a project.
+pub fn no_project_cache_dir() -> PathBuf {
+    let mut dir = clap_cache_dir();
+    dir.push(PROJECTS_DIR);
+    dir.push(NO_CMD_DIR);
+    dir
+}
+
+/// Creates the cache directory of the project whose root is `root` if it does not exist yet.
+///
+/// The project root is recorded so that the project can be discovered by [`list_projects`].
+pub fn create_project_cache_dir(root: &Path) -> io::Result<PathBuf> {
+    let dir = project_cache_dir(root);
+    let root_file = dir.join(ROOT_FILE);
+    if !root_file.exists() {
+        std::fs::create_dir_all(&dir)?;
+        let root = paths::normalize(root);
+        std::fs::write(&root_file, root.to_string_lossy().as_bytes())?;
+    }
+    Ok(dir)
+}
+
+/// Returns the `(project root, project cache dir)` of all the cached projects.
+pub fn list_projects() -> Vec<(PathBuf, PathBuf)> {
+    let entries = match std::fs::read_dir(clap_cache_dir().join(PROJECTS_DIR)) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+    let mut projects = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter_map(|dir| {
+            let root = std::fs::read_to_string(dir.join(ROOT_FILE)).ok()?;
+            Some((PathBuf::from(root), dir))
+        })
+        .collect::<Vec<_>>();
+    projects.sort();
+    projects
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_base_cache_dir() {
+        let lookup = |var: &str| match var {
+            "HOME" => Some("/home/clap".to_string()),
+            "XDG_CACHE_HOME" => Some("relative/cache".to_string()),
+            "LOCALAPPDATA" => Some("relative/appdata".to_string()),
+            _ => None,
+        };
+        if cfg!(windows) {
+            assert_eq!(base_cache_dir_with(lookup), None);
+        } else if cfg!(target_os = "macos") {
+            assert_eq!(
+                base_cache_dir_with(lookup),
+                Some(PathBuf::from("/home/clap/Library/Caches"))
+            );
+        } else {
+            // The relative XDG_CACHE_HOME is ignored.
+            assert_eq!(
+                base_cache_dir_with(lookup),
+                Some(PathBuf::from("/home/clap/.cache"))
+            );
+        }
+        assert_eq!(base_cache_dir_with(|_| None), None);
+    }
+
+    #[test]
+    fn test_project_cache_dir() {
+        let root = std::env::temp_dir().join(format!("clap_test_project_{}", std::process::id()));
+        let dir = create_project_cache_dir(&root).unwrap();
+        assert_eq!(dir, project_cache_dir(&root));
+        assert!(list_projects().contains(&(root.clone(), dir.clone())));
+        // The same project given with a trailing separator.
+        assert_eq!(project_cache_dir(&root.join("")), dir);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -10,10 +10,21 @@ use std::process::{Command, Output};
 
 use anyhow::{anyhow, Result};
 
+pub mod cache_writer;
+pub mod compression;
+pub mod dirs;
 mod macros;
+pub mod paths;
+pub mod tty;
 
 pub const CLAP_CACHE: &str = "vim.clap";
 
+/// File name of the cache entry that is still being written by the forerunner job.
+pub const PARTIAL_CACHE_ENTRY: &str = "partial";
+
+/// Extension of the sidecar file storing the metadata of a cache entry.
+pub const CACHE_META_EXTENSION: &str = "meta";
+
 /// Removes all the file and directories under `target_dir`.
 pub fn remove_dir_contents(target_dir: &Path) -> Result<()> {
     let entries = read_dir(target_dir)?;
@@ -38,6 +49,17 @@ pub fn is_git_repo(dir: &Path) -> bool {
     gitdir.exists()
 }
 
+/// Returns the rest of `line` if it starts with the path `dir`.
+///
+/// This works for both the file path line and the grep line which starts with a file path.
+pub fn strip_dir_prefix<'a>(line: &'a str, dir: &Path) -> Option<&'a str> {
+    let dir = paths::simplified(dir);
+    let dir = dir.to_str()?;
+    let dir = dir.trim_end_matches(|c: char| c == '/' || c == std::path::MAIN_SEPARATOR);
+    line.strip_prefix(dir)
+        .and_then(|rest| rest.strip_prefix(|c: char| c == '/' || c == std::path::MAIN_SEPARATOR))
+}
+
 /// Returns an Iterator to the Reader of the lines of the file.
 ///
 /// The output is wrapped in a Result to allow matching on errors.
@@ -50,12 +72,14 @@ where
 }
 
 /// Returns the first number lines given the file path.
+///
+/// The compressed cache file is decompressed transparently.
 pub fn read_first_lines<P: AsRef<Path>>(
     path: P,
     number: usize,
 ) -> io::Result<impl Iterator<Item = String>> {
-    let file = File::open(path)?;
-    Ok(io::BufReader::new(file)
+    let reader = compression::open_decompressed(path.as_ref())?;
+    Ok(io::BufReader::new(reader)
         .lines()
         .filter_map(|i| i.ok())
         .take(number))
@@ -69,34 +93,68 @@ pub fn calculate_hash<T: Hash>(t: &T) -> u64 {
 
 #[inline]
 pub fn clap_cache_dir() -> PathBuf {
-    let mut dir = std::env::temp_dir();
-    dir.push(CLAP_CACHE);
-    dir
+    dirs::clap_cache_dir()
 }
 
 /// Returns the cache path for clap.
 ///
-/// The reason for using hash(cmd_dir) instead of cmd_dir directory is to avoid the possible issue
-/// of using a path as the directory name.
-///
-/// Formula: temp_dir + clap_cache + arg1_arg2_arg3 + hash(cmd_dir)
+/// Formula: clap_cache + projects + hash(cmd_dir) + arg1_arg2_arg3
 pub fn get_cache_dir(args: &[&str], cmd_dir: &Path) -> PathBuf {
-    let mut dir = clap_cache_dir();
+    let mut dir = dirs::project_cache_dir(cmd_dir);
     dir.push(args.join("_"));
-    // TODO: use a readable cache cmd_dir name?
-    dir.push(format!("{}", calculate_hash(&cmd_dir)));
     dir
 }
 
+/// Returns true if `path` is a cache entry that is still being written.
+#[inline]
+pub fn is_partial_cache(path: &Path) -> bool {
+    path.file_name() == Some(OsStr::new(PARTIAL_CACHE_ENTRY))
+}
+
+/// Returns the path of sidecar metadata file of the cache entry `entry`.
+#[inline]
+pub fn cache_meta_path(entry: &Path) -> PathBuf {
+    entry.with_extension(CACHE_META_EXTENSION)
+}
+
+/// Returns true if `path` is the sidecar metadata file of a cache entry.
+#[inline]
+pub fn is_cache_meta(path: &Path) -> bool {
+    path.extension() == Some(OsStr::new(CACHE_META_EXTENSION))
+}
+
+/// Returns true if `path` is a complete cache entry instead of the auxiliary files.
+#[inline]
+fn is_complete_cache(path: &Path) -> bool {
+    !is_partial_cache(path) && !is_cache_meta(path) && !cache_writer::is_temp_file(path)
+}
+
+/// Returns true if `path` is a complete cache entry written by maple, which is replaced by
+/// renaming instead of being modified in place.
+pub fn is_cache_entry(path: &Path) -> bool {
+    path.starts_with(clap_cache_dir()) && is_complete_cache(path)
+}
+
+/// Returns the cache entry that is still being written given the cmd args and working dir.
+pub fn get_partial_cache(args: &[&str], cmd_dir: &Path) -> Option<PathBuf> {
+    let partial = get_cache_dir(args, &cmd_dir).join(PARTIAL_CACHE_ENTRY);
+    if partial.exists() {
+        Some(partial)
+    } else {
+        None
+    }
+}
+
 /// Returns the cached entry given the cmd args and working dir.
 pub fn get_cached_entry(args: &[&str], cmd_dir: &Path) -> Result<DirEntry> {
     let cache_dir = get_cache_dir(args, &cmd_dir);
     if cache_dir.exists() {
-        let mut entries = read_dir(cache_dir)?;
-
         // Everytime when we are about to create a new cache entry, the old entry will be removed,
-        // so there is only one cache entry, therefore it should be always the latest one.
-        if let Some(Ok(first_entry)) = entries.next() {
+        // so there is only one complete cache entry, therefore it should be always the latest one.
+        if let Some(first_entry) = read_dir(cache_dir)?
+            .filter_map(|entry| entry.ok())
+            .find(|entry| is_complete_cache(&entry.path()))
+        {
             return Ok(first_entry);
         }
     }
@@ -157,7 +215,8 @@ pub fn as_std_command<P: AsRef<Path>>(shell_cmd: impl AsRef<OsStr>, dir: Option<
     };
 
     if let Some(d) = dir {
-        cmd.current_dir(d);
+        // cmd does not support the verbatim path as the working directory.
+        cmd.current_dir(paths::simplified(d.as_ref()));
     }
 
     cmd
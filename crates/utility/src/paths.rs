@@ -0,0 +1,345 @@
+//! Expands the home directory and environment variables in the paths passed from the command line.
+//!
+//! The verbatim paths (`\\?\C:\foo`, `\\?\UNC\server\share`) on Windows are simplified to the
+//! ordinary form whenever possible, since they are not understood by cmd and most external
+//! programs, the verbatim prefix is only added back when opening a path longer than `MAX_PATH`.
+
+use std::borrow::Cow;
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+
+/// Maximum length of a path on Windows without the verbatim prefix.
+const MAX_PATH: usize = 260;
+
+const VERBATIM_PREFIX: &str = r"\\?\";
+
+const VERBATIM_UNC_PREFIX: &str = r"\\?\UNC\";
+
+fn is_var_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+/// Returns the home directory given the environment variable lookup, `USERPROFILE` is for
+/// Windows.
+fn home_dir_with<F>(lookup: F) -> Option<String>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    lookup("HOME").or_else(|| lookup("USERPROFILE"))
+}
+
+/// Returns the home directory, the same one `~` is expanded to.
+///
+/// Unlike the deprecated `std::env::home_dir()`, `HOME` of Windows is respected too.
+pub fn home_dir() -> Option<PathBuf> {
+    home_dir_with(|name| std::env::var(name).ok()).map(PathBuf::from)
+}
+
+/// Expands the leading `~`, `$VAR`, `${VAR}` and `%VAR%` in `path` using `lookup`.
+///
+/// The variables that can not be resolved are kept as they are.
+fn expand_with<F>(path: &str, lookup: F) -> String
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let mut expanded = String::with_capacity(path.len());
+
+    let mut rest = path;
+    if let Some(after_tilde) = path.strip_prefix('~') {
+        if after_tilde.is_empty() || after_tilde.starts_with(|c: char| c == '/' || c == '\\') {
+            if let Some(home) = home_dir_with(&lookup) {
+                expanded.push_str(&home);
+                rest = after_tilde;
+            }
+        }
+    }
+
+    while let Some(idx) = rest.find(|c: char| c == '$' || c == '%') {
+        expanded.push_str(&rest[..idx]);
+        let (sigil, after) = (&rest[idx..idx + 1], &rest[idx + 1..]);
+
+        let (name, remaining, braced) = if sigil == "$" {
+            if let Some(inner) = after.strip_prefix('{') {
+                match inner.find('}') {
+                    Some(end) => (&inner[..end], &inner[end + 1..], true),
+                    None => ("", after, false),
+                }
+            } else {
+                let end = after.find(|c: char| !is_var_char(c)).unwrap_or(after.len());
+                (&after[..end], &after[end..], false)
+            }
+        } else {
+            match after.find('%') {
+                Some(end) if after[..end].chars().all(is_var_char) => {
+                    (&after[..end], &after[end + 1..], true)
+                }
+                _ => ("", after, false),
+            }
+        };
+
+        match lookup(name).filter(|_| !name.is_empty()) {
+            Some(value) => expanded.push_str(&value),
+            None => {
+                expanded.push_str(sigil);
+                if braced {
+                    // Keep the original text of the unresolved variable.
+                    let consumed = after.len() - remaining.len();
+                    expanded.push_str(&after[..consumed]);
+                } else {
+                    expanded.push_str(name);
+                }
+            }
+        }
+
+        rest = remaining;
+    }
+
+    expanded.push_str(rest);
+    expanded
+}
+
+/// Expands the home directory and environment variables in `path`.
+pub fn expand(path: &str) -> PathBuf {
+    expand_with(path, |name| std::env::var(name).ok()).into()
+}
+
+/// Parser of the path-taking flags, used as `parse(from_os_str = utility::paths::expand_os)`.
+pub fn expand_os(path: &OsStr) -> PathBuf {
+    let expanded = match path.to_str() {
+        Some(path) => expand(path),
+        None => path.into(),
+    };
+    simplified(&expanded).into_owned()
+}
+
+/// Returns true if `name` is a reserved device name on Windows, e.g., `nul` or `COM1.txt`.
+fn is_reserved_name(name: &str) -> bool {
+    let stem = name.split('.').next().unwrap_or(name).trim_end();
+    let stem = stem.to_ascii_uppercase();
+    match stem.as_str() {
+        "CON" | "PRN" | "AUX" | "NUL" => true,
+        _ => {
+            (stem.starts_with("COM") || stem.starts_with("LPT"))
+                && stem.len() == 4
+                && matches!(stem.as_bytes()[3], b'1'..=b'9')
+        }
+    }
+}
+
+/// Returns true if `path` starts with a drive and a separator, e.g., `C:\`.
+fn is_drive_absolute(path: &str) -> bool {
+    let bytes = path.as_bytes();
+    bytes.len() >= 3
+        && bytes[0].is_ascii_alphabetic()
+        && bytes[1] == b':'
+        && (bytes[2] == b'\\' || bytes[2] == b'/')
+}
+
+/// Returns the verbatim `path` without the prefix if it still refers to the same file.
+///
+/// Only the disk and UNC paths are stripped, the other verbatim paths like `\\?\Volume{..}` are
+/// kept.
+fn strip_verbatim(path: &str) -> Option<String> {
+    let (stripped, rest) = if let Some(rest) = path.strip_prefix(VERBATIM_UNC_PREFIX) {
+        (format!(r"\\{}", rest), rest)
+    } else {
+        let rest = path.strip_prefix(VERBATIM_PREFIX)?;
+        if !is_drive_absolute(rest) {
+            return None;
+        }
+        (rest.to_string(), &rest[3..])
+    };
+
+    // The components are taken literally in the verbatim path, but not in the ordinary one.
+    let rest = rest.strip_suffix('\\').unwrap_or(rest);
+    let is_literal_component = |c: &str| {
+        !c.is_empty()
+            && !c.ends_with('.')
+            && !c.ends_with(' ')
+            && !c.contains('/')
+            && !is_reserved_name(c)
+    };
+    if stripped.len() < MAX_PATH && (rest.is_empty() || rest.split('\\').all(is_literal_component))
+    {
+        Some(stripped)
+    } else {
+        None
+    }
+}
+
+/// Returns the absolute `path` with the verbatim prefix if it's too long for the ordinary form.
+fn add_verbatim(path: &str) -> Option<String> {
+    if path.len() < MAX_PATH || path.starts_with(VERBATIM_PREFIX) {
+        return None;
+    }
+    let path = path.replace('/', "\\");
+    let (prefix, rest) = if let Some(rest) = path.strip_prefix(r"\\") {
+        (VERBATIM_UNC_PREFIX, rest)
+    } else if is_drive_absolute(&path) {
+        (VERBATIM_PREFIX, path.as_str())
+    } else {
+        return None;
+    };
+    let components = rest
+        .split('\\')
+        .filter(|c| !c.is_empty())
+        .collect::<Vec<_>>();
+    // `.` and `..` are not resolved in the verbatim path.
+    if components.iter().any(|&c| c == "." || c == "..") {
+        return None;
+    }
+    Some(format!("{}{}", prefix, components.join("\\")))
+}
+
+/// Returns `path` without the verbatim prefix on Windows whenever possible, e.g., the result of
+/// `canonicalize`, which is then usable as the working directory of cmd.
+pub fn simplified(path: &Path) -> Cow<'_, Path> {
+    if cfg!(windows) {
+        if let Some(stripped) = path.to_str().and_then(strip_verbatim) {
+            return Cow::Owned(stripped.into());
+        }
+    }
+    Cow::Borrowed(path)
+}
+
+/// Returns `path` with the verbatim prefix on Windows if it exceeds `MAX_PATH`, for opening it.
+pub fn long_path(path: &Path) -> Cow<'_, Path> {
+    if cfg!(windows) {
+        if let Some(verbatim) = path.to_str().and_then(add_verbatim) {
+            return Cow::Owned(verbatim.into());
+        }
+    }
+    Cow::Borrowed(path)
+}
+
+/// Returns the normalized `path`, so that the same directory given in different forms, with a
+/// trailing separator or the verbatim prefix, is mapped to the same cache.
+pub fn normalize(path: &Path) -> PathBuf {
+    simplified(path).components().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lookup(name: &str) -> Option<String> {
+        match name {
+            "HOME" => Some("/home/clap".into()),
+            "USERPROFILE" => Some(r"C:\Users\clap".into()),
+            "PROJ" => Some("vim-clap".into()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_expand_home() {
+        assert_eq!(expand_with("~", lookup), "/home/clap");
+        assert_eq!(expand_with("~/foo.txt", lookup), "/home/clap/foo.txt");
+        assert_eq!(expand_with(r"~\foo.txt", lookup), r"/home/clap\foo.txt");
+        assert_eq!(expand_with("~foo/bar", lookup), "~foo/bar");
+        assert_eq!(expand_with("/tmp/~/foo", lookup), "/tmp/~/foo");
+    }
+
+    #[test]
+    fn test_expand_home_from_userprofile() {
+        let windows_lookup = |name: &str| match name {
+            "USERPROFILE" => Some(r"C:\Users\clap".to_string()),
+            _ => None,
+        };
+        assert_eq!(
+            expand_with(r"~\foo.txt", windows_lookup),
+            r"C:\Users\clap\foo.txt"
+        );
+        assert_eq!(
+            expand_with(r"%USERPROFILE%\foo.txt", windows_lookup),
+            r"C:\Users\clap\foo.txt"
+        );
+        assert_eq!(
+            home_dir_with(windows_lookup).as_deref(),
+            Some(r"C:\Users\clap")
+        );
+        assert_eq!(home_dir_with(lookup).as_deref(), Some("/home/clap"));
+    }
+
+    #[test]
+    fn test_expand_env_vars() {
+        assert_eq!(expand_with("$HOME/foo.txt", lookup), "/home/clap/foo.txt");
+        assert_eq!(
+            expand_with("${HOME}/src/${PROJ}", lookup),
+            "/home/clap/src/vim-clap"
+        );
+        assert_eq!(
+            expand_with(r"%USERPROFILE%\src\%PROJ%", lookup),
+            r"C:\Users\clap\src\vim-clap"
+        );
+        assert_eq!(expand_with("$HOME$PROJ", lookup), "/home/clapvim-clap");
+    }
+
+    #[test]
+    fn test_keep_unresolved() {
+        assert_eq!(expand_with("$NOPE/foo", lookup), "$NOPE/foo");
+        assert_eq!(expand_with("${NOPE}/foo", lookup), "${NOPE}/foo");
+        assert_eq!(expand_with("%NOPE%/foo", lookup), "%NOPE%/foo");
+        assert_eq!(expand_with("${HOME/foo", lookup), "${HOME/foo");
+        assert_eq!(expand_with("100%/foo", lookup), "100%/foo");
+        assert_eq!(expand_with("a$/b", lookup), "a$/b");
+        assert_eq!(expand_with("50% or 60%", lookup), "50% or 60%");
+    }
+
+    #[test]
+    fn test_strip_verbatim() {
+        assert_eq!(
+            strip_verbatim(r"\\?\C:\Users\clap\src").as_deref(),
+            Some(r"C:\Users\clap\src")
+        );
+        assert_eq!(strip_verbatim(r"\\?\C:\").as_deref(), Some(r"C:\"));
+        assert_eq!(
+            strip_verbatim(r"\\?\UNC\server\share\src").as_deref(),
+            Some(r"\\server\share\src")
+        );
+        assert_eq!(strip_verbatim(r"C:\Users\clap"), None);
+        assert_eq!(strip_verbatim(r"\\?\Volume{1234}\src"), None);
+        assert_eq!(strip_verbatim(r"\\?\C:\src\nul"), None);
+        assert_eq!(strip_verbatim(r"\\?\C:\src\foo."), None);
+        assert_eq!(strip_verbatim(r"\\?\C:\src\a/b"), None);
+        let long = format!(r"\\?\C:\{}", "a".repeat(MAX_PATH));
+        assert_eq!(strip_verbatim(&long), None);
+    }
+
+    #[test]
+    fn test_add_verbatim() {
+        assert_eq!(add_verbatim(r"C:\Users\clap"), None);
+        let name = "a".repeat(MAX_PATH);
+        assert_eq!(
+            add_verbatim(&format!(r"C:\src/{}\", name)),
+            Some(format!(r"\\?\C:\src\{}", name))
+        );
+        assert_eq!(
+            add_verbatim(&format!(r"\\server\share\{}", name)),
+            Some(format!(r"\\?\UNC\server\share\{}", name))
+        );
+        assert_eq!(add_verbatim(&format!(r"C:\src\..\{}", name)), None);
+        assert_eq!(add_verbatim(&format!(r"src\{}", name)), None);
+        let verbatim = format!(r"\\?\C:\{}", name);
+        assert_eq!(add_verbatim(&verbatim), None);
+    }
+
+    #[test]
+    fn test_normalize() {
+        if cfg!(windows) {
+            assert_eq!(
+                normalize(Path::new(r"\\?\C:\Users\clap\")),
+                PathBuf::from(r"C:\Users\clap")
+            );
+            assert_eq!(
+                normalize(Path::new(r"\\?\UNC\server\share\src")),
+                normalize(Path::new(r"\\server\share\src\"))
+            );
+        } else {
+            assert_eq!(
+                normalize(Path::new("/home/clap//src/./")),
+                PathBuf::from("/home/clap/src")
+            );
+        }
+    }
+}
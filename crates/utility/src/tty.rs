@@ -0,0 +1,71 @@
+//! Detects the width of the terminal when maple is not run by the vim frontend.
+
+/// Parses the value of `COLUMNS`, zero or malformed values are ignored.
+fn parse_columns(columns: &str) -> Option<usize> {
+    columns.trim().parse().ok().filter(|&width| width > 0)
+}
+
+#[cfg(unix)]
+fn ioctl_width(fd: libc::c_int) -> Option<usize> {
+    let mut winsize = libc::winsize {
+        ws_row: 0,
+        ws_col: 0,
+        ws_xpixel: 0,
+        ws_ypixel: 0,
+    };
+    // SAFETY: TIOCGWINSZ only writes to the winsize struct we pass in.
+    let ret = unsafe { libc::ioctl(fd, libc::TIOCGWINSZ, &mut winsize) };
+    if ret == 0 && winsize.ws_col > 0 {
+        Some(winsize.ws_col as usize)
+    } else {
+        None
+    }
+}
+
+/// Returns the width of the terminal attached to stdout or stderr.
+#[cfg(unix)]
+fn detect_width() -> Option<usize> {
+    ioctl_width(libc::STDOUT_FILENO).or_else(|| ioctl_width(libc::STDERR_FILENO))
+}
+
+#[cfg(not(unix))]
+fn detect_width() -> Option<usize> {
+    None
+}
+
+/// Returns the width of the terminal, `COLUMNS` takes precedence over the detected one.
+///
+/// Returns `None` when the output is not a terminal, e.g., maple is spawned by vim.
+pub fn terminal_width() -> Option<usize> {
+    std::env::var("COLUMNS")
+        .ok()
+        .and_then(|columns| parse_columns(&columns))
+        .or_else(detect_width)
+}
+
+/// Returns the width used for truncating the output lines.
+///
+/// `winwidth` is the value of `--winwidth`, `default` is used when neither `--winwidth`
+/// nor the terminal width is available.
+pub fn display_width(winwidth: Option<usize>, default: usize) -> usize {
+    winwidth.or_else(terminal_width).unwrap_or(default)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_columns() {
+        assert_eq!(parse_columns("120"), Some(120));
+        assert_eq!(parse_columns(" 80\n"), Some(80));
+        assert_eq!(parse_columns("0"), None);
+        assert_eq!(parse_columns("wide"), None);
+        assert_eq!(parse_columns(""), None);
+    }
+
+    #[test]
+    fn test_explicit_winwidth_wins() {
+        assert_eq!(display_width(Some(42), 100), 42);
+    }
+}
@@ -48,6 +48,13 @@ augroup ClapBuffers
   autocmd BufWinEnter,WinEnter * let g:__clap_buffers[bufnr('')] = reltimefloat(reltime())
 augroup END
 
+if get(g:, 'clap_refresh_grep_cache_on_save', 0)
+  augroup ClapRefreshGrepCache
+    autocmd!
+    autocmd BufWritePost * call clap#maple#refresh_file(+expand('<abuf>'))
+  augroup END
+endif
+
 " yanks provider
 if get(g:, 'clap_enable_yanks_provider', 1)
   augroup ClapYanks
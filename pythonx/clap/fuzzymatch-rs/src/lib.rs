@@ -49,6 +49,9 @@ impl From<HashMap<String, String>> for MatchContext {
         if let Some(language) = ctx.get("language") {
             bonuses.push(Bonus::Language(language.into()));
         }
+        if let Some(filetype) = ctx.get("filetype") {
+            bonuses.push(Bonus::FileType(filetype.into()));
+        }
 
         Self {
             winwidth,